@@ -206,7 +206,8 @@ fn bench_video<V>(
                     frame_image_producer: producer,
                     border_changes: border_changes.iter().copied(),
                     border_size: BorderSize::Full,
-                    invert_flash
+                    invert_flash,
+                    precise_border: false
                 };
                 renderer.render_pixels::<PixelBuf,SpectrumPal,V>(&mut buffer, pitch);
                 black_box(&mut buffer);
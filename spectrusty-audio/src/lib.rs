@@ -22,4 +22,5 @@
 pub mod carousel;
 pub mod host;
 pub mod music;
+pub mod resample;
 pub mod synth;
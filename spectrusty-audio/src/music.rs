@@ -12,11 +12,29 @@ use core::convert::TryInto;
 /// `hz` base frequency is in Hz (use 440.0 as a good default).
 /// `n0` an index from the base frequency to the first note in the table: 0 is for "A" note, -9 for "C".
 /// `steps` determines how many halftones will be rendered, (12 is the usual number).
+///
+/// This is a shortcut for [equal_tempered_note_freqs] with the octave divided into `steps` notes.
 pub fn equal_tempered_scale_note_freqs(hz: f32, n0: i16, steps: i16)
                                          -> impl IntoIterator<Item=f32> + Clone + ExactSizeIterator
 {
+    equal_tempered_note_freqs(hz, n0, steps, steps)
+}
+
+/// Returns an iterator of frequencies of an equal temperament with an octave divided into `divisions` notes.
+///
+/// `hz` a reference frequency is in Hz.
+/// `n0` an index from the reference frequency to the first note in the table, in `1/divisions` octave units.
+/// `steps` determines how many notes will be rendered.
+/// `divisions` the number of notes per octave, e.g. 12 for the usual scale, 19 or 24 for microtonal scales.
+///
+/// # Panics
+/// Panics if `divisions` is not greater than 0.
+pub fn equal_tempered_note_freqs(hz: f32, n0: i16, steps: i16, divisions: i16)
+                                   -> impl IntoIterator<Item=f32> + Clone + ExactSizeIterator
+{
+    assert!(divisions > 0, "divisions must be greater than 0");
     (0..steps).map(move |n| {
-        hz * (2.0f32).powf( (n + n0) as f32 / steps as f32 )
+        hz * (2.0f32).powf( (n + n0) as f32 / divisions as f32 )
     })
 }
 
@@ -62,4 +80,20 @@ mod tests {
             assert!(nearly_equal(freq1, freq0, f32::EPSILON));
         }
     }
+
+    #[test]
+    fn equal_tempered_note_freqs_works() {
+        let freqs: Vec<_> = equal_tempered_note_freqs(440.0, 0, 12, 12).into_iter().collect();
+        let freqs0: Vec<_> = equal_tempered_scale_note_freqs(440.0, 0, 12).into_iter().collect();
+        assert_eq!(freqs, freqs0);
+        let freqs24: Vec<_> = equal_tempered_note_freqs(440.0, 0, 24, 24).into_iter().collect();
+        for (freq1, freq0) in freqs24.into_iter().step_by(2).zip(freqs0) {
+            assert!(nearly_equal(freq1, freq0, f32::EPSILON));
+        }
+        let freqs19: Vec<_> = equal_tempered_note_freqs(440.0, -19, 39, 19).into_iter().collect();
+        assert_eq!(freqs19.len(), 39);
+        assert!(nearly_equal(freqs19[0], 220.0, f32::EPSILON));
+        assert!(nearly_equal(freqs19[19], 440.0, f32::EPSILON));
+        assert!(nearly_equal(freqs19[38], 880.0, f32::EPSILON));
+    }
 }
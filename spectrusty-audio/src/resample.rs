@@ -0,0 +1,241 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! Resampling of rendered audio samples to an arbitrary sample rate.
+//!
+//! [Blep] implementations render samples at the requested sample rate directly. However, it's sometimes
+//! desirable to render audio frames at a rate being a clean divisor of the emulated CPU clock and then
+//! to convert them to the rate of the audio output device, e.g. from 43750 Hz (3.5 MHz / 80) to 44100 Hz.
+//!
+//! [Resampler] performs such conversion on a continuous stream of samples, one audio frame at a time.
+//! When both rates are equal, the samples are passed through unchanged, regardless of the selected quality.
+//!
+//! [Blep]: spectrusty_core::audio::Blep
+use spectrusty_core::audio::FromSample;
+
+/// Selects the interpolation method used by [Resampler].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResampleQuality {
+    /// Takes the nearest input sample. The fastest and the lowest quality method.
+    Nearest,
+    /// Interpolates linearly between two adjacent input samples.
+    Linear,
+    /// Interpolates using the windowed sinc function (Lanczos kernel) with [SINC_HALF_TAPS] taps on each
+    /// side of the output sample. The slowest and the highest quality method.
+    ///
+    /// Available with the `"sinc"` feature.
+    #[cfg(feature = "sinc")]
+    Sinc
+}
+
+/// The number of input samples on each side of an output sample used by [ResampleQuality::Sinc]
+/// when upsampling. When downsampling, the number is scaled by the rate ratio.
+#[cfg(feature = "sinc")]
+pub const SINC_HALF_TAPS: usize = 8;
+
+/// Converts a continuous stream of `f32` samples from one sample rate to another.
+///
+/// The resampler keeps the tail of the previously resampled input, so frames can be fed one after
+/// another without discontinuities. Depending on the quality, the output lags behind the input by
+/// at most a few samples.
+#[derive(Clone, Debug)]
+pub struct Resampler {
+    quality: ResampleQuality,
+    input_rate: u32,
+    output_rate: u32,
+    step: f64,
+    pos: f64,
+    buffer: Vec<f32>
+}
+
+impl Resampler {
+    /// Creates a new resampler converting samples from `input_rate` to `output_rate` samples per second.
+    ///
+    /// # Panics
+    /// Panics if any of the rates is `0`.
+    pub fn new(quality: ResampleQuality, input_rate: u32, output_rate: u32) -> Self {
+        let mut resampler = Resampler {
+            quality, input_rate, output_rate, step: 1.0, pos: 0.0, buffer: Vec::new()
+        };
+        resampler.set_rates(input_rate, output_rate);
+        resampler
+    }
+    /// Returns the selected interpolation method.
+    pub fn quality(&self) -> ResampleQuality {
+        self.quality
+    }
+    /// Returns the configured `(input_rate, output_rate)`.
+    pub fn rates(&self) -> (u32, u32) {
+        (self.input_rate, self.output_rate)
+    }
+    /// Returns `true` if the samples are passed through unchanged, because the rates are equal.
+    pub fn is_passthrough(&self) -> bool {
+        self.input_rate == self.output_rate
+    }
+    /// Changes the interpolation method and resets the resampler.
+    pub fn set_quality(&mut self, quality: ResampleQuality) {
+        self.quality = quality;
+        self.reset();
+    }
+    /// Changes the sample rates and resets the resampler.
+    ///
+    /// # Panics
+    /// Panics if any of the rates is `0`.
+    pub fn set_rates(&mut self, input_rate: u32, output_rate: u32) {
+        assert!(input_rate != 0 && output_rate != 0, "sample rates must not be 0");
+        self.input_rate = input_rate;
+        self.output_rate = output_rate;
+        self.step = input_rate as f64 / output_rate as f64;
+        self.reset();
+    }
+    /// Discards the remaining input samples and the interpolation history.
+    pub fn reset(&mut self) {
+        let (left, _) = self.kernel_extent();
+        self.buffer.clear();
+        self.buffer.resize(left, 0.0);
+        self.pos = left as f64;
+    }
+    /// Resamples the `input` samples and appends the result to the `output`.
+    ///
+    /// Returns the number of samples appended.
+    pub fn resample<I, S>(&mut self, input: I, output: &mut Vec<S>) -> usize
+        where I: IntoIterator<Item=f32>,
+              S: FromSample<f32>
+    {
+        let start = output.len();
+        if self.is_passthrough() {
+            output.extend(input.into_iter().map(S::from_sample));
+            return output.len() - start
+        }
+        self.buffer.extend(input);
+        let (left, right) = self.kernel_extent();
+        while (self.pos as usize) + right < self.buffer.len() {
+            output.push(S::from_sample(self.sample_at(self.pos)));
+            self.pos += self.step;
+        }
+        let consumed = (self.pos as usize).saturating_sub(left).min(self.buffer.len());
+        self.buffer.drain(..consumed);
+        self.pos -= consumed as f64;
+        output.len() - start
+    }
+
+    fn kernel_extent(&self) -> (usize, usize) {
+        match self.quality {
+            ResampleQuality::Nearest|ResampleQuality::Linear => (0, 1),
+            #[cfg(feature = "sinc")]
+            ResampleQuality::Sinc => {
+                let half = (SINC_HALF_TAPS as f64 * self.step.max(1.0)).ceil() as usize;
+                (half, half)
+            }
+        }
+    }
+
+    fn sample_at(&self, pos: f64) -> f32 {
+        let index = pos as usize;
+        let frac = pos - index as f64;
+        match self.quality {
+            ResampleQuality::Nearest => {
+                if frac < 0.5 { self.buffer[index] } else { self.buffer[index + 1] }
+            }
+            ResampleQuality::Linear => {
+                let (a, b) = (self.buffer[index], self.buffer[index + 1]);
+                a + (b - a) * frac as f32
+            }
+            #[cfg(feature = "sinc")]
+            ResampleQuality::Sinc => self.sinc_sample_at(index, frac)
+        }
+    }
+
+    #[cfg(feature = "sinc")]
+    fn sinc_sample_at(&self, index: usize, frac: f64) -> f32 {
+        fn sinc(x: f64) -> f64 {
+            if x == 0.0 {
+                1.0
+            }
+            else {
+                let px = core::f64::consts::PI * x;
+                px.sin() / px
+            }
+        }
+        if frac == 0.0 && self.step <= 1.0 {
+            return self.buffer[index]
+        }
+        let (half, _) = self.kernel_extent();
+        let cutoff = 1.0 / self.step.max(1.0);
+        let window = SINC_HALF_TAPS as f64;
+        let (mut sum, mut weights) = (0.0, 0.0);
+        for j in (index + 1 - half)..=(index + half) {
+            let x = (j as f64 - index as f64 - frac) * cutoff;
+            if x.abs() < window {
+                let weight = sinc(x) * sinc(x / window);
+                sum += self.buffer[j] as f64 * weight;
+                weights += weight;
+            }
+        }
+        (sum / weights) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qualities() -> Vec<ResampleQuality> {
+        #[allow(unused_mut)]
+        let mut qualities = vec![ResampleQuality::Nearest, ResampleQuality::Linear];
+        #[cfg(feature = "sinc")]
+        qualities.push(ResampleQuality::Sinc);
+        qualities
+    }
+
+    #[test]
+    fn resampler_passthrough_works() {
+        let input: Vec<f32> = (0..100).map(|n| (n as f32 / 10.0).sin()).collect();
+        for quality in qualities() {
+            let mut resampler = Resampler::new(quality, 44100, 44100);
+            assert!(resampler.is_passthrough());
+            let mut output: Vec<f32> = Vec::new();
+            assert_eq!(resampler.resample(input.iter().copied(), &mut output), 100);
+            assert_eq!(output, input);
+            let mut output: Vec<i16> = Vec::new();
+            resampler.resample(input.iter().copied(), &mut output);
+            assert_eq!(output, input.iter().map(|&s| i16::from_sample(s)).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn resampler_works() {
+        for quality in qualities() {
+            for &(input_rate, output_rate) in &[(43750, 44100), (44100, 22050), (22050, 44100)] {
+                let mut resampler = Resampler::new(quality, input_rate, output_rate);
+                assert!(!resampler.is_passthrough());
+                let mut output: Vec<f32> = Vec::new();
+                let frame = vec![0.5f32; input_rate as usize / 50];
+                for _ in 0..50 {
+                    resampler.resample(frame.iter().copied(), &mut output);
+                }
+                let expected = output_rate as usize;
+                assert!(output.len() <= expected && output.len() + 2 * 16 >= expected,
+                        "{:?} {} -> {}: {}", quality, input_rate, output_rate, output.len());
+                // a constant signal remains constant after the initial kernel lag
+                assert!(output[32..].iter().all(|&s| (s - 0.5).abs() < 1e-4), "{:?}", quality);
+                resampler.reset();
+                let mut output: Vec<f32> = Vec::new();
+                resampler.resample([1.0, 0.0].iter().copied(), &mut output);
+                assert!(output.len() <= 2);
+            }
+        }
+        let mut resampler = Resampler::new(ResampleQuality::Linear, 1, 2);
+        let mut output: Vec<f32> = Vec::new();
+        resampler.resample([0.0, 1.0, 0.0].iter().copied(), &mut output);
+        assert_eq!(output, [0.0, 0.5, 1.0, 0.5]);
+        let mut resampler = Resampler::new(ResampleQuality::Nearest, 2, 1);
+        let mut output: Vec<f32> = Vec::new();
+        resampler.resample([0.0, 1.0, 2.0, 3.0, 4.0, 5.0].iter().copied(), &mut output);
+        assert_eq!(output, [0.0, 2.0, 4.0]);
+    }
+}
@@ -339,6 +339,11 @@ where T: Copy + Default + SampleDelta + MulNorm
         self.set_frame_time(frame_time, margin_time);
     }
 
+    #[inline]
+    fn channels(&self) -> usize {
+        self.channels.get()
+    }
+
     #[inline]
     fn end_frame(&mut self, timestamp: FTs) -> usize {
         debug_assert!(timestamp > 0);
@@ -72,6 +72,11 @@ pub trait Blep {
     /// to the last [Blep::end_frame] to `timestamp` - `frame_ts`. In other words, the next frame starts
     /// when the previous ends minus frame duration.
     fn ensure_frame_time(&mut self, sample_rate: u32, ts_rate: f64, frame_ts: FTs, margin_ts: FTs);
+    /// Returns the number of output audio channels.
+    ///
+    /// The `channel` argument passed to [Blep::add_step] must be less than the returned value.
+    /// The default implementation returns `usize::MAX`, accepting any `channel`.
+    fn channels(&self) -> usize { usize::MAX }
     /// This method is being used to add square-wave pulse steps within a boundary of a single frame.
     ///
     //  * `channel` specifies an output audio channel.
@@ -80,6 +85,17 @@ pub trait Blep {
     ///
     /// The implementation may panic if `timestamp` boundary limits are not uphold.
     fn add_step(&mut self, channel: usize, timestamp: FTs, delta: Self::SampleDelta);
+    /// This method is being used to add a batch of square-wave pulse steps to a single `channel`.
+    ///
+    /// * `steps` contains pairs of a time stamp and a pulse height (∆ amplitude), ordered by time stamps.
+    ///
+    /// The default implementation calls [Blep::add_step] for each step. Implementations may override it
+    /// to process runs of steps more efficiently, as long as the result is the same.
+    fn add_steps(&mut self, channel: usize, steps: &[(FTs, Self::SampleDelta)]) {
+        for &(timestamp, delta) in steps {
+            self.add_step(channel, timestamp, delta)
+        }
+    }
     /// Finalizes audio frame.
     ///
     /// Some frames can end little late or earlier and this method should allow for such a flexibility.
@@ -177,6 +193,8 @@ pub trait EarMicOutAudioFrame<B: Blep> {
     ///    1    0     2
     ///    1    1     3
     ///```
+    /// [EarMicAmps4] and [EarOutAmps4] provide ready-made amplitudes for the beeper.
+    ///
     /// `channel` - target [Blep] audio channel.
     fn render_earmic_out_audio_frame<V: AmpLevels<B::SampleDelta>>(&self, blep: &mut B, channel: usize);
 }
@@ -188,6 +206,26 @@ pub trait EarInAudioFrame<B: Blep> {
     /// Provide [AmpLevels] that can handle `level` values from 0 to 1 (1-bit).
     /// `channel` - target [Blep] audio channel.
     fn render_ear_in_audio_frame<V: AmpLevels<B::SampleDelta>>(&self, blep: &mut B, channel: usize);
+    /// Renders EAR input as band-limited pulses via [Blep] interface, spreading each transition
+    /// of the input level over `transition_ts` T-states.
+    ///
+    /// This reduces the aliasing of noisy tape audio when monitoring tape loading. For the accurate
+    /// square-wave rendering, use [EarInAudioFrame::render_ear_in_audio_frame].
+    ///
+    /// Provide [AmpLevels] that can handle `level` values from 0 to 1 (1-bit).
+    /// `channel` - target [Blep] audio channel.
+    ///
+    /// The default implementation ignores `transition_ts` and renders square-wave pulses.
+    fn render_ear_in_audio_frame_smooth<V: AmpLevels<B::SampleDelta>>(
+            &self,
+            blep: &mut B,
+            channel: usize,
+            _transition_ts: u32
+        )
+        where B::SampleDelta: MulNorm + FromSample<f32>
+    {
+        self.render_ear_in_audio_frame::<V>(blep, channel)
+    }
 }
 
 /*
@@ -212,11 +250,18 @@ pub const AMPS_EAR_IN_I16:  [i16; 2] = [0x0bc3, 0x16d5];
 /// Implements [AmpLevels] trait, useful when rendering combined EAR OUT and MIC OUT audio signal.
 ///
 /// Uses 2 lowest bits of a given `level`.
+///
+/// The amplitudes are proportional to the voltages measured on the Issue 3 ULA output, relative to the
+/// highest one: `0.34 V` (none), `0.66 V` (MIC), `3.56 V` (EAR) and `3.70 V` (EAR and MIC), so the
+/// amplitude swing of the EAR OUT is about 10 times larger than the one of the MIC OUT. See [AMPS_EAR_MIC].
 #[derive(Clone, Default, Debug)]
 pub struct EarMicAmps4<T>(PhantomData<T>);
 /// Implements [AmpLevels] trait, useful when rendering EAR OUT audio ignoring MIC OUT signal.
 ///
 /// Uses 2 lowest bits of a given `level`, but ignores the lowest bit.
+///
+/// The amplitudes are proportional to the voltages measured on the Issue 3 ULA output, relative to the
+/// highest one: `0.34 V` (EAR low) and `3.70 V` (EAR high). See [AMPS_EAR_OUT].
 #[derive(Clone, Default, Debug)]
 pub struct EarOutAmps4<T>(PhantomData<T>);
 /// Implements [AmpLevels] trait, useful when rendering EAR IN audio.
@@ -253,6 +298,36 @@ impl_amp_levels!([f32, AMPS_EAR_MIC, AMPS_EAR_OUT, AMPS_EAR_IN],
                  [i32, AMPS_EAR_MIC_I32, AMPS_EAR_OUT_I32, AMPS_EAR_IN_I32],
                  [i16, AMPS_EAR_MIC_I16, AMPS_EAR_OUT_I16, AMPS_EAR_IN_I16]);
 
+/// Implements [AmpLevels] trait by scaling amplitudes of the wrapped [AmpLevels] implementation `A`
+/// by the ratio: `NUM / DEN`.
+///
+/// Can be used as a static volume control of a single audio source, e.g. `GainAmpLevels<A, 1, 2>`
+/// halves the amplitudes of `A`. Integer amplitudes are clamped to the range of the sample type.
+///
+/// `DEN` must not be `0`.
+#[derive(Clone, Default, Debug)]
+pub struct GainAmpLevels<A, const NUM: u32, const DEN: u32>(PhantomData<A>);
+
+impl<A: AmpLevels<f32>, const NUM: u32, const DEN: u32> AmpLevels<f32> for GainAmpLevels<A, NUM, DEN> {
+    #[inline(always)]
+    fn amp_level(level: u32) -> f32 {
+        A::amp_level(level) * (NUM as f32 / DEN as f32)
+    }
+}
+
+macro_rules! impl_gain_amp_levels {
+    ($($ty:ty),*) => { $(
+        impl<A: AmpLevels<$ty>, const NUM: u32, const DEN: u32> AmpLevels<$ty> for GainAmpLevels<A, NUM, DEN> {
+            #[inline(always)]
+            fn amp_level(level: u32) -> $ty {
+                let amp = (A::amp_level(level) as i64).saturating_mul(NUM as i64) / DEN as i64;
+                amp.clamp(<$ty>::MIN as i64, <$ty>::MAX as i64) as $ty
+            }
+        }
+    )* };
+}
+impl_gain_amp_levels!(i32, i16);
+
 impl<B: Blep> BlepAmpFilter<B> {
     pub fn build(filter: B::SampleDelta) -> impl FnOnce(B) -> Self
     {
@@ -310,6 +385,10 @@ impl<B> Blep for BlepAmpFilter<B>
         self.blep.ensure_frame_time(sample_rate, ts_rate, frame_ts, margin_ts)
     }
     #[inline]
+    fn channels(&self) -> usize {
+        self.blep.channels()
+    }
+    #[inline]
     fn end_frame(&mut self, timestamp: FTs) -> usize {
         self.blep.end_frame(timestamp)
     }
@@ -359,6 +438,8 @@ pub fn render_audio_frame_vts<VF,VL,L,A,T>(
           A: Blep<SampleDelta=L>,
           T: Copy, (VideoTs, u8): From<T>,
 {
+    debug_assert!(channel < blep.channels(),
+        "audio channel {} out of range, the Blep has {} channel(s)", channel, blep.channels());
     let mut last_vol = VL::amp_level(prev_state.into());
     for &tsd in changes.iter() {
         let (ts, state) = tsd.into();
@@ -377,6 +458,80 @@ pub fn render_audio_frame_vts<VF,VL,L,A,T>(
     }
 }
 
+/// The maximum number of pulse steps each transition is being split into by [render_smooth_audio_frame_vts].
+pub const SMOOTH_TRANSITION_STEPS_MAX: u32 = 8;
+
+/// A helper method for rendering band-limited audio from slices containing updates of audio
+/// digital levels, sorted by time encoded in [VideoTs] time stamps.
+///
+/// Unlike [render_audio_frame_vts], each transition between levels is rendered as a linear ramp,
+/// approximated with up to [SMOOTH_TRANSITION_STEPS_MAX] pulse steps spread over `transition_ts` T-states.
+/// A transition is cut short by the next change and completed no later than at `end_ts`.
+pub fn render_smooth_audio_frame_vts<VF,VL,L,A,T>(
+            prev_state: u8,
+            end_ts: Option<VFrameTs<VF>>,
+            changes: &[T],
+            blep: &mut A, channel: usize,
+            transition_ts: u32
+        )
+    where VF: VideoFrame,
+          VL: AmpLevels<L>,
+          L: SampleDelta + MulNorm + FromSample<f32>,
+          A: Blep<SampleDelta=L>,
+          T: Copy, (VideoTs, u8): From<T>,
+{
+    debug_assert!(channel < blep.channels(),
+        "audio channel {} out of range, the Blep has {} channel(s)", channel, blep.channels());
+    let end_ts = end_ts.map(|vts| vts.into_tstates());
+    let mut changes = changes.iter().map(|&tsd| {
+        let (ts, state) = tsd.into();
+        (VFrameTs::<VF>::from(ts).into_tstates(), state)
+    })
+    .take_while(|&(ts, _)| match end_ts {
+        Some(end_ts) => ts < end_ts,
+        None => true
+    })
+    .peekable();
+    let steps = transition_ts.clamp(1, SMOOTH_TRANSITION_STEPS_MAX);
+    let mut last_vol = VL::amp_level(prev_state.into());
+    while let Some((ts, state)) = changes.next() {
+        let start_vol = last_vol;
+        let next_vol = VL::amp_level(state.into());
+        let limit_ts = changes.peek().map(|&(ts, _)| ts);
+        for step in 1..=steps {
+            let mut step_ts = ts + (u64::from(transition_ts) * u64::from(step - 1) / u64::from(steps)) as FTs;
+            if let Some(limit_ts) = limit_ts {
+                if step_ts >= limit_ts {
+                    break
+                }
+            }
+            let mut vol = if step == steps {
+                next_vol
+            }
+            else {
+                let t = step as f32 / steps as f32;
+                start_vol.mul_norm(L::from_sample(1.0 - t))
+                         .saturating_add(next_vol.mul_norm(L::from_sample(t)))
+            };
+            let mut is_cut = false;
+            if let Some(end_ts) = end_ts {
+                if step_ts >= end_ts {
+                    step_ts = end_ts - 1;
+                    vol = next_vol;
+                    is_cut = true;
+                }
+            }
+            if let Some(delta) = last_vol.sample_delta(vol) {
+                blep.add_step(channel, step_ts, delta);
+                last_vol = vol;
+            }
+            if is_cut {
+                break
+            }
+        }
+    }
+}
+
 /// A helper method for rendering square-wave audio from slices containing updates of audio
 /// digital levels, sorted by T-state counter value.
 pub fn render_audio_frame_ts<VL,L,A,T>(
@@ -391,6 +546,8 @@ pub fn render_audio_frame_ts<VL,L,A,T>(
           A: Blep<SampleDelta=L>,
           T: Copy, (FTs, u8): From<T>,
 {
+    debug_assert!(channel < blep.channels(),
+        "audio channel {} out of range, the Blep has {} channel(s)", channel, blep.channels());
     let mut last_vol = VL::amp_level(prev_state.into());
     for &tsd in changes.iter() {
         let (ts, state) = tsd.into();
@@ -407,3 +564,62 @@ pub fn render_audio_frame_ts<VL,L,A,T>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gain_amp_levels_works() {
+        for level in 0..4 {
+            assert_eq!(GainAmpLevels::<EarMicAmps4<f32>, 1, 1>::amp_level(level),
+                       EarMicAmps4::<f32>::amp_level(level));
+            assert_eq!(GainAmpLevels::<EarMicAmps4<f32>, 1, 2>::amp_level(level),
+                       EarMicAmps4::<f32>::amp_level(level) / 2.0);
+            assert_eq!(GainAmpLevels::<EarMicAmps4<i16>, 1, 4>::amp_level(level),
+                       EarMicAmps4::<i16>::amp_level(level) / 4);
+            assert_eq!(GainAmpLevels::<EarMicAmps4<i32>, 3, 10>::amp_level(level),
+                       (EarMicAmps4::<i32>::amp_level(level) as i64 * 3 / 10) as i32);
+            assert_eq!(GainAmpLevels::<EarMicAmps4<i16>, 0, 1>::amp_level(level), 0);
+        }
+        assert_eq!(GainAmpLevels::<EarOutAmps4<i16>, 4, 1>::amp_level(3), i16::MAX);
+        assert_eq!(GainAmpLevels::<EarOutAmps4<i32>, 4, 1>::amp_level(3), i32::MAX);
+        assert_eq!(GainAmpLevels::<EarOutAmps4<f32>, 4, 1>::amp_level(3), EarOutAmps4::<f32>::amp_level(3) * 4.0);
+        assert_eq!(GainAmpLevels::<GainAmpLevels<EarOutAmps4<i16>, 1, 2>, 1, 2>::amp_level(3),
+                   EarOutAmps4::<i16>::amp_level(3) / 4);
+    }
+
+    #[derive(Default)]
+    struct TestBlep(Vec<(usize, FTs, f32)>);
+
+    impl Blep for TestBlep {
+        type SampleDelta = f32;
+        fn ensure_frame_time(&mut self, _sample_rate: u32, _ts_rate: f64, _frame_ts: FTs, _margin_ts: FTs) {}
+        fn channels(&self) -> usize { 2 }
+        fn end_frame(&mut self, _timestamp: FTs) -> usize { 0 }
+        fn add_step(&mut self, channel: usize, timestamp: FTs, delta: f32) {
+            self.0.push((channel, timestamp, delta));
+        }
+    }
+
+    #[test]
+    fn blep_channels_works() {
+        let mut blep = TestBlep::default();
+        render_audio_frame_ts::<EarOutAmps4<f32>,_,_,_>(0, None, &[(10, 3u8)], &mut blep, 1);
+        assert_eq!(blep.0.iter().map(|&(chan, ts, _)| (chan, ts)).collect::<Vec<_>>(), [(1, 10)]);
+        let blep = BlepAmpFilter::new(0.5, TestBlep::default());
+        assert_eq!(blep.channels(), 2);
+        let mut blep = BlepStereo::new(0.5, TestBlep::default());
+        assert_eq!(blep.channels(), usize::MAX);
+        render_audio_frame_ts::<EarOutAmps4<f32>,_,_,_>(0, None, &[(10, 3u8)], &mut blep, 2);
+        assert_eq!(blep.blep.0.len(), 2);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "audio channel 2 out of range")]
+    fn blep_channel_out_of_range_panics() {
+        let mut blep = TestBlep::default();
+        render_audio_frame_ts::<EarOutAmps4<f32>,_,_,_>(0, None, &[(10, 3u8)], &mut blep, 2);
+    }
+}
@@ -167,6 +167,59 @@ pub trait BusDevice: Debug {
     fn type_id(&self) -> TypeId where Self: 'static {
         TypeId::of::<Self>()
     }
+    /// Searches the daisy chain for the first device of a type given as parameter `B`, starting from
+    /// `self`, returning a reference to a device.
+    ///
+    /// The bus wrappers: [OptionalBusDevice] and [DynamicBus] search their attached devices before
+    /// forwarding the search to the next device, so the device can be found regardless of how deeply
+    /// it's nested.
+    ///
+    /// Default implementation checks `self` and forwards this call to the next device.
+    #[inline]
+    fn find_device_by_type_ref<B: 'static>(&self) -> Option<&B>
+        where Self: Sized + 'static
+    {
+        cast_device_ref(self).or_else(|| self.next_device_ref().find_device_by_type_ref())
+    }
+    /// Searches the daisy chain for the first device of a type given as parameter `B`, starting from
+    /// `self`, returning a mutable reference to a device.
+    ///
+    /// See [BusDevice::find_device_by_type_ref] for more details.
+    ///
+    /// Default implementation checks `self` and forwards this call to the next device.
+    #[inline]
+    fn find_device_by_type_mut<B: 'static>(&mut self) -> Option<&mut B>
+        where Self: Sized + 'static
+    {
+        if TypeId::of::<Self>() == TypeId::of::<B>() {
+            return cast_device_mut(self)
+        }
+        self.next_device_mut().find_device_by_type_mut()
+    }
+}
+
+/// Returns `device` as `B` if `D` is the same type as `B`.
+#[inline(always)]
+fn cast_device_ref<D: 'static, B: 'static>(device: &D) -> Option<&B> {
+    if TypeId::of::<D>() == TypeId::of::<B>() {
+        unsafe {
+            Some(&*(device as *const D as *const B))
+        }
+    } else {
+        None
+    }
+}
+
+/// Returns `device` as `B` if `D` is the same type as `B`.
+#[inline(always)]
+fn cast_device_mut<D: 'static, B: 'static>(device: &mut D) -> Option<&mut B> {
+    if TypeId::of::<D>() == TypeId::of::<B>() {
+        unsafe {
+            Some(&mut *(device as *mut D as *mut B))
+        }
+    } else {
+        None
+    }
 }
 
 impl<D: BusDevice> BusDevice for Box<D> {
@@ -205,6 +258,21 @@ impl<D: BusDevice> BusDevice for Box<D> {
     fn write_io(&mut self, port: u16, data: u8, timestamp: Self::Timestamp) -> Option<u16> {
         (**self).write_io(port, data, timestamp)
     }
+    #[inline]
+    fn find_device_by_type_ref<B: 'static>(&self) -> Option<&B>
+        where Self: 'static
+    {
+        cast_device_ref(self).or_else(|| (**self).find_device_by_type_ref())
+    }
+    #[inline]
+    fn find_device_by_type_mut<B: 'static>(&mut self) -> Option<&mut B>
+        where Self: 'static
+    {
+        if TypeId::of::<Self>() == TypeId::of::<B>() {
+            return cast_device_mut(self)
+        }
+        (**self).find_device_by_type_mut()
+    }
 }
 
 /// A helper trait for matching I/O port addresses.
@@ -270,6 +338,20 @@ impl<T> BusDevice for NullDevice<T> {
     fn write_io(&mut self, _port: u16, _data: u8, _timestamp: Self::Timestamp) -> Option<u16> {
         None
     }
+
+    #[inline]
+    fn find_device_by_type_ref<B: 'static>(&self) -> Option<&B>
+        where Self: 'static
+    {
+        cast_device_ref(self)
+    }
+
+    #[inline]
+    fn find_device_by_type_mut<B: 'static>(&mut self) -> Option<&mut B>
+        where Self: 'static
+    {
+        cast_device_mut(self)
+    }
 }
 
 impl<T> fmt::Debug for NullDevice<T> {
@@ -305,6 +387,34 @@ impl<D, N> OptionalBusDevice<D, N>
     }
 }
 
+impl<D, N> OptionalBusDevice<D, N> {
+    /// Returns `true` if the device is currently "plugged in".
+    #[inline]
+    pub fn is_attached(&self) -> bool {
+        self.device.is_some()
+    }
+    /// Returns an optional reference to the "plugged in" device.
+    #[inline]
+    pub fn device_ref(&self) -> Option<&D> {
+        self.device.as_ref()
+    }
+    /// Returns an optional mutable reference to the "plugged in" device.
+    #[inline]
+    pub fn device_mut(&mut self) -> Option<&mut D> {
+        self.device.as_mut()
+    }
+    /// "Unplugs" the device, returning it if it was present.
+    #[inline]
+    pub fn take_device(&mut self) -> Option<D> {
+        self.device.take()
+    }
+    /// "Plugs in" the given `device`, returning the previous one if it was present.
+    #[inline]
+    pub fn replace_device(&mut self, device: D) -> Option<D> {
+        self.device.replace(device)
+    }
+}
+
 impl<D, N> Deref for OptionalBusDevice<D, N> {
     type Target = Option<D>;
     fn deref(&self) -> &Self::Target {
@@ -389,6 +499,30 @@ impl<D, N> BusDevice for OptionalBusDevice<D, N>
         }
         self.next_device.write_io(port, data, timestamp)
     }
+    /// # Note
+    /// The search is forwarded to the "plugged in" device before the next device.
+    #[inline]
+    fn find_device_by_type_ref<B: 'static>(&self) -> Option<&B>
+        where Self: 'static
+    {
+        cast_device_ref(self)
+        .or_else(|| self.device.as_ref().and_then(|dev| dev.find_device_by_type_ref()))
+        .or_else(|| self.next_device.find_device_by_type_ref())
+    }
+    /// # Note
+    /// The search is forwarded to the "plugged in" device before the next device.
+    #[inline]
+    fn find_device_by_type_mut<B: 'static>(&mut self) -> Option<&mut B>
+        where Self: 'static
+    {
+        if TypeId::of::<Self>() == TypeId::of::<B>() {
+            return cast_device_mut(self)
+        }
+        if let Some(device) = self.device.as_mut().and_then(|dev| dev.find_device_by_type_mut()) {
+            return Some(device)
+        }
+        self.next_device.find_device_by_type_mut()
+    }
 }
 
 impl<D, N> fmt::Display for OptionalBusDevice<D, N>
@@ -6,6 +6,7 @@
     For the full copyright notice, see the lib.rs file.
 */
 use core::mem;
+use core::any::TypeId;
 use core::num::NonZeroU16;
 use core::fmt::{Display, Debug};
 use core::iter::IntoIterator;
@@ -20,7 +21,7 @@ mod serde;
 #[cfg(feature = "snapshot")]
 pub use self::serde::*;
 
-use super::{BusDevice, VFNullDevice, NullDevice};
+use super::{BusDevice, VFNullDevice, NullDevice, cast_device_ref, cast_device_mut};
 
 /// A trait for dynamic bus devices, which currently includes methods from [Display] and [BusDevice].
 /// Devices implementing this trait can be used with a [DynamicBus].
@@ -51,6 +52,18 @@ pub type DynamicVBus<V> = DynamicBus<VFNullDevice<V>>;
 ///
 /// Currently only types implementing [BusDevice] that are directly terminated with [NullDevice]
 /// can be attached as dynamically dispatched objects.
+///
+/// # Ordering
+///
+/// The lifecycle methods: [BusDevice::reset], [BusDevice::update_timestamp] and [BusDevice::next_frame]
+/// are called on every attached device in the order of their index positions, starting from `0`,
+/// and then on the downstream device `D`.
+///
+/// [BusDevice::read_io] and [BusDevice::write_io] are forwarded in the same order. A write is consumed
+/// by the first device that handles it, and a read stops at the first device that requests wait states.
+///
+/// Use [DynamicBus::append_device_front] or [DynamicBus::insert_device] to attach devices that must be
+/// handled before the others, e.g. devices paging memory in or out.
 #[derive(Default, Debug)]
 #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 pub struct DynamicBus<D: BusDevice> {
@@ -99,11 +112,46 @@ impl<D> DynamicBus<D>
         self.devices.push(device.into());
         self.devices.len() - 1
     }
+    /// Inserts an instance of a `device` at the front of the daisy-chain, shifting all other devices.
+    /// Returns its index position in the dynamic device chain, which is always `0`.
+    ///
+    /// The device will be handled before all the other attached devices. See [DynamicBus] ordering.
+    pub fn append_device_front<B>(&mut self, device: B) -> usize
+        where B: Into<BoxNamedDynDevice<D::Timestamp>>
+    {
+        self.insert_device(0, device)
+    }
     /// Removes the last device from the dynamic daisy-chain and returns an instance of the boxed
     /// dynamic object.
     pub fn remove_device(&mut self) -> Option<BoxNamedDynDevice<D::Timestamp>> {
         self.devices.pop()
     }
+    /// Inserts an instance of a `device` at the given `index` position of the daisy-chain, shifting
+    /// all devices after it. If `index` is larger than the number of attached devices, the `device`
+    /// is appended at the end of the chain.
+    /// Returns its index position in the dynamic device chain.
+    ///
+    /// **NOTE**: The order of devices determines the order in which [BusDevice] methods are being called,
+    /// e.g. [BusDevice::reset], [BusDevice::next_frame], and which device responds first to an I/O write.
+    pub fn insert_device<B>(&mut self, index: usize, device: B) -> usize
+        where B: Into<BoxNamedDynDevice<D::Timestamp>>
+    {
+        let index = index.min(self.devices.len());
+        self.devices.insert(index, device.into());
+        index
+    }
+    /// Removes a device at the given `index` position from the dynamic daisy-chain, shifting all devices
+    /// after it, and returns an instance of the boxed dynamic object.
+    ///
+    /// Returns `None` if a device doesn't exist at `index`.
+    pub fn remove_device_at(&mut self, index: usize) -> Option<BoxNamedDynDevice<D::Timestamp>> {
+        if index < self.devices.len() {
+            Some(self.devices.remove(index))
+        }
+        else {
+            None
+        }
+    }
     /// Replaces a device at the given `index` position and returns it.
     /// 
     /// The removed device is replaced by the last device of the chain.
@@ -213,6 +261,20 @@ impl<D> DynamicBus<D>
     {
         self.devices.iter_mut().find_map(|d| d.downcast_mut::<B>())
     }
+    /// Returns an iterator of references to all devices of a type given as parameter `B`.
+    #[inline]
+    pub fn iter_devices_ref<B>(&self) -> impl Iterator<Item=&B>
+        where B: NamedBusDevice<D::Timestamp> + 'static
+    {
+        self.devices.iter().filter_map(|d| d.downcast_ref::<B>())
+    }
+    /// Returns an iterator of mutable references to all devices of a type given as parameter `B`.
+    #[inline]
+    pub fn iter_devices_mut<B>(&mut self) -> impl Iterator<Item=&mut B>
+        where B: NamedBusDevice<D::Timestamp> + 'static
+    {
+        self.devices.iter_mut().filter_map(|d| d.downcast_mut::<B>())
+    }
 }
 
 impl<D: BusDevice> Index<usize> for DynamicBus<D> {
@@ -327,6 +389,45 @@ impl<D> BusDevice for DynamicBus<D>
         }
         self.bus.write_io(port, data, timestamp)
     }
+    /// # Note
+    /// The search is forwarded to the dynamic devices in the order of their index positions
+    /// before the downstream device.
+    #[inline]
+    fn find_device_by_type_ref<B: 'static>(&self) -> Option<&B>
+        where Self: 'static
+    {
+        cast_device_ref(self)
+        .or_else(|| self.devices.iter().find_map(|dev| {
+            if BusDevice::type_id(&**dev) == TypeId::of::<B>() {
+                unsafe {
+                    Some(&*(&**dev as *const NamedDynDevice<D::Timestamp> as *const B))
+                }
+            }
+            else {
+                None
+            }
+        }))
+        .or_else(|| self.bus.find_device_by_type_ref())
+    }
+    /// # Note
+    /// The search is forwarded to the dynamic devices in the order of their index positions
+    /// before the downstream device.
+    #[inline]
+    fn find_device_by_type_mut<B: 'static>(&mut self) -> Option<&mut B>
+        where Self: 'static
+    {
+        if TypeId::of::<Self>() == TypeId::of::<B>() {
+            return cast_device_mut(self)
+        }
+        for dev in self.devices.iter_mut() {
+            if BusDevice::type_id(&**dev) == TypeId::of::<B>() {
+                return unsafe {
+                    Some(&mut *(&mut **dev as *mut NamedDynDevice<D::Timestamp> as *mut B))
+                }
+            }
+        }
+        self.bus.find_device_by_type_mut()
+    }
 }
 
 #[cfg(test)]
@@ -442,4 +543,88 @@ mod tests {
             bus: NullDevice::<i32>::default()
         });
     }
+
+    #[test]
+    fn dynamic_bus_insert_remove_at_works() {
+        let mut dchain: DynamicBus<NullDevice<i32>> = Default::default();
+        assert!(dchain.remove_device_at(0).is_none());
+        assert_eq!(dchain.insert_device(5, TestDevice::default()), 0);
+        assert_eq!(dchain.insert_device(0, NullDevice::default()), 0);
+        assert_eq!(dchain.insert_device(2, TestDevice { foo: 1, ..Default::default() }), 2);
+        assert_eq!(dchain.insert_device(1, NullDevice::default()), 1);
+        assert_eq!(dchain.len(), 4);
+        assert!(dchain.is_device::<NullDevice<_>>(0));
+        assert!(dchain.is_device::<NullDevice<_>>(1));
+        assert!(dchain.is_device::<TestDevice>(2));
+        assert!(dchain.is_device::<TestDevice>(3));
+        assert_eq!(dchain.as_device_ref::<TestDevice>(2).foo, 0);
+        assert_eq!(dchain.as_device_ref::<TestDevice>(3).foo, 1);
+        assert!(dchain.remove_device_at(4).is_none());
+        let device = dchain.remove_device_at(2).unwrap();
+        assert!(device.is::<TestDevice>());
+        assert_eq!(dchain.len(), 3);
+        assert_eq!(dchain.as_device_ref::<TestDevice>(2).foo, 1);
+        assert!(dchain.remove_device_at(0).unwrap().is::<NullDevice<_>>());
+        assert_eq!(dchain.len(), 2);
+        assert!(dchain.is_device::<NullDevice<_>>(0));
+        assert!(dchain.is_device::<TestDevice>(1));
+    }
+
+    #[test]
+    fn dynamic_bus_append_device_front_works() {
+        let mut dchain: DynamicBus<NullDevice<i32>> = Default::default();
+        assert_eq!(dchain.append_device_front(TestDevice { foo: 1, ..Default::default() }), 0);
+        assert_eq!(dchain.append_device(NullDevice::default()), 1);
+        assert_eq!(dchain.append_device_front(TestDevice { foo: 2, ..Default::default() }), 0);
+        assert_eq!(dchain.len(), 3);
+        assert_eq!(dchain.as_device_ref::<TestDevice>(0).foo, 2);
+        assert_eq!(dchain.as_device_ref::<TestDevice>(1).foo, 1);
+        assert!(dchain.is_device::<NullDevice<_>>(2));
+        // the first device consumes the write
+        assert_eq!(dchain.write_io(0, 42, 7), Some(0));
+        assert_eq!(dchain.as_device_ref::<TestDevice>(0).data, 42);
+        assert_eq!(dchain.as_device_ref::<TestDevice>(1).data, 0);
+        assert_eq!(dchain.read_io(0, 7), Some((42, None)));
+    }
+
+    #[test]
+    fn dynamic_bus_iter_devices_works() {
+        let mut dchain: DynamicBus<NullDevice<i32>> = Default::default();
+        assert_eq!(dchain.iter_devices_ref::<TestDevice>().count(), 0);
+        dchain.append_device(TestDevice { foo: 1, ..Default::default() });
+        dchain.append_device(NullDevice::default());
+        dchain.append_device(TestDevice { foo: 2, ..Default::default() });
+        assert_eq!(dchain.iter_devices_ref::<NullDevice<_>>().count(), 1);
+        let foos: Vec<i32> = dchain.iter_devices_ref::<TestDevice>().map(|d| d.foo).collect();
+        assert_eq!(foos, [1, 2]);
+        for dev in dchain.iter_devices_mut::<TestDevice>() {
+            dev.data = 42;
+        }
+        assert_eq!(dchain.as_device_ref::<TestDevice>(0).data, 42);
+        assert_eq!(dchain.as_device_ref::<TestDevice>(2).data, 42);
+    }
+
+    #[test]
+    fn find_device_by_type_works() {
+        use super::super::OptionalBusDevice;
+        let mut dchain: DynamicBus<NullDevice<i32>> = Default::default();
+        assert!(dchain.find_device_by_type_mut::<TestDevice>().is_none());
+        assert!(dchain.find_device_by_type_ref::<DynamicBus<NullDevice<i32>>>().is_some());
+        assert!(dchain.find_device_by_type_ref::<NullDevice<i32>>().is_some());
+        dchain.append_device(NullDevice::default());
+        dchain.append_device(TestDevice { foo: 1, ..Default::default() });
+        dchain.append_device(TestDevice { foo: 2, ..Default::default() });
+        let mut chain = OptionalBusDevice::new(None, Box::new(dchain));
+        assert_eq!(chain.find_device_by_type_ref::<TestDevice>().unwrap().foo, 1);
+        chain.find_device_by_type_mut::<TestDevice>().unwrap().data = 42;
+        assert_eq!(chain.next_device.as_device_ref::<TestDevice>(1).data, 42);
+        assert_eq!(chain.next_device.as_device_ref::<TestDevice>(2).data, 0);
+        // the "plugged in" device is found first
+        chain.replace_device(TestDevice { foo: 3, ..Default::default() });
+        assert_eq!(chain.find_device_by_type_mut::<TestDevice>().unwrap().foo, 3);
+        assert!(chain.find_device_by_type_ref::<DynamicBus<NullDevice<i32>>>().is_some());
+        assert!(chain.find_device_by_type_mut::<Box<DynamicBus<NullDevice<i32>>>>().is_some());
+        assert!(chain.find_device_by_type_ref::<OptionalBusDevice<TestDevice, Box<DynamicBus<NullDevice<i32>>>>>().is_some());
+        assert!(chain.find_device_by_type_mut::<DynamicBus<NullDevice<u8>>>().is_none());
+    }
 }
@@ -6,6 +6,7 @@
     For the full copyright notice, see the lib.rs file.
 */
 #![allow(clippy::borrowed_box)]
+use core::any::TypeId;
 use core::fmt::{self, Debug};
 use core::ops::{Deref, DerefMut};
 use core::marker::PhantomData;
@@ -16,7 +17,7 @@ use ::serde::{
 };
 use crate::clock::TimestampOps;
 use super::*;
-use super::super::{VFNullDevice, BusDevice};
+use super::super::{VFNullDevice, BusDevice, cast_device_ref, cast_device_mut};
 
 /// This trait needs to be implemented by a type provided to [DynamicSerdeBus] as a parameter `S`,
 /// to serialize dynamic devices.
@@ -42,6 +43,106 @@ pub trait DeserializeDynDevice<'de> {
     ) -> Result<Box<dyn NamedBusDevice<T>>, D::Error>;
 }
 
+#[doc(hidden)]
+pub use ::serde as __serde;
+
+/// Declares a registry type of dynamic devices implementing [SerializeDynDevice] and [DeserializeDynDevice],
+/// to be used as a parameter `S` of [DynamicSerdeBus].
+///
+/// Each registered device type is assigned a stable tag. A device is serialized as a 2-element tuple:
+/// `(tag, device)`. When deserialized, the tag determines the type of the device being restored.
+/// The registered device types must implement [Serialize] and [Deserialize].
+///
+/// Serializing a device of an unregistered type or deserializing a device with an unknown tag
+/// results in an error.
+///
+/// The identifier given in angle brackets names the timestamp type parameter, which can be used
+/// in the device types.
+///
+/// # Example
+/// ```
+/// use spectrusty_core::{dyn_device_registry, bus::{NullDevice, DynamicSerdeBus}};
+///
+/// dyn_device_registry! {
+///     /// Dynamic devices of my emulator.
+///     pub struct MyDevices<T> {
+///         "null" => NullDevice<T>,
+///     }
+/// }
+///
+/// type MyDynamicBus = DynamicSerdeBus<MyDevices, NullDevice<i32>>;
+/// ```
+#[macro_export]
+macro_rules! dyn_device_registry {
+    ($(#[$attr:meta])* $vis:vis struct $name:ident<$ts:ident> {
+        $($tag:literal => $device:ty),* $(,)?
+    }) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Default, Debug)]
+        $vis struct $name;
+
+        impl $crate::bus::SerializeDynDevice for $name {
+            fn serialize_dyn_device<$ts, S>(
+                    device: &::std::boxed::Box<dyn $crate::bus::NamedBusDevice<$ts>>,
+                    serializer: S
+                ) -> ::core::result::Result<S::Ok, S::Error>
+                where $ts: $crate::clock::TimestampOps + $crate::bus::__serde::Serialize + 'static,
+                      S: $crate::bus::__serde::Serializer
+            {
+                use $crate::bus::__serde::ser::{Error, SerializeTuple};
+                $(
+                    if let ::core::option::Option::Some(device) = device.downcast_ref::<$device>() {
+                        let mut tuple = serializer.serialize_tuple(2)?;
+                        tuple.serialize_element($tag)?;
+                        tuple.serialize_element(device)?;
+                        return tuple.end()
+                    }
+                )*
+                ::core::result::Result::Err(S::Error::custom(::core::format_args!("unregistered dynamic device: {}", device)))
+            }
+        }
+
+        impl<'de> $crate::bus::DeserializeDynDevice<'de> for $name {
+            fn deserialize_dyn_device<$ts, D>(
+                    deserializer: D
+                ) -> ::core::result::Result<::std::boxed::Box<dyn $crate::bus::NamedBusDevice<$ts>>, D::Error>
+                where $ts: ::core::default::Default + $crate::clock::TimestampOps + $crate::bus::__serde::Deserialize<'de> + 'static,
+                      D: $crate::bus::__serde::Deserializer<'de>
+            {
+                use ::core::{fmt, marker::PhantomData};
+                use $crate::bus::__serde::de::{Error, SeqAccess, Visitor};
+
+                struct DeviceVisitor<$ts>(PhantomData<$ts>);
+
+                impl<'de, $ts> Visitor<'de> for DeviceVisitor<$ts>
+                    where $ts: ::core::default::Default + $crate::clock::TimestampOps + $crate::bus::__serde::Deserialize<'de> + 'static
+                {
+                    type Value = ::std::boxed::Box<dyn $crate::bus::NamedBusDevice<$ts>>;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("a tagged dynamic bus device")
+                    }
+
+                    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> ::core::result::Result<Self::Value, A::Error> {
+                        let tag: ::std::string::String = seq.next_element()?
+                                             .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+                        $(
+                            if tag == $tag {
+                                let device: $device = seq.next_element()?
+                                                         .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+                                return ::core::result::Result::Ok(::std::boxed::Box::new(device))
+                            }
+                        )*
+                        ::core::result::Result::Err(A::Error::unknown_variant(&tag, &[$($tag),*]))
+                    }
+                }
+
+                deserializer.deserialize_tuple(2, DeviceVisitor(PhantomData))
+            }
+        }
+    };
+}
+
 /// A terminated [DynamicSerdeBus] pseudo-device with [`VFNullDevice<V>`][VFNullDevice].
 pub type DynamicSerdeVBus<S, V> = DynamicSerdeBus<S, VFNullDevice<V>>;
 
@@ -294,4 +395,132 @@ impl<S, D: BusDevice> BusDevice for DynamicSerdeBus<S, D>
     fn write_io(&mut self, port: u16, data: u8, timestamp: Self::Timestamp) -> Option<u16> {
         self.0.write_io(port, data, timestamp)
     }
+    #[inline]
+    fn find_device_by_type_ref<B: 'static>(&self) -> Option<&B>
+        where Self: 'static
+    {
+        cast_device_ref(self).or_else(|| self.0.find_device_by_type_ref())
+    }
+    #[inline]
+    fn find_device_by_type_mut<B: 'static>(&mut self) -> Option<&mut B>
+        where Self: 'static
+    {
+        if TypeId::of::<Self>() == TypeId::of::<B>() {
+            return cast_device_mut(self)
+        }
+        self.0.find_device_by_type_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU16;
+    use super::*;
+    use super::super::super::NullDevice;
+
+    #[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+    struct TestDevice<T> {
+        data: u8,
+        #[serde(skip)]
+        bus: NullDevice<T>
+    }
+
+    impl<T> fmt::Display for TestDevice<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("Test Device")
+        }
+    }
+
+    impl<T: Debug> BusDevice for TestDevice<T> {
+        type Timestamp = T;
+        type NextDevice = NullDevice<T>;
+
+        fn next_device_mut(&mut self) -> &mut Self::NextDevice {
+            &mut self.bus
+        }
+        fn next_device_ref(&self) -> &Self::NextDevice {
+            &self.bus
+        }
+        fn into_next_device(self) -> Self::NextDevice {
+            self.bus
+        }
+        fn read_io(&mut self, _port: u16, _timestamp: Self::Timestamp) -> Option<(u8, Option<NonZeroU16>)> {
+            Some((self.data, None))
+        }
+    }
+
+    #[derive(Clone, Default, Debug, Serialize, Deserialize)]
+    struct OtherDevice<T>(NullDevice<T>);
+
+    impl<T> fmt::Display for OtherDevice<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("Other Device")
+        }
+    }
+
+    impl<T: Debug> BusDevice for OtherDevice<T> {
+        type Timestamp = T;
+        type NextDevice = NullDevice<T>;
+
+        fn next_device_mut(&mut self) -> &mut Self::NextDevice {
+            &mut self.0
+        }
+        fn next_device_ref(&self) -> &Self::NextDevice {
+            &self.0
+        }
+        fn into_next_device(self) -> Self::NextDevice {
+            self.0
+        }
+    }
+
+    dyn_device_registry! {
+        struct TestRegistry<T> {
+            "test" => TestDevice<T>,
+            "null" => NullDevice<T>,
+        }
+    }
+
+    // the registry must expand in a scope where the prelude names are shadowed
+    #[allow(dead_code)]
+    mod shadowed {
+        use super::TestDevice;
+        type Result = ();
+        type String = ();
+        type Box = ();
+        type Option = ();
+
+        dyn_device_registry! {
+            pub struct ShadowedRegistry<T> {
+                "test" => TestDevice<T>,
+            }
+        }
+    }
+
+    type TestBus = DynamicSerdeBus<TestRegistry, NullDevice<i32>>;
+
+    #[test]
+    fn dyn_device_registry_works() {
+        let mut dchain = TestBus::default();
+        dchain.append_device(TestDevice { data: 42, ..Default::default() });
+        dchain.append_device(NullDevice::default());
+        dchain.append_device(TestDevice { data: 7, ..Default::default() });
+        let json = serde_json::to_string(&dchain).unwrap();
+        assert_eq!(json, r#"{"bus":null,"devices":[["test",{"data":42}],["null",null],["test",{"data":7}]]}"#);
+        let dchain: TestBus = serde_json::from_str(&json).unwrap();
+        assert_eq!(dchain.len(), 3);
+        assert_eq!(dchain.as_device_ref::<TestDevice<i32>>(0).data, 42);
+        assert!(dchain.is_device::<NullDevice<i32>>(1));
+        assert_eq!(dchain.as_device_ref::<TestDevice<i32>>(2).data, 7);
+        let bin = bincode::serialize(&dchain).unwrap();
+        let dchain: TestBus = bincode::deserialize(&bin).unwrap();
+        assert_eq!(dchain.len(), 3);
+        assert_eq!(dchain.as_device_ref::<TestDevice<i32>>(2).data, 7);
+
+        let err = serde_json::from_str::<TestBus>(r#"{"devices":[["other",null]]}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `other`"));
+        let mut dchain = TestBus::default();
+        dchain.append_device(OtherDevice::default());
+        let err = serde_json::to_string(&dchain).unwrap_err();
+        assert_eq!(err.to_string(), "unregistered dynamic device: Other Device");
+    }
 }
@@ -8,19 +8,23 @@
 //! Chipset emulation building blocks.
 use core::num::NonZeroU32;
 use core::time::Duration;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
 use std::time::Instant;
 
+#[cfg(feature = "std")]
 use z80emu::{CpuDebug, Cpu, host::Result};
 
+#[cfg(feature = "std")]
 use crate::bus::BusDevice;
 use crate::clock::FTs;
+#[cfg(feature = "std")]
 use crate::memory::{ZxMemory, MemoryExtension};
 
 mod flags;
 pub use flags::*;
 
 /// A trait for directly accessing an emulated memory implementation and memory extensions.
+#[cfg(feature = "std")]
 pub trait MemoryAccess {
     type Memory: ZxMemory;
     type MemoryExt: MemoryExtension;
@@ -63,6 +67,7 @@ pub trait FrameState {
 /// This trait provides the interface for running the emulation and accessing instances of peripheral devices.
 ///
 /// It's being implemented by the emulators of core chipsets.
+#[cfg(feature = "std")]
 pub trait ControlUnit {
     /// A type of a chain of emulated peripheral devices should be declared here.
     ///
@@ -120,6 +125,139 @@ pub trait ControlUnit {
             cpu: &mut C,
             debug: Option<F>
     ) -> Result<(), ()>;
+    /// Conditionally prepares the internal state for the next frame and executes instructions on the `cpu`
+    /// until the near end of that frame or until the `PC` register reaches one of the `breakpoints`.
+    ///
+    /// Returns `true` if the frame has been completed. Returns `false` if the execution stopped at a breakpoint
+    /// before executing the instruction at that address.
+    ///
+    /// The first instruction is always executed regardless of the `breakpoints`, so calling this method again
+    /// resumes the execution from the address at which it has stopped.
+    ///
+    /// If `breakpoints` is empty, this method is equivalent to [ControlUnit::execute_next_frame]. Otherwise,
+    /// instructions are executed one by one with [ControlUnit::execute_single_step].
+    fn execute_next_frame_with_breakpoints<C: Cpu>(
+            &mut self,
+            cpu: &mut C,
+            breakpoints: &[u16]
+        ) -> bool
+        where Self: FrameState
+    {
+        if breakpoints.is_empty() {
+            self.execute_next_frame(cpu);
+            return true
+        }
+        const DEBUG: Option<fn(CpuDebug)> = None;
+        loop {
+            let _ = self.execute_single_step(cpu, DEBUG);
+            if self.is_frame_over() {
+                return true
+            }
+            if breakpoints.contains(&cpu.get_pc()) {
+                return false
+            }
+        }
+    }
+    /// Conditionally prepares the internal state for the next frame and executes instructions on the `cpu`
+    /// until the near end of that frame, calling `trap` each time the `PC` register reaches the `address`
+    /// before executing the instruction at that address.
+    ///
+    /// The `trap` function receives the chipset and the `cpu`, so it may alter memory and registers,
+    /// including `PC`. The execution continues from the instruction pointed to by `PC` after the call.
+    /// This allows e.g. to instantly load tape data when the ROM loading routine is being called.
+    ///
+    /// Returns the number of times `trap` has been called.
+    ///
+    /// Instructions are executed one by one with [ControlUnit::execute_single_step], so this method
+    /// is slower than [ControlUnit::execute_next_frame] and should be used only while the trap is needed,
+    /// e.g. when a tape is inserted.
+    fn execute_next_frame_with_trap<C: Cpu, F>(
+            &mut self,
+            cpu: &mut C,
+            address: u16,
+            mut trap: F
+        ) -> u32
+        where Self: FrameState + Sized,
+              F: FnMut(&mut Self, &mut C)
+    {
+        const DEBUG: Option<fn(CpuDebug)> = None;
+        let mut count = 0;
+        self.ensure_next_frame();
+        loop {
+            if cpu.get_pc() == address {
+                trap(self, cpu);
+                count += 1;
+            }
+            let _ = self.execute_single_step(cpu, DEBUG);
+            if self.is_frame_over() {
+                return count
+            }
+        }
+    }
+    /// Conditionally prepares the internal state for the next frame and executes instructions on the `cpu`
+    /// until the near end of that frame, reporting the statistics of the execution.
+    ///
+    /// Instructions are executed one by one, so this method is slower than [ControlUnit::execute_next_frame],
+    /// but the results are deterministic and may be used to detect timing regressions.
+    ///
+    /// The default implementation doesn't count contention, so [FrameStats::contention_tstates] is always `0`.
+    fn run_frame_stats<C: Cpu>(&mut self, cpu: &mut C) -> FrameStats
+        where Self: FrameState
+    {
+        const DEBUG: Option<fn(CpuDebug)> = None;
+        self.ensure_next_frame();
+        let start = self.current_tstate();
+        let mut instructions = 0;
+        loop {
+            let _ = self.execute_single_step(cpu, DEBUG);
+            instructions += 1;
+            if self.is_frame_over() {
+                break
+            }
+        }
+        let tstates = (self.current_tstate() - start) as u32;
+        FrameStats { tstates, instructions, contention_tstates: 0 }
+    }
+    /// Executes the given number of `frames` on the `cpu`, calling [ControlUnit::execute_next_frame]
+    /// for each of them.
+    ///
+    /// Useful for headless testing and benchmarking. The video and audio data of all but the last frame
+    /// are discarded.
+    fn run_frames<C: Cpu>(&mut self, cpu: &mut C, frames: u64) {
+        for _ in 0..frames {
+            self.execute_next_frame(cpu);
+        }
+    }
+    /// Executes frames on the `cpu` until the `predicate` returns `true`, but no more than `max_frames`.
+    ///
+    /// The `predicate` is being called after each executed frame. Returns the number of executed frames
+    /// if the `predicate` has been satisfied. Otherwise, returns `None` after executing `max_frames` frames.
+    fn run_until<C: Cpu, F: FnMut(&Self) -> bool>(
+            &mut self,
+            cpu: &mut C,
+            max_frames: u64,
+            mut predicate: F
+        ) -> Option<u64>
+    {
+        for frames in 1..=max_frames {
+            self.execute_next_frame(cpu);
+            if predicate(self) {
+                return Some(frames)
+            }
+        }
+        None
+    }
+}
+
+/// Statistics of a single frame execution, returned by [ControlUnit::run_frame_stats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FrameStats {
+    /// The number of T-states executed, including contention.
+    pub tstates: u32,
+    /// The number of executed instructions, including interrupt requests and `HALT` cycles.
+    pub instructions: u64,
+    /// The number of T-states added due to memory and I/O contention.
+    pub contention_tstates: u32
 }
 
 /// A trait for reading the MIC line output.
@@ -129,6 +267,19 @@ pub trait MicOut<'a> {
     fn mic_out_pulse_iter(&'a self) -> Self::PulseIter;
 }
 
+/// A trait for reading the combined state of the EAR and MIC output lines.
+///
+/// Unlike [MicOut], which only reports changes of the MIC bit, this trait reports the changes of both
+/// the EAR and MIC bits, so the output level can be determined from all 4 possible states of the lines.
+pub trait EarMicOut<'a> {
+    type EarMicIter: Iterator<Item=(NonZeroU32, EarMic)> + 'a;
+    /// Returns a frame buffered EAR and MIC output as an iterator of level changes.
+    ///
+    /// Each item consists of the interval in T-states counted from the previous change and the new
+    /// state of [EarMic::EAR] and [EarMic::MIC] bits.
+    fn earmic_out_iter(&'a self) -> Self::EarMicIter;
+}
+
 /// A trait for feeding the EAR line input.
 pub trait EarIn {
     /// Sets `EAR IN` bit state after the provided interval in ∆ T-states counted from the last recorded change.
@@ -153,6 +304,15 @@ pub trait EarIn {
     ///
     /// This can be used to help to implement the autoloading of tape data.
     fn read_ear_in_count(&self) -> u32;
+    /// Returns the number of frames that have passed since the EAR input line was read for the last time.
+    ///
+    /// Returns `0` if the line was read during the current frame and `u32::MAX` if it has never been read
+    /// or if the implementation doesn't track it.
+    ///
+    /// This can be used to stop the tape playback automatically when the loader is done reading the tape.
+    fn frames_since_last_ear_read(&self) -> u32 {
+        u32::MAX
+    }
     /// Returns the current mode.
     fn read_ear_mode(&self) -> ReadEarMode {
         ReadEarMode::Clear
@@ -174,6 +334,7 @@ pub trait HostConfig {
     }
     /// Returns the duration of a single execution frame in nanoseconds after multiplying
     /// the CPU rate by the `multiplier`.
+    #[cfg(feature = "std")]
     #[inline]
     fn effective_frame_duration_nanos(multiplier: f64) -> u32 {
         let cpu_rate = Self::effective_cpu_rate(multiplier).round() as u32;
@@ -181,12 +342,17 @@ pub trait HostConfig {
     }
     /// Returns the duration of a single execution frame after multiplying the CPU rate by
     /// the `multiplier`.
+    #[cfg(feature = "std")]
     #[inline]
     fn effective_frame_duration(multiplier: f64) -> Duration {
         let cpu_rate = Self::effective_cpu_rate(multiplier).round() as u32;
         duration_from_frame_tc_cpu_hz(Self::FRAME_TSTATES as u32, cpu_rate)
     }
     /// Returns the duration of a single execution frame in nanoseconds.
+    ///
+    /// This is the authoritative duration of an emulated frame. Note that it's not exactly 1/50th of
+    /// a second on most of the models, e.g. the frame rate of ZX Spectrum 48k is ~50.08 Hz.
+    /// Front-ends pacing the emulation to exactly 50 Hz will accumulate drift between video and audio.
     #[inline]
     fn frame_duration_nanos() -> u32 {
         nanos_from_frame_tc_cpu_hz(Self::FRAME_TSTATES as u32, Self::CPU_HZ) as u32
@@ -228,7 +394,7 @@ pub const fn duration_from_frame_tc_cpu_hz(frame_ts_count: u32, cpu_hz: u32) ->
 }
 
 /// A tool for synchronizing emulation with a running thread.
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
 pub struct ThreadSyncTimer {
     /// The start time of a current synchronization period.
     pub time: Instant,
@@ -236,7 +402,7 @@ pub struct ThreadSyncTimer {
     pub frame_duration: Duration,
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
 // #[allow(clippy::new_without_default)]
 impl ThreadSyncTimer {
     /// Pass the real time duration of a desired synchronization period (usually a duration of a video frame).
@@ -168,6 +168,7 @@ impl fmt::Display for ReadEarMode {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ParseReadEarModeError {}
 
 impl fmt::Display for ParseReadEarModeError {
@@ -199,6 +200,7 @@ impl FromStr for ReadEarMode {
 
 /****************************** EarMic ******************************/
 
+#[cfg(feature = "std")]
 impl std::error::Error for TryFromU8EarMicError {}
 
 impl fmt::Display for TryFromU8EarMicError {
@@ -319,6 +321,7 @@ impl From<Ula3Paging> for u8 {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for TryFromU8Ula3PagingError {}
 
 impl fmt::Display for TryFromU8Ula3PagingError {
@@ -462,6 +465,7 @@ impl From<u8> for UlaPlusRegFlags {
 
 /****************************** ColorMode ******************************/
 
+#[cfg(feature = "std")]
 impl std::error::Error for TryFromU8ColorModeError {}
 
 impl fmt::Display for TryFromU8ColorModeError {
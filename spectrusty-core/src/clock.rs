@@ -11,9 +11,11 @@ use core::convert::{TryInto, TryFrom};
 use core::fmt::Debug;
 use core::hash::{Hash, Hasher};
 use core::marker::PhantomData;
+#[cfg(feature = "std")]
 use core::num::{NonZeroU8, NonZeroU16};
 use core::ops::{Deref, DerefMut};
 
+#[cfg(feature = "std")]
 use z80emu::{Clock, host::cycles::*};
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
@@ -59,6 +61,18 @@ pub trait MemoryContention: Copy + Debug {
     fn is_contended_address(self, address: u16) -> bool;
 }
 
+/// A struct implementing [MemoryContention] with no contended addresses, e.g. for clones without
+/// memory contention such as Pentagon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NoContention;
+
+impl MemoryContention for NoContention {
+    #[inline(always)]
+    fn is_contended_address(self, _address: u16) -> bool {
+        false
+    }
+}
+
 /// A generic [`VFrameTs<V>`][VFrameTs] based T-states counter.
 ///
 /// Implements [Clock] for counting cycles when code is being executed by [z80emu::Cpu].
@@ -80,6 +94,7 @@ pub struct VFrameTsCounter<V, C>  {
 /// to emulate hanging CPU indefinitely.
 pub const HALT_VC_THRESHOLD: i16 = i16::max_value() >> 1;
 
+#[cfg(feature = "std")]
 const WAIT_STATES_THRESHOLD: u16 = i16::max_value() as u16 - 256;
 
 impl VideoTs {
@@ -87,6 +102,35 @@ impl VideoTs {
     pub const fn new(vc: Ts, hc: Ts) -> Self {
         VideoTs { vc, hc }
     }
+    /// Returns a normalized timestamp after adding the given number of T-states (may be negative),
+    /// for the `V:` [VideoFrame].
+    ///
+    /// This is a shortcut for the arithmetic of [VFrameTs].
+    ///
+    /// # Panics
+    /// Panics when the result overflows the capacity of the timestamp.
+    #[inline]
+    pub fn add_tstates<V: VideoFrame>(self, ts: FTs) -> Self {
+        (VFrameTs::<V>::from(self) + ts).into()
+    }
+    /// Returns the difference between `ts_from` and `self` in the number of T-states, for
+    /// the `V:` [VideoFrame].
+    #[inline]
+    pub fn diff<V: VideoFrame>(self, ts_from: VideoTs) -> FTs {
+        VFrameTs::<V>::from(self).diff_from(ts_from.into())
+    }
+    /// Converts the timestamp to the number of T-states, for the `V:` [VideoFrame].
+    #[inline]
+    pub fn to_tstates<V: VideoFrame>(self) -> FTs {
+        VFrameTs::<V>::from(self).into_tstates()
+    }
+    /// Returns `true` if the timestamp is past or near the end of a frame of the `V:` [VideoFrame].
+    ///
+    /// See [VFrameTs::is_eof].
+    #[inline]
+    pub fn is_eof<V: VideoFrame>(self) -> bool {
+        VFrameTs::<V>::from(self).is_eof()
+    }
 }
 
 impl <V: VideoFrame> VFrameTs<V> {
@@ -235,6 +279,7 @@ impl <V: VideoFrame> VFrameTs<V> {
         (frames, ts)
     }
 
+    #[cfg(feature = "std")]
     #[inline]
     fn set_hc_after_small_increment(&mut self, mut hc: Ts) {
         if hc >= V::HTS_RANGE.end {
@@ -307,6 +352,7 @@ impl<V, C> VFrameTsCounter<V, C>
 /// The macro returns a horizontal timestamp pointing after the whole I/O cycle is over.
 /// The `hc` variable is modified to contain a horizontal timestamp indicating when the data R/W operation 
 /// takes place.
+#[cfg(feature = "std")]
 #[macro_export]
 macro_rules! ula_io_contention {
     ($mc:expr, $port:expr, $hc:ident, $contention:path) => {
@@ -397,6 +443,7 @@ impl<V: VideoFrame> Clock for VFrameTs<V> {
     }
 }
 */
+#[cfg(feature = "std")]
 impl<V: VideoFrame, C: MemoryContention> Clock for VFrameTsCounter<V, C> {
     type Limit = Ts;
     type Timestamp = VideoTs;
@@ -5,11 +5,15 @@
 
     For the full copyright notice, see the lib.rs file.
 */
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
 use super::{Ts, FTs, VideoTs, VFrameTs, VideoFrame};
 
 macro_rules! video_ts_packed_data {
     ($name:ident, $bits:literal) => {
         /// A timestamp with packed N-bits of data.
+        #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
         #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug)]
         pub struct $name {
             pub vc: Ts,
@@ -19,11 +19,24 @@
     Author contact information: see Cargo.toml file, section [package.authors].
 */
 //! The core components of the SPECTRUSTY library.
+//!
+//! # `no_std`
+//!
+//! The `"std"` feature is enabled by default. With the default features disabled, this crate compiles
+//! under `no_std` and only the modules: [audio], [clock], [video] and [chip] are available. The `chip`
+//! module lacks the traits depending on the `bus` and `memory` modules: `ControlUnit` and `MemoryAccess`,
+//! as well as `ThreadSyncTimer` and the `HostConfig` methods scaling the frame duration. The [z80emu]
+//! crate is only re-exported with `"std"`, as is the [z80emu::Clock] implementation. This is sufficient to run the sound chip emulation on embedded targets.
+//! The `"snapshot"` feature requires `"std"`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 pub mod audio;
+#[cfg(feature = "std")]
 pub mod bus;
 pub mod chip;
 pub mod clock;
+#[cfg(feature = "std")]
 pub mod memory;
 pub mod video;
 
+#[cfg(feature = "std")]
 pub use z80emu;
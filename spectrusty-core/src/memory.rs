@@ -39,6 +39,8 @@ pub enum ZxMemoryError {
     UnsupportedAddressRange,
     UnsupportedExRomPaging,
     InvalidExRomSize,
+    /// The size of the ROM data doesn't match the size of the ROM bank(s).
+    InvalidRomSize { expected: usize, found: usize },
     Io(io::Error)
 }
 
@@ -52,6 +54,8 @@ impl fmt::Display for ZxMemoryError {
             ZxMemoryError::UnsupportedAddressRange => "Address range is not supported",
             ZxMemoryError::UnsupportedExRomPaging => "EX-ROM mapping is not supported",
             ZxMemoryError::InvalidExRomSize => "EX-ROM size is smaller than the memory page size",
+            ZxMemoryError::InvalidRomSize { expected, found } => return write!(f,
+                "ROM data size: {} doesn't match the expected size: {}", found, expected),
             ZxMemoryError::Io(err) => return err.fmt(f)
         })
     }
@@ -281,6 +285,37 @@ pub trait ZxMemory {
         let slice = self.rom_bank_mut(rom_bank)?;
         rd.read_exact(slice).map_err(ZxMemoryError::Io)
     }
+    /// Loads the whole ROM image from `rd` into the ROM bank `rom_bank`, validating its size.
+    ///
+    /// See [ZxMemory::load_rom_bytes] for the details.
+    fn load_rom<R: Read>(&mut self, rom_bank: usize, rd: R) -> Result<()> {
+        let mut data = Vec::new();
+        rd.take(Self::ROM_SIZE as u64 + 1).read_to_end(&mut data).map_err(ZxMemoryError::Io)?;
+        self.load_rom_bytes(rom_bank, &data)
+    }
+    /// Copies the ROM image `data` into the ROM bank `rom_bank`.
+    ///
+    /// The size of `data` must be equal to the size of the ROM bank or to its multiple. In the latter case
+    /// the data is split across consecutive ROM banks, starting from `rom_bank`, e.g. the concatenated 32K
+    /// image of the two 16K ROMs of the 128k Spectrum can be loaded at once into banks 0 and 1.
+    ///
+    /// # Errors
+    /// Returns [ZxMemoryError::InvalidRomSize] if the data is truncated, too large or its size doesn't
+    /// match the size of the ROM bank(s), or [ZxMemoryError::InvalidBankIndex] if `rom_bank` is out of range.
+    /// The memory is not modified in case of an error.
+    fn load_rom_bytes(&mut self, rom_bank: usize, data: &[u8]) -> Result<()> {
+        let bank_size = self.rom_bank_ref(rom_bank)?.len();
+        let banks = Self::ROM_BANKS_MAX + 1 - rom_bank;
+        let count = data.len() / bank_size;
+        if count == 0 || count > banks || count * bank_size != data.len() {
+            let expected = if data.len() > bank_size { bank_size * banks } else { bank_size };
+            return Err(ZxMemoryError::InvalidRomSize { expected, found: data.len() })
+        }
+        for (bank, chunk) in (rom_bank..).zip(data.chunks(bank_size)) {
+            self.rom_bank_mut(bank)?.copy_from_slice(chunk);
+        }
+        Ok(())
+    }
     /// Returns an iterator of memory page slice references intersecting with a given address range.
     ///
     /// # Errors
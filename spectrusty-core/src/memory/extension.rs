@@ -19,6 +19,14 @@ pub trait MemoryExtension: core::fmt::Debug {
     fn read_opcode<M: ZxMemory>(&mut self, pc: u16, memory: &mut M) -> u8 {
         memory.read(pc)
     }
+    /// Write `value` to the given `memory` at the given `addr` address, optionally altering provided memory.
+    ///
+    /// Allows extensions to intercept writes to the memory paged in by them, e.g. to provide writable RAM
+    /// mapped with [ZxMemory::map_exrom], which is otherwise read-only.
+    #[inline]
+    fn write_mem<M: ZxMemory>(&mut self, addr: u16, value: u8, memory: &mut M) {
+        memory.write(addr, value)
+    }
     // /// Writes to the memory extension port. Should return optionally modified `data` if the extension wants
     // /// to influence some other chipset functions.
     // #[inline]
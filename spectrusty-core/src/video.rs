@@ -31,11 +31,16 @@ pub const PAL_HC: u32 = 704/2;
 pub const MAX_BORDER_SIZE: u32 = 6*8;
 
 /// This enum is used to select border size when rendering video frames.
+///
+/// [BorderSize::Overscan] renders the whole video frame, including the horizontal and vertical blanking
+/// periods, as the border. This may be useful for the full-screen border effects or for debugging raster
+/// timings.
 #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "snapshot", serde(try_from = "u8", into = "u8"))]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum BorderSize {
+    Overscan = 7,
     Full    = 6,
     Large   = 5,
     Medium  = 4,
@@ -118,6 +123,71 @@ pub trait Video {
         pitch: usize,
         border_size: BorderSize
     );
+    /// Renders last emulated frame's video data into the provided pixel `buffer` the same way as
+    /// [Video::render_video_frame], calling `line_hook` after each line has been rendered.
+    ///
+    /// The `line_hook` receives the index of the rendered line in the `buffer` and the video timestamp
+    /// at the beginning of the corresponding video scan line. This can be used e.g. by raster debuggers
+    /// to track the position of the beam.
+    ///
+    /// The default implementation renders the whole frame first and then calls `line_hook` for each
+    /// rendered line in order.
+    fn render_video_frame_with_line_hook<'a, B, P, F>(
+            &mut self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            mut line_hook: F
+        )
+        where B: PixelBuffer<'a>,
+              P: Palette<Pixel=B::Pixel>,
+              F: FnMut(usize, VideoTs)
+    {
+        let lines = buffer.chunks(pitch).len();
+        self.render_video_frame::<B, P>(buffer, pitch, border_size);
+        let vsl_start = Self::VideoFrame::border_top_vsl_iter(border_size).start;
+        let vsl_end = Self::VideoFrame::border_bot_vsl_iter(border_size).end;
+        for (line, vc) in (vsl_start..vsl_end).take(lines).enumerate() {
+            line_hook(line, VideoTs::new(vc, Self::VideoFrame::HTS_RANGE.start));
+        }
+    }
+    /// Renders video scan lines of the current frame with indexes within the given `vsl_range`
+    /// into the provided pixel `buffer`.
+    ///
+    /// The `buffer`, `pitch` and `border_size` are interpreted the same way as in [Video::render_video_frame],
+    /// so the `buffer` should be large enough to hold the whole rendered frame. Only the lines within
+    /// `vsl_range` are written to at their offsets in the `buffer`, other lines are left untouched.
+    ///
+    /// Unlike [Video::render_video_frame], the video data is not being drained, so this method can be
+    /// called many times during the frame, e.g. to show the progress of the frame being drawn while
+    /// stepping the CPU. Lines below the current video scan line will show the screen memory as it is.
+    fn render_video_scanlines<'a, B: PixelBuffer<'a>, P: Palette<Pixel=B::Pixel>>(
+        &mut self,
+        buffer: &'a mut [u8],
+        pitch: usize,
+        border_size: BorderSize,
+        vsl_range: Range<Ts>
+    );
+    /// Fills the whole rendered area of the provided pixel `buffer` with a single border `color`,
+    /// skipping the INK and PAPER area entirely.
+    ///
+    /// The filled area has the same dimensions as the one rendered by [Video::render_video_frame] with
+    /// the same `pitch` and `border_size`, so the same `buffer` can be reused. This is much cheaper than
+    /// rendering a full frame and may be used to refresh a static screen, e.g. when the emulation is paused.
+    ///
+    /// No video data is being consumed.
+    fn render_border_only<'a, B: PixelBuffer<'a>, P: Palette<Pixel=B::Pixel>>(
+        buffer: &'a mut [u8],
+        pitch: usize,
+        border_size: BorderSize,
+        color: BorderColor
+    ) {
+        let (width, height) = Self::render_size_pixels(border_size);
+        let pixel = P::get_pixel(color.into());
+        for line in buffer.chunks_mut(pitch).take(height as usize) {
+            B::from_line(line).put_pixels(pixel, width as usize);
+        }
+    }
     /// Returns rendered screen pixel size (horizontal, vertical), including the border area, measured
     /// in pixels depending on [Video::PIXEL_DENSITY].
     ///
@@ -142,9 +212,40 @@ pub trait Video {
     /// Modifies the current value of the video T-state counter.
     fn set_video_ts(&mut self, vts: VideoTs);
     /// Returns the current value of the video T-state clock.
+    ///
+    /// The returned counter is seeded at the current video timestamp and uses the memory contention
+    /// of the current memory configuration, the same way the clock of the emulated CPU does.
+    ///
+    /// Peripherals may use it to schedule events at the future T-states, e.g. `VFrameTs::from(clock) + delta`
+    /// returns a normalized [VFrameTs][crate::clock::VFrameTs], which may point past the end of the current frame.
+    /// Use [VFrameTs::into_tstates][crate::clock::VFrameTs::into_tstates] to convert it to the number of
+    /// T-states relative to the beginning of the current frame.
     fn current_video_clock(&self) -> VFrameTsCounter<Self::VideoFrame, Self::Contention>;
+    /// Returns the number of additional T-states a memory access (MREQ) at the given `addr` would be
+    /// delayed by, if it was performed at the given video timestamp `vts`.
+    ///
+    /// The memory contention scheme of the emulated model is being used, taking into account the current
+    /// memory paging, via [MemoryContention::is_contended_address].
+    ///
+    /// Returns `0` for uncontended addresses or if `vts` points outside of the contended scan lines.
+    fn contended_access_tstates(&self, addr: u16, vts: VideoTs) -> u32 {
+        let VFrameTsCounter { vts, contention } = VFrameTsCounter::<Self::VideoFrame, _>::from_video_ts(
+                                                        vts, self.current_video_clock().contention);
+        if Self::VideoFrame::is_contended_line_mreq(vts.vc) && contention.is_contended_address(addr) {
+            (Self::VideoFrame::contention(vts.hc) - vts.hc) as u32
+        }
+        else {
+            0
+        }
+    }
     /// Returns the temporary video flash attribute state.
     fn flash_state(&self) -> bool;
+    /// Returns the byte that the ULA would put on the floating data bus at the current video T-state.
+    ///
+    /// The value is read from the currently displayed screen bank at the address determined by
+    /// [VideoFrame::floating_bus_screen_address]. Returns `0xFF` when the ULA is not fetching screen data
+    /// or if the emulated chipset has no floating bus, which is the default implementation.
+    fn floating_bus_value(&self) -> u8 { !0 }
 }
 /// A collection of static methods and constants related to video parameters.
 /// ```text
@@ -179,12 +280,34 @@ pub trait VideoFrame: Copy + Debug {
     const VSL_COUNT: Ts;
     /// The total number of T-states per frame.
     const FRAME_TSTATES_COUNT: FTs = Self::HTS_COUNT as FTs * Self::VSL_COUNT as FTs;
+    /// The CPU clock rate in T-states per second of the models using this video frame.
+    ///
+    /// The default is the CPU clock rate of the PAL 16k/48k models.
+    const CPU_HZ: u32 = 3_500_000;
+    /// The offset in T-states of the frame interrupt request relative to the frame start.
+    ///
+    /// A negative value means the interrupt is being requested earlier, so all other timings are later
+    /// in relation to the interrupt, the same way as in the "late timings" mode. Allows to distinguish
+    /// between ULA revisions raising the `INT` signal at slightly different moments in relation to the
+    /// display. The interrupt request window must fit in the first video scan line, so the offset should
+    /// be small.
+    ///
+    /// The "late timings" mode doesn't add to a negative offset, as it already makes the timings late.
+    const INT_OFFSET_TS: Ts = 0;
+    /// The duration in T-states of the frame interrupt request signal of the models using this video frame.
+    ///
+    /// The default is the duration of the signal asserted by the 16k/48k ULA.
+    const INT_LENGTH_TS: Ts = 32;
     /// A rendered screen border size in pixels depending on the border size selection.
     ///
     /// **NOTE**: The upper and lower border size may be lower than the value returned here
     ///  e.g. in the NTSC video frame.
+    ///
+    /// For [BorderSize::Overscan] this method returns [MAX_BORDER_SIZE], as the size of the overscan border
+    /// differs on each side of the screen. Use the border iterators to determine the actual size.
     fn border_size_pixels(border_size: BorderSize) -> u32 {
         match border_size {
+            BorderSize::Overscan|
             BorderSize::Full    => MAX_BORDER_SIZE,
             BorderSize::Large   => MAX_BORDER_SIZE -   8,
             BorderSize::Medium  => MAX_BORDER_SIZE - 2*8,
@@ -197,22 +320,41 @@ pub trait VideoFrame: Copy + Debug {
     /// Returns output screen pixel size (horizontal, vertical), including the border area, measured
     /// in low-resolution pixels.
     ///
-    /// The size depends on the given `border_size`.
+    /// The size depends on the given `border_size`. The height is the exact number of scan lines
+    /// produced by the renderer: [VideoFrame::border_top_vsl_iter], followed by [VideoFrame::VSL_PIXELS]
+    /// and [VideoFrame::border_bot_vsl_iter].
     fn screen_size_pixels(border_size: BorderSize) -> (u32, u32) {
-        let border = 2 * Self::border_size_pixels(border_size);
+        let border = if border_size == BorderSize::Overscan {
+            8 * (Self::border_left_hts_iter(border_size).count() +
+                 Self::border_right_hts_iter(border_size).count()) as u32
+        }
+        else {
+            2 * Self::border_size_pixels(border_size)
+        };
         let w = PAL_HC - 2*MAX_BORDER_SIZE + border;
-        let h = (PAL_VC - 2*MAX_BORDER_SIZE + border)
-                .min((Self::VSL_BORDER_BOT + 1 - Self::VSL_BORDER_TOP) as u32);
+        let top = Self::border_top_vsl_iter(border_size);
+        let bot = Self::border_bot_vsl_iter(border_size);
+        let h = (top.end - top.start + Self::VSL_PIXELS.end - Self::VSL_PIXELS.start + bot.end - bot.start) as u32;
         (w, h)
     }
     /// Returns an iterator of the top border low-resolution scan line indexes.
+    ///
+    /// For [BorderSize::Overscan] the range starts from the first scan line of the frame.
     fn border_top_vsl_iter(border_size: BorderSize) -> Range<Ts> {
+        if border_size == BorderSize::Overscan {
+            return 0..Self::VSL_PIXELS.start
+        }
         let border = Self::border_size_pixels(border_size) as Ts;
         let top = (Self::VSL_PIXELS.start - border).max(Self::VSL_BORDER_TOP);
         top..Self::VSL_PIXELS.start
     }
     /// Returns an iterator of the bottom border low-resolution scan line indexes.
+    ///
+    /// For [BorderSize::Overscan] the range ends with the last scan line of the frame.
     fn border_bot_vsl_iter(border_size: BorderSize) -> Range<Ts> {
+        if border_size == BorderSize::Overscan {
+            return Self::VSL_PIXELS.end..Self::VSL_COUNT
+        }
         let border = Self::border_size_pixels(border_size) as Ts;
         let bot = (Self::VSL_PIXELS.end + border).min(Self::VSL_BORDER_BOT);
         Self::VSL_PIXELS.end..bot
@@ -221,6 +363,8 @@ pub trait VideoFrame: Copy + Debug {
     /// An iterator for rendering borders.
     type BorderHtsIter: Iterator<Item=Ts>;
     /// Returns an iterator of border latch horizontal T-states.
+    ///
+    /// For [BorderSize::Overscan] the iterator should cover the whole [VideoFrame::HTS_RANGE].
     fn border_whole_line_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter;
     /// Returns an iterator of left border latch horizontal T-states.
     fn border_left_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter;
@@ -287,6 +431,7 @@ pub trait VideoFrame: Copy + Debug {
 impl From<BorderSize> for &'static str {
     fn from(border: BorderSize) -> &'static str {
         match border {
+            BorderSize::Overscan => "overscan",
             BorderSize::Full    => "full",
             BorderSize::Large   => "large",
             BorderSize::Medium  => "medium",
@@ -304,6 +449,7 @@ impl fmt::Display for BorderSize {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ParseBorderSizeError {}
 
 impl fmt::Display for ParseBorderSizeError {
@@ -315,9 +461,13 @@ impl fmt::Display for ParseBorderSizeError {
 impl FromStr for BorderSize {
     type Err = ParseBorderSizeError;
     /// Parses a single word describing border size using case insensitive matching
-    /// or a single digit from 0 to 6.
+    /// or a single digit from 0 to 7.
     fn from_str(name: &str) -> Result<Self, Self::Err> {
-        if name.eq_ignore_ascii_case("full") ||
+        if name.eq_ignore_ascii_case("overscan") ||
+           name.eq_ignore_ascii_case("over") {
+            Ok(BorderSize::Overscan)
+        }
+        else if name.eq_ignore_ascii_case("full") ||
            name.eq_ignore_ascii_case("maxi") ||
            name.eq_ignore_ascii_case("max") {
             Ok(BorderSize::Full)
@@ -364,6 +514,7 @@ impl From<BorderSize> for u8 {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for TryFromUIntBorderSizeError {}
 
 impl fmt::Display for TryFromUIntBorderSizeError {
@@ -377,6 +528,7 @@ impl TryFrom<u8> for BorderSize {
     fn try_from(border: u8) -> Result<Self, Self::Error> {
         use BorderSize::*;
         Ok(match border {
+            7 => Overscan,
             6 => Full,
             5 => Large,
             4 => Medium,
@@ -389,6 +541,7 @@ impl TryFrom<u8> for BorderSize {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for TryFromU8BorderColorError {}
 
 impl fmt::Display for TryFromU8BorderColorError {
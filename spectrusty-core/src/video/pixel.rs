@@ -6,8 +6,15 @@
     For the full copyright notice, see the lib.rs file.
 */
 //! Building blocks for rendering pixel surfaces.
+use core::marker::PhantomData;
 use core::slice::IterMut;
 
+/// Luma coefficients used for converting colors to grayscale by the grayscale palettes, [Rec. 709],
+/// scaled by 2^16: `[red, green, blue]`.
+///
+/// [Rec. 709]: https://en.wikipedia.org/wiki/Rec._709
+pub const LUMA_WEIGHTS_REC709: [u32; 3] = [13933, 46871, 4732];
+
 /// A trait for providing a way for placing pixels into byte buffers.
 pub trait PixelBuffer<'a> {
     /// Specifies the type used for pixels.
@@ -86,6 +93,10 @@ pub struct PixelBufP32<'a> {
 }
 
 /// A [PixelBuffer] tool for placing pixels into byte buffers using `u16` packed color channels.
+///
+/// Pixels are written as 2 bytes each in the native byte order. Each line of the buffer, as determined by
+/// the `pitch` argument of the rendering methods, should start at an address aligned to 2 bytes, otherwise
+/// the unaligned leading byte is skipped. Hence the `pitch` should be an even number of bytes.
 pub struct PixelBufP16<'a> {
     iter: IterMut<'a, u16>
 }
@@ -111,6 +122,10 @@ pub struct SpectrumPalA8R8G8B8;
 pub struct SpectrumPalR8G8B8A8;
 
 /// A color ZX Spectrum [Palette] implementation to be used with [PixelBufP16].
+///
+/// Produces `u16` pixels in the RGB565 format: `0bRRRRR_GGGGGG_BBBBB`. The channels are converted from the
+/// same 8-bit color values as in [SpectrumPalRGB24] by taking their 5 (red and blue) or 6 (green) most
+/// significant bits, so e.g. the bright white color is `0xFFFF` and the non-bright red is `0b10110_000000_00000`.
 pub struct SpectrumPalR5G6B5;
 
 /// A color ZX Spectrum [Palette] implementation to be used with [PixelBufP8].
@@ -137,6 +152,28 @@ pub struct GrayscalePalR5G6B5;
 /// A grayscale ZX Spectrum [Palette] implementation to be used with [PixelBufP8].
 pub struct GrayscalePalR3G3B2;
 
+/// A high contrast, color-blind friendly [Palette] wrapper.
+///
+/// Replaces the 15 ZX Spectrum colors with the hues based on the [Okabe-Ito] color scheme, which remain
+/// distinguishable with the most common types of color vision deficiency. The bright colors are the lighter
+/// variants of the same hues.
+///
+/// The pixel format is determined by the wrapped palette `P`, e.g. `HighContrastPal<SpectrumPalA8R8G8B8>`
+/// produces `u32` pixels. Other colors (ULAplus and grayscale) are forwarded unchanged to `P`.
+///
+/// [Okabe-Ito]: https://jfly.uni-koeln.de/color/
+pub struct HighContrastPal<P>(PhantomData<P>);
+
+/// A [Palette] wrapper setting the alpha channel of the RGBA pixels to the given `ALPHA` value.
+///
+/// The wrapped palette `P` must produce `[u8;4]` pixels with the alpha channel in the 4th byte, e.g.
+/// [SpectrumPalRGBA32], [GrayscalePalRGBA32] or `HighContrastPal<SpectrumPalRGBA32>`. The pixels are to be
+/// used with [PixelBufA32]. Both the border and the screen pixels are rendered with the same alpha value.
+///
+/// E.g. `RgbaAlphaPal<SpectrumPalRGBA32, 128>` renders semi-transparent pixels, while the plain
+/// [SpectrumPalRGBA32] renders fully opaque pixels (`255`).
+pub struct RgbaAlphaPal<P, const ALPHA: u8>(PhantomData<P>);
+
 #[allow(clippy::unusual_byte_groupings)]
 #[inline]
 fn index_to_grb(index: u8) -> u8 {
@@ -162,6 +199,80 @@ fn index_to_grb(index: u8) -> u8 {
     }
 }
 
+#[allow(clippy::unusual_byte_groupings)]
+#[inline]
+fn index_to_grb_high_contrast(index: u8) -> u8 {
+    #[allow(clippy::inconsistent_digit_grouping)]
+    match index & 15 {
+         0 => 0b000_000_00, // black
+         1 => 0b011_000_10, // blue
+         2 => 0b010_110_00, // vermillion
+         3 => 0b011_110_10, // reddish purple
+         4 => 0b100_000_01, // bluish green
+         5 => 0b101_010_11, // sky blue
+         6 => 0b111_111_01, // yellow
+         7 => 0b101_101_10, // light gray
+         8 => 0b000_000_00, // black
+         9 => 0b101_001_11, // blue
+        10 => 0b100_111_01, // orange
+        11 => 0b100_111_11, // pink
+        12 => 0b111_000_10, // green
+        13 => 0b111_100_11, // light sky blue
+        14 => 0b111_111_10, // light yellow
+        15 => 0b111_111_11, // white
+        _ => unsafe { core::hint::unreachable_unchecked() }
+    }
+}
+
+impl<P: Palette> Palette for HighContrastPal<P> {
+    type Pixel = P::Pixel;
+
+    #[inline]
+    fn get_pixel(index: u8) -> Self::Pixel {
+        P::get_pixel_grb8(index_to_grb_high_contrast(index))
+    }
+    #[inline(always)]
+    fn get_pixel_gray(index: u8) -> Self::Pixel {
+        P::get_pixel_gray(index)
+    }
+    #[inline(always)]
+    fn get_pixel_grb8(g3r3b2: u8) -> Self::Pixel {
+        P::get_pixel_grb8(g3r3b2)
+    }
+    #[inline(always)]
+    fn get_pixel_gray8(value: u8) -> Self::Pixel {
+        P::get_pixel_gray8(value)
+    }
+}
+
+impl<P: Palette<Pixel=[u8;4]>, const ALPHA: u8> RgbaAlphaPal<P, ALPHA> {
+    #[inline(always)]
+    fn with_alpha([r, g, b, _]: [u8;4]) -> [u8;4] {
+        [r, g, b, ALPHA]
+    }
+}
+
+impl<P: Palette<Pixel=[u8;4]>, const ALPHA: u8> Palette for RgbaAlphaPal<P, ALPHA> {
+    type Pixel = [u8;4];
+
+    #[inline(always)]
+    fn get_pixel(index: u8) -> Self::Pixel {
+        Self::with_alpha(P::get_pixel(index))
+    }
+    #[inline(always)]
+    fn get_pixel_gray(index: u8) -> Self::Pixel {
+        Self::with_alpha(P::get_pixel_gray(index))
+    }
+    #[inline(always)]
+    fn get_pixel_grb8(g3r3b2: u8) -> Self::Pixel {
+        Self::with_alpha(P::get_pixel_grb8(g3r3b2))
+    }
+    #[inline(always)]
+    fn get_pixel_gray8(value: u8) -> Self::Pixel {
+        Self::with_alpha(P::get_pixel_gray8(value))
+    }
+}
+
 macro_rules! impl_pixel_buffer {
     ($pixel_buf:ty, $pixel:ty) => {
         impl<'a> PixelBuffer<'a> for $pixel_buf {
@@ -235,6 +346,8 @@ macro_rules! impl_pixel_grb_const {
 macro_rules! impl_pixel_grb_gray {
     ($palette:ty, $pixel:ty, $grayscale:ident) => {
         impl $palette {
+            /// Luma coefficients used for converting colors to grayscale: `[red, green, blue]` scaled by 2^16.
+            pub const LUMA_WEIGHTS: [u32; 3] = LUMA_WEIGHTS_REC709;
             #[inline(always)]
             fn pixel_grb(g3r3b2: u8) -> $pixel {
                 Self::pixel_gray_grb(g3r3b2)
@@ -278,7 +391,8 @@ const fn pack_332(a: u8, b: u8, c: u8) -> u8 {
 }
 
 const fn grayscale(r: u8, g: u8, b: u8) -> u8 {
-    ((13933 * r as u32 + 46871 * g as u32 + 4732 * b as u32) >> 16) as u8
+    let [wr, wg, wb] = LUMA_WEIGHTS_REC709;
+    ((wr * r as u32 + wg * g as u32 + wb * b as u32) >> 16) as u8
 }
 
 macro_rules! impl_palette_plus_formats {
@@ -471,6 +585,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn pixel_palette_high_contrast_works() {
+        assert_eq!(LUMA_WEIGHTS_REC709.iter().sum::<u32>(), 1 << 16);
+        type HC = HighContrastPal<SpectrumPalRGB24>;
+        for i in 0..16 {
+            assert_eq!(HC::get_pixel(i), SpectrumPalRGB24::get_pixel_grb8(index_to_grb_high_contrast(i)));
+            assert_eq!(HC::get_pixel(i + 16), HC::get_pixel(i));
+            assert_eq!(HC::get_pixel_gray(i), SpectrumPalRGB24::get_pixel_gray(i));
+        }
+        assert_eq!(HC::get_pixel(0), [0, 0, 0]);
+        assert_eq!(HC::get_pixel(8), [0, 0, 0]);
+        assert_eq!(HC::get_pixel(15), [255, 255, 255]);
+        // all colors except black must be distinct
+        for i in 1..16 {
+            for j in (i + 1)..16 {
+                if i != 8 && j != 8 {
+                    assert_ne!(HC::get_pixel(i), HC::get_pixel(j));
+                }
+            }
+        }
+        assert_eq!(HighContrastPal::<SpectrumPalA8R8G8B8>::get_pixel(15), 0xffff_ffff);
+        for i in 0..=255u8 {
+            assert_eq!(HC::get_pixel_grb8(i), SpectrumPalRGB24::get_pixel_grb8(i));
+            assert_eq!(HC::get_pixel_gray8(i), SpectrumPalRGB24::get_pixel_gray8(i));
+        }
+    }
+
     #[test]
     fn pixel_palette_grb_works() {
         assert_eq!(SpectrumPalRGB24::get_pixel_grb8(0), [0, 0, 0]);
@@ -503,6 +644,57 @@ mod tests {
         assert_eq!(SpectrumPalR3G3B2::get_pixel_grb8(255), 0xff);
     }
 
+    #[test]
+    fn pixel_palette_rgba_alpha_works() {
+        type Translucent = RgbaAlphaPal<SpectrumPalRGBA32, 128>;
+        type Transparent = RgbaAlphaPal<HighContrastPal<GrayscalePalRGBA32>, 0>;
+        for i in 0..=255u8 {
+            let [r, g, b, a] = SpectrumPalRGBA32::get_pixel(i);
+            assert_eq!(a, 255);
+            assert_eq!(Translucent::get_pixel(i), [r, g, b, 128]);
+            let [r, g, b, _] = SpectrumPalRGBA32::get_pixel_grb8(i);
+            assert_eq!(Translucent::get_pixel_grb8(i), [r, g, b, 128]);
+            assert_eq!(Translucent::get_pixel_gray8(i), [i, i, i, 128]);
+            let [r, g, b, _] = SpectrumPalRGBA32::get_pixel_gray(i);
+            assert_eq!(Translucent::get_pixel_gray(i), [r, g, b, 128]);
+            let [r, g, b, _] = HighContrastPal::<GrayscalePalRGBA32>::get_pixel(i);
+            assert_eq!(Transparent::get_pixel(i), [r, g, b, 0]);
+        }
+        assert_eq!(RgbaAlphaPal::<SpectrumPalRGBA32, 255>::get_pixel(15), [255, 255, 255, 255]);
+        let mut buffer = [0u8; 12];
+        let mut writer = PixelBufA32::from_line(&mut buffer);
+        writer.put_pixel(Translucent::get_pixel(2));
+        writer.put_pixels(Translucent::get_pixel(15), 2);
+        assert_eq!(buffer, [0b10110110, 0, 0, 128, 255, 255, 255, 128, 255, 255, 255, 128]);
+    }
+
+    #[test]
+    fn pixel_buffer_p16_works() {
+        const WIDTH: usize = 3;
+        const PITCH: usize = WIDTH * 2 + 4;
+        let mut buffer = [0xAAAAu16; PITCH];
+        let bytes = unsafe { core::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, PITCH * 2) };
+        for (line, color) in bytes.chunks_mut(PITCH).zip([2u8, 15].iter().copied()) {
+            let mut writer = PixelBufP16::from_line(&mut line[..WIDTH * 2]);
+            writer.put_pixel(SpectrumPalR5G6B5::get_pixel(color));
+            writer.put_pixels(SpectrumPalR5G6B5::get_pixel(color), WIDTH);
+        }
+        assert_eq!(PixelBufP16::pixel_stride(), 2);
+        assert_eq!(SpectrumPalR5G6B5::get_pixel(2), 0xB000);
+        let red = 0xB000u16.to_ne_bytes();
+        let white = 0xFFFFu16.to_ne_bytes();
+        let pad = [0xAA; 4];
+        let expected: Vec<u8> = [red, red, red].iter().flatten().chain(pad.iter())
+                                .chain([white, white, white].iter().flatten()).chain(pad.iter())
+                                .copied().collect();
+        assert_eq!(bytes, &expected[..]);
+        for i in 0..16 {
+            let [r, g, b] = SpectrumPalRGB24::get_pixel(i);
+            assert_eq!(SpectrumPalR5G6B5::get_pixel(i),
+                       ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3));
+        }
+    }
+
     #[test]
     fn pixel_palette_gray_works() {
         let grayscale_u16 = |v| (((v as u16) >> 3) << 11)|(((v as u16) >> 2) << 5)|((v as u16) >> 3);
@@ -28,7 +28,7 @@ pub mod tap;
 pub mod snapshot;
 pub mod scr;
 pub mod z80;
-// pub mod tzx;
+pub mod tzx;
 
 /// A trait that extends [Read] with methods that ease reading from chunked files.
 pub trait ReadExactEx: Read {
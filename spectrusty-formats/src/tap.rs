@@ -214,9 +214,10 @@ use core::borrow::Borrow;
 use std::borrow::Cow;
 use std::fmt;
 use core::convert::TryFrom;
+use core::num::NonZeroU32;
 use std::io::{ErrorKind, Error, Read, Write, Seek, Result, Cursor};
 
-use pulse::ReadEncPulseIter;
+use pulse::{ReadEncPulseIter, PulseDecodeWriter};
 
 pub mod pulse;
 mod read;
@@ -259,6 +260,34 @@ pub fn read_tap_pulse_iter<T, R>(rd: T) -> TapChunkPulseIter<R>
     TapChunkPulseIter::from(ep_iter)
 }
 
+/// Decodes *TAPE* T-state pulse intervals into *TAP* chunks.
+///
+/// The pulses should follow the timings of the ZX Spectrum's ROM saving routines, e.g. as produced by
+/// [MicOut::mic_out_pulse_iter] when executing the `SAVE` command. Lead, sync and data pulses are being
+/// recognized by [PulseDecodeWriter].
+///
+/// Each returned chunk contains a flag byte, data bytes and a checksum byte, just like the *TAP* chunks.
+/// The chunks are not verified; use [checksum] to check their integrity (it should be `0` for valid chunks).
+///
+/// [MicOut::mic_out_pulse_iter]: spectrusty_core::chip::MicOut::mic_out_pulse_iter
+pub fn pulses_to_tap_blocks<I>(iter: I) -> Vec<Vec<u8>>
+    where I: IntoIterator<Item=NonZeroU32>
+{
+    let mut blocks = Vec::new();
+    let mut decoder = PulseDecodeWriter::new(Vec::new());
+    let mut iter = iter.into_iter().peekable();
+    // writing to a Vec never fails
+    while iter.peek().is_some() {
+        if let Ok(Some(_)) = decoder.write_decoded_pulses(iter.by_ref()) {
+            blocks.push(core::mem::take(decoder.get_mut()));
+        }
+    }
+    if let Ok(Some(_)) = decoder.end() {
+        blocks.push(decoder.into_inner());
+    }
+    blocks
+}
+
 /// Creates an instance of [TapChunkWriter] from the given writer on success.
 pub fn write_tap<W>(wr: W) -> Result<TapChunkWriter<W>>
     where W: Write + Seek
@@ -861,6 +890,20 @@ mod tests {
     use std::fs::File;
     use smallvec::SmallVec;
 
+    #[test]
+    fn pulses_to_tap_blocks_works() {
+        let head = [HEAD_BLOCK_FLAG, 1, 2, 3, 0];
+        let data = [DATA_BLOCK_FLAG, 0xA5, 0x5A, 0xFF, 0xFF];
+        let pulses = ReadEncPulseIter::new(&head[..])
+                     .chain(Some(pulse::consts::PAUSE_PULSE_LENGTH))
+                     .chain(ReadEncPulseIter::new(&data[..]));
+        let blocks = pulses_to_tap_blocks(pulses);
+        assert_eq!(vec![head.to_vec(), data.to_vec()], blocks);
+        assert_eq!(0, checksum(&blocks[0]));
+        assert_eq!(0, checksum(&blocks[1]));
+        assert!(pulses_to_tap_blocks(ReadEncPulseIter::new(&[][..])).is_empty());
+    }
+
     #[test]
     fn with_name_pads_name() {
         let header = Header::new_code(1u16).with_name("01234567");
@@ -10,6 +10,7 @@
 
 mod decoding;
 mod encoding;
+mod samples;
 
 pub mod consts {
     use core::num::NonZeroU32;
@@ -34,6 +35,7 @@ pub mod consts {
 
 pub use decoding::*;
 pub use encoding::*;
+pub use samples::*;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,183 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+use core::iter::Copied;
+use core::num::NonZeroU32;
+use core::slice;
+
+/// The default trigger hysteresis of [SamplesPulseIter].
+pub const SAMPLES_HYSTERESIS: i16 = 1024;
+/// The default minimum interval in T-states between edges detected by [SamplesPulseIter].
+pub const SAMPLES_MIN_DELTA: u32 = 200;
+
+/// Detects edges of the signal recorded as audio samples and converts them to *TAPE* T-state pulse
+/// intervals via an [Iterator] interface.
+///
+/// The signal level becomes high when a sample value exceeds the `hysteresis` and becomes low when it
+/// falls below the negated `hysteresis`. The level is unchanged for samples in between.
+///
+/// A level change is being accepted only if the new level persists for at least `min_delta` T-states.
+/// Otherwise, the change is considered to be a spurious noise and is ignored.
+///
+/// The initial signal level is determined by the first sample exceeding the `hysteresis`,
+/// without producing an edge.
+///
+/// This iterator may be used to feed pulses to the `EAR IN` buffer of the ZX Spectrum emulator
+/// (e.g. via [EarIn::feed_ear_in][spectrusty_core::chip::EarIn::feed_ear_in]).
+#[derive(Clone, Debug)]
+pub struct SamplesPulseIter<I> {
+    samples: I,
+    sample_rate: u64,
+    cpu_hz: u64,
+    hysteresis: i16,
+    min_delta: u32,
+    position: u64,
+    level: Option<bool>,
+    pending: Option<u64>,
+    last_edge: u64,
+}
+
+/// Creates an instance of [SamplesPulseIter] from the given slice of 16-bit audio `samples`
+/// with the default trigger hysteresis and the minimum interval between edges.
+///
+/// * `sample_rate` is the number of `samples` per second.
+/// * `cpu_hz` is the CPU clock frequency used to calculate T-state intervals.
+///
+/// # Panics
+/// Panics if `sample_rate` is `0`.
+pub fn wav_to_ear_deltas(samples: &[i16], sample_rate: u32, cpu_hz: u32) -> SamplesPulseIter<Copied<slice::Iter<'_, i16>>> {
+    SamplesPulseIter::new(samples.iter().copied(), sample_rate, cpu_hz)
+}
+
+impl<I> SamplesPulseIter<I> {
+    /// Creates a new `SamplesPulseIter` from the given iterator of 16-bit audio `samples`.
+    ///
+    /// * `sample_rate` is the number of `samples` per second.
+    /// * `cpu_hz` is the CPU clock frequency used to calculate T-state intervals.
+    ///
+    /// # Panics
+    /// Panics if `sample_rate` is `0`.
+    pub fn new(samples: I, sample_rate: u32, cpu_hz: u32) -> Self {
+        assert_ne!(sample_rate, 0, "sample rate must not be 0");
+        SamplesPulseIter {
+            samples,
+            sample_rate: sample_rate.into(),
+            cpu_hz: cpu_hz.into(),
+            hysteresis: SAMPLES_HYSTERESIS,
+            min_delta: SAMPLES_MIN_DELTA,
+            position: 0,
+            level: None,
+            pending: None,
+            last_edge: 0
+        }
+    }
+    /// Sets the trigger hysteresis.
+    pub fn with_hysteresis(mut self, hysteresis: i16) -> Self {
+        self.hysteresis = hysteresis.max(0);
+        self
+    }
+    /// Sets the minimum interval in T-states between edges.
+    pub fn with_min_delta(mut self, min_delta: u32) -> Self {
+        self.min_delta = min_delta;
+        self
+    }
+    /// Returns the current signal level or `None` if it hasn't been determined yet.
+    pub fn level(&self) -> Option<bool> {
+        self.level
+    }
+    /// Returns the underlying iterator.
+    pub fn into_inner(self) -> I {
+        self.samples
+    }
+}
+
+impl<I: Iterator<Item=i16>> Iterator for SamplesPulseIter<I> {
+    type Item = NonZeroU32;
+    fn next(&mut self) -> Option<NonZeroU32> {
+        for sample in self.samples.by_ref() {
+            let ts = self.position * self.cpu_hz / self.sample_rate;
+            self.position += 1;
+            let sample_level = if sample > self.hysteresis {
+                Some(true)
+            }
+            else if sample < -self.hysteresis {
+                Some(false)
+            }
+            else {
+                None
+            };
+            match (self.level, sample_level) {
+                (None, Some(level)) => {
+                    self.level = Some(level);
+                    self.last_edge = ts;
+                }
+                (Some(level), Some(sample_level)) => {
+                    if level == sample_level {
+                        self.pending = None;
+                    }
+                    else if self.pending.is_none() {
+                        self.pending = Some(ts);
+                    }
+                }
+                _ => {}
+            }
+            if let Some(edge) = self.pending {
+                if ts - edge >= self.min_delta.into() {
+                    let (delta, level) = edge_delta(edge, self.last_edge, self.level);
+                    self.pending = None;
+                    self.level = level;
+                    self.last_edge = edge;
+                    if delta.is_some() {
+                        return delta
+                    }
+                }
+            }
+        }
+        let edge = self.pending.take()?;
+        let (delta, level) = edge_delta(edge, self.last_edge, self.level);
+        self.level = level;
+        self.last_edge = edge;
+        delta
+    }
+}
+
+#[inline]
+fn edge_delta(edge: u64, last_edge: u64, level: Option<bool>) -> (Option<NonZeroU32>, Option<bool>) {
+    let delta = (edge - last_edge).min(u32::MAX.into()) as u32;
+    (NonZeroU32::new(delta), level.map(|level| !level))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_pulse_iter_works() {
+        let mut samples = vec![0i16; 5];
+        samples.extend_from_slice(&[10000; 30]);
+        samples.extend_from_slice(&[-10000; 30]);
+        samples.extend_from_slice(&[10000; 30]);
+        samples.extend_from_slice(&[-10000; 30]);
+        samples[50] = 10000; // a spurious spike
+        samples[100] = 500; // within hysteresis
+        let deltas: Vec<u32> = wav_to_ear_deltas(&samples, 44100, 3_500_000).map(NonZeroU32::get).collect();
+        // edges at samples: 35, 65, 95
+        let ts = |n: u32| n * 3_500_000 / 44100;
+        assert_eq!(vec![ts(35) - ts(5), ts(65) - ts(35), ts(95) - ts(65)], deltas);
+
+        let deltas: Vec<u32> = wav_to_ear_deltas(&samples, 44100, 3_500_000)
+                               .with_min_delta(0)
+                               .with_hysteresis(200)
+                               .map(NonZeroU32::get).collect();
+        assert_eq!(vec![ts(35) - ts(5), ts(50) - ts(35), ts(51) - ts(50), ts(65) - ts(51),
+                        ts(95) - ts(65), ts(100) - ts(95), ts(101) - ts(100)], deltas);
+
+        let mut iter = wav_to_ear_deltas(&[], 44100, 3_500_000);
+        assert_eq!(None, iter.next());
+        assert_eq!(None, iter.level());
+    }
+}
@@ -5,23 +5,42 @@
 
     For the full copyright notice, see the lib.rs file.
 */
+/*! **TZX** file format utilities.
+
+A **TZX** file starts with a 10 byte header: the `"ZXTape!"` signature followed by the `0x1A` byte
+and the major and minor revision numbers. The header is followed by a sequence of blocks, each
+starting with a single byte [block ID][TzxId].
+
+The following blocks are interpreted by [TzxReader] and converted to [TzxBlock]s:
+
+* `0x10` standard speed data, the same as *TAP* chunks with an additional pause.
+* `0x11` turbo speed data, with custom pilot, sync and bit pulse timings.
+* `0x12` pure tone.
+* `0x13` a sequence of pulses of various lengths.
+* `0x14` pure data, without pilot and sync pulses.
+* `0x20` pause or "stop the tape" command.
+
+All other blocks are skipped, using their length fields.
+
+Each [TzxBlock] can be converted to T-state pulse intervals via [TzxBlock::pulse_iter], which can be used
+to feed the `EAR IN` buffer of the ZX Spectrum emulator
+(e.g. via [EarIn::feed_ear_in][spectrusty_core::chip::EarIn::feed_ear_in]).
+*/
 use core::num::NonZeroU32;
 use core::convert::TryFrom;
-use std::io::{self, Read, Write, Seek};
+use std::io::{self, Read};
 
-use super::tap::TapChunkWriter;
+use crate::ReadExactEx;
+use crate::tap::pulse::consts::*;
 
-pub trait TzxChunk {
-    type PulseIter: Iterator<Item=NonZeroU32>;
-    fn id(&self) -> TzxId;
-    fn len(&self) -> usize;
-    fn pulse_iter(&self) -> Self::PulseIter;
-    fn as_slice(&self) -> &[u8];
-    fn write_to_tap<W: Write + Seek>(&self, rd: &mut TapChunkWriter<W>) -> io::Result<Option<usize>>;
-}
+/// The signature of the **TZX** file, including the end of text marker.
+pub const TZX_SIGNATURE: &[u8;8] = b"ZXTape!\x1A";
+/// The number of T-states in a millisecond of a pause, assuming the 3.5 MHz clock.
+pub const PAUSE_MS_TSTATES: u32 = 3500;
 
 macro_rules! tzx_id {
     ($($id:ident = $n:literal),*) => {
+        /// **TZX** block identifiers.
         #[repr(u8)]
         #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
         pub enum TzxId {
@@ -74,3 +93,472 @@ impl From<TzxId> for u8 {
         id as u8
     }
 }
+
+/// Pulse timings of the turbo speed data block, in T-states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TurboTimings {
+    /// The length of a single pilot pulse.
+    pub pilot: u16,
+    /// The length of the 1st sync pulse.
+    pub sync1: u16,
+    /// The length of the 2nd sync pulse.
+    pub sync2: u16,
+    /// The length of the bit value 0 pulse.
+    pub zero: u16,
+    /// The length of the bit value 1 pulse.
+    pub one: u16,
+    /// The number of pilot pulses.
+    pub pilot_count: u16,
+}
+
+/// A **TZX** block converted from the data read by [TzxReader].
+///
+/// Pause durations are given in milliseconds.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TzxBlock {
+    /// Standard speed data, with timings of the ROM *TAPE* routines.
+    StandardSpeed { pause: u16, data: Vec<u8> },
+    /// Turbo speed data, with custom timings.
+    ///
+    /// `used_bits` is the number of bits used in the last byte of `data`, starting with the most significant one.
+    TurboSpeed { timings: TurboTimings, used_bits: u8, pause: u16, data: Vec<u8> },
+    /// `count` pulses, each `pulse` T-states long.
+    PureTone { pulse: u16, count: u16 },
+    /// A sequence of pulses, in T-states.
+    SeqOfPulses(Vec<u16>),
+    /// Data without pilot and sync pulses.
+    ///
+    /// `zero` and `one` are the lengths of the bit pulses in T-states.
+    /// `used_bits` is the number of bits used in the last byte of `data`, starting with the most significant one.
+    PureData { zero: u16, one: u16, used_bits: u8, pause: u16, data: Vec<u8> },
+    /// A pause, or if `0`, the "stop the tape" command.
+    Pause(u16),
+}
+
+/// Iterates over the T-state pulse intervals of a single [TzxBlock].
+///
+/// A pulse with the length of `0` T-states merges its neighbouring pulses into one, so the polarity of
+/// the following pulses is preserved. Such a pulse at the beginning or the end of the block is omitted.
+#[derive(Clone, Debug)]
+pub struct TzxPulseIter<'a> {
+    tone: u16,
+    tone_count: u16,
+    seq: &'a [u16],
+    sync: [u16; 2],
+    sync_count: u8,
+    data: &'a [u8],
+    zero: u16,
+    one: u16,
+    data_pulses: u32,
+    pulse: u32,
+    pause: u16,
+    next: Option<u32>,
+}
+
+/// Reads **TZX** blocks from the underlying reader via an [Iterator] interface.
+///
+/// Unsupported blocks are skipped. Errors are reported as `Some(Err(..))` items.
+#[derive(Debug)]
+pub struct TzxReader<R> {
+    rd: R,
+    version: (u8, u8),
+}
+
+/// Validates the **TZX** header read from `rd` and returns an instance of [TzxReader].
+pub fn read_tzx<R: Read>(rd: R) -> io::Result<TzxReader<R>> {
+    TzxReader::new(rd)
+}
+
+impl Default for TurboTimings {
+    fn default() -> Self {
+        TurboTimings {
+            pilot: LEAD_PULSE_LENGTH.get() as u16,
+            sync1: SYNC_PULSE1_LENGTH.get() as u16,
+            sync2: SYNC_PULSE2_LENGTH.get() as u16,
+            zero: ZERO_PULSE_LENGTH.get() as u16,
+            one: ONE_PULSE_LENGTH.get() as u16,
+            pilot_count: LEAD_PULSES_DATA,
+        }
+    }
+}
+
+impl TurboTimings {
+    /// Returns timings of the ROM *TAPE* routines for a block with the given `flag` byte.
+    pub fn standard(flag: u8) -> Self {
+        let pilot_count = if flag & 0x80 == 0 { LEAD_PULSES_HEAD } else { LEAD_PULSES_DATA };
+        TurboTimings { pilot_count, ..Default::default() }
+    }
+}
+
+impl TzxBlock {
+    /// Returns the ID of this block.
+    pub fn id(&self) -> TzxId {
+        match self {
+            TzxBlock::StandardSpeed {..} => TzxId::StandardSpeed,
+            TzxBlock::TurboSpeed {..} => TzxId::TurboSpeed,
+            TzxBlock::PureTone {..} => TzxId::PureTone,
+            TzxBlock::SeqOfPulses(..) => TzxId::SeqOfPulses,
+            TzxBlock::PureData {..} => TzxId::PureData,
+            TzxBlock::Pause(..) => TzxId::Pause,
+        }
+    }
+    /// Returns the data of this block if it's a data block.
+    pub fn data(&self) -> Option<&[u8]> {
+        match self {
+            TzxBlock::StandardSpeed { data, .. }|
+            TzxBlock::TurboSpeed { data, .. }|
+            TzxBlock::PureData { data, .. } => Some(data),
+            _ => None
+        }
+    }
+    /// Returns an iterator of T-state pulse intervals generated from this block.
+    ///
+    /// Data blocks and pause blocks end with a single interval of the pause duration if it's not `0`.
+    pub fn pulse_iter(&self) -> TzxPulseIter<'_> {
+        let mut iter = TzxPulseIter {
+            tone: 0, tone_count: 0,
+            seq: &[],
+            sync: [0; 2], sync_count: 0,
+            data: &[], zero: 0, one: 0, data_pulses: 0, pulse: 0,
+            pause: 0,
+            next: None
+        };
+        match *self {
+            TzxBlock::StandardSpeed { pause, ref data } => {
+                let timings = TurboTimings::standard(data.first().copied().unwrap_or(0));
+                iter.set_timings(&timings);
+                iter.set_data(data, 8, timings.zero, timings.one);
+                iter.pause = pause;
+            }
+            TzxBlock::TurboSpeed { ref timings, used_bits, pause, ref data } => {
+                iter.set_timings(timings);
+                iter.set_data(data, used_bits, timings.zero, timings.one);
+                iter.pause = pause;
+            }
+            TzxBlock::PureTone { pulse, count } => {
+                iter.tone = pulse;
+                iter.tone_count = count;
+            }
+            TzxBlock::SeqOfPulses(ref pulses) => {
+                iter.seq = pulses;
+            }
+            TzxBlock::PureData { zero, one, used_bits, pause, ref data } => {
+                iter.set_data(data, used_bits, zero, one);
+                iter.pause = pause;
+            }
+            TzxBlock::Pause(pause) => {
+                iter.pause = pause;
+            }
+        }
+        iter
+    }
+}
+
+impl<'a> TzxPulseIter<'a> {
+    fn set_timings(&mut self, timings: &TurboTimings) {
+        self.tone = timings.pilot;
+        self.tone_count = timings.pilot_count;
+        self.sync = [timings.sync1, timings.sync2];
+        self.sync_count = 2;
+    }
+
+    fn set_data(&mut self, data: &'a [u8], used_bits: u8, zero: u16, one: u16) {
+        let used_bits = if (1..=8).contains(&used_bits) { used_bits } else { 8 };
+        self.data = data;
+        self.zero = zero;
+        self.one = one;
+        self.data_pulses = match data.len() {
+            0 => 0,
+            len => 2 * ((len as u32 - 1) * 8 + used_bits as u32)
+        };
+    }
+
+    fn next_pulse(&mut self) -> Option<u32> {
+        if self.tone_count != 0 {
+            self.tone_count -= 1;
+            return Some(self.tone.into())
+        }
+        if let Some((&pulse, seq)) = self.seq.split_first() {
+            self.seq = seq;
+            return Some(pulse.into())
+        }
+        if self.sync_count != 0 {
+            let pulse = self.sync[2 - self.sync_count as usize];
+            self.sync_count -= 1;
+            return Some(pulse.into())
+        }
+        if self.pulse < self.data_pulses {
+            let bit = self.pulse >> 1;
+            self.pulse += 1;
+            let byte = self.data[(bit >> 3) as usize];
+            return Some(if byte & (0x80 >> (bit & 7)) != 0 { self.one } else { self.zero }.into())
+        }
+        if self.pause != 0 {
+            let pause = self.pause as u32 * PAUSE_MS_TSTATES;
+            self.pause = 0;
+            return Some(pause)
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for TzxPulseIter<'a> {
+    type Item = NonZeroU32;
+    fn next(&mut self) -> Option<NonZeroU32> {
+        loop {
+            let mut pulse = match self.next.take() {
+                Some(pulse) => pulse,
+                None => self.next_pulse()?
+            };
+            loop {
+                match self.next_pulse() {
+                    // the edges on both sides of a zero-length pulse cancel each other out
+                    Some(0) => match self.next_pulse() {
+                        Some(next) => pulse = pulse.saturating_add(next),
+                        None => break
+                    },
+                    next => {
+                        self.next = next;
+                        break
+                    }
+                }
+            }
+            if let Some(pulse) = NonZeroU32::new(pulse) {
+                return Some(pulse)
+            }
+        }
+    }
+}
+
+impl<R> TzxReader<R> {
+    /// Returns the major and minor revision numbers of the **TZX** file.
+    pub fn version(&self) -> (u8, u8) {
+        self.version
+    }
+    /// Returns a shared reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+    /// Returns a mutable reference to the inner reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.rd
+    }
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+}
+
+impl<R: Read> TzxReader<R> {
+    /// Creates a new `TzxReader` after reading and validating the **TZX** header.
+    ///
+    /// Returns an error with [io::ErrorKind::InvalidData] if the signature doesn't match.
+    pub fn new(mut rd: R) -> io::Result<Self> {
+        let mut header = [0u8; 10];
+        rd.read_exact(&mut header)?;
+        if &header[..8] != TZX_SIGNATURE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a TZX file"))
+        }
+        Ok(TzxReader { rd, version: (header[8], header[9]) })
+    }
+    /// Reads the next supported block, skipping the unsupported ones.
+    ///
+    /// Returns `Ok(None)` if there are no more blocks.
+    pub fn read_block(&mut self) -> io::Result<Option<TzxBlock>> {
+        loop {
+            let mut id = [0u8];
+            if !self.rd.read_exact_or_none(&mut id)? {
+                return Ok(None)
+            }
+            let block = match TzxId::try_from(id[0]) {
+                Ok(TzxId::StandardSpeed) => {
+                    let [pause, len] = self.read_words()?;
+                    let data = self.read_data(len.into())?;
+                    TzxBlock::StandardSpeed { pause, data }
+                }
+                Ok(TzxId::TurboSpeed) => {
+                    let [pilot, sync1, sync2, zero, one, pilot_count] = self.read_words()?;
+                    let used_bits = self.read_byte()?;
+                    let [pause] = self.read_words()?;
+                    let len = self.read_len(3)?;
+                    let data = self.read_data(len)?;
+                    let timings = TurboTimings { pilot, sync1, sync2, zero, one, pilot_count };
+                    TzxBlock::TurboSpeed { timings, used_bits, pause, data }
+                }
+                Ok(TzxId::PureTone) => {
+                    let [pulse, count] = self.read_words()?;
+                    TzxBlock::PureTone { pulse, count }
+                }
+                Ok(TzxId::SeqOfPulses) => {
+                    let count = self.read_byte()?;
+                    let pulses = (0..count).map(|_| self.read_words().map(|[pulse]| pulse))
+                                           .collect::<io::Result<_>>()?;
+                    TzxBlock::SeqOfPulses(pulses)
+                }
+                Ok(TzxId::PureData) => {
+                    let [zero, one] = self.read_words()?;
+                    let used_bits = self.read_byte()?;
+                    let [pause] = self.read_words()?;
+                    let len = self.read_len(3)?;
+                    let data = self.read_data(len)?;
+                    TzxBlock::PureData { zero, one, used_bits, pause, data }
+                }
+                Ok(TzxId::Pause) => {
+                    let [pause] = self.read_words()?;
+                    TzxBlock::Pause(pause)
+                }
+                id => {
+                    self.skip_block(id.ok())?;
+                    continue
+                }
+            };
+            return Ok(Some(block))
+        }
+    }
+
+    fn skip_block(&mut self, id: Option<TzxId>) -> io::Result<()> {
+        let len = match id {
+            Some(TzxId::DirectRec) => {
+                self.skip(5)?;
+                self.read_len(3)?
+            }
+            Some(TzxId::GroupStart)|Some(TzxId::Text) => self.read_len(1)?,
+            Some(TzxId::GroupEnd)|Some(TzxId::LoopEnd)|Some(TzxId::Return) => 0,
+            Some(TzxId::Jump)|Some(TzxId::LoopStart) => 2,
+            Some(TzxId::CallSeq) => self.read_len(2)? * 2,
+            Some(TzxId::Select)|Some(TzxId::Archive) => self.read_len(2)?,
+            Some(TzxId::Message) => {
+                self.skip(1)?;
+                self.read_len(1)?
+            }
+            Some(TzxId::Hardware) => self.read_len(1)? * 3,
+            Some(TzxId::Custom) => {
+                self.skip(16)?;
+                self.read_len(4)?
+            }
+            Some(TzxId::Glue) => 9,
+            // all the other blocks, including the ones defined in the future
+            // revisions, start with a 4-byte length field
+            _ => self.read_len(4)?
+        };
+        self.skip(len)
+    }
+
+    fn skip(&mut self, len: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut self.rd.by_ref().take(len), &mut io::sink())?;
+        if skipped != len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of TZX block"))
+        }
+        Ok(())
+    }
+
+    fn read_byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0u8];
+        self.rd.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn read_words<const N: usize>(&mut self) -> io::Result<[u16; N]> {
+        let mut words = [0u16; N];
+        for word in words.iter_mut() {
+            let mut buf = [0u8; 2];
+            self.rd.read_exact(&mut buf)?;
+            *word = u16::from_le_bytes(buf);
+        }
+        Ok(words)
+    }
+    /// Reads a little-endian length field of `size` bytes.
+    fn read_len(&mut self, size: usize) -> io::Result<u64> {
+        let mut buf = [0u8; 8];
+        self.rd.read_exact(&mut buf[..size])?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn read_data(&mut self, len: u64) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.rd.by_ref().take(len).read_to_end(&mut data)?;
+        if data.len() as u64 != len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of TZX block"))
+        }
+        Ok(data)
+    }
+}
+
+impl<R: Read> Iterator for TzxReader<R> {
+    type Item = io::Result<TzxBlock>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_block().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+
+    #[test]
+    fn read_tzx_works() -> io::Result<()> {
+        let mut tzx = Vec::new();
+        tzx.extend_from_slice(b"ZXTape!\x1A\x01\x14");
+        tzx.extend_from_slice(&[0x30, 3, b'a', b'b', b'c']);
+        tzx.extend_from_slice(&[0x10, 0xE8, 0x03, 2, 0, 0xFF, 0x80]);
+        tzx.extend_from_slice(&[0x12, 100, 0, 3, 0]);
+        tzx.extend_from_slice(&[0x13, 3, 10, 0, 0, 0, 20, 0]);
+        tzx.extend_from_slice(&[0x13, 5, 0, 0, 5, 0, 0, 0, 0, 0, 6, 0]);
+        tzx.extend_from_slice(&[0x11, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 2, 0, 2, 0, 0, 1, 0, 0, 0xC0]);
+        tzx.extend_from_slice(&[0x14, 4, 0, 5, 0, 1, 2, 0, 2, 0, 0, 0, 0x7F]);
+        tzx.extend_from_slice(&[0x99, 2, 0, 0, 0, 0xAA, 0xBB]);
+        tzx.extend_from_slice(&[0x20, 0, 0]);
+        let mut reader = read_tzx(Cursor::new(tzx))?;
+        assert_eq!((1, 20), reader.version());
+
+        let block = reader.next().unwrap()?;
+        assert_eq!(TzxBlock::StandardSpeed { pause: 1000, data: vec![0xFF, 0x80] }, block);
+        assert_eq!(TzxId::StandardSpeed, block.id());
+        let pulses: Vec<u32> = block.pulse_iter().map(NonZeroU32::get).collect();
+        assert_eq!(LEAD_PULSES_DATA as usize + 2 + 32 + 1, pulses.len());
+        assert!(pulses[..LEAD_PULSES_DATA as usize].iter().all(|&p| p == LEAD_PULSE_LENGTH.get()));
+        let pulses = &pulses[LEAD_PULSES_DATA as usize..];
+        assert_eq!(&[SYNC_PULSE1_LENGTH.get(), SYNC_PULSE2_LENGTH.get()], &pulses[..2]);
+        assert!(pulses[2..18].iter().all(|&p| p == ONE_PULSE_LENGTH.get()));
+        assert!(pulses[18..20].iter().all(|&p| p == ONE_PULSE_LENGTH.get()));
+        assert!(pulses[20..34].iter().all(|&p| p == ZERO_PULSE_LENGTH.get()));
+        assert_eq!(1000 * PAUSE_MS_TSTATES, pulses[34]);
+
+        let block = reader.next().unwrap()?;
+        assert_eq!(TzxBlock::PureTone { pulse: 100, count: 3 }, block);
+        assert_eq!(vec![100, 100, 100], block.pulse_iter().map(NonZeroU32::get).collect::<Vec<_>>());
+
+        let block = reader.next().unwrap()?;
+        assert_eq!(TzxBlock::SeqOfPulses(vec![10, 0, 20]), block);
+        assert_eq!(vec![30], block.pulse_iter().map(NonZeroU32::get).collect::<Vec<_>>());
+
+        let block = reader.next().unwrap()?;
+        assert_eq!(TzxBlock::SeqOfPulses(vec![0, 5, 0, 0, 6]), block);
+        assert_eq!(vec![5, 6], block.pulse_iter().map(NonZeroU32::get).collect::<Vec<_>>());
+
+        let block = reader.next().unwrap()?;
+        assert_eq!(TzxBlock::TurboSpeed {
+            timings: TurboTimings { pilot: 1, sync1: 2, sync2: 3, zero: 4, one: 5, pilot_count: 2 },
+            used_bits: 2, pause: 0, data: vec![0xC0]
+        }, block);
+        assert_eq!(vec![1, 1, 2, 3, 5, 5, 5, 5],
+                   block.pulse_iter().map(NonZeroU32::get).collect::<Vec<_>>());
+
+        let block = reader.next().unwrap()?;
+        assert_eq!(TzxBlock::PureData { zero: 4, one: 5, used_bits: 1, pause: 2, data: vec![0, 0x7F] }, block);
+        assert_eq!(vec![4; 18].into_iter().chain(Some(2 * PAUSE_MS_TSTATES)).collect::<Vec<_>>(),
+                   block.pulse_iter().map(NonZeroU32::get).collect::<Vec<_>>());
+
+        let block = reader.next().unwrap()?;
+        assert_eq!(TzxBlock::Pause(0), block);
+        assert_eq!(None, block.pulse_iter().next());
+        assert!(reader.next().is_none());
+
+        let err = read_tzx(Cursor::new(b"ZXTape?\x1A\x01\x14")).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        let mut reader = read_tzx(Cursor::new(b"ZXTape!\x1A\x01\x14\x10\x00\x00\x05\x00\xFF"))?;
+        assert_eq!(io::ErrorKind::UnexpectedEof, reader.next().unwrap().unwrap_err().kind());
+        Ok(())
+    }
+}
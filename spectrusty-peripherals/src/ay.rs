@@ -13,11 +13,14 @@
 use core::fmt;
 use core::ops::{Deref, DerefMut};
 use core::marker::PhantomData;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
 
 pub mod audio;
+#[cfg(feature = "std")]
 pub mod serial128;
 
 use spectrusty_core::clock::FTs;
@@ -47,6 +50,7 @@ pub enum AyRegister {
 
 pub const NUM_SOUND_GEN_REGISTERS: usize = 14;
 
+// Unused bits of the registers are always read back as 0 on AY-3-8910.
 const REG_MASKS: [u8;16] = [
     0xff, 0x0f, 0xff, 0x0f, 0xff, 0x0f, 0x1f, 0xff,
     0x1f, 0x1f, 0x1f, 0xff, 0xff, 0x0f, 0xff, 0xff
@@ -172,6 +176,33 @@ pub trait AyIoPort {
 #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 pub struct AyIoNullPort<T>(PhantomData<T>);
 
+/// An [Ay3_891xIo] I/O device implementation that calls user provided closures.
+///
+/// Allows wiring the AY-3-8910 general-purpose I/O ports to any external peripheral without the need
+/// to implement [AyIoPort] trait. Each callback is optional. If the read callback is not set, reading
+/// from the port returns `0xff`.
+///
+/// Callbacks receive the timestamp of the I/O operation, so they can be used to emulate time-sensitive
+/// devices.
+///
+/// # Note
+/// Callbacks are not serialized and should be provided again after deserialization.
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct AyIoCallbackPort<T> {
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    read: Option<Box<AyIoReadFn<T>>>,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    write: Option<Box<AyIoWriteFn<T>>>,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    reset: Option<Box<AyIoEventFn<T>>>,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    end_frame: Option<Box<AyIoEventFn<T>>>,
+}
+
+type AyIoReadFn<T> = dyn FnMut(u16, T) -> u8;
+type AyIoWriteFn<T> = dyn FnMut(u16, u8, T);
+type AyIoEventFn<T> = dyn FnMut(T);
+
 /// Allows recording of changes to AY-3-8910 audio registers with timestamps.
 pub trait AyRegRecorder {
     type Timestamp;
@@ -204,7 +235,10 @@ pub type Ay3_8913Io<T> = Ay3_8910Io<T>;
 pub struct Ay3_891xIo<T, R, A, B> {
     /// Provides access to the recorded changes of sound generator registers.
     /// The changes are required to generate sound with [audio::Ay3_891xAudio].
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    ///
+    /// The recorded changes are serialized, so the sound of the frame being emulated when the snapshot
+    /// was taken can be rendered after the snapshot is restored.
+    #[cfg_attr(feature = "snapshot", serde(default))]
     pub recorder: R,
     /// An instance of port `A` [AyIoPort] I/O device implementation.
     #[cfg_attr(feature = "snapshot", serde(default))]
@@ -222,10 +256,12 @@ pub struct Ay3_891xIo<T, R, A, B> {
 ///
 /// Useful when no sound will be generated.
 #[derive(Default, Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 pub struct AyRegNullRecorder<T>(PhantomData<T>);
 
 /// A convenient recorder for [Ay3_891xIo] that records changes in a [Vec].
 #[derive(Default, Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 pub struct AyRegVecRecorder<T>(pub Vec<(T,AyRegister,u8)>);
 
 impl<T,R,A,B> Ay3_891xIo<T,R,A,B>
@@ -329,6 +365,10 @@ where A: AyIoPort<Timestamp=T>,
     }
     /// Reads data from a previously selected register.
     ///
+    /// Like on the AY-3-8910 chip, the unused bits of the registers are read as 0, e.g. reading back
+    /// `0xFF` written to the coarse tone period register results in `0x0F`. The mixer and the I/O
+    /// registers are read back with all bits.
+    ///
     /// This method is being used to interface the host controller I/O operation.
     #[inline]
     pub fn data_port_read(&mut self, port: u16, timestamp: T) -> u8 {
@@ -400,10 +440,154 @@ impl AyRegChange {
     }
 }
 
+/// Formats the change as the register name and its value, followed by the interpretation of the
+/// value, e.g. `ToneFineA=0x7C (period low: 124)` or `EnvShape=0x0E (continue, attack, alternate)`.
+///
+/// The timestamp is not included.
+impl fmt::Display for AyRegChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use AyRegister::*;
+        let AyRegChange { reg, val, .. } = *self;
+        write!(f, "{:?}=0x{:02X}", reg, val)?;
+        let val = val & REG_MASKS[reg as usize];
+        match reg {
+            ToneFineA|ToneFineB|ToneFineC|EnvPerFine => write!(f, " (period low: {})", val),
+            ToneCoarseA|ToneCoarseB|ToneCoarseC|EnvPerCoarse => {
+                write!(f, " (period high: {})", val)
+            }
+            NoisePeriod => write!(f, " (period {})", val),
+            MixerControl => {
+                let chans = |f: &mut fmt::Formatter<'_>, bits: u8| -> fmt::Result {
+                    if bits & 7 == 7 {
+                        return f.write_str("-")
+                    }
+                    for (n, chan) in ['A', 'B', 'C'].iter().enumerate() {
+                        if bits & (1 << n) == 0 {
+                            write!(f, "{}", chan)?;
+                        }
+                    }
+                    Ok(())
+                };
+                f.write_str(" (tone: ")?;
+                chans(f, val)?;
+                f.write_str(", noise: ")?;
+                chans(f, val >> 3)?;
+                let dir = |bit: u8| if val & bit == 0 { "in" } else { "out" };
+                write!(f, ", IoA: {}, IoB: {})", dir(0x40), dir(0x80))
+            }
+            AmpLevelA|AmpLevelB|AmpLevelC => {
+                if val & 0x10 != 0 {
+                    f.write_str(" (envelope)")
+                }
+                else {
+                    write!(f, " (level {})", val)
+                }
+            }
+            EnvShape => {
+                let flags = [(audio::ENV_SHAPE_CONT_MASK, "continue"),
+                             (audio::ENV_SHAPE_ATTACK_MASK, "attack"),
+                             (audio::ENV_SHAPE_ALT_MASK, "alternate"),
+                             (audio::ENV_SHAPE_HOLD_MASK, "hold")];
+                let mut sep = " (";
+                for (_, name) in flags.iter().filter(|(mask, _)| val & mask != 0) {
+                    write!(f, "{}{}", sep, name)?;
+                    sep = ", ";
+                }
+                if val == 0 {
+                    f.write_str(" (-")?;
+                }
+                f.write_str(")")
+            }
+            IoA|IoB => Ok(())
+        }
+    }
+}
+
 impl<T> AyIoPort for AyIoNullPort<T> {
     type Timestamp = T;
 }
 
+impl<T> AyIoCallbackPort<T> {
+    /// Sets the closure called when the port is being read from.
+    ///
+    /// The closure receives the I/O port address and the timestamp and should return the port data.
+    pub fn set_read_callback<F: FnMut(u16, T) -> u8 + 'static>(&mut self, f: F) {
+        self.read = Some(Box::new(f));
+    }
+    /// Sets the closure called when the port is being written to.
+    ///
+    /// The closure receives the I/O port address, the written data and the timestamp.
+    pub fn set_write_callback<F: FnMut(u16, u8, T) + 'static>(&mut self, f: F) {
+        self.write = Some(Box::new(f));
+    }
+    /// Sets the closure called when the port is being reset with the timestamp of the reset.
+    pub fn set_reset_callback<F: FnMut(T) + 'static>(&mut self, f: F) {
+        self.reset = Some(Box::new(f));
+    }
+    /// Sets the closure called at the end of each frame with the value of the cycle clock after
+    /// the frame execution stopped.
+    pub fn set_end_frame_callback<F: FnMut(T) + 'static>(&mut self, f: F) {
+        self.end_frame = Some(Box::new(f));
+    }
+    /// Removes all callbacks.
+    pub fn clear_callbacks(&mut self) {
+        self.read = None;
+        self.write = None;
+        self.reset = None;
+        self.end_frame = None;
+    }
+}
+
+impl<T> Default for AyIoCallbackPort<T> {
+    fn default() -> Self {
+        AyIoCallbackPort { read: None, write: None, reset: None, end_frame: None }
+    }
+}
+
+impl<T> fmt::Debug for AyIoCallbackPort<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AyIoCallbackPort")
+            .field("read", &self.read.is_some())
+            .field("write", &self.write.is_some())
+            .field("reset", &self.reset.is_some())
+            .field("end_frame", &self.end_frame.is_some())
+            .finish()
+    }
+}
+
+impl<T> AyIoPort for AyIoCallbackPort<T> {
+    type Timestamp = T;
+
+    #[inline]
+    fn ay_io_reset(&mut self, timestamp: T) {
+        if let Some(reset) = self.reset.as_mut() {
+            reset(timestamp)
+        }
+    }
+
+    #[inline]
+    fn ay_io_write(&mut self, addr: u16, data: u8, timestamp: T) {
+        if let Some(write) = self.write.as_mut() {
+            write(addr, data, timestamp)
+        }
+    }
+
+    #[inline]
+    fn ay_io_read(&mut self, addr: u16, timestamp: T) -> u8 {
+        match self.read.as_mut() {
+            Some(read) => read(addr, timestamp),
+            None => 0xff
+        }
+    }
+
+    #[inline]
+    fn end_frame(&mut self, timestamp: T) {
+        if let Some(end_frame) = self.end_frame.as_mut() {
+            end_frame(timestamp)
+        }
+    }
+}
+
 impl<T> AyRegRecorder for AyRegNullRecorder<T> {
     type Timestamp = T;
     #[inline]
@@ -443,3 +627,27 @@ impl<T> DerefMut for AyRegVecRecorder<T> {
         &mut self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ay_reg_change_display_works() {
+        let text = |reg, val| AyRegChange::new(0, reg, val).to_string();
+        assert_eq!(text(AyRegister::ToneFineA, 0x7C), "ToneFineA=0x7C (period low: 124)");
+        assert_eq!(text(AyRegister::ToneCoarseB, 0xF1), "ToneCoarseB=0xF1 (period high: 1)");
+        assert_eq!(text(AyRegister::NoisePeriod, 0x3F), "NoisePeriod=0x3F (period 31)");
+        assert_eq!(text(AyRegister::MixerControl, 0b0111_1010),
+                   "MixerControl=0x7A (tone: AC, noise: -, IoA: out, IoB: in)");
+        assert_eq!(text(AyRegister::MixerControl, 0b1010_1000),
+                   "MixerControl=0xA8 (tone: ABC, noise: B, IoA: in, IoB: out)");
+        assert_eq!(text(AyRegister::AmpLevelC, 0x0F), "AmpLevelC=0x0F (level 15)");
+        assert_eq!(text(AyRegister::AmpLevelA, 0x1F), "AmpLevelA=0x1F (envelope)");
+        assert_eq!(text(AyRegister::EnvPerCoarse, 0x12), "EnvPerCoarse=0x12 (period high: 18)");
+        assert_eq!(text(AyRegister::EnvShape, 0x0E), "EnvShape=0x0E (continue, attack, alternate)");
+        assert_eq!(text(AyRegister::EnvShape, 0x09), "EnvShape=0x09 (continue, hold)");
+        assert_eq!(text(AyRegister::EnvShape, 0x00), "EnvShape=0x00 (-)");
+        assert_eq!(text(AyRegister::IoB, 0x55), "IoB=0x55");
+    }
+}
@@ -6,13 +6,15 @@
     For the full copyright notice, see the lib.rs file.
 */
 //! The emulation of the AY-3-8910/8912/8913 sound generator.
+#[cfg(feature = "std")]
 use core::num::NonZeroU16;
 use core::marker::PhantomData;
+use alloc::vec::Vec;
 
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
 
-use super::{AyRegister, AyRegChange};
+use super::{AyRegister, AyRegChange, NUM_SOUND_GEN_REGISTERS};
 use spectrusty_core::audio::*;
 
 /// Internal clock divisor.
@@ -75,7 +77,9 @@ pub const FUSE_AMPS_I16: [i16;16] = [0x0000, 0x01c2, 0x029e, 0x03b8,
 
 /// This may be used to calculate other levels, but I'd discourage from using it in the player
 /// as it uses expensive float calculations.
+#[cfg(feature = "std")]
 pub struct LogAmpLevels16<T>(PhantomData<T>);
+#[cfg(feature = "std")]
 impl<T: Copy + FromSample<f32>> AmpLevels<T> for LogAmpLevels16<T> {
     fn amp_level(level: u32) -> T {
         // as proposed by https://www.dr-lex.be/info-stuff/volumecontrols.html
@@ -125,10 +129,64 @@ pub trait AyAudioFrame<B: Blep> {
     );
 }
 
+/// A mapping of AY-3-891x `[A, B, C]` channels to [Blep] audio channels.
+///
+/// The stereo presets assume the channel layout of [BlepStereo]: `0` is the left channel, `1` is the
+/// right channel and `2` is being mixed into both of them (center).
+///
+/// Convert it into `[usize; 3]` to pass it as the `channels` argument to the audio rendering methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct AyChannelMap(pub [usize; 3]);
+
+impl Default for AyChannelMap {
+    fn default() -> Self {
+        AyChannelMap::mono()
+    }
+}
+
+impl AyChannelMap {
+    /// All channels are rendered to the first [Blep] channel.
+    pub const fn mono() -> Self {
+        AyChannelMap([0, 0, 0])
+    }
+    /// `A` on the left, `B` in the center, `C` on the right.
+    pub const fn abc_stereo() -> Self {
+        AyChannelMap([0, 2, 1])
+    }
+    /// `A` on the left, `C` in the center, `B` on the right.
+    pub const fn acb_stereo() -> Self {
+        AyChannelMap([0, 1, 2])
+    }
+    /// Returns the number of [Blep] channels required by this mapping.
+    pub fn min_channels(self) -> usize {
+        self.0.iter().max().unwrap() + 1
+    }
+    /// Returns `true` if all the mapped channel indexes are less than `num_channels`.
+    pub fn is_valid_for(self, num_channels: usize) -> bool {
+        self.min_channels() <= num_channels
+    }
+    /// Returns the mapping as `[usize; 3]` if all the mapped channel indexes are less than `num_channels`.
+    pub fn checked_channels(self, num_channels: usize) -> Option<[usize; 3]> {
+        if self.is_valid_for(num_channels) {
+            Some(self.0)
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl From<AyChannelMap> for [usize; 3] {
+    fn from(AyChannelMap(channels): AyChannelMap) -> Self {
+        channels
+    }
+}
+
 /// Implements AY-3-8910/8912/8913 programmable sound generator.
 ///
 /// For the implementation of I/O ports see [crate::ay].
-#[derive(Default, Clone, Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
 pub struct Ay3_891xAudio {
@@ -139,6 +197,28 @@ pub struct Ay3_891xAudio {
     noise_control: NoiseControl,
     tone_control: [ToneControl; 3],
     mixer: Mixer,
+    #[cfg_attr(feature = "snapshot", serde(default = "tone_min_threshold_default"))]
+    tone_min_threshold: u16,
+}
+
+impl Default for Ay3_891xAudio {
+    fn default() -> Self {
+        Ay3_891xAudio {
+            current_ts: 0,
+            last_levels: Default::default(),
+            amp_levels: Default::default(),
+            env_control: Default::default(),
+            noise_control: Default::default(),
+            tone_control: Default::default(),
+            mixer: Default::default(),
+            tone_min_threshold: TONE_GEN_MIN_THRESHOLD
+        }
+    }
+}
+
+#[cfg(feature = "snapshot")]
+fn tone_min_threshold_default() -> u16 {
+    TONE_GEN_MIN_THRESHOLD
 }
 
 /// A type for AY-3-891x amplitude level register values.
@@ -285,6 +365,7 @@ impl EnvelopeControl {
 }
 
 const NOISE_PERIOD_MASK: u8 = 0x1F;
+const NOISE_LFSR_MASK: i32 = 0x1FFFF;
 
 /// A type implementing AY-3-891x noise progression.
 #[derive(Clone, Copy, Debug)]
@@ -303,6 +384,13 @@ impl Default for NoiseControl {
 }
 
 impl NoiseControl {
+    #[inline]
+    fn set_rng(&mut self, mut rng: i32) {
+        rng &= NOISE_LFSR_MASK;
+        if rng == 0 { rng = 1 }
+        self.rng = rng;
+    }
+
     #[inline]
     fn set_period(&mut self, mut period: u8) {
         period &= NOISE_PERIOD_MASK;
@@ -331,7 +419,10 @@ impl NoiseControl {
     }
 }
 
-const TONE_GEN_MIN_THRESHOLD: u16 = 5;
+/// The default tone period threshold below which the tone generator output stays high.
+///
+/// See [Ay3_891xAudio::set_tone_min_threshold].
+pub const TONE_GEN_MIN_THRESHOLD: u16 = 5;
 const TONE_PERIOD_MASK: u16 = 0xFFF;
 
 /// A type implementing AY-3-891x tone progression.
@@ -366,9 +457,9 @@ impl ToneControl {
     }
 
     #[inline]
-    fn update_is_low(&mut self) -> bool {
+    fn update_is_low(&mut self, min_threshold: u16) -> bool {
         let ToneControl { period, mut tick, mut low } = *self;
-        if period < TONE_GEN_MIN_THRESHOLD {
+        if period < min_threshold {
             low = false;
         }
         else if tick >= period {
@@ -412,8 +503,26 @@ impl Iterator for Ticker {
 /// Use the [Default] trait to create instances of this struct.
 impl Ay3_891xAudio {
     /// Resets the internal state to the one initialized with.
+    ///
+    /// The tone period threshold set with [Ay3_891xAudio::set_tone_min_threshold] is preserved.
     pub fn reset(&mut self) {
-        *self = Default::default()
+        *self = Ay3_891xAudio {
+            tone_min_threshold: self.tone_min_threshold,
+            ..Default::default()
+        }
+    }
+    /// Returns the tone period threshold below which the tone generator output stays high.
+    #[inline]
+    pub fn tone_min_threshold(&self) -> u16 {
+        self.tone_min_threshold
+    }
+    /// Sets the tone period threshold below which the tone generator output stays high.
+    ///
+    /// Different sources cite different values for this threshold. The default is
+    /// [TONE_GEN_MIN_THRESHOLD]. Setting it to `0` or `1` disables the threshold.
+    #[inline]
+    pub fn set_tone_min_threshold(&mut self, threshold: u16) {
+        self.tone_min_threshold = threshold;
     }
     /// Converts a tone frequency given in Hz to a closest 16-bit tone period register value.
     ///
@@ -422,6 +531,7 @@ impl Ay3_891xAudio {
     ///
     /// Returns `None` if the result can't be properly represented by 16-bit unsigned integer or if
     /// the result is `0`.
+    #[cfg(feature = "std")]
     #[allow(clippy::float_cmp)]
     pub fn freq_to_tone_period(clock_hz: f32, hz: f32) -> Option<NonZeroU16> {
         let ftp = (clock_hz / (INTERNAL_CLOCK_DIVISOR as f32 * hz)).round();
@@ -437,8 +547,30 @@ impl Ay3_891xAudio {
     ///
     /// `clock_hz` AY-3-891x clock frequency in Hz. In ZX Spectrum it equals to CPU_HZ / 2.
     /// Amstrad CPC has PSG clocked at 1 MHz. Atari ST at 2 MHz.
+    ///
+    /// The period `0` is being treated the same way as `1`, as does the chip.
     pub fn tone_period_to_freq(clock_hz: f32, tp: u16) -> f32 {
-        clock_hz / (tp as f32 * INTERNAL_CLOCK_DIVISOR as f32)
+        clock_hz / (tp.max(1) as f32 * INTERNAL_CLOCK_DIVISOR as f32)
+    }
+    /// Converts a 5-bit noise period register value to a frequency in Hz at which the noise
+    /// generator is being updated.
+    ///
+    /// `clock_hz` AY-3-891x clock frequency in Hz. In ZX Spectrum it equals to CPU_HZ / 2.
+    ///
+    /// Only the lowest 5 bits of `np` are being used. The period `0` is being treated the same way as `1`.
+    pub fn noise_period_to_freq(clock_hz: f32, np: u8) -> f32 {
+        let np = (np & NOISE_PERIOD_MASK).max(1);
+        clock_hz / (np as f32 * INTERNAL_CLOCK_DIVISOR as f32)
+    }
+    /// Converts a 16-bit envelope period register value to a frequency in Hz of a single envelope
+    /// cycle, which consists of 16 level steps.
+    ///
+    /// `clock_hz` AY-3-891x clock frequency in Hz. In ZX Spectrum it equals to CPU_HZ / 2.
+    ///
+    /// The period `0` is being treated the same way as `1`. Note that the repeating triangle
+    /// envelope shapes span two envelope cycles.
+    pub fn envelope_period_to_freq(clock_hz: f32, ep: u16) -> f32 {
+        clock_hz / (ep.max(1) as f32 * (INTERNAL_CLOCK_DIVISOR * 16) as f32)
     }
     /// Creates an iterator of tone periods for the AY-3-891x chip.
     ///
@@ -450,6 +582,7 @@ impl Ay3_891xAudio {
     ///
     /// # Panics
     /// Panics if any period can't be expressed by 16-bit unsigned integer.
+    #[cfg(feature = "std")]
     pub fn tone_periods<I>(
                 clock_hz: f32,
                 min_octave: i32,
@@ -490,12 +623,119 @@ impl Ay3_891xAudio {
         where V: AmpLevels<A::SampleDelta>,
               I: IntoIterator<Item=AyRegChange>,
               A: Blep
+    {
+        debug_assert_blep_chans(blep, chans);
+        self.render_steps::<V,_,_,_>(changes, end_ts, frame_tstates, None, |index, tick, delta| {
+            blep.add_step(chans[index], tick, delta)
+        })
+    }
+    /// Renders square-wave pulses via [Blep] interface like [Ay3_891xAudio::render_audio] but instead
+    /// of applying register changes with timestamps beyond the last rendered tick of the frame,
+    /// appends them to `carry`.
+    ///
+    /// The carried changes have their timestamps rebased by `frame_tstates`, so they are relative to the
+    /// beginning of the next frame. They should be passed before any other changes when rendering the
+    /// next frame, e.g. by chaining `carry.drain(..)` with the next frame's changes.
+    pub fn render_audio_carry<V,I,A>(&mut self,
+                changes: I,
+                blep: &mut A,
+                end_ts: FTs,
+                frame_tstates: FTs,
+                chans: [usize; 3],
+                carry: &mut Vec<AyRegChange>
+            )
+        where V: AmpLevels<A::SampleDelta>,
+              I: IntoIterator<Item=AyRegChange>,
+              A: Blep
+    {
+        debug_assert_blep_chans(blep, chans);
+        self.render_steps::<V,_,_,_>(changes, end_ts, frame_tstates, Some(carry), |index, tick, delta| {
+            blep.add_step(chans[index], tick, delta)
+        })
+    }
+    /// Renders square-wave audio pulses via the [Blep] interface with `f32` sample deltas, using [AyAmps]
+    /// as [AmpLevels].
+    ///
+    /// See [Ay3_891xAudio::render_audio] for the description of arguments.
+    #[inline]
+    pub fn render_audio_f32<I, A>(&mut self, changes: I, blep: &mut A, end_ts: FTs, frame_tstates: FTs, chans: [usize; 3])
+        where I: IntoIterator<Item=AyRegChange>,
+              A: Blep<SampleDelta=f32>
+    {
+        self.render_audio::<AyAmps<f32>,_,_>(changes, blep, end_ts, frame_tstates, chans)
+    }
+    /// Renders square-wave audio pulses via the [Blep] interface with `i16` sample deltas, using [AyAmps]
+    /// as [AmpLevels].
+    ///
+    /// See [Ay3_891xAudio::render_audio] for the description of arguments.
+    #[inline]
+    pub fn render_audio_i16<I, A>(&mut self, changes: I, blep: &mut A, end_ts: FTs, frame_tstates: FTs, chans: [usize; 3])
+        where I: IntoIterator<Item=AyRegChange>,
+              A: Blep<SampleDelta=i16>
+    {
+        self.render_audio::<AyAmps<i16>,_,_>(changes, blep, end_ts, frame_tstates, chans)
+    }
+    /// Renders square-wave audio pulses via the [Blep] interface with `i32` sample deltas, using [AyAmps]
+    /// as [AmpLevels].
+    ///
+    /// See [Ay3_891xAudio::render_audio] for the description of arguments.
+    #[inline]
+    pub fn render_audio_i32<I, A>(&mut self, changes: I, blep: &mut A, end_ts: FTs, frame_tstates: FTs, chans: [usize; 3])
+        where I: IntoIterator<Item=AyRegChange>,
+              A: Blep<SampleDelta=i32>
+    {
+        self.render_audio::<AyAmps<i32>,_,_>(changes, blep, end_ts, frame_tstates, chans)
+    }
+    /// Renders square-wave audio pulses via the [Blep] interface while mutating the internal state,
+    /// the same way as [Ay3_891xAudio::render_audio] does, but first accumulates pulse steps for each of the
+    /// `[A, B, C]` AY channels in `steps` and then flushes them to `blep` with [Blep::add_steps].
+    ///
+    /// `steps` are cleared before and after rendering, so the same buffers, with their capacity preserved,
+    /// may be provided for each frame.
+    ///
+    /// The rendered audio is identical to the one produced by [Ay3_891xAudio::render_audio] as long as each
+    /// [Blep] audio channel receives pulse steps from only one of the AY channels.
+    pub fn render_audio_batched<V,I,A>(&mut self,
+                changes: I,
+                blep: &mut A,
+                end_ts: FTs,
+                frame_tstates: FTs,
+                chans: [usize; 3],
+                steps: &mut [Vec<(FTs, A::SampleDelta)>; 3]
+            )
+        where V: AmpLevels<A::SampleDelta>,
+              I: IntoIterator<Item=AyRegChange>,
+              A: Blep
+    {
+        debug_assert_blep_chans(blep, chans);
+        for chan_steps in steps.iter_mut() {
+            chan_steps.clear();
+        }
+        self.render_steps::<V,_,_,_>(changes, end_ts, frame_tstates, None, |index, tick, delta| {
+            steps[index].push((tick, delta))
+        });
+        for (chan, chan_steps) in chans.iter().copied().zip(steps.iter_mut()) {
+            blep.add_steps(chan, chan_steps);
+            chan_steps.clear();
+        }
+    }
+
+    fn render_steps<V,I,L,F>(&mut self,
+                changes: I,
+                end_ts: FTs,
+                frame_tstates: FTs,
+                mut carry: Option<&mut Vec<AyRegChange>>,
+                mut add_step: F
+            )
+        where V: AmpLevels<L>,
+              I: IntoIterator<Item=AyRegChange>,
+              L: SampleDelta,
+              F: FnMut(usize, FTs, L)
     {
         let mut change_iter = changes.into_iter().peekable();
         let mut ticker = Ticker::new(self.current_ts, end_ts);
         let mut tone_levels: [u8; 3] = self.last_levels;
-        let mut vol_levels: [A::SampleDelta;3] = Default::default();
-
+        let mut vol_levels: [L;3] = Default::default();
         for (level, tgt_amp) in tone_levels.iter().copied()
                                 .zip(vol_levels.iter_mut()) {
             *tgt_amp = V::amp_level(level.into());
@@ -515,10 +755,11 @@ impl Ay3_891xAudio {
             let env_level = self.env_control.update_level();
             let noise_low = self.noise_control.update_is_low();
             let mut mixer = self.mixer;
+            let tone_min_threshold = self.tone_min_threshold;
             for ((level, tone_control), tgt_lvl) in self.amp_levels.iter()
                                                     .zip(self.tone_control.iter_mut())
                                                         .zip(tone_levels.iter_mut()) {
-                *tgt_lvl = if (mixer.has_tone() && tone_control.update_is_low()) ||
+                *tgt_lvl = if (mixer.has_tone() && tone_control.update_is_low(tone_min_threshold)) ||
                    (mixer.has_noise() && noise_low) {
                     0
                 }
@@ -531,19 +772,23 @@ impl Ay3_891xAudio {
                 mixer.next_chan();
             }
 
-            for (chan, (level, last_vol)) in chans.iter().copied()
-                                                  .zip(tone_levels.iter().copied()
-                                                  .zip(vol_levels.iter_mut())) {
+            for (index, (level, last_vol)) in tone_levels.iter().copied()
+                                                   .zip(vol_levels.iter_mut()).enumerate() {
                 let vol = V::amp_level(level.into());
                 if let Some(delta) = last_vol.sample_delta(vol) {
-                    blep.add_step(chan, tick, delta);
+                    add_step(index, tick, delta);
                     *last_vol = vol;
                 }
             }
 
         }
-        for AyRegChange { reg, val, .. } in change_iter {
-            self.update_register(reg, val);
+        for change in change_iter {
+            match carry {
+                Some(ref mut carry) if change.time >= ticker.current => {
+                    carry.push(AyRegChange { time: change.time - frame_tstates, ..change })
+                }
+                _ => self.update_register(change.reg, change.val)
+            }
         }
 
         self.current_ts = ticker.current - frame_tstates;
@@ -585,6 +830,28 @@ impl Ay3_891xAudio {
             _ => ()
         }
     }
+    /// Updates the values of all the sound generator registers at once, with the values given in `regs`
+    /// indexed by the [AyRegister] numbers.
+    ///
+    /// Writing to the [AyRegister::EnvShape] register restarts the envelope. If `retrigger_envelope` is
+    /// `false`, the envelope is restarted only when the new shape differs from the current one, so the
+    /// envelope generator continues uninterrupted when restoring the same register set.
+    ///
+    /// Like [Ay3_891xAudio::update_register] this method doesn't generate audio pulses.
+    pub fn apply_register_snapshot(
+            &mut self,
+            regs: &[u8; NUM_SOUND_GEN_REGISTERS],
+            retrigger_envelope: bool
+        )
+    {
+        for (reg, &val) in AyRegister::enumerate().zip(regs.iter()) {
+            if reg == AyRegister::EnvShape && !retrigger_envelope &&
+                    val & !ENV_CYCLE_MASK == self.get_envelope_shape() {
+                continue
+            }
+            self.update_register(reg, val);
+        }
+    }
     /// Returns the current tone periods of each channel.
     ///
     /// The period is in the range: [1, 4095].
@@ -653,12 +920,32 @@ impl Ay3_891xAudio {
     pub fn get_envelope_period(&self) -> u16 {
         self.env_control.period
     }
+    /// Returns the current state of the 17-bit noise generator LFSR (linear-feedback shift register).
+    #[inline]
+    pub fn noise_lfsr(&self) -> i32 {
+        self.noise_control.rng
+    }
+    /// Sets the state of the 17-bit noise generator LFSR (linear-feedback shift register).
+    ///
+    /// Only the lowest 17 bits of the `state` are used. If these are all `0`, which would lock the LFSR,
+    /// the state is set to `1` instead.
+    #[inline]
+    pub fn set_noise_lfsr(&mut self, state: i32) {
+        self.noise_control.set_rng(state);
+    }
+}
+
+#[inline]
+fn debug_assert_blep_chans<A: Blep>(blep: &A, chans: [usize; 3]) {
+    debug_assert!(chans.iter().all(|&chan| chan < blep.channels()),
+        "AY audio channels {:?} out of range, the Blep has {} channel(s)", chans, blep.channels());
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "std")]
     #[test]
     fn ay_3_889x_tone_periods() {
         use spectrusty_audio::music::*;
@@ -668,6 +955,19 @@ mod tests {
         assert_eq!(5, Ay3_891xAudio::freq_to_tone_period(clock_hz, 24000.0).unwrap().get());
         assert_eq!(439.84375, Ay3_891xAudio::tone_period_to_freq(clock_hz, 252));
         assert_eq!(22168.125, Ay3_891xAudio::tone_period_to_freq(clock_hz, 5));
+        assert_eq!(Ay3_891xAudio::tone_period_to_freq(clock_hz, 0),
+                   Ay3_891xAudio::tone_period_to_freq(clock_hz, 1));
+        for tp in 1..=0xFFF {
+            let hz = Ay3_891xAudio::tone_period_to_freq(clock_hz, tp);
+            assert_eq!(tp, Ay3_891xAudio::freq_to_tone_period(clock_hz, hz).unwrap().get());
+        }
+        assert_eq!(110840.625, Ay3_891xAudio::noise_period_to_freq(clock_hz, 1));
+        assert_eq!(110840.625, Ay3_891xAudio::noise_period_to_freq(clock_hz, 0));
+        assert_eq!(110840.625 / 31.0, Ay3_891xAudio::noise_period_to_freq(clock_hz, 31));
+        assert_eq!(110840.625 / 31.0, Ay3_891xAudio::noise_period_to_freq(clock_hz, 0xFF));
+        assert_eq!(6927.539, Ay3_891xAudio::envelope_period_to_freq(clock_hz, 1));
+        assert_eq!(6927.539, Ay3_891xAudio::envelope_period_to_freq(clock_hz, 0));
+        assert_eq!(6927.539 / 16.0, Ay3_891xAudio::envelope_period_to_freq(clock_hz, 16));
         notes.extend(Ay3_891xAudio::tone_periods(clock_hz, 0, 7, equal_tempered_scale_note_freqs(440.0, 0, 12)));
         assert_eq!(
             vec![4031, 3804, 3591, 3389, 3199, 3020, 2850, 2690, 2539, 2397, 2262, 2135,
@@ -680,6 +980,174 @@ mod tests {
                    31,   30,   28,   26,   25,   24,   22,   21,   20,   19,   18,   17], notes);
     }
 
+    #[test]
+    fn ay_channel_map_works() {
+        assert_eq!(AyChannelMap::default(), AyChannelMap::mono());
+        let chans: [usize; 3] = AyChannelMap::mono().into();
+        assert_eq!(chans, [0, 0, 0]);
+        assert_eq!(<[usize; 3]>::from(AyChannelMap::abc_stereo()), [0, 2, 1]);
+        assert_eq!(<[usize; 3]>::from(AyChannelMap::acb_stereo()), [0, 1, 2]);
+        assert_eq!(AyChannelMap::mono().min_channels(), 1);
+        assert_eq!(AyChannelMap::abc_stereo().min_channels(), 3);
+        assert!(AyChannelMap::mono().is_valid_for(1));
+        assert!(!AyChannelMap::mono().is_valid_for(0));
+        assert!(!AyChannelMap::acb_stereo().is_valid_for(2));
+        assert_eq!(AyChannelMap::acb_stereo().checked_channels(2), None);
+        assert_eq!(AyChannelMap::acb_stereo().checked_channels(3), Some([0, 1, 2]));
+        assert_eq!(AyChannelMap([1, 4, 1]).checked_channels(5), Some([1, 4, 1]));
+    }
+
+    #[test]
+    fn ay_3_889x_render_audio_batched() {
+        use spectrusty_audio::synth::BandLimited;
+        use AyRegister::*;
+        const FRAME_TS: FTs = 69888;
+        let changes = [
+            AyRegChange::new(0, ToneFineA, 252),
+            AyRegChange::new(0, ToneFineB, 100),
+            AyRegChange::new(0, ToneFineC, 33),
+            AyRegChange::new(0, NoisePeriod, 7),
+            AyRegChange::new(0, MixerControl, 0b0011_0000),
+            AyRegChange::new(0, AmpLevelA, 15),
+            AyRegChange::new(0, AmpLevelB, 12),
+            AyRegChange::new(1000, AmpLevelC, 16),
+            AyRegChange::new(1000, EnvPerFine, 16),
+            AyRegChange::new(1000, EnvShape, 0b1010),
+            AyRegChange::new(30000, ToneFineA, 100),
+        ];
+        let mut ay = Ay3_891xAudio::default();
+        let mut ay_batched = Ay3_891xAudio::default();
+        let mut blep = BandLimited::<f32>::new(3);
+        let mut blep_batched = BandLimited::<f32>::new(3);
+        for blep in [&mut blep, &mut blep_batched].iter_mut() {
+            blep.ensure_frame_time(44100, 3_500_000.0, FRAME_TS, 100);
+        }
+        let mut steps = Default::default();
+        for _ in 0..2 {
+            ay.render_audio_f32(changes.iter().copied(), &mut blep, FRAME_TS, FRAME_TS, [0, 1, 2]);
+            ay_batched.render_audio_batched::<AyAmps<f32>,_,_>(
+                changes.iter().copied(), &mut blep_batched, FRAME_TS, FRAME_TS, [0, 1, 2], &mut steps);
+            assert!(steps.iter().all(|chan_steps| chan_steps.is_empty()));
+            assert_eq!(Blep::end_frame(&mut blep, FRAME_TS), Blep::end_frame(&mut blep_batched, FRAME_TS));
+            for chan in 0..3 {
+                let samples: Vec<f32> = blep.sum_iter(chan).collect();
+                let samples_batched: Vec<f32> = blep_batched.sum_iter(chan).collect();
+                assert!(samples.iter().any(|&sample| sample != 0.0));
+                assert_eq!(samples, samples_batched);
+            }
+            blep.next_frame();
+            blep_batched.next_frame();
+        }
+    }
+
+    #[test]
+    fn ay_3_889x_render_audio_carry() {
+        use spectrusty_audio::synth::BandLimited;
+        use AyRegister::*;
+        const FRAME_TS: FTs = 69888;
+        let mut ay = Ay3_891xAudio::default();
+        let mut blep = BandLimited::<f32>::new(3);
+        blep.ensure_frame_time(44100, 3_500_000.0, FRAME_TS, 100);
+        let mut carry = Vec::new();
+        let changes = [
+            AyRegChange::new(100, AmpLevelA, 15),
+            AyRegChange::new(FRAME_TS + 20, AmpLevelB, 12),
+            AyRegChange::new(FRAME_TS + 40, ToneFineC, 33),
+        ];
+        ay.render_audio_carry::<AyAmps<f32>,_,_>(
+            changes.iter().copied(), &mut blep, FRAME_TS, FRAME_TS, [0, 1, 2], &mut carry);
+        assert_eq!(ay.get_amp_levels(), [15, 0, 0]);
+        assert_eq!(ay.get_tone_periods(), [0, 0, 0]);
+        let carried: Vec<_> = carry.iter().map(|ch| (ch.time, ch.reg, ch.val)).collect();
+        assert_eq!(carried, [(20, AmpLevelB, 12), (40, ToneFineC, 33)]);
+        Blep::end_frame(&mut blep, FRAME_TS);
+        blep.next_frame();
+        let changes = [AyRegChange::new(1000, AmpLevelC, 7)];
+        let mut next_carry = Vec::new();
+        ay.render_audio_carry::<AyAmps<f32>,_,_>(
+            carry.drain(..).chain(changes.iter().copied()),
+            &mut blep, FRAME_TS, FRAME_TS, [0, 1, 2], &mut next_carry);
+        assert!(next_carry.is_empty());
+        assert_eq!(ay.get_amp_levels(), [15, 12, 7]);
+        assert_eq!(ay.get_tone_periods(), [0, 0, 33]);
+        // render_audio applies leftover changes immediately
+        let mut ay = Ay3_891xAudio::default();
+        ay.render_audio_f32(
+            [AyRegChange::new(FRAME_TS + 20, AmpLevelB, 12)].iter().copied(),
+            &mut blep, FRAME_TS, FRAME_TS, [0, 1, 2]);
+        assert_eq!(ay.get_amp_levels(), [0, 12, 0]);
+    }
+
+    #[test]
+    fn ay_3_889x_apply_register_snapshot() {
+        let mut ay = Ay3_891xAudio::default();
+        let regs = [0x7C, 0x01, 0x01, 0x02, 0xFF, 0x0F, 0x1F, 0x38, 0x0A, 0x10, 0x05, 0x01, 0x00, 0x0E];
+        ay.apply_register_snapshot(&regs, false);
+        assert_eq!(ay.get_tone_periods(), [0x17C, 0x201, 0xFFF]);
+        assert_eq!(ay.get_noise_pitch(), 0x1F);
+        assert_eq!(ay.get_mixer(), 0x38);
+        assert_eq!(ay.get_envelope_period(), 1);
+        assert_eq!(ay.get_envelope_shape(), 0x0E);
+        assert_eq!(ay.get_amp_levels(), [0x0A, 0, 0x05]);
+        for _ in 0..5 {
+            ay.env_control.update_level();
+        }
+        assert_eq!(ay.get_amp_levels(), [0x0A, 4, 0x05]);
+        // the same shape doesn't restart the envelope
+        ay.apply_register_snapshot(&regs, false);
+        assert_eq!(ay.get_amp_levels(), [0x0A, 4, 0x05]);
+        ay.apply_register_snapshot(&regs, true);
+        assert_eq!(ay.get_amp_levels(), [0x0A, 0, 0x05]);
+        ay.env_control.update_level();
+        let mut regs1 = regs;
+        regs1[AyRegister::EnvShape as usize] = 0x08;
+        ay.apply_register_snapshot(&regs1, false);
+        assert_eq!(ay.get_envelope_shape(), 0x08);
+        assert_eq!(ay.get_amp_levels(), [0x0A, 15, 0x05]);
+    }
+
+    #[test]
+    fn ay_3_889x_noise_lfsr() {
+        let mut ay = Ay3_891xAudio::default();
+        assert_eq!(ay.noise_lfsr(), 1);
+        ay.noise_control.set_period(1);
+        for _ in 0..1000 {
+            ay.noise_control.update_is_low();
+        }
+        let state = ay.noise_lfsr();
+        assert_eq!(state & !NOISE_LFSR_MASK, 0);
+        let mut ay_replay = Ay3_891xAudio::default();
+        ay_replay.noise_control.set_period(1);
+        ay_replay.set_noise_lfsr(state);
+        ay_replay.noise_control.low = ay.noise_control.low;
+        ay_replay.noise_control.tick = ay.noise_control.tick;
+        for _ in 0..1000 {
+            assert_eq!(ay.noise_control.update_is_low(), ay_replay.noise_control.update_is_low());
+            assert_eq!(ay.noise_lfsr(), ay_replay.noise_lfsr());
+        }
+        ay.set_noise_lfsr(0);
+        assert_eq!(ay.noise_lfsr(), 1);
+        ay.set_noise_lfsr(0x20000);
+        assert_eq!(ay.noise_lfsr(), 1);
+        ay.set_noise_lfsr(-1);
+        assert_eq!(ay.noise_lfsr(), 0x1FFFF);
+    }
+
+    #[test]
+    fn ay_3_889x_tone_min_threshold() {
+        let mut ay = Ay3_891xAudio::default();
+        assert_eq!(ay.tone_min_threshold(), TONE_GEN_MIN_THRESHOLD);
+        let mut tone = ToneControl::default();
+        tone.set_period(4);
+        for _ in 0..16 {
+            assert!(!tone.update_is_low(ay.tone_min_threshold()));
+        }
+        ay.set_tone_min_threshold(4);
+        assert!((0..16).any(|_| tone.update_is_low(ay.tone_min_threshold())));
+        ay.reset();
+        assert_eq!(ay.tone_min_threshold(), 4);
+    }
+
     #[test]
     fn ay_3_889x_env_works() {
         // println!("Ay3_891xAudio {:?}", core::mem::size_of::<Ay3_891xAudio>());
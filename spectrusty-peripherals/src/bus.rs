@@ -7,9 +7,13 @@
 */
 //! System bus device emulators to be used with [ControlUnit][spectrusty_core::chip::ControlUnit]s.
 pub mod ay;
+pub mod betadisk;
 pub mod debug;
+pub mod divide;
 pub mod joystick;
 pub mod mouse;
+pub mod multiface;
 pub mod parallel;
+pub mod plus3fdc;
 pub mod zxinterface1;
 pub mod zxprinter;
@@ -23,9 +23,11 @@ use spectrusty_core::{
     clock::FTs
 };
 
+use super::joystick::FullerJoystick;
+
 pub use crate::ay::{
     audio::Ay3_891xAudio,
-    Ay3_8910Io, Ay3_8912Io, Ay3_8913Io, AyIoPort, AyIoNullPort, AyRegister,
+    Ay3_8910Io, Ay3_8912Io, Ay3_8913Io, AyIoPort, AyIoNullPort, AyIoCallbackPort, AyRegister, AyRegChange,
     AyPortDecode, Ay128kPortDecode, AyFullerBoxPortDecode
 };
 
@@ -44,6 +46,24 @@ pub type Ay3_891xFullerBox<D> = Ay3_891xBusDevice<AyFullerBoxPortDecode,
                                                 AyIoNullPort<<D as BusDevice>::Timestamp>,
                                                 D>;
 
+/// A convenient [Ay3_891xFullerBox] type combined with a [FullerJoystick], emulating the complete
+/// `Fuller Box` interface.
+///
+/// The joystick is accessible via [Ay3_891xBusDevice::fuller_joystick] and
+/// [Ay3_891xBusDevice::fuller_joystick_mut].
+pub type FullerBoxBusDevice<D> = Ay3_891xFullerBox<FullerJoystick<D>>;
+
+impl<D: BusDevice> FullerBoxBusDevice<D> {
+    /// Returns a reference to the `Fuller Box` joystick.
+    pub fn fuller_joystick(&self) -> &FullerJoystick<D> {
+        &self.bus
+    }
+    /// Returns a mutable reference to the `Fuller Box` joystick.
+    pub fn fuller_joystick_mut(&mut self) -> &mut FullerJoystick<D> {
+        &mut self.bus
+    }
+}
+
 impl<D: BusDevice> fmt::Display for Ay3_891xMelodik<D> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("AY-3-8913 (Melodik)")
@@ -85,7 +105,9 @@ pub trait AyAudioBusDevice: BusDevice {
 /// Provides a helper method to produce sound generated by the last emulated frame.
 #[derive(Clone, Default, Debug)]
 #[cfg_attr(feature = "snapshot", derive(Serialize))]
-#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "snapshot", serde(bound(serialize = "
+    A: Serialize, B: Serialize, D: Serialize, D::Timestamp: Serialize"),
+    rename_all = "camelCase"))]
 pub struct Ay3_891xBusDevice<P, A, B, D: BusDevice> {
     /// Provides direct access to the sound generator.
     pub ay_sound: Ay3_891xAudio,
@@ -227,6 +249,21 @@ impl<P, PA, PB, D> AyAudioBusDevice for Ay3_891xBusDevice<P, PA, PB, D>
     }
 }
 
+impl<P, PA, PB, D> Ay3_891xBusDevice<P, PA, PB, D>
+    where D: BusDevice,
+          D::Timestamp: Into<FTs>
+{
+    /// Drains the AY register changes recorded during the current frame and returns them with
+    /// their timestamps converted to frame T-states, without rendering any audio.
+    ///
+    /// Use it instead of [AyAudioBusDevice::render_ay_audio] to log or export the register changes.
+    /// The changes are not being applied to [Ay3_891xBusDevice::ay_sound]. If audio is going to be
+    /// rendered later, the changes should be passed to [Ay3_891xAudio::update_register].
+    pub fn take_ay_reg_changes(&mut self) -> Vec<AyRegChange> {
+        self.ay_io.recorder.drain_ay_reg_changes().collect()
+    }
+}
+
 impl_ay_audio_boxed_bus_device!(NullDevice<T>);
 impl<T: Into<FTs> + fmt::Debug> AyAudioBusDevice for NullDevice<T> {
     #[inline(always)]
@@ -300,3 +337,146 @@ impl<P, A, B, D> BusDevice for Ay3_891xBusDevice<P, A, B, D>
         self.bus.next_frame(timestamp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::joystick::{JoystickInterface, Directions};
+    use super::*;
+
+    #[test]
+    fn fuller_box_bus_device_works() {
+        let mut fuller: FullerBoxBusDevice<NullDevice<FTs>> = Default::default();
+        assert_eq!(fuller.to_string(), "AY-3-8913 (Fuller Box)");
+        assert_eq!(fuller.fuller_joystick().to_string(), "Fuller Joystick");
+        assert_eq!(fuller.write_io(0x3f, 8, 0), Some(0));
+        assert_eq!(fuller.write_io(0x5f, 0x0c, 1), Some(0));
+        assert_eq!(fuller.read_io(0x3f, 2), Some((0x0c, None)));
+        assert_eq!(fuller.read_io(0x7f, 3), Some((0xff, None)));
+        fuller.fuller_joystick_mut().fire(0, true);
+        fuller.fuller_joystick_mut().set_directions(Directions::UP|Directions::RIGHT);
+        assert_eq!(fuller.read_io(0x7f, 4), Some((0b0111_0110, None)));
+        assert_eq!(fuller.read_io(0xfe, 5), None);
+        assert_eq!(fuller.write_io(0x7f, 0, 6), None);
+    }
+
+    #[test]
+    fn ay_register_read_back_works() {
+        type TestAy = Ay3_891xBusDevice<Ay128kPortDecode, AyIoNullPort<FTs>, AyIoNullPort<FTs>, NullDevice<FTs>>;
+        let mut ay = TestAy::default();
+        let masks = [0xff, 0x0f, 0xff, 0x0f, 0xff, 0x0f, 0x1f, 0xff,
+                     0x1f, 0x1f, 0x1f, 0xff, 0xff, 0x0f];
+        for (reg, &mask) in masks.iter().enumerate() {
+            ay.write_io(0xfffd, reg as u8, 0);
+            ay.write_io(0xbffd, 0xff, 1);
+            assert_eq!(ay.read_io(0xfffd, 2), Some((mask, None)));
+            ay.write_io(0xbffd, 0x55, 3);
+            assert_eq!(ay.read_io(0xfffd, 4), Some((0x55 & mask, None)));
+        }
+        // a common AY detection routine: select a register, write a value and read it back
+        ay.write_io(0xfffd, 0, 10);
+        ay.write_io(0xbffd, 0xa5, 11);
+        ay.write_io(0xfffd, 1, 12);
+        ay.write_io(0xbffd, 0x5a, 13);
+        ay.write_io(0xfffd, 0, 14);
+        assert_eq!(ay.read_io(0xfffd, 15), Some((0xa5, None)));
+        ay.write_io(0xfffd, 1, 16);
+        assert_eq!(ay.read_io(0xfffd, 17), Some((0x0a, None)));
+        // the register index only uses 4 lowest bits
+        ay.write_io(0xfffd, 0x10, 18);
+        assert_eq!(ay.read_io(0xfffd, 19), Some((0xa5, None)));
+        // the I/O ports set as input read back the port input
+        ay.write_io(0xfffd, 7, 20);
+        ay.write_io(0xbffd, 0, 20);
+        ay.write_io(0xfffd, 14, 20);
+        ay.write_io(0xbffd, 0x12, 21);
+        assert_eq!(ay.read_io(0xfffd, 22), Some((0xff, None)));
+        assert_eq!(ay.ay_io.get(AyRegister::IoA), 0x12);
+        ay.write_io(0xfffd, 7, 23);
+        ay.write_io(0xbffd, 0x40, 24);
+        ay.write_io(0xfffd, 14, 25);
+        assert_eq!(ay.read_io(0xfffd, 26), Some((0x12, None)));
+    }
+
+    #[test]
+    fn ay_take_reg_changes_works() {
+        let mut ay = Ay3_891xMelodik::<NullDevice<FTs>>::default();
+        assert!(ay.take_ay_reg_changes().is_empty());
+        ay.write_io(0xfffd, 0, 10);
+        ay.write_io(0xbffd, 0xa5, 11);
+        ay.write_io(0xfffd, 8, 12);
+        ay.write_io(0xbffd, 0x0f, 13);
+        let changes: Vec<_> = ay.take_ay_reg_changes().into_iter().map(|c| (c.time, c.reg, c.val)).collect();
+        assert_eq!(changes, [(11, AyRegister::ToneFineA, 0xa5), (13, AyRegister::AmpLevelA, 0x0f)]);
+        assert!(ay.ay_io.recorder.is_empty());
+        assert!(ay.take_ay_reg_changes().is_empty());
+        assert_eq!(ay.ay_io.get(AyRegister::AmpLevelA), 0x0f);
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn ay_bus_device_snapshot_works() {
+        type TestAy = Ay3_891xMelodik<NullDevice<FTs>>;
+        let mut ay = TestAy::default();
+        ay.write_io(0xfffd, 0, 10);
+        ay.write_io(0xbffd, 0xa5, 11);
+        ay.write_io(0xfffd, 8, 12);
+        ay.write_io(0xbffd, 0x0f, 13);
+        ay.write_io(0xfffd, 7, 14);
+        assert_eq!(ay.ay_io.recorder.len(), 2);
+        let json = serde_json::to_string(&ay).unwrap();
+        let mut ay1: TestAy = serde_json::from_str(&json).unwrap();
+        assert_eq!(ay1.ay_io.registers(), ay.ay_io.registers());
+        assert_eq!(ay1.ay_io.selected_register(), AyRegister::MixerControl);
+        assert_eq!(ay1.ay_io.recorder.0, ay.ay_io.recorder.0);
+        ay.write_io(0xbffd, 0x38, 15);
+        ay1.write_io(0xbffd, 0x38, 15);
+        let changes = |ay: &mut TestAy| ay.ay_io.recorder.drain_ay_reg_changes()
+                                              .map(|c| (c.time, c.reg, c.val))
+                                              .collect::<Vec<_>>();
+        let expected = [(11, AyRegister::ToneFineA, 0xa5),
+                        (13, AyRegister::AmpLevelA, 0x0f),
+                        (15, AyRegister::MixerControl, 0x38)];
+        assert_eq!(changes(&mut ay), expected);
+        assert_eq!(changes(&mut ay1), expected);
+        // snapshots without the recorder are still accepted
+        let json = json.replace(r#""recorder":[[11,"ToneFineA",165],[13,"AmpLevelA",15]],"#, "");
+        let ay2: TestAy = serde_json::from_str(&json).unwrap();
+        assert!(ay2.ay_io.recorder.is_empty());
+        assert_eq!(ay2.ay_io.get(AyRegister::ToneFineA), 0xa5);
+    }
+
+    #[test]
+    fn ay_io_callback_port_works() {
+        use std::{rc::Rc, cell::RefCell};
+        type TestAy = Ay3_891xBusDevice<Ay128kPortDecode, AyIoCallbackPort<FTs>, AyIoNullPort<FTs>, NullDevice<FTs>>;
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut ay = TestAy::default();
+        assert_eq!(format!("{:?}", ay.ay_io.port_a), "AyIoCallbackPort { read: false, write: false, reset: false, end_frame: false }");
+        let port_a = &mut ay.ay_io.port_a;
+        let rlog = Rc::clone(&log);
+        port_a.set_read_callback(move |addr, ts| { rlog.borrow_mut().push(('r', addr, 0, ts)); 0x5a });
+        let wlog = Rc::clone(&log);
+        port_a.set_write_callback(move |addr, data, ts| wlog.borrow_mut().push(('w', addr, data, ts)));
+        let xlog = Rc::clone(&log);
+        port_a.set_reset_callback(move |ts| xlog.borrow_mut().push(('x', 0, 0, ts)));
+        let elog = Rc::clone(&log);
+        port_a.set_end_frame_callback(move |ts| elog.borrow_mut().push(('e', 0, 0, ts)));
+        // select IoA and read
+        ay.write_io(0xfffd, 14, 0);
+        assert_eq!(ay.read_io(0xfffd, 10), Some((0x5a, None)));
+        // set IoA to output and write
+        ay.write_io(0xfffd, 7, 20);
+        ay.write_io(0xbffd, 0x40, 30);
+        ay.write_io(0xfffd, 14, 40);
+        ay.write_io(0xbffd, 0xa5, 50);
+        ay.next_frame(100);
+        ay.reset(110);
+        assert_eq!(*log.borrow(), [('r', 0xfffd, 0, 10),
+                                   ('w', 0xbffd, 0xa5, 50),
+                                   ('e', 0, 0, 100),
+                                   ('x', 0, 0, 110)]);
+        ay.ay_io.port_a.clear_callbacks();
+        ay.write_io(0xfffd, 14, 0);
+        assert_eq!(ay.read_io(0xfffd, 10), Some((0xff, None)));
+    }
+}
@@ -16,7 +16,7 @@ impl<'de, P, A, B, D> Deserialize<'de> for Ay3_891xBusDevice<P, A, B, D>
     where A: Deserialize<'de> + Default,
           B: Deserialize<'de> + Default,
           D: Deserialize<'de> + Default + BusDevice,
-          D::Timestamp: Default
+          D::Timestamp: Deserialize<'de> + Default
 {
     fn deserialize<DE>(deserializer: DE) -> Result<Self, DE::Error>
         where DE: Deserializer<'de>,
@@ -37,7 +37,7 @@ impl<'de, P, A, B, D> Deserialize<'de> for Ay3_891xBusDevice<P, A, B, D>
             where A: Deserialize<'de> + Default,
                   B: Deserialize<'de> + Default,
                   D: Deserialize<'de> + Default + BusDevice,
-                  D::Timestamp: Default
+                  D::Timestamp: Deserialize<'de> + Default
         {
             type Value = Ay3_891xBusDevice<P, A, B, D>;
 
@@ -0,0 +1,239 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! A bus device for connecting the **Beta Disk** interface with the **TR-DOS** floppy disk drives.
+use core::cell::Cell;
+use core::num::NonZeroU16;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use spectrusty_core::{
+    bus::BusDevice,
+    clock::TimestampOps
+};
+use super::ay::PassByAyAudioBusDevice;
+
+pub use crate::storage::trd::*;
+pub use crate::storage::wd1793::*;
+pub use crate::memory::BetaDiskMemExt;
+
+/// The drive selection bits of the **Beta Disk** system register.
+pub const BETA_DRIVE_MASK: u8 = 0b0000_0011;
+/// The reset bit of the **Beta Disk** system register: the controller is reset while this bit is `0`.
+pub const BETA_RESET_BIT: u8 = 0b0000_0100;
+/// The side selection bit of the **Beta Disk** system register: `0` selects the upper side.
+pub const BETA_SIDE_BIT: u8 = 0b0001_0000;
+
+const BETA_INTRQ_BIT: u8 = 0b1000_0000;
+const BETA_DRQ_BIT: u8 = 0b0100_0000;
+
+/// Connects the [Wd1793] floppy disk controller as a [BusDevice] the way it's wired in the **Beta Disk**
+/// interface.
+///
+/// The controller's registers are accessed via I/O ports `0x1F` (status/command), `0x3F` (track),
+/// `0x5F` (sector), `0x7F` (data) and the system register via port `0xFF`.
+///
+/// The ports are only accessible while the **TR-DOS** ROM is paged in by the linked [BetaDiskMemExt].
+/// If the device isn't linked with the memory extension, the ports are always accessible.
+///
+/// # Note
+/// The link with the memory extension is not preserved by serialization and should be re-established
+/// with [BetaDiskBusDevice::link_memory_extension] after deserialization.
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
+pub struct BetaDiskBusDevice<D> {
+    /// Provides direct access to the [Wd1793] controller.
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    pub fdc: Wd1793,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    system: u8,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    trdos_active: Option<Rc<Cell<bool>>>,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    bus: D
+}
+
+impl<D> fmt::Display for BetaDiskBusDevice<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Beta Disk")
+    }
+}
+
+impl<D> Deref for BetaDiskBusDevice<D> {
+    type Target = Wd1793;
+    fn deref(&self) -> &Self::Target {
+        &self.fdc
+    }
+}
+
+impl<D> DerefMut for BetaDiskBusDevice<D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.fdc
+    }
+}
+
+impl<D> BetaDiskBusDevice<D> {
+    /// Links the device with the memory extension, so the I/O ports are only accessible while
+    /// the **TR-DOS** ROM is paged in.
+    pub fn link_memory_extension(&mut self, memext: &BetaDiskMemExt) {
+        self.trdos_active = Some(memext.active_state());
+    }
+    /// Removes the link with the memory extension.
+    pub fn unlink_memory_extension(&mut self) {
+        self.trdos_active = None;
+    }
+    /// Returns the last value written to the system register.
+    pub fn system_register(&self) -> u8 {
+        self.system
+    }
+
+    #[inline]
+    fn is_active(&self) -> bool {
+        self.trdos_active.as_ref().map(|active| active.get()).unwrap_or(true)
+    }
+
+    fn write_system(&mut self, data: u8) {
+        self.system = data;
+        if data & BETA_RESET_BIT == 0 {
+            self.fdc.reset();
+        }
+        let side = if data & BETA_SIDE_BIT == 0 { 1 } else { 0 };
+        self.fdc.select(data & BETA_DRIVE_MASK, side);
+    }
+}
+
+impl<D> PassByAyAudioBusDevice for BetaDiskBusDevice<D> {}
+
+impl<D> BusDevice for BetaDiskBusDevice<D>
+    where D: BusDevice,
+          D::Timestamp: TimestampOps
+{
+    type Timestamp = D::Timestamp;
+    type NextDevice = D;
+
+    #[inline]
+    fn next_device_mut(&mut self) -> &mut Self::NextDevice {
+        &mut self.bus
+    }
+
+    #[inline]
+    fn next_device_ref(&self) -> &Self::NextDevice {
+        &self.bus
+    }
+
+    #[inline]
+    fn into_next_device(self) -> Self::NextDevice {
+        self.bus
+    }
+
+    #[inline]
+    fn reset(&mut self, timestamp: Self::Timestamp) {
+        self.fdc.reset();
+        self.system = 0;
+        self.bus.reset(timestamp);
+    }
+
+    #[inline]
+    fn update_timestamp(&mut self, timestamp: Self::Timestamp) {
+        self.fdc.update_timestamp(timestamp.into_tstates());
+        self.bus.update_timestamp(timestamp);
+    }
+
+    #[inline]
+    fn next_frame(&mut self, eof_timestamp: Self::Timestamp) {
+        self.fdc.next_frame(eof_timestamp.into_tstates());
+        self.bus.next_frame(eof_timestamp);
+    }
+
+    #[inline]
+    fn read_io(&mut self, port: u16, timestamp: Self::Timestamp) -> Option<(u8, Option<NonZeroU16>)> {
+        if self.is_active() {
+            let data = match port & 0xFF {
+                0x1F => self.fdc.read_status(timestamp.into_tstates()),
+                0x3F => self.fdc.read_track(),
+                0x5F => self.fdc.read_sector(),
+                0x7F => self.fdc.read_data(),
+                0xFF => {
+                    let mut data = !(BETA_INTRQ_BIT|BETA_DRQ_BIT);
+                    if self.fdc.intrq(timestamp.into_tstates()) {
+                        data |= BETA_INTRQ_BIT;
+                    }
+                    if self.fdc.drq() {
+                        data |= BETA_DRQ_BIT;
+                    }
+                    data
+                }
+                _ => return self.bus.read_io(port, timestamp)
+            };
+            return Some((data, None))
+        }
+        self.bus.read_io(port, timestamp)
+    }
+
+    #[inline]
+    fn write_io(&mut self, port: u16, data: u8, timestamp: Self::Timestamp) -> Option<u16> {
+        if self.is_active() {
+            match port & 0xFF {
+                0x1F => self.fdc.write_command(data, timestamp.into_tstates()),
+                0x3F => self.fdc.write_track(data),
+                0x5F => self.fdc.write_sector(data),
+                0x7F => self.fdc.write_data(data),
+                0xFF => self.write_system(data),
+                _ => return self.bus.write_io(port, data, timestamp)
+            }
+            return Some(0)
+        }
+        self.bus.write_io(port, data, timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spectrusty_core::bus::NullDevice;
+    use spectrusty_core::clock::FTs;
+    use super::*;
+
+    #[test]
+    fn beta_disk_bus_device_works() {
+        let mut beta: BetaDiskBusDevice<NullDevice<FTs>> = Default::default();
+        assert_eq!(format!("{}", beta), "Beta Disk");
+        beta.insert_disk(0, TrdDisk::new_formatted(80, 2));
+        assert_eq!(beta.write_io(0xFF, 0x3C, 0), Some(0));
+        assert_eq!(beta.selected_drive(), 0);
+        assert_eq!(beta.selected_side(), 0);
+        // read the system sector
+        assert_eq!(beta.write_io(0x5F, 9, 0), Some(0));
+        assert_eq!(beta.write_io(0x1F, 0x80, 0), Some(0));
+        assert_eq!(beta.read_io(0xFF, 0), Some((0x7F, None)));
+        let data: Vec<u8> = (0..TRD_SECTOR_SIZE).map(|_| beta.read_io(0x7F, 0).unwrap().0).collect();
+        assert_eq!(data[0xE3], 0x16);
+        assert_eq!(beta.read_io(0xFF, 0), Some((0xBF, None)));
+        assert_eq!(beta.read_io(0x1F, 0), Some((0x00, None)));
+        assert_eq!(beta.read_io(0xFF, 0), Some((0x3F, None)));
+        // restore takes time
+        beta.write_io(0x1F, 0x08, 100);
+        assert_eq!(beta.read_io(0xFF, 200), Some((0x3F, None)));
+        beta.update_timestamp(100 + WD_STEP_RATE_TS[0]);
+        assert_eq!(beta.read_io(0xFF, 100 + WD_STEP_RATE_TS[0]), Some((0xBF, None)));
+        // the upper side
+        beta.write_io(0xFF, 0x2C, 0);
+        assert_eq!(beta.selected_side(), 1);
+        // gated by the TR-DOS ROM paging
+        let memext = BetaDiskMemExt::default();
+        beta.link_memory_extension(&memext);
+        assert_eq!(beta.read_io(0x1F, 0), None);
+        assert_eq!(beta.write_io(0x7F, 0, 0), None);
+        beta.unlink_memory_extension();
+        assert_eq!(beta.read_io(0x3F, 0), Some((0, None)));
+        assert_eq!(beta.read_io(0xFE, 0), None);
+    }
+}
@@ -5,9 +5,11 @@
 
     For the full copyright notice, see the lib.rs file.
 */
-//! A passthrough debugging device.
+//! Passthrough debugging devices.
 use core::num::NonZeroU16;
 use core::fmt::Debug;
+use core::marker::PhantomData;
+use std::collections::{VecDeque, vec_deque};
 
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
@@ -15,7 +17,7 @@ use serde::{Serialize, Deserialize};
 #[allow(unused_imports)]
 use log::{error, warn, info, debug, trace};
 
-use spectrusty_core::bus::BusDevice;
+use spectrusty_core::bus::{BusDevice, PortAddress};
 use super::ay::PassByAyAudioBusDevice;
 
 /// A passthrough [BusDevice] that outputs I/O data read and written by CPU using [log] `debug`.
@@ -26,6 +28,117 @@ pub struct DebugBusDevice<D> {
     bus: D,
 }
 
+/// The default maximum number of I/O accesses recorded by [IoTraceBusDevice].
+pub const IO_TRACE_DEFAULT_CAPACITY: usize = 1024;
+
+/// An I/O access recorded by [IoTraceBusDevice].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoTrace<T> {
+    /// Data has been read from the I/O `port`. `data` is `None` if none of the devices responded.
+    Read { port: u16, data: Option<u8>, timestamp: T },
+    /// Data has been written to the I/O `port`.
+    Write { port: u16, data: u8, timestamp: T },
+}
+
+/// A passthrough [BusDevice] that records I/O data read and written by CPU in a ring buffer.
+///
+/// When the number of recorded accesses exceeds the capacity of the buffer, the oldest records
+/// are being discarded.
+///
+/// Read accesses are recorded after being forwarded to the next device, so the data returned
+/// by the device chain can be recorded.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct IoTraceBusDevice<D: BusDevice> {
+    capacity: usize,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    trace: VecDeque<IoTrace<D::Timestamp>>,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    bus: D,
+}
+
+/// An I/O access recorded by [PortProbeBusDevice].
+pub type PortAccess<T> = IoTrace<T>;
+
+/// A passthrough [BusDevice] that records I/O data read and written by CPU to ports matching `P`.
+///
+/// Unlike [IoTraceBusDevice], the log is not limited in size. Use [PortProbeBusDevice::drain_log]
+/// periodically to retrieve the recorded accesses.
+///
+/// The device itself never responds to I/O, all accesses are forwarded to the next device unchanged.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct PortProbeBusDevice<P, D: BusDevice> {
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    log: Vec<PortAccess<D::Timestamp>>,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    bus: D,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    _port_decode: PhantomData<P>,
+}
+
+impl<D: BusDevice + Default> Default for IoTraceBusDevice<D> {
+    fn default() -> Self {
+        IoTraceBusDevice::with_capacity(IO_TRACE_DEFAULT_CAPACITY)
+    }
+}
+
+impl<D: BusDevice + Default> IoTraceBusDevice<D> {
+    /// Creates a new device recording at most `capacity` I/O accesses.
+    pub fn with_capacity(capacity: usize) -> Self {
+        IoTraceBusDevice { capacity, trace: VecDeque::new(), bus: D::default() }
+    }
+}
+
+impl<D: BusDevice> IoTraceBusDevice<D> {
+    /// Returns the maximum number of recorded I/O accesses.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Changes the maximum number of recorded I/O accesses, discarding the oldest records if necessary.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        if self.trace.len() > capacity {
+            self.trace.drain(..self.trace.len() - capacity);
+        }
+    }
+    /// Returns a reference to the recorded I/O accesses, from the oldest to the newest.
+    pub fn trace(&self) -> &VecDeque<IoTrace<D::Timestamp>> {
+        &self.trace
+    }
+    /// Removes all recorded I/O accesses and returns them as an iterator, from the oldest to the newest.
+    pub fn drain_trace(&mut self) -> vec_deque::Drain<'_, IoTrace<D::Timestamp>> {
+        self.trace.drain(..)
+    }
+
+    fn record(&mut self, io: IoTrace<D::Timestamp>) {
+        if self.capacity == 0 {
+            return
+        }
+        if self.trace.len() >= self.capacity {
+            self.trace.pop_front();
+        }
+        self.trace.push_back(io);
+    }
+}
+
+impl<P, D: BusDevice + Default> Default for PortProbeBusDevice<P, D> {
+    fn default() -> Self {
+        PortProbeBusDevice { log: Vec::new(), bus: D::default(), _port_decode: PhantomData }
+    }
+}
+
+impl<P, D: BusDevice> PortProbeBusDevice<P, D> {
+    /// Returns a reference to the recorded I/O accesses, from the oldest to the newest.
+    pub fn log(&self) -> &[PortAccess<D::Timestamp>] {
+        &self.log
+    }
+    /// Removes and returns all recorded I/O accesses, from the oldest to the newest.
+    pub fn drain_log(&mut self) -> Vec<PortAccess<D::Timestamp>> {
+        core::mem::take(&mut self.log)
+    }
+}
+
 impl<D: BusDevice> BusDevice for DebugBusDevice<D>
     where D::Timestamp: Debug
 {
@@ -53,3 +166,123 @@ impl<D: BusDevice> BusDevice for DebugBusDevice<D>
 }
 
 impl<D> PassByAyAudioBusDevice for DebugBusDevice<D> {}
+
+impl<D: BusDevice> BusDevice for IoTraceBusDevice<D>
+    where D::Timestamp: Copy + Debug
+{
+    type Timestamp = D::Timestamp;
+    type NextDevice = D;
+
+    #[inline]
+    fn next_device_mut(&mut self) -> &mut Self::NextDevice {
+        &mut self.bus
+    }
+    #[inline]
+    fn next_device_ref(&self) -> &Self::NextDevice {
+        &self.bus
+    }
+    #[inline]
+    fn into_next_device(self) -> Self::NextDevice {
+        self.bus
+    }
+    #[inline]
+    fn read_io(&mut self, port: u16, timestamp: Self::Timestamp) -> Option<(u8, Option<NonZeroU16>)> {
+        let res = self.bus.read_io(port, timestamp);
+        self.record(IoTrace::Read { port, data: res.map(|(data, _)| data), timestamp });
+        res
+    }
+    #[inline]
+    fn write_io(&mut self, port: u16, data: u8, timestamp: Self::Timestamp) -> Option<u16> {
+        self.record(IoTrace::Write { port, data, timestamp });
+        self.bus.write_io(port, data, timestamp)
+    }
+}
+
+impl<D: BusDevice> PassByAyAudioBusDevice for IoTraceBusDevice<D> {}
+
+impl<P, D> BusDevice for PortProbeBusDevice<P, D>
+    where P: PortAddress,
+          D: BusDevice,
+          D::Timestamp: Copy + Debug
+{
+    type Timestamp = D::Timestamp;
+    type NextDevice = D;
+
+    #[inline]
+    fn next_device_mut(&mut self) -> &mut Self::NextDevice {
+        &mut self.bus
+    }
+    #[inline]
+    fn next_device_ref(&self) -> &Self::NextDevice {
+        &self.bus
+    }
+    #[inline]
+    fn into_next_device(self) -> Self::NextDevice {
+        self.bus
+    }
+    #[inline]
+    fn read_io(&mut self, port: u16, timestamp: Self::Timestamp) -> Option<(u8, Option<NonZeroU16>)> {
+        let res = self.bus.read_io(port, timestamp);
+        if P::match_port(port) {
+            self.log.push(PortAccess::Read { port, data: res.map(|(data, _)| data), timestamp });
+        }
+        res
+    }
+    #[inline]
+    fn write_io(&mut self, port: u16, data: u8, timestamp: Self::Timestamp) -> Option<u16> {
+        if P::match_port(port) {
+            self.log.push(PortAccess::Write { port, data, timestamp });
+        }
+        self.bus.write_io(port, data, timestamp)
+    }
+}
+
+impl<P, D: BusDevice> PassByAyAudioBusDevice for PortProbeBusDevice<P, D> {}
+
+#[cfg(test)]
+mod tests {
+    use spectrusty_core::bus::NullDevice;
+    use super::*;
+
+    #[test]
+    fn io_trace_bus_device_works() {
+        let mut bus = IoTraceBusDevice::<NullDevice<u32>>::with_capacity(2);
+        assert_eq!(bus.read_io(0x1F, 1), None);
+        assert_eq!(bus.write_io(0xFE, 7, 2), None);
+        assert_eq!(bus.trace().len(), 2);
+        assert_eq!(bus.write_io(0x7FFD, 0x10, 3), None);
+        assert_eq!(bus.drain_trace().collect::<Vec<_>>(), vec![
+            IoTrace::Write { port: 0xFE, data: 7, timestamp: 2 },
+            IoTrace::Write { port: 0x7FFD, data: 0x10, timestamp: 3 }
+        ]);
+        assert!(bus.trace().is_empty());
+        assert_eq!(bus.read_io(0xFFFD, 4), None);
+        assert_eq!(bus.trace()[0], IoTrace::Read { port: 0xFFFD, data: None, timestamp: 4 });
+        bus.set_capacity(0);
+        assert!(bus.trace().is_empty());
+        bus.write_io(0xFE, 0, 5);
+        assert!(bus.trace().is_empty());
+    }
+
+    #[test]
+    fn port_probe_bus_device_works() {
+        #[derive(Debug)]
+        struct UlaPortAddress;
+        impl PortAddress for UlaPortAddress {
+            const ADDRESS_MASK: u16 = 0x0001;
+            const ADDRESS_BITS: u16 = 0x00fe;
+        }
+        let mut bus = PortProbeBusDevice::<UlaPortAddress, NullDevice<u32>>::default();
+        assert_eq!(bus.read_io(0x1F, 1), None);
+        assert_eq!(bus.read_io(0xFEFE, 2), None);
+        assert_eq!(bus.write_io(0x7FFD, 0x10, 3), None);
+        assert_eq!(bus.write_io(0xFE, 7, 4), None);
+        assert_eq!(bus.log(), [
+            PortAccess::Read { port: 0xFEFE, data: None, timestamp: 2 },
+            PortAccess::Write { port: 0xFE, data: 7, timestamp: 4 }
+        ]);
+        assert_eq!(bus.drain_log().len(), 2);
+        assert!(bus.log().is_empty());
+        assert!(bus.drain_log().is_empty());
+    }
+}
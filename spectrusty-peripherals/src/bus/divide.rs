@@ -0,0 +1,233 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! A bus device for connecting the **DivIDE** mass storage interface.
+use core::cell::Cell;
+use core::num::NonZeroU16;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use std::io::{Read, Write, Seek};
+use std::rc::Rc;
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use spectrusty_core::bus::{BusDevice, PortAddress};
+use super::ay::PassByAyAudioBusDevice;
+
+pub use crate::storage::ide::*;
+pub use crate::memory::{DivIdeMemExt, DIVIDE_CONMEM, DIVIDE_MAPRAM, DIVIDE_BANK_MASK};
+
+/// Connects the emulated **DivIDE** interface as a [BusDevice].
+///
+/// The IDE task file registers are available via I/O ports `0xA3`, `0xA7`, ..., `0xBF` and
+/// the write-only control port `0xE3`.
+///
+/// The **DivIDE** memory paging is implemented by [DivIdeMemExt] which should be installed as
+/// the memory extension of the emulated chipset and linked to the device with
+/// [DivIdeBusDevice::link_memory_extension], so the writes to the control port change the memory paging.
+///
+/// `F` is the type of the attached disk image.
+///
+/// # Note
+/// The link with the memory extension is not preserved by serialization and should be re-established
+/// with [DivIdeBusDevice::link_memory_extension] after deserialization.
+#[derive(Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(bound(deserialize = "D: Deserialize<'de> + Default",
+                                              serialize = "D: Serialize")))]
+pub struct DivIdeBusDevice<F, D> {
+    /// Provides direct access to the emulated IDE drive.
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    pub drive: AtaDrive<F>,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    control: u8,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    memext_control: Option<Rc<Cell<u8>>>,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    bus: D
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+struct DivIdeAtaPortAddress;
+impl PortAddress for DivIdeAtaPortAddress {
+    const ADDRESS_MASK: u16 = 0b0000_0000_1110_0011;
+    const ADDRESS_BITS: u16 = 0b0000_0000_1010_0011;
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+struct DivIdeControlPortAddress;
+impl PortAddress for DivIdeControlPortAddress {
+    const ADDRESS_MASK: u16 = 0b0000_0000_1111_1111;
+    const ADDRESS_BITS: u16 = 0b0000_0000_1110_0011;
+}
+
+impl<F, D: Default> Default for DivIdeBusDevice<F, D> {
+    fn default() -> Self {
+        DivIdeBusDevice {
+            drive: AtaDrive::default(),
+            control: 0,
+            memext_control: None,
+            bus: D::default()
+        }
+    }
+}
+
+impl<F, D> fmt::Display for DivIdeBusDevice<F, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DivIDE")
+    }
+}
+
+impl<F, D> Deref for DivIdeBusDevice<F, D> {
+    type Target = AtaDrive<F>;
+    fn deref(&self) -> &Self::Target {
+        &self.drive
+    }
+}
+
+impl<F, D> DerefMut for DivIdeBusDevice<F, D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.drive
+    }
+}
+
+impl<F, D> DivIdeBusDevice<F, D> {
+    /// Links the device with the memory extension, so the writes to the control port change the
+    /// memory paging.
+    ///
+    /// The current value of the control register is passed to the memory extension.
+    pub fn link_memory_extension(&mut self, memext: &DivIdeMemExt) {
+        let control = memext.control_state();
+        control.set(self.control);
+        self.memext_control = Some(control);
+    }
+    /// Removes the link with the memory extension.
+    pub fn unlink_memory_extension(&mut self) {
+        self.memext_control = None;
+    }
+    /// Returns the last value written to the control port.
+    pub fn control(&self) -> u8 {
+        self.control
+    }
+    /// Returns `true` if the `CONMEM` bit of the control port is set.
+    pub fn is_conmem(&self) -> bool {
+        self.control & DIVIDE_CONMEM != 0
+    }
+    /// Returns `true` if the `MAPRAM` bit of the control port is set.
+    pub fn is_mapram(&self) -> bool {
+        self.control & DIVIDE_MAPRAM != 0
+    }
+    /// Returns the index of the selected RAM bank.
+    pub fn ram_bank(&self) -> u8 {
+        self.control & DIVIDE_BANK_MASK
+    }
+
+    #[inline]
+    fn set_control(&mut self, control: u8) {
+        self.control = control;
+        if let Some(memext_control) = self.memext_control.as_ref() {
+            memext_control.set(control);
+        }
+    }
+}
+
+impl<F: Read + Write + Seek, D> DivIdeBusDevice<F, D> {
+    /// Attaches the disk `image` to the IDE drive, returning the previously attached one, if any.
+    ///
+    /// See [AtaDrive::attach_image] for details.
+    pub fn attach_image(&mut self, image: F) -> std::io::Result<Option<F>> {
+        self.drive.attach_image(image)
+    }
+}
+
+impl<F, D> PassByAyAudioBusDevice for DivIdeBusDevice<F, D> {}
+
+impl<F, D> BusDevice for DivIdeBusDevice<F, D>
+    where F: Read + Write + Seek + fmt::Debug,
+          D: BusDevice
+{
+    type Timestamp = D::Timestamp;
+    type NextDevice = D;
+
+    #[inline]
+    fn next_device_mut(&mut self) -> &mut Self::NextDevice {
+        &mut self.bus
+    }
+
+    #[inline]
+    fn next_device_ref(&self) -> &Self::NextDevice {
+        &self.bus
+    }
+
+    #[inline]
+    fn into_next_device(self) -> Self::NextDevice {
+        self.bus
+    }
+
+    #[inline]
+    fn reset(&mut self, timestamp: Self::Timestamp) {
+        // MAPRAM can be only cleared by a power cycle
+        self.set_control(self.control & DIVIDE_MAPRAM);
+        self.drive.reset();
+        self.bus.reset(timestamp);
+    }
+
+    #[inline]
+    fn read_io(&mut self, port: u16, timestamp: Self::Timestamp) -> Option<(u8, Option<NonZeroU16>)> {
+        if DivIdeAtaPortAddress::match_port(port) {
+            let reg = AtaRegister::from_index((port >> 2) as u8);
+            return Some((self.drive.read_register(reg), None))
+        }
+        self.bus.read_io(port, timestamp)
+    }
+
+    #[inline]
+    fn write_io(&mut self, port: u16, data: u8, timestamp: Self::Timestamp) -> Option<u16> {
+        if DivIdeAtaPortAddress::match_port(port) {
+            let reg = AtaRegister::from_index((port >> 2) as u8);
+            self.drive.write_register(reg, data);
+            return Some(0)
+        }
+        if DivIdeControlPortAddress::match_port(port) {
+            // MAPRAM once set can't be reset by software
+            self.set_control(data | self.control & DIVIDE_MAPRAM);
+            return Some(0)
+        }
+        self.bus.write_io(port, data, timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use spectrusty_core::bus::NullDevice;
+    use super::*;
+
+    #[test]
+    fn divide_bus_device_works() {
+        let mut divide: DivIdeBusDevice<Cursor<Vec<u8>>, NullDevice<()>> = Default::default();
+        assert_eq!(format!("{}", divide), "DivIDE");
+        divide.attach_image(Cursor::new(vec![7u8; 2 * ATA_SECTOR_SIZE])).unwrap();
+        assert_eq!(divide.read_io(0x00BF, ()), Some((0x50, None)));
+        assert_eq!(divide.write_io(0x00BB, 0xE0, ()), Some(0));
+        assert_eq!(divide.write_io(0x00AF, 1, ()), Some(0));
+        assert_eq!(divide.write_io(0x00BF, 0x20, ()), Some(0));
+        assert_eq!(divide.read_io(0x00BF, ()), Some((0x58, None)));
+        assert_eq!(divide.read_io(0x00A3, ()), Some((7, None)));
+        assert_eq!(divide.read_io(0x00FE, ()), None);
+        assert_eq!(divide.write_io(0x00E3, 0xC1, ()), Some(0));
+        assert!(divide.is_conmem());
+        assert!(divide.is_mapram());
+        assert_eq!(divide.ram_bank(), 1);
+        assert_eq!(divide.write_io(0x00E3, 0x02, ()), Some(0));
+        assert_eq!(divide.control(), 0x42);
+        divide.reset(());
+        assert_eq!(divide.control(), DIVIDE_MAPRAM);
+        assert_eq!(divide.read_io(0x00BF, ()), Some((0x50, None)));
+    }
+}
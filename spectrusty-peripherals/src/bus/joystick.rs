@@ -33,6 +33,11 @@ pub type KempstonJoystick<D> = JoystickBusDevice<
                                                 KempstonJoyPortAddress,
                                                 KempstonJoystickDevice,
                                                 D>;
+/// A convenient Kempston Joystick with two fire buttons [BusDevice] type.
+pub type KempstonTwoButtonJoystick<D> = JoystickBusDevice<
+                                                KempstonJoyPortAddress,
+                                                TwoButtonJoystickDevice,
+                                                D>;
 /// A convenient Fuller Joystick [BusDevice] type.
 pub type FullerJoystick<D> = JoystickBusDevice<
                                                 FullerJoyPortAddress,
@@ -79,6 +84,7 @@ macro_rules! joystick_names {
 
 joystick_names! {
     KempstonJoystick<D>: "Kempston",
+    KempstonTwoButtonJoystick<D>: "Kempston 2-Button",
     FullerJoystick<D>: "Fuller",
     SinclairRightJoystick<D>: "Sinclair #1",
     SinclairLeftJoystick<D>: "Sinclair #2",
@@ -252,6 +258,7 @@ pub enum JoystickSelect {
     Sinclair(SinclairJoystickDevice<SinclairJoyRightMap>,
              SinclairJoystickDevice<SinclairJoyLeftMap>),
     Cursor(CursorJoystickDevice),
+    KempstonTwoButton(TwoButtonJoystickDevice),
 }
 
 impl Default for JoystickSelect {
@@ -280,6 +287,7 @@ impl From<JoystickSelect> for &str {
             Fuller(..)   => "Fuller",
             Sinclair(..) => "Sinclair",
             Cursor(..)   => "Cursor",
+            KempstonTwoButton(..) => "Kempston 2-Button",
         }
     }
 }
@@ -342,7 +350,7 @@ impl<'a> TryFrom<Cow<'a, str>> for JoystickSelect {
 #[allow(clippy::len_without_is_empty)]
 impl JoystickSelect {
     /// The largest value that can be passed as a `global_index` to [JoystickSelect::new_with_index].
-    pub const MAX_GLOBAL_INDEX: usize = 4;
+    pub const MAX_GLOBAL_INDEX: usize = 5;
     /// Creates a new joystick device variant from a given name.
     ///
     /// On success returns a tuple with one of the joystick variants and the number of
@@ -353,6 +361,11 @@ impl JoystickSelect {
         if name.eq_ignore_ascii_case("Kempston") {
             Some((Kempston(Default::default()), 1))
         }
+        else if name.eq_ignore_ascii_case("Kempston 2-Button")
+              ||name.eq_ignore_ascii_case("Kempston2")
+              ||name.eq_ignore_ascii_case("Two-Button") {
+            Some((KempstonTwoButton(Default::default()), 1))
+        }
         else if name.eq_ignore_ascii_case("Fuller") {
             Some((Fuller(Default::default()), 1))
         }
@@ -388,6 +401,7 @@ impl JoystickSelect {
             1 => Some((Fuller(Default::default()), 0)),
             i@2|i@3 => Some((Sinclair(Default::default(), Default::default()), i-2)),
             4 => Some((Cursor(Default::default()), 0)),
+            5 => Some((KempstonTwoButton(Default::default()), 0)),
             _ => None
         }
     }
@@ -412,6 +426,7 @@ impl JoystickSelect {
             JoystickSelect::Sinclair(ref mut joy, _) if index == 0 => Some(joy),
             JoystickSelect::Sinclair(_, ref mut joy) if index == 1 => Some(joy),
             JoystickSelect::Cursor(ref mut joy) if index == 0 => Some(joy),
+            JoystickSelect::KempstonTwoButton(ref mut joy) if index == 0 => Some(joy),
             _ => None
         }
     }
@@ -428,13 +443,14 @@ impl JoystickSelect {
             Fuller(..) => Sinclair(Default::default(), Default::default()),
             Sinclair(..) if index == 0 => return 1,
             Sinclair(..) => Cursor(Default::default()),
-            Cursor(..) => Kempston(Default::default()),
+            Cursor(..) => KempstonTwoButton(Default::default()),
+            KempstonTwoButton(..) => Kempston(Default::default()),
         };
         0
     }
     #[inline]
     pub fn is_last(&self) -> bool {
-        self.is_cursor()
+        self.is_kempston_two_button()
     }
     #[inline]
     pub fn is_kempston(&self) -> bool {
@@ -464,6 +480,13 @@ impl JoystickSelect {
         }
         false
     }
+    #[inline]
+    pub fn is_kempston_two_button(&self) -> bool {
+        if let JoystickSelect::KempstonTwoButton(..) = self {
+            return true
+        }
+        false
+    }
 }
 
 impl<D> PassByAyAudioBusDevice for MultiJoystickBusDevice<D> {}
@@ -501,6 +524,8 @@ impl<D> BusDevice for MultiJoystickBusDevice<D>
                 Some(joystick.port_read(port))
             }
             Sinclair(joy1, joy2) => {
+                // each joystick responds only to its own keyboard half-row, when both are
+                // being addressed at once the results are combined like the keyboard rows
                 let joy_data1 = if SinclairRightJoyPortAddress::match_port(port) {
                     Some(joy1.port_read(port))
                 }
@@ -523,6 +548,9 @@ impl<D> BusDevice for MultiJoystickBusDevice<D>
             Cursor(joystick) if CursorJoyPortAddress::match_port(port) => {
                 Some(joystick.port_read(port))
             }
+            KempstonTwoButton(joystick) if KempstonJoyPortAddress::match_port(port) => {
+                Some(joystick.port_read(port))
+            }
             _ => None
         };
         if let Some(joy_data) = joy_data {
@@ -541,6 +569,7 @@ impl<D> BusDevice for MultiJoystickBusDevice<D>
 #[cfg(feature = "snapshot")]
 mod tests {
     use spectrusty_core::{bus::NullDevice, clock::FTs};
+    use crate::joystick::Directions;
     use super::*;
 
     #[test]
@@ -562,6 +591,66 @@ mod tests {
             JoystickSelect::new_from_name("sinclair").unwrap().0).to_string(), "Sinclair Joystick");
         assert_eq!(MultiJoystickBusDevice::<TerminatorDevice>::new_with(
             JoystickSelect::new_from_name("cursor").unwrap().0).to_string(), "Cursor Joystick");
+        assert_eq!(KempstonTwoButtonJoystick::<TerminatorDevice>::default().to_string(), "Kempston 2-Button Joystick");
+        assert_eq!(MultiJoystickBusDevice::<TerminatorDevice>::new_with(
+            JoystickSelect::new_from_name("kempston2").unwrap().0).to_string(), "Kempston 2-Button Joystick");
+    }
+
+    #[test]
+    fn joystick_two_buttons() {
+        let mut joy = KempstonTwoButtonJoystick::<NullDevice<FTs>>::default();
+        assert_eq!(joy.read_io(0x1f, 0), Some((0, None)));
+        joy.fire(0, true);
+        joy.set_fire2(true);
+        assert!(joy.get_fire(0) && joy.get_fire(1) && joy.get_fire2());
+        joy.set_directions(Directions::UP|Directions::LEFT);
+        assert_eq!(joy.read_io(0x1f, 0), Some((0b0011_1010, None)));
+        joy.fire(1, false);
+        assert!(!joy.get_fire2());
+        assert_eq!(joy.read_io(0x1f, 0), Some((0b0001_1010, None)));
+        let mut kempston = KempstonJoystick::<NullDevice<FTs>>::default();
+        kempston.set_fire2(true);
+        assert!(!kempston.get_fire2());
+        assert_eq!(kempston.read_io(0x1f, 0), Some((0, None)));
+        let mut multi = MultiJoystickBusDevice::<NullDevice<FTs>>::new_with(
+            JoystickSelect::new_with_index(JoystickSelect::MAX_GLOBAL_INDEX).unwrap().0);
+        assert!(multi.is_kempston_two_button() && multi.is_last());
+        multi.joystick_interface(0).unwrap().set_fire2(true);
+        assert_eq!(multi.read_io(0x1f, 0), Some((0b0010_0000, None)));
+        assert_eq!(multi.select_next_joystick(0), 0);
+        assert!(multi.is_kempston());
+    }
+
+    #[test]
+    fn joystick_sinclair_ports() {
+        let mut multi = MultiJoystickBusDevice::<NullDevice<FTs>>::new_with(
+            JoystickSelect::new_from_name("Sinclair").unwrap().0);
+        let mut chain = SinclairJoystick::<NullDevice<FTs>>::default();
+        // (right #1 directions, right #1 fire, left #2 directions, left #2 fire)
+        // ports: (right #1 row 0xEFFE, left #2 row 0xF7FE, both rows 0xE7FE)
+        for &((dir1, fire1, dir2, fire2), (right, left, both)) in &[
+            ((Directions::empty(), false, Directions::empty(), false), (0xFF, 0xFF, 0xFF)),
+            ((Directions::UP, false, Directions::empty(), true),       (0xFD, 0xEF, 0xED)),
+            ((Directions::empty(), true, Directions::LEFT, false),     (0xFE, 0xFE, 0xFE)),
+            ((Directions::RIGHT|Directions::DOWN, false, Directions::empty(), false), (0xF3, 0xFF, 0xF3)),
+            ((Directions::empty(), false, Directions::RIGHT|Directions::DOWN, false), (0xFF, 0xF9, 0xF9)),
+        ] {
+            for (index, &(dir, fire)) in [(dir1, fire1), (dir2, fire2)].iter().enumerate() {
+                let joy = multi.joystick_interface(index).unwrap();
+                joy.set_directions(dir);
+                joy.fire(0, fire);
+            }
+            chain.next_device_mut().set_directions(dir1);
+            chain.next_device_mut().fire(0, fire1);
+            chain.set_directions(dir2);
+            chain.fire(0, fire2);
+            for &(port, data) in &[(0xeffe, right), (0xf7fe, left), (0xe7fe, both)] {
+                assert_eq!(multi.read_io(port, 0), Some((data, None)), "{:04x}", port);
+                assert_eq!(chain.read_io(port, 0), Some((data, None)), "{:04x}", port);
+            }
+            assert_eq!(multi.read_io(0xfefe, 0), None);
+            assert_eq!(chain.read_io(0xfefe, 0), None);
+        }
     }
 
     #[test]
@@ -0,0 +1,185 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! A bus device for connecting the **Multiface** family of interfaces.
+use core::cell::Cell;
+use core::num::NonZeroU16;
+use core::fmt;
+use core::marker::PhantomData;
+use std::rc::Rc;
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use spectrusty_core::bus::BusDevice;
+use super::ay::PassByAyAudioBusDevice;
+
+pub use crate::memory::{MultifaceMemExt, MultifaceState};
+
+/// The I/O ports (`A0-A7`) used by the **Multiface** model to page its memory in and out.
+pub trait MultifacePorts: fmt::Debug {
+    /// Reading from this port pages the **Multiface** memory in.
+    const PAGE_IN: u8;
+    /// Reading from this port pages the **Multiface** memory out.
+    const PAGE_OUT: u8;
+    /// The name of the model.
+    const NAME: &'static str;
+}
+
+/// The **Multiface One** ports.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MultifaceOnePorts;
+impl MultifacePorts for MultifaceOnePorts {
+    const PAGE_IN: u8 = 0x9F;
+    const PAGE_OUT: u8 = 0x1F;
+    const NAME: &'static str = "Multiface One";
+}
+
+/// The **Multiface 128** ports.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Multiface128Ports;
+impl MultifacePorts for Multiface128Ports {
+    const PAGE_IN: u8 = 0xBF;
+    const PAGE_OUT: u8 = 0x3F;
+    const NAME: &'static str = "Multiface 128";
+}
+
+/// The **Multiface 3** ports.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Multiface3Ports;
+impl MultifacePorts for Multiface3Ports {
+    const PAGE_IN: u8 = 0x3F;
+    const PAGE_OUT: u8 = 0xBF;
+    const NAME: &'static str = "Multiface 3";
+}
+
+pub type MultifaceOne<D> = MultifaceBusDevice<MultifaceOnePorts, D>;
+pub type Multiface128<D> = MultifaceBusDevice<Multiface128Ports, D>;
+pub type Multiface3<D> = MultifaceBusDevice<Multiface3Ports, D>;
+
+/// Emulates the **Multiface** button and paging ports as a [BusDevice].
+///
+/// The memory paging is performed by the linked [MultifaceMemExt] which should be installed as the memory
+/// extension of the emulated chipset and linked with [MultifaceBusDevice::link_memory_extension].
+///
+/// To activate the **Multiface** call [MultifaceBusDevice::press_button] followed by
+/// [ControlUnit::nmi][spectrusty_core::chip::ControlUnit::nmi].
+///
+/// # Note
+/// The link with the memory extension is not preserved by serialization and should be re-established
+/// after deserialization.
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct MultifaceBusDevice<P, D> {
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    state: Rc<Cell<MultifaceState>>,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    bus: D,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    _ports: PhantomData<P>
+}
+
+impl<P: MultifacePorts, D> fmt::Display for MultifaceBusDevice<P, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(P::NAME)
+    }
+}
+
+impl<P, D> MultifaceBusDevice<P, D> {
+    /// Links the device with the memory extension, sharing the state of the **Multiface**.
+    pub fn link_memory_extension(&mut self, memext: &MultifaceMemExt) {
+        self.state = memext.shared_state();
+    }
+    /// Presses the **Multiface** button, latching the request to page in its memory when
+    /// the **NMI** is being handled.
+    ///
+    /// The **NMI** should be triggered with [ControlUnit::nmi][spectrusty_core::chip::ControlUnit::nmi].
+    pub fn press_button(&mut self) {
+        self.update_state(|state| state.button_latch = true);
+    }
+    /// Returns the current state of the **Multiface**.
+    pub fn state(&self) -> MultifaceState {
+        self.state.get()
+    }
+    /// Returns `true` if the **Multiface** memory has been requested to be paged in.
+    pub fn is_paged_in(&self) -> bool {
+        self.state.get().paged_in
+    }
+
+    fn update_state<F: FnOnce(&mut MultifaceState)>(&self, f: F) {
+        let mut state = self.state.get();
+        f(&mut state);
+        self.state.set(state);
+    }
+}
+
+impl<P, D> PassByAyAudioBusDevice for MultifaceBusDevice<P, D> {}
+
+impl<P: MultifacePorts, D: BusDevice> BusDevice for MultifaceBusDevice<P, D> {
+    type Timestamp = D::Timestamp;
+    type NextDevice = D;
+
+    #[inline]
+    fn next_device_mut(&mut self) -> &mut Self::NextDevice {
+        &mut self.bus
+    }
+
+    #[inline]
+    fn next_device_ref(&self) -> &Self::NextDevice {
+        &self.bus
+    }
+
+    #[inline]
+    fn into_next_device(self) -> Self::NextDevice {
+        self.bus
+    }
+
+    #[inline]
+    fn reset(&mut self, timestamp: Self::Timestamp) {
+        self.state.set(MultifaceState::default());
+        self.bus.reset(timestamp);
+    }
+
+    #[inline]
+    fn read_io(&mut self, port: u16, timestamp: Self::Timestamp) -> Option<(u8, Option<NonZeroU16>)> {
+        match port as u8 {
+            p if p == P::PAGE_IN => self.update_state(|state| state.paged_in = true),
+            p if p == P::PAGE_OUT => self.update_state(|state| state.paged_in = false),
+            _ => {}
+        }
+        self.bus.read_io(port, timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spectrusty_core::bus::NullDevice;
+    use super::*;
+
+    #[test]
+    fn multiface_bus_device_works() {
+        let memext = MultifaceMemExt::default();
+        let mut mf: Multiface128<NullDevice<()>> = Default::default();
+        assert_eq!(format!("{}", mf), "Multiface 128");
+        mf.link_memory_extension(&memext);
+        mf.press_button();
+        assert_eq!(memext.state(), MultifaceState { button_latch: true, paged_in: false });
+        assert_eq!(mf.read_io(0x00BF, ()), None);
+        assert!(mf.is_paged_in());
+        assert!(memext.state().paged_in);
+        assert_eq!(mf.read_io(0x003F, ()), None);
+        assert!(!mf.is_paged_in());
+        mf.read_io(0x00BF, ());
+        mf.reset(());
+        assert_eq!(memext.state(), MultifaceState::default());
+        let mut mf3: Multiface3<NullDevice<()>> = Default::default();
+        assert_eq!(format!("{}", mf3), "Multiface 3");
+        mf3.read_io(0x3F, ());
+        assert!(mf3.is_paged_in());
+        assert!(!memext.state().paged_in);
+    }
+}
@@ -0,0 +1,152 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! A bus device for connecting the ZX Spectrum +3 floppy disk controller.
+use core::num::NonZeroU16;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use spectrusty_core::bus::{BusDevice, PortAddress};
+use super::ay::PassByAyAudioBusDevice;
+
+pub use crate::storage::dsk::*;
+pub use crate::storage::upd765::*;
+
+/// The drive motor bit of the +3 system control port `0x1FFD`.
+pub const PLUS3_MOTOR_BIT: u8 = 0b0000_1000;
+
+/// Connects the [Upd765] floppy disk controller as a [BusDevice] the way it's wired in the
+/// ZX Spectrum +3 and +2A/B.
+///
+/// The main status register is read from port `0x2FFD` and the data register is accessed via
+/// port `0x3FFD`. Writes to the system control port `0x1FFD` are inspected for the state of the drive
+/// motors and then passed to the next device.
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct Plus3FdcBusDevice<D> {
+    /// Provides direct access to the [Upd765] controller.
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    pub fdc: Upd765,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    bus: D
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+struct Plus3FdcStatusPortAddress;
+impl PortAddress for Plus3FdcStatusPortAddress {
+    const ADDRESS_MASK: u16 = 0b1111_0000_0000_0010;
+    const ADDRESS_BITS: u16 = 0b0010_1111_1111_1101;
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+struct Plus3FdcDataPortAddress;
+impl PortAddress for Plus3FdcDataPortAddress {
+    const ADDRESS_MASK: u16 = 0b1111_0000_0000_0010;
+    const ADDRESS_BITS: u16 = 0b0011_1111_1111_1101;
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+struct Plus3CtrlPortAddress;
+impl PortAddress for Plus3CtrlPortAddress {
+    const ADDRESS_MASK: u16 = 0b1111_0000_0000_0010;
+    const ADDRESS_BITS: u16 = 0b0001_1111_1111_1101;
+}
+
+impl<D> fmt::Display for Plus3FdcBusDevice<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("+3 FDC")
+    }
+}
+
+impl<D> Deref for Plus3FdcBusDevice<D> {
+    type Target = Upd765;
+    fn deref(&self) -> &Self::Target {
+        &self.fdc
+    }
+}
+
+impl<D> DerefMut for Plus3FdcBusDevice<D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.fdc
+    }
+}
+
+impl<D> PassByAyAudioBusDevice for Plus3FdcBusDevice<D> {}
+
+impl<D: BusDevice> BusDevice for Plus3FdcBusDevice<D> {
+    type Timestamp = D::Timestamp;
+    type NextDevice = D;
+
+    #[inline]
+    fn next_device_mut(&mut self) -> &mut Self::NextDevice {
+        &mut self.bus
+    }
+
+    #[inline]
+    fn next_device_ref(&self) -> &Self::NextDevice {
+        &self.bus
+    }
+
+    #[inline]
+    fn into_next_device(self) -> Self::NextDevice {
+        self.bus
+    }
+
+    #[inline]
+    fn reset(&mut self, timestamp: Self::Timestamp) {
+        self.fdc.reset();
+        self.bus.reset(timestamp);
+    }
+
+    #[inline]
+    fn read_io(&mut self, port: u16, timestamp: Self::Timestamp) -> Option<(u8, Option<NonZeroU16>)> {
+        if Plus3FdcStatusPortAddress::match_port(port) {
+            return Some((self.fdc.read_status(), None))
+        }
+        if Plus3FdcDataPortAddress::match_port(port) {
+            return Some((self.fdc.read_data(), None))
+        }
+        self.bus.read_io(port, timestamp)
+    }
+
+    #[inline]
+    fn write_io(&mut self, port: u16, data: u8, timestamp: Self::Timestamp) -> Option<u16> {
+        if Plus3FdcDataPortAddress::match_port(port) {
+            self.fdc.write_data(data);
+            return Some(0)
+        }
+        if Plus3CtrlPortAddress::match_port(port) {
+            self.fdc.set_motor(data & PLUS3_MOTOR_BIT != 0);
+        }
+        self.bus.write_io(port, data, timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spectrusty_core::bus::NullDevice;
+    use super::*;
+
+    #[test]
+    fn plus3_fdc_bus_device_works() {
+        let mut fdc: Plus3FdcBusDevice<NullDevice<()>> = Default::default();
+        assert_eq!(format!("{}", fdc), "+3 FDC");
+        assert_eq!(fdc.write_io(0x1FFD, 0x08, ()), None);
+        assert!(fdc.is_motor_on());
+        assert_eq!(fdc.read_io(0x2FFD, ()), Some((0x80, None)));
+        assert_eq!(fdc.write_io(0x3FFD, 0x08, ()), Some(0));
+        assert_eq!(fdc.read_io(0x2FFD, ()), Some((0xD0, None)));
+        assert_eq!(fdc.read_io(0x3FFD, ()), Some((0x80, None)));
+        assert_eq!(fdc.read_io(0x2FFD, ()), Some((0x80, None)));
+        assert_eq!(fdc.read_io(0x1FFD, ()), None);
+        fdc.write_io(0x1FFD, 0x00, ());
+        assert!(!fdc.is_motor_on());
+    }
+}
@@ -44,10 +44,21 @@ pub trait JoystickInterface {
     /// Press or release a "fire" button. `btn` is the button number for cases when the joystick have more
     /// than one button.
     ///
-    /// Currently, `btn` is not being used by any of the implemented devices.
+    /// Currently, `btn` is only being used by [kempston::TwoButtonJoystickDevice], where `1` selects
+    /// the second button. Other devices ignore it.
     fn fire(&mut self, btn: u8, pressed: bool);
     /// Returns `true` if an indicated "fire" button is being pressed, otherwise returns `false`.
     fn get_fire(&self, btn: u8) -> bool;
+    /// Press or release the second "fire" button.
+    ///
+    /// The default implementation does nothing, for devices that support only one button.
+    #[inline]
+    fn set_fire2(&mut self, _pressed: bool) {}
+    /// Returns `true` if the second "fire" button is being pressed, otherwise returns `false`.
+    ///
+    /// The default implementation always returns `false`.
+    #[inline]
+    fn get_fire2(&self) -> bool { false }
     /// Changes the stick direction using provided flags.
     fn set_directions(&mut self, dir: Directions);
     /// Returns the current stick direction.
@@ -95,7 +106,7 @@ pub trait JoystickInterface {
     /// Returns `true` if a joystick is in the center (neutral) position.
     #[inline]
     fn is_center(&self) -> bool {
-        self.get_directions().intersects(Directions::empty())
+        self.get_directions().is_empty()
     }
 }
 
@@ -128,3 +139,41 @@ impl JoystickInterface for NullJoystickDevice {
         Directions::empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joystick_state_works<J: JoystickInterface + Default>() {
+        let mut joy = J::default();
+        assert!(joy.is_center());
+        assert!(!joy.get_fire(0));
+        joy.direction(JoyDirection::UpLeft);
+        assert_eq!(joy.get_directions(), Directions::UP|Directions::LEFT);
+        assert!(joy.is_up() && joy.is_left() && !joy.is_down() && !joy.is_right());
+        assert!(!joy.is_center());
+        joy.fire(0, true);
+        assert!(joy.get_fire(0));
+        joy.direction(JoyDirection::DownRight);
+        assert_eq!(joy.get_directions(), Directions::DOWN|Directions::RIGHT);
+        assert!(joy.get_fire(0));
+        joy.fire(0, false);
+        assert!(!joy.get_fire(0));
+        joy.center();
+        assert!(joy.is_center());
+        assert_eq!(joy.get_directions(), Directions::empty());
+    }
+
+    #[test]
+    fn joystick_state_getters_work() {
+        joystick_state_works::<cursor::CursorJoystickDevice>();
+        joystick_state_works::<fuller::FullerJoystickDevice>();
+        joystick_state_works::<kempston::KempstonJoystickDevice>();
+        joystick_state_works::<kempston::TwoButtonJoystickDevice>();
+        joystick_state_works::<sinclair::SinclairJoystickDevice<sinclair::SinclairJoyLeftMap>>();
+        joystick_state_works::<sinclair::SinclairJoystickDevice<sinclair::SinclairJoyRightMap>>();
+        let mut joy = kempston::TwoButtonJoystickDevice::default();
+        joy.fire(1, true);
+        assert!(joy.get_fire(1) && joy.get_fire2() && !joy.get_fire(0));
+    }
+}
@@ -75,3 +75,39 @@ impl JoystickInterface for CursorJoystickDevice {
         self.directions
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_joystick_port_read() {
+        // 0xF7FE: keys 1-5, bit 4 = "5" (left)
+        // 0xEFFE: keys 0-6, bit 0 = "0" (fire), bit 2 = "8" (right), bit 3 = "7" (up), bit 4 = "6" (down)
+        let mut joy = CursorJoystickDevice::default();
+        for port in [0xF7FE, 0xEFFE, 0xE7FE, 0xFEFE] {
+            assert_eq!(joy.port_read(port), 0xFF);
+        }
+        for (dir, data_f7, data_ef) in [(Directions::LEFT,  0b1110_1111, 0xFF),
+                                        (Directions::DOWN,  0xFF, 0b1110_1111),
+                                        (Directions::UP,    0xFF, 0b1111_0111),
+                                        (Directions::RIGHT, 0xFF, 0b1111_1011),
+                                        (Directions::UP|Directions::LEFT, 0b1110_1111, 0b1111_0111),
+                                        (Directions::DOWN|Directions::RIGHT, 0xFF, 0b1110_1011)] {
+            joy.set_directions(dir);
+            assert_eq!(joy.get_directions(), dir);
+            assert_eq!(joy.port_read(0xF7FE), data_f7);
+            assert_eq!(joy.port_read(0xEFFE), data_ef);
+            assert_eq!(joy.port_read(0xE7FE), data_f7 & data_ef);
+            assert_eq!(joy.port_read(0xFBFE), 0xFF);
+            joy.fire(0, true);
+            assert!(joy.get_fire(0));
+            assert_eq!(joy.port_read(0xF7FE), data_f7);
+            assert_eq!(joy.port_read(0xEFFE), data_ef & !1);
+            joy.fire(0, false);
+            assert_eq!(joy.port_read(0xEFFE), data_ef);
+        }
+        joy.center();
+        assert_eq!(joy.port_read(0xE7FE), 0xFF);
+    }
+}
@@ -7,7 +7,8 @@
 */
 //! Kempston Joystick implementation.
 use super::{JoystickDevice, Directions, JoystickInterface};
-                      // 000F_UDLR
+                      // 00GF_UDLR
+const FIRE2_MASK: u8 = 0b0010_0000;
 const FIRE_MASK:  u8 = 0b0001_0000;
 const RIGHT_MASK: u8 = 0b0000_0001;
 const LEFT_MASK:  u8 = 0b0000_0010;
@@ -54,3 +55,64 @@ impl JoystickInterface for KempstonJoystickDevice {
         self.directions
     }
 }
+
+/// The Kempston compatible joystick device with two "fire" buttons implements [JoystickDevice] and
+/// [JoystickInterface].
+///
+/// The second button is reported on bit 5 of the Kempston port, as expected by the games and
+/// interfaces supporting more than one button.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TwoButtonJoystickDevice {
+    joystick: KempstonJoystickDevice
+}
+
+impl JoystickDevice for TwoButtonJoystickDevice {
+    #[inline]
+    fn port_read(&self, port: u16) -> u8 {
+        self.joystick.port_read(port)
+    }
+}
+
+impl JoystickInterface for TwoButtonJoystickDevice {
+    /// `btn`: `0` - the first button, `1` - the second button.
+    fn fire(&mut self, btn: u8, pressed: bool) {
+        if btn == 1 {
+            self.set_fire2(pressed)
+        }
+        else {
+            self.joystick.fire(btn, pressed)
+        }
+    }
+
+    fn get_fire(&self, btn: u8) -> bool {
+        if btn == 1 {
+            self.get_fire2()
+        }
+        else {
+            self.joystick.get_fire(btn)
+        }
+    }
+
+    fn set_fire2(&mut self, pressed: bool) {
+        if pressed {
+            self.joystick.data |= FIRE2_MASK;
+        }
+        else {
+            self.joystick.data &= !FIRE2_MASK;
+        }
+    }
+
+    fn get_fire2(&self) -> bool {
+        self.joystick.data & FIRE2_MASK == FIRE2_MASK
+    }
+
+    fn set_directions(&mut self, dir: Directions) {
+        let fire2 = self.joystick.data & FIRE2_MASK;
+        self.joystick.set_directions(dir);
+        self.joystick.data |= fire2;
+    }
+
+    fn get_directions(&self) -> Directions {
+        self.joystick.get_directions()
+    }
+}
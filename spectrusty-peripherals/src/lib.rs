@@ -19,16 +19,35 @@
     Author contact information: see Cargo.toml file, section [package.authors].
 */
 //! Emulator components of various ZX Spectrum peripheral devices for the SPECTRUSTY library.
+//!
+//! # `no_std`
+//!
+//! The `"std"` feature is enabled by default. With the default features disabled, this crate compiles
+//! under `no_std` with `alloc` and only the [ay] module is available, without [ay::serial128] and
+//! the float helpers that require `std`: [ay::audio::LogAmpLevels16],
+//! [ay::audio::Ay3_891xAudio::freq_to_tone_period] and [ay::audio::Ay3_891xAudio::tone_periods]. The `"snapshot"` feature requires `"std"`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+extern crate alloc;
+#[cfg(feature = "std")]
 #[macro_use]
 extern crate bitflags;
 
 pub mod ay;
+#[cfg(feature = "std")]
 pub mod bus;
+#[cfg(feature = "std")]
 pub mod joystick;
+#[cfg(feature = "std")]
 pub mod memory;
+#[cfg(feature = "std")]
 pub mod mouse;
+#[cfg(feature = "std")]
 pub mod network;
+#[cfg(feature = "std")]
 pub mod parallel;
+#[cfg(feature = "std")]
 pub mod serial;
+#[cfg(feature = "std")]
 pub mod storage;
+#[cfg(feature = "std")]
 pub mod zxprinter;
@@ -6,6 +6,28 @@
     For the full copyright notice, see the lib.rs file.
 */
 //! Memory extensions.
+use std::rc::Rc;
+
+use spectrusty_core::memory::{ExRom, ZxMemory};
+
+mod betadisk;
+mod divide;
+mod multiface;
 mod zxinterface1;
 
+pub use betadisk::*;
+pub use divide::*;
+pub use multiface::*;
 pub use zxinterface1::*;
+
+/// Writes `value` at the given `offset` of the `exrom` bank, which is paged in the `memory` at the given `page`.
+///
+/// The bank is temporarily unmapped, so it can be modified in place (or copied if shared) and then mapped again.
+fn write_mapped_exrom<M: ZxMemory>(exrom: &mut ExRom, page: u8, offset: usize, value: u8, memory: &mut M) {
+    memory.unmap_exrom(exrom);
+    if Rc::get_mut(exrom).is_none() {
+        *exrom = Rc::from(&exrom[..]);
+    }
+    Rc::get_mut(exrom).unwrap()[offset] = value;
+    memory.map_exrom(Rc::clone(exrom), page).expect("the EX-ROM bank should be mappable again");
+}
@@ -0,0 +1,122 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+use core::cell::Cell;
+use std::rc::Rc;
+use std::io::{self, Read};
+
+use spectrusty_core::memory::{
+    MemoryExtension, MemoryKind, ExRom, ZxMemory, ZxMemoryError
+};
+#[cfg(feature = "snapshot")]
+use spectrusty_core::memory::serde::{serialize_mem, deserialize_mem};
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+/// The **Beta Disk** interface memory [extension][MemoryExtension] paging the **TR-DOS** ROM.
+///
+/// The **TR-DOS** ROM is paged in when the processor fetches an instruction from the `0x3D00-0x3DFF`
+/// range while the 48k BASIC ROM is paged in. It is paged out when the processor fetches an instruction
+/// from RAM at `0x4000` or above.
+///
+/// The 48k BASIC ROM is assumed to be the last ROM bank, but not higher than `1`.
+///
+/// The state of the **TR-DOS** ROM paging is shared with the linked [BetaDiskBusDevice], so its
+/// I/O ports are only accessible when the **TR-DOS** ROM is active.
+///
+/// [BetaDiskBusDevice]: crate::bus::betadisk::BetaDiskBusDevice
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct BetaDiskMemExt {
+    #[cfg_attr(feature = "snapshot",
+        serde(serialize_with = "serialize_mem", deserialize_with = "deserialize_mem"))]
+    #[cfg_attr(feature = "snapshot", serde(default = "exrom_default"))]
+    exrom: ExRom,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    active: Rc<Cell<bool>>
+}
+
+impl Default for BetaDiskMemExt {
+    fn default() -> Self {
+        let exrom = Rc::new([]);
+        BetaDiskMemExt { exrom, active: Rc::default() }
+    }
+}
+
+impl MemoryExtension for BetaDiskMemExt {
+    #[inline(always)]
+    fn read_opcode<M: ZxMemory>(&mut self, pc: u16, memory: &mut M) -> u8 {
+        match pc {
+            0x3D00..=0x3DFF if !self.active.get() => {
+                let basic_rom = M::ROM_BANKS_MAX.min(1);
+                if let Ok((MemoryKind::Rom, bank)) = memory.page_bank(0) {
+                    if bank == basic_rom && memory.map_exrom(Rc::clone(&self.exrom), 0).is_ok() {
+                        self.active.set(true);
+                    }
+                }
+            }
+            0x4000..=0xFFFF if self.active.get() => {
+                memory.unmap_exrom(&self.exrom);
+                self.active.set(false);
+            }
+            _ => {}
+        }
+        memory.read(pc)
+    }
+}
+
+impl BetaDiskMemExt {
+    /// Provide a reader with 16kb of **TR-DOS** ROM program code.
+    pub fn load_trdos_rom<R: Read>(&mut self, mut rd: R) -> io::Result<()> {
+        let mut exrom = Rc::new([!0u8;0x4000]);
+        rd.read_exact(&mut Rc::get_mut(&mut exrom).unwrap()[..])?;
+        self.exrom = exrom;
+        Ok(())
+    }
+    /// Returns a reference to the EX-ROM bank.
+    pub fn exrom(&self) -> &ExRom {
+        &self.exrom
+    }
+    /// Returns `true` if the **TR-DOS** ROM is considered to be paged in.
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+    /// Returns the state of the **TR-DOS** ROM paging shared with the I/O ports.
+    pub fn active_state(&self) -> Rc<Cell<bool>> {
+        Rc::clone(&self.active)
+    }
+    /// Maps EX-ROM into `memory` page `0`.
+    ///
+    /// # Errors
+    /// Returns an error if the extension's EX-ROM bank is not populated with ROM data.
+    pub fn map_exrom<M: ZxMemory>(&self, memory: &mut M) -> Result<(), ZxMemoryError> {
+        memory.map_exrom(Rc::clone(&self.exrom), 0)?;
+        self.active.set(true);
+        Ok(())
+    }
+    /// Unmaps EX-ROM from `memory`.
+    pub fn unmap_exrom<M: ZxMemory>(&self, memory: &mut M) {
+        memory.unmap_exrom(&self.exrom);
+        self.active.set(false);
+    }
+    /// Returns `true` if EX-ROM is currently paged in.
+    pub fn is_mapped_exrom<M: ZxMemory>(&self, memory: &M) -> bool {
+        memory.has_mapped_exrom(&self.exrom)
+    }
+    /// Synchronizes the shared paging state with the actual state of the `memory`.
+    ///
+    /// Useful after restoring the emulator state from a snapshot.
+    pub fn sync_active<M: ZxMemory>(&self, memory: &M) {
+        self.active.set(self.is_mapped_exrom(memory));
+    }
+}
+
+#[cfg(feature = "snapshot")]
+fn exrom_default() -> ExRom {
+    Rc::new([])
+}
+
@@ -0,0 +1,263 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+use core::cell::Cell;
+use std::rc::Rc;
+use std::io::{self, Read};
+
+use spectrusty_core::memory::{
+    MemoryExtension, ExRom, ZxMemory, ZxMemoryError
+};
+#[cfg(feature = "snapshot")]
+use spectrusty_core::memory::serde::{serialize_mem, deserialize_mem};
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use super::write_mapped_exrom;
+
+/// The size of the **DivIDE** EPROM in bytes.
+pub const DIVIDE_EPROM_SIZE: usize = 0x2000;
+/// The size of a single **DivIDE** RAM bank in bytes.
+pub const DIVIDE_RAM_BANK_SIZE: usize = 0x2000;
+/// The number of **DivIDE** RAM banks.
+pub const DIVIDE_RAM_BANKS: usize = 4;
+/// The `CONMEM` bit of the **DivIDE** control port.
+pub const DIVIDE_CONMEM: u8 = 0b1000_0000;
+/// The `MAPRAM` bit of the **DivIDE** control port.
+pub const DIVIDE_MAPRAM: u8 = 0b0100_0000;
+/// The RAM bank selection bits of the **DivIDE** control port.
+pub const DIVIDE_BANK_MASK: u8 = 0b0000_0011;
+
+const DIVIDE_RAM_SIZE: usize = DIVIDE_RAM_BANKS * DIVIDE_RAM_BANK_SIZE;
+const DIVIDE_MAPRAM_BANK: usize = 3;
+
+/// The **DivIDE** memory [extension][MemoryExtension] implementing the memory paging.
+///
+/// The **DivIDE** memory consists of 8kb of EPROM and 32kb of RAM divided into 4 banks of 8kb. When paged in,
+/// the EPROM is mapped at `0x0000-0x1FFF` and the RAM bank selected by the control port is mapped at
+/// `0x2000-0x3FFF`.
+///
+/// The memory is paged in while the `CONMEM` bit of the control port is set or when the automatic paging is active.
+/// The automatic paging is activated after the processor fetches the instruction at one of the addresses:
+/// `0x0000`, `0x0008`, `0x0038`, `0x0066`, `0x04C6` or `0x0562`, and instantly when the instruction
+/// is fetched from `0x3D00-0x3DFF`. It is deactivated after the instruction is fetched from `0x1FF8-0x1FFF`.
+///
+/// When the `MAPRAM` bit is set, the automatic paging maps RAM bank 3 instead of the EPROM at `0x0000-0x1FFF`.
+/// Bank 3 is write-protected in this mode. The EPROM is always write-protected.
+///
+/// The control register is shared with [DivIdeBusDevice] which should be linked to the extension with
+/// [DivIdeBusDevice::link_memory_extension]. Changes of the control register are applied to the memory
+/// before the next opcode fetch.
+///
+/// # Note
+/// The paging requires memory with 16kb pages that supports EX-ROM banks (see [ZxMemory::map_exrom]).
+///
+/// [DivIdeBusDevice]: crate::bus::divide::DivIdeBusDevice
+/// [DivIdeBusDevice::link_memory_extension]: crate::bus::divide::DivIdeBusDevice::link_memory_extension
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct DivIdeMemExt {
+    #[cfg_attr(feature = "snapshot",
+        serde(serialize_with = "serialize_mem", deserialize_with = "deserialize_mem"))]
+    #[cfg_attr(feature = "snapshot", serde(default = "eprom_default"))]
+    eprom: Box<[u8;DIVIDE_EPROM_SIZE]>,
+    #[cfg_attr(feature = "snapshot",
+        serde(serialize_with = "serialize_mem", deserialize_with = "deserialize_mem"))]
+    #[cfg_attr(feature = "snapshot", serde(default = "ram_default"))]
+    ram: Box<[u8;DIVIDE_RAM_SIZE]>,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    automap: bool,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    control: Rc<Cell<u8>>,
+    // the EX-ROM bank composed of the currently mapped EPROM or RAM banks
+    #[cfg_attr(feature = "snapshot", serde(skip, default = "exrom_default"))]
+    exrom: ExRom,
+    // the (MAPRAM bank in place of EPROM, upper RAM bank) layout of exrom, None if not composed yet
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    layout: Option<(bool, usize)>
+}
+
+impl Default for DivIdeMemExt {
+    fn default() -> Self {
+        DivIdeMemExt {
+            eprom: Box::new([!0u8;DIVIDE_EPROM_SIZE]),
+            ram: Box::new([0u8;DIVIDE_RAM_SIZE]),
+            automap: false,
+            control: Rc::default(),
+            exrom: Rc::new([]),
+            layout: None
+        }
+    }
+}
+
+impl MemoryExtension for DivIdeMemExt {
+    #[inline(always)]
+    fn read_opcode<M: ZxMemory>(&mut self, pc: u16, memory: &mut M) -> u8 {
+        self.apply_paging(memory);
+        match pc {
+            0x0000|0x0008|0x0038|0x0066|0x04C6|0x0562 if !self.automap => {
+                let res = memory.read(pc);
+                self.automap = true;
+                self.apply_paging(memory);
+                res
+            }
+            0x3D00..=0x3DFF if !self.automap => {
+                self.automap = true;
+                self.apply_paging(memory);
+                memory.read(pc)
+            }
+            0x1FF8..=0x1FFF if self.automap => {
+                let res = memory.read(pc);
+                self.automap = false;
+                self.apply_paging(memory);
+                res
+            }
+            _ => memory.read(pc)
+        }
+    }
+
+    #[inline]
+    fn write_mem<M: ZxMemory>(&mut self, addr: u16, value: u8, memory: &mut M) {
+        let addr = addr as usize;
+        if (DIVIDE_EPROM_SIZE..DIVIDE_EPROM_SIZE + DIVIDE_RAM_BANK_SIZE).contains(&addr) &&
+                memory.has_mapped_exrom(&self.exrom) {
+            if let Some((mapram, bank)) = self.layout {
+                if !(mapram && bank == DIVIDE_MAPRAM_BANK) {
+                    let offset = addr - DIVIDE_EPROM_SIZE;
+                    self.ram[bank * DIVIDE_RAM_BANK_SIZE + offset] = value;
+                    write_mapped_exrom(&mut self.exrom, 0, addr, value, memory);
+                }
+                return
+            }
+        }
+        memory.write(addr as u16, value)
+    }
+}
+
+impl DivIdeMemExt {
+    /// Provide a reader with 8kb of **DivIDE** EPROM program code, e.g. **ESXDOS**.
+    ///
+    /// # Note
+    /// If the EPROM has been paged in, the content of `memory` will be updated before the next opcode fetch.
+    pub fn load_eprom<R: Read>(&mut self, mut rd: R) -> io::Result<()> {
+        rd.read_exact(&mut self.eprom[..])?;
+        self.layout = None;
+        Ok(())
+    }
+    /// Returns a reference to the EPROM data.
+    pub fn eprom_ref(&self) -> &[u8] {
+        &self.eprom[..]
+    }
+    /// Returns a reference to the RAM data of all banks.
+    pub fn ram_ref(&self) -> &[u8] {
+        &self.ram[..]
+    }
+    /// Returns a mutable reference to the RAM data of all banks.
+    ///
+    /// # Note
+    /// If the RAM has been paged in, the content of `memory` will be updated before the next opcode fetch.
+    pub fn ram_mut(&mut self) -> &mut [u8] {
+        self.layout = None;
+        &mut self.ram[..]
+    }
+    /// Returns the last value written to the control port.
+    pub fn control(&self) -> u8 {
+        self.control.get()
+    }
+    /// Returns the control register shared with the I/O port.
+    pub fn control_state(&self) -> Rc<Cell<u8>> {
+        Rc::clone(&self.control)
+    }
+    /// Returns `true` if the automatic paging is active.
+    pub fn is_automap(&self) -> bool {
+        self.automap
+    }
+    /// Returns `true` if the **DivIDE** memory is currently paged in.
+    pub fn is_mapped_exrom<M: ZxMemory>(&self, memory: &M) -> bool {
+        memory.has_mapped_exrom(&self.exrom)
+    }
+    /// Pages the **DivIDE** memory in or out of `memory` according to the state of the control register
+    /// and the automatic paging.
+    ///
+    /// This is done automatically before each opcode fetch, but may be useful e.g. after deserialization.
+    ///
+    /// # Errors
+    /// Returns an error if the `memory` doesn't support EX-ROM banks.
+    pub fn sync_paging<M: ZxMemory>(&mut self, memory: &mut M) -> Result<(), ZxMemoryError> {
+        match self.paged_layout() {
+            Some(layout) => {
+                if self.layout != Some(layout) {
+                    memory.unmap_exrom(&self.exrom);
+                    self.compose_exrom(layout);
+                }
+                if !memory.has_mapped_exrom(&self.exrom) {
+                    memory.map_exrom(Rc::clone(&self.exrom), 0)?;
+                }
+            }
+            None => memory.unmap_exrom(&self.exrom)
+        }
+        Ok(())
+    }
+
+    // Returns the layout of the memory that should be paged in or None if it should be paged out.
+    #[inline]
+    fn paged_layout(&self) -> Option<(bool, usize)> {
+        let control = self.control.get();
+        let conmem = control & DIVIDE_CONMEM != 0;
+        if conmem || self.automap {
+            let mapram = !conmem && control & DIVIDE_MAPRAM != 0;
+            Some((mapram, (control & DIVIDE_BANK_MASK) as usize))
+        }
+        else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    fn apply_paging<M: ZxMemory>(&mut self, memory: &mut M) {
+        let layout = self.paged_layout();
+        let is_mapped = memory.has_mapped_exrom(&self.exrom);
+        if (layout.is_some() != is_mapped || is_mapped && layout != self.layout) &&
+                self.sync_paging(memory).is_err() {
+            // the memory can't page in DivIDE, behave as if the automatic paging wasn't triggered
+            self.automap = false;
+        }
+    }
+
+    fn compose_exrom(&mut self, (mapram, bank): (bool, usize)) {
+        if self.exrom.len() != DIVIDE_EPROM_SIZE + DIVIDE_RAM_BANK_SIZE || Rc::get_mut(&mut self.exrom).is_none() {
+            self.exrom = Rc::new([0u8;DIVIDE_EPROM_SIZE + DIVIDE_RAM_BANK_SIZE]);
+        }
+        let exrom = Rc::get_mut(&mut self.exrom).unwrap();
+        let (lower, upper) = exrom.split_at_mut(DIVIDE_EPROM_SIZE);
+        if mapram {
+            let offset = DIVIDE_MAPRAM_BANK * DIVIDE_RAM_BANK_SIZE;
+            lower.copy_from_slice(&self.ram[offset..offset + DIVIDE_RAM_BANK_SIZE]);
+        }
+        else {
+            lower.copy_from_slice(&self.eprom[..]);
+        }
+        let offset = bank * DIVIDE_RAM_BANK_SIZE;
+        upper.copy_from_slice(&self.ram[offset..offset + DIVIDE_RAM_BANK_SIZE]);
+        self.layout = Some((mapram, bank));
+    }
+}
+
+#[cfg(feature = "snapshot")]
+fn eprom_default() -> Box<[u8;DIVIDE_EPROM_SIZE]> {
+    Box::new([!0u8;DIVIDE_EPROM_SIZE])
+}
+
+#[cfg(feature = "snapshot")]
+fn ram_default() -> Box<[u8;DIVIDE_RAM_SIZE]> {
+    Box::new([0u8;DIVIDE_RAM_SIZE])
+}
+
+#[cfg(feature = "snapshot")]
+fn exrom_default() -> ExRom {
+    Rc::new([])
+}
@@ -0,0 +1,159 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+use core::cell::Cell;
+use std::rc::Rc;
+use std::io::{self, Read};
+
+use spectrusty_core::memory::{
+    MemoryExtension, ExRom, ZxMemory
+};
+#[cfg(feature = "snapshot")]
+use spectrusty_core::memory::serde::{serialize_mem, deserialize_mem};
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use super::write_mapped_exrom;
+
+/// The size of the **Multiface** ROM in bytes.
+pub const MULTIFACE_ROM_SIZE: usize = 0x2000;
+/// The size of the **Multiface** RAM in bytes.
+pub const MULTIFACE_RAM_SIZE: usize = 0x2000;
+
+/// The state of the **Multiface** shared between [MultifaceMemExt] and [MultifaceBusDevice].
+///
+/// [MultifaceBusDevice]: crate::bus::multiface::MultifaceBusDevice
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
+pub struct MultifaceState {
+    /// `true` if the button has been pressed and the **NMI** routine hasn't been entered yet.
+    pub button_latch: bool,
+    /// `true` if the **Multiface** memory should be paged in.
+    pub paged_in: bool
+}
+
+/// The **Multiface** memory [extension][MemoryExtension].
+///
+/// The EX-ROM bank consists of the 8kb of ROM mapped at `0x0000-0x1FFF` followed by the 8kb of
+/// RAM mapped at `0x2000-0x3FFF`.
+///
+/// The EX-ROM is paged in when the processor fetches the instruction at `0x0066` (the **NMI** handler)
+/// after the **Multiface** button has been pressed. Otherwise the paging follows the state requested by
+/// the I/O ports of the linked [MultifaceBusDevice]. When paged out the original memory mapping is restored.
+///
+/// The RAM part of the EX-ROM bank can be written to by the emulated program while paged in.
+///
+/// # Note
+/// The paging requires memory with 16kb pages that supports EX-ROM banks (see [ZxMemory::map_exrom]).
+///
+/// [MultifaceBusDevice]: crate::bus::multiface::MultifaceBusDevice
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct MultifaceMemExt {
+    #[cfg_attr(feature = "snapshot",
+        serde(serialize_with = "serialize_mem", deserialize_with = "deserialize_mem"))]
+    #[cfg_attr(feature = "snapshot", serde(default = "exrom_default"))]
+    exrom: ExRom,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    state: Rc<Cell<MultifaceState>>
+}
+
+impl Default for MultifaceMemExt {
+    fn default() -> Self {
+        let exrom = Rc::new([]);
+        MultifaceMemExt { exrom, state: Rc::default() }
+    }
+}
+
+impl MemoryExtension for MultifaceMemExt {
+    #[inline(always)]
+    fn read_opcode<M: ZxMemory>(&mut self, pc: u16, memory: &mut M) -> u8 {
+        let mut state = self.state.get();
+        if pc == 0x0066 && state.button_latch {
+            state.button_latch = false;
+            state.paged_in = true;
+            self.state.set(state);
+        }
+        if state.paged_in != memory.has_mapped_exrom(&self.exrom) {
+            if state.paged_in {
+                if memory.map_exrom(Rc::clone(&self.exrom), 0).is_err() {
+                    // the ROM is not loaded or the memory can't page in Multiface
+                    state.paged_in = false;
+                    self.state.set(state);
+                }
+            }
+            else {
+                memory.unmap_exrom(&self.exrom);
+            }
+        }
+        memory.read(pc)
+    }
+
+    #[inline]
+    fn write_mem<M: ZxMemory>(&mut self, addr: u16, value: u8, memory: &mut M) {
+        let offset = addr as usize;
+        if (MULTIFACE_ROM_SIZE..MULTIFACE_ROM_SIZE + MULTIFACE_RAM_SIZE).contains(&offset) &&
+                memory.has_mapped_exrom(&self.exrom) {
+            write_mapped_exrom(&mut self.exrom, 0, offset, value, memory);
+        }
+        else {
+            memory.write(addr, value)
+        }
+    }
+}
+
+impl MultifaceMemExt {
+    /// Provide a reader with 8kb of **Multiface** ROM program code.
+    ///
+    /// The RAM part of the EX-ROM bank is cleared.
+    pub fn load_rom<R: Read>(&mut self, mut rd: R) -> io::Result<()> {
+        let mut exrom = Rc::new([0u8;MULTIFACE_ROM_SIZE + MULTIFACE_RAM_SIZE]);
+        let exrom_slice = &mut Rc::get_mut(&mut exrom).unwrap()[0..MULTIFACE_ROM_SIZE];
+        rd.read_exact(exrom_slice)?;
+        self.exrom = exrom;
+        Ok(())
+    }
+    /// Provide a reader with 8kb of data to be placed in the RAM part of the EX-ROM bank.
+    ///
+    /// # Errors
+    /// Returns an error if the ROM has not been loaded yet.
+    ///
+    /// # Note
+    /// If the EX-ROM bank has been paged in, the content of `memory` will be updated before the next
+    /// opcode fetch.
+    pub fn load_ram<R: Read>(&mut self, mut rd: R) -> io::Result<()> {
+        if self.exrom.len() < MULTIFACE_ROM_SIZE + MULTIFACE_RAM_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the Multiface ROM is not loaded"))
+        }
+        let mut exrom: Rc<[u8]> = Rc::from(&self.exrom[..]);
+        rd.read_exact(&mut Rc::get_mut(&mut exrom).unwrap()[MULTIFACE_ROM_SIZE..MULTIFACE_ROM_SIZE + MULTIFACE_RAM_SIZE])?;
+        self.exrom = exrom;
+        Ok(())
+    }
+    /// Returns a reference to the EX-ROM bank.
+    pub fn exrom(&self) -> &ExRom {
+        &self.exrom
+    }
+    /// Returns the current state of the **Multiface**.
+    pub fn state(&self) -> MultifaceState {
+        self.state.get()
+    }
+    /// Returns the state of the **Multiface** shared with the I/O ports.
+    pub fn shared_state(&self) -> Rc<Cell<MultifaceState>> {
+        Rc::clone(&self.state)
+    }
+    /// Returns `true` if EX-ROM is currently paged in.
+    pub fn is_mapped_exrom<M: ZxMemory>(&self, memory: &M) -> bool {
+        memory.has_mapped_exrom(&self.exrom)
+    }
+}
+
+#[cfg(feature = "snapshot")]
+fn exrom_default() -> ExRom {
+    Rc::new([])
+}
@@ -45,6 +45,13 @@ pub trait MouseInterface {
     fn get_buttons(&self) -> MouseButtons;
     /// Moves the mouse by the given interval.
     fn move_mouse(&mut self, mov: MouseMovement);
+    /// Rotates the mouse wheel by the given number of steps.
+    ///
+    /// Positive values should be used when the wheel is being rotated forward (away from the user)
+    /// and negative values when it's being rotated backward (towards the user).
+    ///
+    /// Devices without a wheel should ignore this call. The default implementation does exactly just that.
+    fn set_wheel_delta(&mut self, _delta: i8) {}
 }
 
 /// A mouse device interface for the mouse [bus][crate::bus::mouse] device implementations.
@@ -14,10 +14,13 @@ use super::{MouseInterface, MouseDevice, MouseMovement, MouseButtons};
     Horizontal position: IN 64479
     Vertical postition: IN 65503
     Buttons: IN 64223 [255 = None], [254 = Right], [253 = Left], [252 = Both]
+    Kempston Mouse Turbo: bit 2 = middle button, bits 4-7 = wheel position
 */
 const RIGHT_BTN_MASK:  u8 = 0b0000_0001;
 const LEFT_BTN_MASK:   u8 = 0b0000_0010;
 const MIDDLE_BTN_MASK: u8 = 0b0000_0100; // extension
+const WHEEL_MASK:      u8 = 0b1111_0000; // extension
+const WHEEL_SHIFT:     u32 = 4;
 const UNUSED_BTN_MASK: u8 = !(MIDDLE_BTN_MASK|LEFT_BTN_MASK|RIGHT_BTN_MASK);
 
 const PORT_BTN_MASK: u16 = 0b0000_0001_0000_0000;
@@ -36,8 +39,12 @@ const PORT_Y_BITS:   u16 = 0b0000_0101_0000_0000;
 /// A vertical position is being provided when bits of the port address: A8 and A10 is 1.
 /// A button state is being provided when A8 bit of the port address is 0:
 ///
-/// * bit 0 is 0 when the left button is being pressed and 1 when the left button is released.
-/// * bit 1 is 0 when the right button is being pressed and 1 when the right button is released.
+/// * bit 0 is 0 when the right button is being pressed and 1 when the right button is released.
+/// * bit 1 is 0 when the left button is being pressed and 1 when the left button is released.
+/// * bit 2 is 0 when the middle button is being pressed and 1 when the middle button is released.
+/// * bits 4 to 7 contain the 4-bit wheel position counter, as in the Kempston Mouse Turbo interface.
+///   The counter wraps around and is being increased when the wheel is rotated forward and decreased
+///   when it's rotated backward. See [MouseInterface::set_wheel_delta].
 #[derive(Clone, Copy, Debug)]
 #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
@@ -95,6 +102,11 @@ impl MouseInterface for KempstonMouseDevice {
         self.data_x = clamped_move(self.data_x, movement.horizontal);
         self.data_y = clamped_move(self.data_y, -movement.vertical);
     }
+    #[inline]
+    fn set_wheel_delta(&mut self, delta: i8) {
+        let wheel = (self.data_btn >> WHEEL_SHIFT).wrapping_add(delta as u8);
+        self.data_btn = (self.data_btn & !WHEEL_MASK) | (wheel << WHEEL_SHIFT);
+    }
 }
 
 #[inline(always)]
@@ -108,3 +120,28 @@ fn clamped_move(prev: u8, mut delta: i16) -> u8 {
     }
     prev.wrapping_add(delta as u8)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kempston_mouse_buttons_and_wheel_works() {
+        const PORT_BTN: u16 = 64223;
+        let mut mouse = KempstonMouseDevice::default();
+        assert_eq!(mouse.port_read(PORT_BTN), 0b1111_1111);
+        mouse.set_buttons(MouseButtons::LEFT);
+        assert_eq!(mouse.port_read(PORT_BTN), 0b1111_1101);
+        mouse.set_buttons(MouseButtons::RIGHT|MouseButtons::MIDDLE);
+        assert_eq!(mouse.port_read(PORT_BTN), 0b1111_1010);
+        mouse.set_wheel_delta(1);
+        assert_eq!(mouse.port_read(PORT_BTN), 0b0000_1010);
+        mouse.set_wheel_delta(3);
+        assert_eq!(mouse.port_read(PORT_BTN), 0b0011_1010);
+        mouse.set_buttons(MouseButtons::empty());
+        assert_eq!(mouse.port_read(PORT_BTN), 0b0011_1111);
+        mouse.set_wheel_delta(-5);
+        assert_eq!(mouse.port_read(PORT_BTN), 0b1110_1111);
+        assert_eq!(mouse.get_buttons(), MouseButtons::empty());
+    }
+}
@@ -6,4 +6,9 @@
     For the full copyright notice, see the lib.rs file.
 */
 //! Data storage related.
+pub mod dsk;
+pub mod ide;
 pub mod microdrives;
+pub mod trd;
+pub mod upd765;
+pub mod wd1793;
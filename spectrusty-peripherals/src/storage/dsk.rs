@@ -0,0 +1,306 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! Floppy disk images in the **CPCEMU** `.DSK` format.
+//!
+//! Both the standard and the extended variants of the format are supported.
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+/// The signature of the standard `.DSK` format.
+pub const DSK_SIGNATURE: &[u8] = b"MV - CPC";
+/// The signature of the extended `.DSK` format.
+pub const EXTENDED_DSK_SIGNATURE: &[u8] = b"EXTENDED CPC DSK File\r\nDisk-Info\r\n";
+/// The signature of each track information block.
+pub const TRACK_SIGNATURE: &[u8] = b"Track-Info\r\n";
+
+const INFO_BLOCK_SIZE: usize = 0x100;
+const MAX_SECTORS: usize = (INFO_BLOCK_SIZE - 0x18) / 8;
+const CREATOR: &[u8;14] = b"SPECTRUSTY    ";
+
+/// A single disk sector.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct DskSector {
+    /// The cylinder number (`C`) of the sector ID.
+    pub cylinder: u8,
+    /// The head number (`H`) of the sector ID.
+    pub head: u8,
+    /// The sector number (`R`) of the sector ID.
+    pub id: u8,
+    /// The sector size code (`N`) of the sector ID. The nominal size in bytes is `128 << N`.
+    pub size: u8,
+    /// The FDC status register 1 flags stored with the sector.
+    pub st1: u8,
+    /// The FDC status register 2 flags stored with the sector.
+    pub st2: u8,
+    /// The sector data.
+    pub data: Vec<u8>
+}
+
+/// A single disk track.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct DskTrack {
+    /// The physical track number.
+    pub track: u8,
+    /// The physical side number.
+    pub side: u8,
+    /// The gap #3 length used for formatting.
+    pub gap: u8,
+    /// The filler byte used for formatting.
+    pub filler: u8,
+    /// The sectors of the track in the physical order.
+    pub sectors: Vec<DskSector>
+}
+
+/// A floppy disk image.
+///
+/// The tracks are stored in the order of the physical track number and then the side.
+/// An unformatted track has no sectors.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct DskImage {
+    /// The number of sides of the disk: `1` or `2`.
+    pub sides: u8,
+    /// All the tracks of the disk.
+    pub tracks: Vec<DskTrack>
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl DskSector {
+    /// Returns the nominal size of the sector data in bytes as determined by the size code.
+    pub fn nominal_size(&self) -> usize {
+        128 << self.size.min(8)
+    }
+}
+
+impl DskTrack {
+    /// Returns a reference to the first sector with the matching ID.
+    pub fn find_sector(&self, cylinder: u8, head: u8, id: u8, size: u8) -> Option<&DskSector> {
+        self.sectors.iter().find(|s| s.cylinder == cylinder && s.head == head && s.id == id && s.size == size)
+    }
+    /// Returns a mutable reference to the first sector with the matching ID.
+    pub fn find_sector_mut(&mut self, cylinder: u8, head: u8, id: u8, size: u8) -> Option<&mut DskSector> {
+        self.sectors.iter_mut().find(|s| s.cylinder == cylinder && s.head == head && s.id == id && s.size == size)
+    }
+}
+
+impl DskImage {
+    /// Returns the number of tracks per side.
+    pub fn num_tracks(&self) -> usize {
+        self.tracks.len() / self.sides.max(1) as usize
+    }
+    /// Returns a reference to the track at the given physical `track` number and `side`.
+    pub fn track(&self, track: u8, side: u8) -> Option<&DskTrack> {
+        if side >= self.sides {
+            return None
+        }
+        self.tracks.get(track as usize * self.sides as usize + side as usize)
+    }
+    /// Returns a mutable reference to the track at the given physical `track` number and `side`.
+    pub fn track_mut(&mut self, track: u8, side: u8) -> Option<&mut DskTrack> {
+        if side >= self.sides {
+            return None
+        }
+        self.tracks.get_mut(track as usize * self.sides as usize + side as usize)
+    }
+    /// Reads a disk image from the `.DSK` file.
+    ///
+    /// Both the standard and the extended formats are recognized.
+    pub fn read_dsk<R: Read>(mut rd: R) -> io::Result<Self> {
+        let mut header = [0u8; INFO_BLOCK_SIZE];
+        rd.read_exact(&mut header)?;
+        let extended = if header.starts_with(EXTENDED_DSK_SIGNATURE) {
+            true
+        }
+        else if header.starts_with(DSK_SIGNATURE) {
+            false
+        }
+        else {
+            return Err(invalid_data("not a DSK file"))
+        };
+        let num_tracks = header[0x30] as usize;
+        let sides = header[0x31];
+        if !(1..=2).contains(&sides) {
+            return Err(invalid_data("invalid number of sides"))
+        }
+        let std_track_size = u16::from_le_bytes([header[0x32], header[0x33]]) as usize;
+        let num_blocks = num_tracks * sides as usize;
+        if extended && num_blocks > INFO_BLOCK_SIZE - 0x34 {
+            return Err(invalid_data("too many tracks"))
+        }
+        let mut tracks = Vec::with_capacity(num_blocks);
+        for index in 0..num_blocks {
+            let track_size = if extended {
+                (header[0x34 + index] as usize) << 8
+            }
+            else {
+                std_track_size
+            };
+            let track_no = (index / sides as usize) as u8;
+            let side = (index % sides as usize) as u8;
+            if track_size == 0 {
+                tracks.push(DskTrack { track: track_no, side, ..DskTrack::default() });
+                continue
+            }
+            if track_size < INFO_BLOCK_SIZE {
+                return Err(invalid_data("invalid track size"))
+            }
+            let mut block = vec![0u8; track_size];
+            rd.read_exact(&mut block)?;
+            tracks.push(Self::parse_track(&block, extended)?);
+        }
+        Ok(DskImage { sides, tracks })
+    }
+
+    fn parse_track(block: &[u8], extended: bool) -> io::Result<DskTrack> {
+        if !block.starts_with(TRACK_SIGNATURE) {
+            return Err(invalid_data("missing track information block"))
+        }
+        let track = block[0x10];
+        let side = block[0x11];
+        let sector_size = block[0x14];
+        let num_sectors = block[0x15] as usize;
+        let gap = block[0x16];
+        let filler = block[0x17];
+        if num_sectors > MAX_SECTORS {
+            return Err(invalid_data("too many sectors"))
+        }
+        let mut offset = INFO_BLOCK_SIZE;
+        let mut sectors = Vec::with_capacity(num_sectors);
+        for info in block[0x18..0x18 + num_sectors * 8].chunks_exact(8) {
+            let len = if extended {
+                u16::from_le_bytes([info[6], info[7]]) as usize
+            }
+            else {
+                128 << sector_size.min(6)
+            };
+            let data = block.get(offset..offset + len)
+                            .ok_or_else(|| invalid_data("sector data exceeds the track size"))?
+                            .to_vec();
+            offset += len;
+            sectors.push(DskSector {
+                cylinder: info[0], head: info[1], id: info[2], size: info[3],
+                st1: info[4], st2: info[5], data
+            });
+        }
+        Ok(DskTrack { track, side, gap, filler, sectors })
+    }
+
+    /// Writes the disk image to the `.DSK` file in the extended format.
+    pub fn write_dsk<W: Write>(&self, mut wr: W) -> io::Result<()> {
+        let sides = self.sides.max(1) as usize;
+        let num_tracks = (self.tracks.len() + sides - 1) / sides;
+        if self.tracks.len() > INFO_BLOCK_SIZE - 0x34 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many tracks"))
+        }
+        let mut header = [0u8; INFO_BLOCK_SIZE];
+        header[..EXTENDED_DSK_SIGNATURE.len()].copy_from_slice(EXTENDED_DSK_SIGNATURE);
+        header[0x22..0x30].copy_from_slice(CREATOR);
+        header[0x30] = num_tracks as u8;
+        header[0x31] = sides as u8;
+        let mut blocks = Vec::with_capacity(self.tracks.len());
+        for (track, size_hi) in self.tracks.iter().zip(header[0x34..].iter_mut()) {
+            if track.sectors.is_empty() {
+                blocks.push(Vec::new());
+                continue
+            }
+            if track.sectors.len() > MAX_SECTORS {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many sectors"))
+            }
+            let mut block = vec![0u8; INFO_BLOCK_SIZE];
+            block[..TRACK_SIGNATURE.len()].copy_from_slice(TRACK_SIGNATURE);
+            block[0x10] = track.track;
+            block[0x11] = track.side;
+            block[0x14] = track.sectors[0].size;
+            block[0x15] = track.sectors.len() as u8;
+            block[0x16] = track.gap;
+            block[0x17] = track.filler;
+            for (sector, info) in track.sectors.iter().zip(block[0x18..].chunks_exact_mut(8)) {
+                let len = sector.data.len() as u16;
+                info.copy_from_slice(&[sector.cylinder, sector.head, sector.id, sector.size,
+                                       sector.st1, sector.st2, len as u8, (len >> 8) as u8]);
+            }
+            for sector in track.sectors.iter() {
+                block.extend_from_slice(&sector.data);
+            }
+            let size = (block.len() + 0xFF) & !0xFF;
+            if size > 0xFF00 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "the track is too large"))
+            }
+            block.resize(size, 0);
+            *size_hi = (size >> 8) as u8;
+            blocks.push(block);
+        }
+        wr.write_all(&header)?;
+        for block in blocks.iter() {
+            wr.write_all(block)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standard_dsk() -> Vec<u8> {
+        let mut dsk = vec![0u8; INFO_BLOCK_SIZE];
+        dsk[..DSK_SIGNATURE.len()].copy_from_slice(DSK_SIGNATURE);
+        dsk[0x30] = 2;
+        dsk[0x31] = 1;
+        dsk[0x33] = 0x05;
+        for track in 0..2u8 {
+            let mut block = vec![0u8; 0x500];
+            block[..TRACK_SIGNATURE.len()].copy_from_slice(TRACK_SIGNATURE);
+            block[0x10] = track;
+            block[0x14] = 2;
+            block[0x15] = 2;
+            block[0x16] = 0x2A;
+            block[0x17] = 0xE5;
+            for (i, info) in block[0x18..0x28].chunks_exact_mut(8).enumerate() {
+                info[..4].copy_from_slice(&[track, 0, i as u8 + 1, 2]);
+            }
+            for (i, data) in block[0x100..].chunks_exact_mut(0x200).enumerate() {
+                data.iter_mut().for_each(|p| *p = track * 2 + i as u8);
+            }
+            dsk.extend_from_slice(&block);
+        }
+        dsk
+    }
+
+    #[test]
+    fn read_write_dsk_works() {
+        let dsk = DskImage::read_dsk(&standard_dsk()[..]).unwrap();
+        assert_eq!(dsk.sides, 1);
+        assert_eq!(dsk.num_tracks(), 2);
+        let track = dsk.track(1, 0).unwrap();
+        assert_eq!(track.sectors.len(), 2);
+        assert_eq!(track.gap, 0x2A);
+        let sector = track.find_sector(1, 0, 2, 2).unwrap();
+        assert_eq!(sector.nominal_size(), 512);
+        assert!(sector.data.iter().all(|&b| b == 3));
+        assert!(track.find_sector(0, 0, 2, 2).is_none());
+        assert!(dsk.track(0, 1).is_none());
+        assert!(dsk.track(2, 0).is_none());
+
+        let mut extended = Vec::new();
+        dsk.write_dsk(&mut extended).unwrap();
+        assert!(extended.starts_with(EXTENDED_DSK_SIGNATURE));
+        assert_eq!(&extended[0x34..0x37], &[5, 5, 0]);
+        assert_eq!(DskImage::read_dsk(&extended[..]).unwrap(), dsk);
+
+        assert!(DskImage::read_dsk(&[0u8; INFO_BLOCK_SIZE][..]).is_err());
+        assert!(DskImage::read_dsk(&standard_dsk()[..0x400]).is_err());
+    }
+}
@@ -0,0 +1,538 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! An emulator of an **IDE/ATA** hard disk drive backed by a host disk image.
+//!
+//! Both raw disk images (`.img`) and **RS-IDE** hard disk files (`.hdf`) are supported.
+use std::io::{self, Read, Write, Seek, SeekFrom};
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+/// The size of a single disk sector in bytes.
+pub const ATA_SECTOR_SIZE: usize = 512;
+/// The signature found at the beginning of **RS-IDE** hard disk files (`.hdf`).
+pub const HDF_SIGNATURE: &[u8;7] = b"RS-IDE\x1A";
+/// The number of heads used for translating CHS addresses.
+pub const ATA_CHS_HEADS: u32 = 16;
+/// The number of sectors per track used for translating CHS addresses.
+pub const ATA_CHS_SECTORS: u32 = 63;
+
+const HDF_HEADER_MIN_SIZE: usize = 11;
+const HDF_DATA_OFFSET_POS: usize = 9;
+const ATA_MAX_CYLINDERS: u32 = 16383;
+const IDENTIFY_MODEL: &[u8] = b"SPECTRUSTY IDE DISK";
+const IDENTIFY_SERIAL: &[u8] = b"SPECTRUSTY";
+const IDENTIFY_FIRMWARE: &[u8] = b"1.0";
+
+/// The ATA task file registers as selected by the `A0-A2` address lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AtaRegister {
+    /// Data transfers.
+    Data,
+    /// Error on read, features on write.
+    ErrorFeatures,
+    /// The number of sectors to be transferred.
+    SectorCount,
+    /// The sector number or LBA bits 0-7.
+    SectorNumber,
+    /// The cylinder low byte or LBA bits 8-15.
+    CylinderLow,
+    /// The cylinder high byte or LBA bits 16-23.
+    CylinderHigh,
+    /// The device and head selection or LBA bits 24-27.
+    DeviceHead,
+    /// Status on read, command on write.
+    StatusCommand
+}
+
+bitflags! {
+    /// The ATA status register flags.
+    #[derive(Default)]
+    #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+    pub struct AtaStatus: u8 {
+        const BSY  = 0b1000_0000;
+        const DRDY = 0b0100_0000;
+        const DF   = 0b0010_0000;
+        const DSC  = 0b0001_0000;
+        const DRQ  = 0b0000_1000;
+        const CORR = 0b0000_0100;
+        const IDX  = 0b0000_0010;
+        const ERR  = 0b0000_0001;
+        const READY = Self::DRDY.bits() | Self::DSC.bits();
+    }
+}
+
+bitflags! {
+    /// The ATA error register flags.
+    #[derive(Default)]
+    #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+    pub struct AtaError: u8 {
+        const UNC  = 0b0100_0000;
+        const IDNF = 0b0001_0000;
+        const ABRT = 0b0000_0100;
+    }
+}
+
+/// Emulates a single (master) IDE/ATA hard disk drive.
+///
+/// Implements `READ SECTORS`, `WRITE SECTORS`, `IDENTIFY DEVICE` commands and acknowledges commands
+/// not requiring data transfers. Other commands are aborted. Commands are completed instantly.
+///
+/// Both LBA and CHS addressing modes are supported. In the CHS mode the geometry of
+/// [ATA_CHS_HEADS] heads and [ATA_CHS_SECTORS] sectors per track is assumed.
+///
+/// The drive ignores all accesses while the slave device is selected.
+///
+/// `F` is the type of the attached disk image, usually a [std::fs::File].
+#[derive(Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(bound = "", rename_all = "camelCase"))]
+pub struct AtaDrive<F> {
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    image: Option<F>,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    data_offset: u64,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    total_sectors: u32,
+    status: AtaStatus,
+    error: AtaError,
+    features: u8,
+    sector_count: u8,
+    sector_number: u8,
+    cylinder_low: u8,
+    cylinder_high: u8,
+    device_head: u8,
+    buffer: Vec<u8>,
+    buffer_index: usize,
+    sectors_left: u32,
+    writing: bool
+}
+
+impl<F> Default for AtaDrive<F> {
+    fn default() -> Self {
+        AtaDrive {
+            image: None,
+            data_offset: 0,
+            total_sectors: 0,
+            status: AtaStatus::empty(),
+            error: AtaError::empty(),
+            features: 0,
+            sector_count: 1,
+            sector_number: 1,
+            cylinder_low: 0,
+            cylinder_high: 0,
+            device_head: 0,
+            buffer: vec![0; ATA_SECTOR_SIZE],
+            buffer_index: ATA_SECTOR_SIZE,
+            sectors_left: 0,
+            writing: false
+        }
+    }
+}
+
+impl AtaRegister {
+    /// Returns the register selected by the 3 lowest bits of `index`.
+    pub fn from_index(index: u8) -> Self {
+        use AtaRegister::*;
+        match index & 7 {
+            0 => Data,
+            1 => ErrorFeatures,
+            2 => SectorCount,
+            3 => SectorNumber,
+            4 => CylinderLow,
+            5 => CylinderHigh,
+            6 => DeviceHead,
+            _ => StatusCommand
+        }
+    }
+}
+
+impl<F> AtaDrive<F> {
+    /// Detaches and returns the disk image, if any.
+    pub fn detach_image(&mut self) -> Option<F> {
+        self.total_sectors = 0;
+        self.data_offset = 0;
+        self.reset();
+        self.image.take()
+    }
+    /// Returns `true` if a disk image is attached.
+    pub fn has_image(&self) -> bool {
+        self.image.is_some()
+    }
+    /// Returns a reference to the attached disk image.
+    pub fn image_ref(&self) -> Option<&F> {
+        self.image.as_ref()
+    }
+    /// Returns the total number of sectors of the attached disk image.
+    pub fn total_sectors(&self) -> u32 {
+        self.total_sectors
+    }
+    /// Returns the current status of the drive.
+    pub fn status(&self) -> AtaStatus {
+        self.status
+    }
+    /// Returns the number of cylinders reported to the host for the attached disk image.
+    pub fn cylinders(&self) -> u32 {
+        (self.total_sectors / (ATA_CHS_HEADS * ATA_CHS_SECTORS)).min(ATA_MAX_CYLINDERS)
+    }
+    /// Performs a hardware reset of the drive.
+    pub fn reset(&mut self) {
+        let image = self.image.take();
+        let data_offset = self.data_offset;
+        let total_sectors = self.total_sectors;
+        let buffer = core::mem::take(&mut self.buffer);
+        *self = AtaDrive { image, data_offset, total_sectors, buffer, ..AtaDrive::default() };
+        if self.image.is_some() {
+            self.status = AtaStatus::READY;
+        }
+    }
+    #[inline]
+    fn is_slave_selected(&self) -> bool {
+        self.device_head & 0x10 != 0
+    }
+
+    fn lba(&self) -> u32 {
+        if self.device_head & 0x40 != 0 {
+            (self.device_head as u32 & 0x0F) << 24 |
+            (self.cylinder_high as u32) << 16 |
+            (self.cylinder_low as u32) << 8 |
+            self.sector_number as u32
+        }
+        else {
+            let cylinder = (self.cylinder_high as u32) << 8 | self.cylinder_low as u32;
+            let head = self.device_head as u32 & 0x0F;
+            (cylinder * ATA_CHS_HEADS + head) * ATA_CHS_SECTORS + (self.sector_number as u32).wrapping_sub(1)
+        }
+    }
+
+    fn set_lba(&mut self, lba: u32) {
+        if self.device_head & 0x40 != 0 {
+            self.sector_number = lba as u8;
+            self.cylinder_low = (lba >> 8) as u8;
+            self.cylinder_high = (lba >> 16) as u8;
+            self.device_head = self.device_head & 0xF0 | (lba >> 24) as u8 & 0x0F;
+        }
+        else {
+            let sector = lba % ATA_CHS_SECTORS + 1;
+            let head = lba / ATA_CHS_SECTORS % ATA_CHS_HEADS;
+            let cylinder = lba / (ATA_CHS_SECTORS * ATA_CHS_HEADS);
+            self.sector_number = sector as u8;
+            self.cylinder_low = cylinder as u8;
+            self.cylinder_high = (cylinder >> 8) as u8;
+            self.device_head = self.device_head & 0xF0 | head as u8;
+        }
+    }
+
+    fn abort(&mut self, error: AtaError) {
+        self.sectors_left = 0;
+        self.buffer_index = ATA_SECTOR_SIZE;
+        self.error = error;
+        self.status = AtaStatus::READY | AtaStatus::ERR;
+    }
+
+    fn complete(&mut self) {
+        self.sectors_left = 0;
+        self.buffer_index = ATA_SECTOR_SIZE;
+        self.error = AtaError::empty();
+        self.status = AtaStatus::READY;
+    }
+
+    fn identify(&mut self) {
+        fn put_str(buf: &mut [u8], s: &[u8]) {
+            for (i, p) in buf.iter_mut().enumerate() {
+                // ATA strings have bytes swapped in each word
+                *p = s.get(i ^ 1).copied().unwrap_or(b' ');
+            }
+        }
+        let cylinders = self.cylinders() as u16;
+        let total_sectors = self.total_sectors;
+        let buf = &mut self.buffer[..];
+        buf.iter_mut().for_each(|p| *p = 0);
+        let mut put_word = |index: usize, word: u16| {
+            buf[index*2..index*2 + 2].copy_from_slice(&word.to_le_bytes());
+        };
+        put_word(0, 0x0040);
+        put_word(1, cylinders);
+        put_word(3, ATA_CHS_HEADS as u16);
+        put_word(6, ATA_CHS_SECTORS as u16);
+        put_word(49, 0x0200);
+        put_word(53, 0x0001);
+        put_word(54, cylinders);
+        put_word(55, ATA_CHS_HEADS as u16);
+        put_word(56, ATA_CHS_SECTORS as u16);
+        put_word(60, total_sectors as u16);
+        put_word(61, (total_sectors >> 16) as u16);
+        put_str(&mut buf[20..40], IDENTIFY_SERIAL);
+        put_str(&mut buf[46..54], IDENTIFY_FIRMWARE);
+        put_str(&mut buf[54..94], IDENTIFY_MODEL);
+        self.buffer_index = 0;
+        self.sectors_left = 0;
+        self.writing = false;
+        self.error = AtaError::empty();
+        self.status = AtaStatus::READY | AtaStatus::DRQ;
+    }
+}
+
+impl<F: Read + Write + Seek> AtaDrive<F> {
+    /// Attaches the disk `image` to the drive, returning the previously attached one, if any.
+    ///
+    /// If the image begins with the [HDF_SIGNATURE] it's treated as an **RS-IDE** file and the sector
+    /// data is read after the header. Otherwise the image is treated as a raw sequence of sectors.
+    ///
+    /// # Note
+    /// Sectors of an **RS-IDE** file are expected to hold the full 512 bytes of data.
+    ///
+    /// # Errors
+    /// Returns an error if the image can't be read or it's not large enough to hold at least one sector.
+    pub fn attach_image(&mut self, mut image: F) -> io::Result<Option<F>> {
+        let len = image.seek(SeekFrom::End(0))?;
+        image.seek(SeekFrom::Start(0))?;
+        let mut header = [0u8; HDF_HEADER_MIN_SIZE];
+        let data_offset = if len >= HDF_HEADER_MIN_SIZE as u64 {
+            image.read_exact(&mut header)?;
+            if &header[..HDF_SIGNATURE.len()] == HDF_SIGNATURE {
+                let pos = HDF_DATA_OFFSET_POS;
+                u16::from_le_bytes([header[pos], header[pos + 1]]) as u64
+            }
+            else {
+                0
+            }
+        }
+        else {
+            0
+        };
+        let total_sectors = (len.saturating_sub(data_offset) / ATA_SECTOR_SIZE as u64)
+                            .min(0x0FFF_FFFF) as u32;
+        if total_sectors == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the disk image is too small"))
+        }
+        let prev = self.image.replace(image);
+        self.data_offset = data_offset;
+        self.total_sectors = total_sectors;
+        self.reset();
+        Ok(prev)
+    }
+    /// Reads the content of the task file `reg`ister.
+    pub fn read_register(&mut self, reg: AtaRegister) -> u8 {
+        if self.image.is_none() || self.is_slave_selected() {
+            return match reg {
+                AtaRegister::DeviceHead => self.device_head,
+                _ => 0
+            }
+        }
+        match reg {
+            AtaRegister::Data => self.read_data(),
+            AtaRegister::ErrorFeatures => self.error.bits(),
+            AtaRegister::SectorCount => self.sector_count,
+            AtaRegister::SectorNumber => self.sector_number,
+            AtaRegister::CylinderLow => self.cylinder_low,
+            AtaRegister::CylinderHigh => self.cylinder_high,
+            AtaRegister::DeviceHead => self.device_head,
+            AtaRegister::StatusCommand => self.status.bits()
+        }
+    }
+    /// Writes `data` to the task file `reg`ister.
+    pub fn write_register(&mut self, reg: AtaRegister, data: u8) {
+        match reg {
+            AtaRegister::Data => self.write_data(data),
+            AtaRegister::ErrorFeatures => self.features = data,
+            AtaRegister::SectorCount => self.sector_count = data,
+            AtaRegister::SectorNumber => self.sector_number = data,
+            AtaRegister::CylinderLow => self.cylinder_low = data,
+            AtaRegister::CylinderHigh => self.cylinder_high = data,
+            AtaRegister::DeviceHead => self.device_head = data,
+            AtaRegister::StatusCommand => self.command(data)
+        }
+    }
+
+    fn command(&mut self, cmd: u8) {
+        if self.image.is_none() || self.is_slave_selected() {
+            return
+        }
+        match cmd {
+            // READ SECTORS, WRITE SECTORS (with and without retries)
+            0x20|0x21|0x30|0x31 => {
+                self.sectors_left = if self.sector_count == 0 { 256 } else { self.sector_count as u32 };
+                self.writing = cmd >= 0x30;
+                self.error = AtaError::empty();
+                if self.writing {
+                    self.buffer_index = 0;
+                    self.status = AtaStatus::READY | AtaStatus::DRQ;
+                }
+                else {
+                    self.load_sector();
+                }
+            }
+            // IDENTIFY DEVICE
+            0xEC => self.identify(),
+            // RECALIBRATE, SEEK, READ VERIFY SECTORS, INITIALIZE DEVICE PARAMETERS, SET FEATURES, ...
+            0x10..=0x1F|0x70..=0x7F|0x40|0x41|0x91|0xEF|0xE0..=0xE7 => self.complete(),
+            _ => self.abort(AtaError::ABRT)
+        }
+    }
+
+    fn read_data(&mut self) -> u8 {
+        if self.buffer_index >= ATA_SECTOR_SIZE || self.writing {
+            return !0
+        }
+        let data = self.buffer[self.buffer_index];
+        self.buffer_index += 1;
+        if self.buffer_index == ATA_SECTOR_SIZE {
+            if self.sectors_left == 0 {
+                self.complete();
+            }
+            else {
+                self.load_sector();
+            }
+        }
+        data
+    }
+
+    fn write_data(&mut self, data: u8) {
+        if self.buffer_index >= ATA_SECTOR_SIZE || !self.writing || self.is_slave_selected() {
+            return
+        }
+        self.buffer[self.buffer_index] = data;
+        self.buffer_index += 1;
+        if self.buffer_index == ATA_SECTOR_SIZE {
+            self.store_sector();
+        }
+    }
+
+    fn sector_position(&self, lba: u32) -> Option<u64> {
+        if lba < self.total_sectors {
+            Some(self.data_offset + lba as u64 * ATA_SECTOR_SIZE as u64)
+        }
+        else {
+            None
+        }
+    }
+
+    fn load_sector(&mut self) {
+        let lba = self.lba();
+        let pos = match self.sector_position(lba) {
+            Some(pos) => pos,
+            None => return self.abort(AtaError::IDNF | AtaError::ABRT)
+        };
+        let (image, buffer) = (self.image.as_mut().unwrap(), &mut self.buffer);
+        match image.seek(SeekFrom::Start(pos)).and_then(|_| image.read_exact(buffer)) {
+            Ok(()) => {
+                self.sectors_left -= 1;
+                self.buffer_index = 0;
+                self.status = AtaStatus::READY | AtaStatus::DRQ;
+                if self.sectors_left != 0 {
+                    self.set_lba(lba.wrapping_add(1));
+                }
+            }
+            Err(_) => self.abort(AtaError::UNC | AtaError::ABRT)
+        }
+    }
+
+    fn store_sector(&mut self) {
+        let lba = self.lba();
+        let pos = match self.sector_position(lba) {
+            Some(pos) => pos,
+            None => return self.abort(AtaError::IDNF | AtaError::ABRT)
+        };
+        let (image, buffer) = (self.image.as_mut().unwrap(), &self.buffer);
+        match image.seek(SeekFrom::Start(pos)).and_then(|_| image.write_all(buffer)) {
+            Ok(()) => {
+                self.sectors_left -= 1;
+                if self.sectors_left == 0 {
+                    let _ = image.flush();
+                    self.complete();
+                }
+                else {
+                    self.set_lba(lba.wrapping_add(1));
+                    self.buffer_index = 0;
+                    self.status = AtaStatus::READY | AtaStatus::DRQ;
+                }
+            }
+            Err(_) => self.abort(AtaError::ABRT)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+
+    fn read_sector(drive: &mut AtaDrive<Cursor<Vec<u8>>>) -> Vec<u8> {
+        (0..ATA_SECTOR_SIZE).map(|_| drive.read_register(AtaRegister::Data)).collect()
+    }
+
+    #[test]
+    fn ata_drive_works() {
+        let mut image = vec![0u8; 4 * ATA_SECTOR_SIZE];
+        for (i, chunk) in image.chunks_mut(ATA_SECTOR_SIZE).enumerate() {
+            chunk.iter_mut().for_each(|p| *p = i as u8 + 1);
+        }
+        let mut drive = AtaDrive::default();
+        assert_eq!(drive.read_register(AtaRegister::StatusCommand), 0);
+        assert!(drive.attach_image(Cursor::new(image)).unwrap().is_none());
+        assert_eq!(drive.total_sectors(), 4);
+        assert_eq!(drive.status(), AtaStatus::READY);
+        // LBA read of 2 sectors starting from sector 1
+        drive.write_register(AtaRegister::DeviceHead, 0xE0);
+        drive.write_register(AtaRegister::SectorNumber, 1);
+        drive.write_register(AtaRegister::SectorCount, 2);
+        drive.write_register(AtaRegister::StatusCommand, 0x20);
+        assert_eq!(drive.status(), AtaStatus::READY|AtaStatus::DRQ);
+        assert!(read_sector(&mut drive).iter().all(|&b| b == 2));
+        assert_eq!(drive.read_register(AtaRegister::SectorNumber), 2);
+        assert!(read_sector(&mut drive).iter().all(|&b| b == 3));
+        assert_eq!(drive.status(), AtaStatus::READY);
+        assert_eq!(drive.read_register(AtaRegister::Data), !0);
+        // CHS write of sector 0
+        drive.write_register(AtaRegister::DeviceHead, 0xA0);
+        drive.write_register(AtaRegister::SectorNumber, 1);
+        drive.write_register(AtaRegister::SectorCount, 1);
+        drive.write_register(AtaRegister::StatusCommand, 0x30);
+        assert_eq!(drive.status(), AtaStatus::READY|AtaStatus::DRQ);
+        for i in 0..ATA_SECTOR_SIZE {
+            drive.write_register(AtaRegister::Data, i as u8);
+        }
+        assert_eq!(drive.status(), AtaStatus::READY);
+        // out of range
+        drive.write_register(AtaRegister::SectorNumber, 5);
+        drive.write_register(AtaRegister::StatusCommand, 0x20);
+        assert_eq!(drive.status(), AtaStatus::READY|AtaStatus::ERR);
+        assert_eq!(drive.read_register(AtaRegister::ErrorFeatures), (AtaError::IDNF|AtaError::ABRT).bits());
+        // identify
+        drive.write_register(AtaRegister::StatusCommand, 0xEC);
+        let ident = read_sector(&mut drive);
+        assert_eq!(&ident[54..58], b"PSCE");
+        assert_eq!(&ident[120..124], &[4, 0, 0, 0]);
+        // unknown command
+        drive.write_register(AtaRegister::StatusCommand, 0xFF);
+        assert_eq!(drive.read_register(AtaRegister::ErrorFeatures), AtaError::ABRT.bits());
+        // the slave is not present
+        drive.write_register(AtaRegister::DeviceHead, 0xB0);
+        assert_eq!(drive.read_register(AtaRegister::StatusCommand), 0);
+        drive.write_register(AtaRegister::DeviceHead, 0xA0);
+        assert_eq!(drive.read_register(AtaRegister::StatusCommand), 0x51);
+
+        let image = drive.detach_image().unwrap().into_inner();
+        assert!(!drive.has_image());
+        assert!(image[..ATA_SECTOR_SIZE].iter().enumerate().all(|(i, &b)| b == i as u8));
+        assert!(image[ATA_SECTOR_SIZE..].iter().all(|&b| b != 0));
+
+        let mut hdf = vec![0u8; 0x80 + ATA_SECTOR_SIZE];
+        hdf[..7].copy_from_slice(HDF_SIGNATURE);
+        hdf[9] = 0x80;
+        hdf[0x80] = 42;
+        drive.attach_image(Cursor::new(hdf)).unwrap();
+        assert_eq!(drive.total_sectors(), 1);
+        drive.write_register(AtaRegister::DeviceHead, 0xE0);
+        drive.write_register(AtaRegister::SectorNumber, 0);
+        drive.write_register(AtaRegister::StatusCommand, 0x20);
+        assert_eq!(drive.read_register(AtaRegister::Data), 42);
+        assert!(drive.attach_image(Cursor::new(vec![0u8; 100])).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! **TR-DOS** floppy disk images in the `.TRD` and `.SCL` formats.
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+/// The size of a single **TR-DOS** sector in bytes.
+pub const TRD_SECTOR_SIZE: usize = 256;
+/// The number of sectors on each track.
+pub const TRD_SECTORS_PER_TRACK: usize = 16;
+/// The size of a single track in bytes.
+pub const TRD_TRACK_SIZE: usize = TRD_SECTOR_SIZE * TRD_SECTORS_PER_TRACK;
+/// The number of cylinders of the disk images created from `.SCL` files.
+pub const TRD_DEFAULT_CYLINDERS: u8 = 80;
+/// The signature of the `.SCL` files.
+pub const SCL_SIGNATURE: &[u8;8] = b"SINCLAIR";
+
+const SYSTEM_SECTOR_OFFSET: usize = 8 * TRD_SECTOR_SIZE;
+const DISK_TYPE_OFFSET: usize = SYSTEM_SECTOR_OFFSET + 0xE3;
+const DISK_TYPE_80_DS: u8 = 0x16;
+const DISK_TYPE_40_DS: u8 = 0x17;
+const DISK_TYPE_80_SS: u8 = 0x18;
+const DISK_TYPE_40_SS: u8 = 0x19;
+const TRDOS_ID: u8 = 0x10;
+const CATALOG_ENTRY_SIZE: usize = 16;
+const SCL_HEADER_SIZE: usize = 14;
+const MAX_FILES: usize = 128;
+const MAX_CYLINDERS: usize = 86;
+
+/// A **TR-DOS** disk image.
+///
+/// Each track consists of [TRD_SECTORS_PER_TRACK] sectors of [TRD_SECTOR_SIZE] bytes with IDs
+/// numbered from `1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct TrdDisk {
+    cylinders: u8,
+    sides: u8,
+    data: Vec<u8>
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl TrdDisk {
+    /// Creates a blank, formatted disk image with the given number of `cylinders` and `sides`.
+    ///
+    /// # Panics
+    /// Panics if `cylinders` or `sides` are out of range.
+    pub fn new_formatted(cylinders: u8, sides: u8) -> Self {
+        assert!((1..=MAX_CYLINDERS).contains(&(cylinders as usize)), "invalid number of cylinders");
+        assert!((1..=2).contains(&sides), "invalid number of sides");
+        let tracks = cylinders as usize * sides as usize;
+        let mut data = vec![0u8; tracks * TRD_TRACK_SIZE];
+        let free = (tracks - 1) * TRD_SECTORS_PER_TRACK;
+        let system = &mut data[SYSTEM_SECTOR_OFFSET..SYSTEM_SECTOR_OFFSET + TRD_SECTOR_SIZE];
+        system[0xE1] = 0;
+        system[0xE2] = 1;
+        system[0xE3] = match (cylinders > 40, sides) {
+            (true, 2)  => DISK_TYPE_80_DS,
+            (false, 2) => DISK_TYPE_40_DS,
+            (true, _)  => DISK_TYPE_80_SS,
+            (false, _) => DISK_TYPE_40_SS
+        };
+        system[0xE5..0xE7].copy_from_slice(&(free as u16).to_le_bytes());
+        system[0xE7] = TRDOS_ID;
+        system[0xEA..0xF3].iter_mut().for_each(|p| *p = b' ');
+        system[0xF5..0xFD].iter_mut().for_each(|p| *p = b' ');
+        TrdDisk { cylinders, sides, data }
+    }
+    /// Returns the number of cylinders.
+    pub fn cylinders(&self) -> u8 {
+        self.cylinders
+    }
+    /// Returns the number of sides.
+    pub fn sides(&self) -> u8 {
+        self.sides
+    }
+    /// Returns a reference to the raw image data.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn sector_offset(&self, cylinder: u8, side: u8, id: u8) -> Option<usize> {
+        if cylinder >= self.cylinders || side >= self.sides ||
+           !(1..=TRD_SECTORS_PER_TRACK).contains(&(id as usize)) {
+            return None
+        }
+        let track = cylinder as usize * self.sides as usize + side as usize;
+        Some((track * TRD_SECTORS_PER_TRACK + id as usize - 1) * TRD_SECTOR_SIZE)
+    }
+    /// Returns a reference to the sector data with the given `id` at the physical `cylinder` and `side`.
+    pub fn sector(&self, cylinder: u8, side: u8, id: u8) -> Option<&[u8]> {
+        self.sector_offset(cylinder, side, id).map(|offset| &self.data[offset..offset + TRD_SECTOR_SIZE])
+    }
+    /// Returns a mutable reference to the sector data with the given `id` at the physical `cylinder` and `side`.
+    pub fn sector_mut(&mut self, cylinder: u8, side: u8, id: u8) -> Option<&mut [u8]> {
+        self.sector_offset(cylinder, side, id).map(move |offset| &mut self.data[offset..offset + TRD_SECTOR_SIZE])
+    }
+    /// Reads a disk image from the `.TRD` file.
+    ///
+    /// The number of sides is determined from the disk type found in the system sector. Images shorter than
+    /// the full disk size are padded with zeroes.
+    pub fn read_trd<R: Read>(mut rd: R) -> io::Result<Self> {
+        let mut data = Vec::new();
+        rd.read_to_end(&mut data)?;
+        if data.len() <= DISK_TYPE_OFFSET {
+            return Err(invalid_data("the TRD image is too small"))
+        }
+        let sides: u8 = match data[DISK_TYPE_OFFSET] {
+            DISK_TYPE_80_SS|DISK_TYPE_40_SS => 1,
+            _ => 2
+        };
+        let track_size = TRD_TRACK_SIZE * sides as usize;
+        let cylinders = (data.len() + track_size - 1) / track_size;
+        if cylinders > MAX_CYLINDERS {
+            return Err(invalid_data("the TRD image is too large"))
+        }
+        data.resize(cylinders * track_size, 0);
+        Ok(TrdDisk { cylinders: cylinders as u8, sides, data })
+    }
+    /// Reads a disk image from the `.SCL` file.
+    ///
+    /// The files are placed on a newly formatted disk with [TRD_DEFAULT_CYLINDERS] cylinders and 2 sides.
+    pub fn read_scl<R: Read>(mut rd: R) -> io::Result<Self> {
+        let mut signature = [0u8; 9];
+        rd.read_exact(&mut signature)?;
+        if &signature[..8] != SCL_SIGNATURE {
+            return Err(invalid_data("not an SCL file"))
+        }
+        let num_files = signature[8] as usize;
+        if num_files > MAX_FILES {
+            return Err(invalid_data("too many files"))
+        }
+        let mut headers = vec![0u8; num_files * SCL_HEADER_SIZE];
+        rd.read_exact(&mut headers)?;
+        let mut disk = TrdDisk::new_formatted(TRD_DEFAULT_CYLINDERS, 2);
+        let free = u16::from_le_bytes([disk.data[SYSTEM_SECTOR_OFFSET + 0xE5],
+                                       disk.data[SYSTEM_SECTOR_OFFSET + 0xE6]]) as usize;
+        let mut position = TRD_SECTORS_PER_TRACK;
+        for (index, header) in headers.chunks_exact(SCL_HEADER_SIZE).enumerate() {
+            let sectors = header[13] as usize;
+            let start = position * TRD_SECTOR_SIZE;
+            let end = start + sectors * TRD_SECTOR_SIZE;
+            if end > disk.data.len() {
+                return Err(invalid_data("the SCL files don't fit on the disk"))
+            }
+            rd.read_exact(&mut disk.data[start..end])?;
+            let entry = &mut disk.data[index * CATALOG_ENTRY_SIZE..(index + 1) * CATALOG_ENTRY_SIZE];
+            entry[..SCL_HEADER_SIZE].copy_from_slice(header);
+            entry[14] = (position % TRD_SECTORS_PER_TRACK) as u8;
+            entry[15] = (position / TRD_SECTORS_PER_TRACK) as u8;
+            position += sectors;
+        }
+        let used = position - TRD_SECTORS_PER_TRACK;
+        let system = &mut disk.data[SYSTEM_SECTOR_OFFSET..SYSTEM_SECTOR_OFFSET + TRD_SECTOR_SIZE];
+        system[0xE1] = (position % TRD_SECTORS_PER_TRACK) as u8;
+        system[0xE2] = (position / TRD_SECTORS_PER_TRACK) as u8;
+        system[0xE4] = num_files as u8;
+        system[0xE5..0xE7].copy_from_slice(&(free.saturating_sub(used) as u16).to_le_bytes());
+        Ok(disk)
+    }
+    /// Writes the disk image to the `.TRD` file.
+    pub fn write_trd<W: Write>(&self, mut wr: W) -> io::Result<()> {
+        wr.write_all(&self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trd_disk_works() {
+        let mut scl = Vec::new();
+        scl.extend_from_slice(SCL_SIGNATURE);
+        scl.push(2);
+        scl.extend_from_slice(b"boot    B\x10\x00\x10\x00\x01");
+        scl.extend_from_slice(b"game    C\x00\x80\x00\x02\x02");
+        scl.extend_from_slice(&[1; TRD_SECTOR_SIZE]);
+        scl.extend_from_slice(&[2; 2 * TRD_SECTOR_SIZE]);
+        scl.extend_from_slice(&[0, 0, 0, 0]);
+        let disk = TrdDisk::read_scl(&scl[..]).unwrap();
+        assert_eq!(disk.cylinders(), 80);
+        assert_eq!(disk.sides(), 2);
+        let catalog = disk.sector(0, 0, 1).unwrap();
+        assert_eq!(&catalog[..16], b"boot    B\x10\x00\x10\x00\x01\x00\x01");
+        assert_eq!(&catalog[16..32], b"game    C\x00\x80\x00\x02\x02\x01\x01");
+        let system = disk.sector(0, 0, 9).unwrap();
+        assert_eq!(&system[0xE1..0xE8], &[3, 1, 0x16, 2, 0xED, 0x09, 0x10]);
+        assert!(disk.sector(0, 1, 1).unwrap().iter().all(|&b| b == 1));
+        assert!(disk.sector(0, 1, 3).unwrap().iter().all(|&b| b == 2));
+        assert!(disk.sector(0, 1, 4).unwrap().iter().all(|&b| b == 0));
+        assert!(disk.sector(0, 0, 0).is_none());
+        assert!(disk.sector(0, 0, 17).is_none());
+        assert!(disk.sector(80, 0, 1).is_none());
+        assert!(disk.sector(0, 2, 1).is_none());
+
+        let mut trd = Vec::new();
+        disk.write_trd(&mut trd).unwrap();
+        assert_eq!(trd.len(), 655360);
+        assert_eq!(TrdDisk::read_trd(&trd[..0x1000]).unwrap().cylinders(), 1);
+        assert_eq!(TrdDisk::read_trd(&trd[..]).unwrap(), disk);
+        let mut disk = TrdDisk::new_formatted(40, 1);
+        disk.sector_mut(39, 0, 16).unwrap()[255] = 42;
+        let mut trd = Vec::new();
+        disk.write_trd(&mut trd).unwrap();
+        assert_eq!(trd.len(), 40 * TRD_TRACK_SIZE);
+        let disk = TrdDisk::read_trd(&trd[..]).unwrap();
+        assert_eq!(disk.sides(), 1);
+        assert_eq!(disk.sector(39, 0, 16).unwrap()[255], 42);
+        assert!(TrdDisk::read_scl(&trd[..]).is_err());
+    }
+}
@@ -0,0 +1,612 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! An emulator of the **uPD765** floppy disk controller.
+use std::io::{self, Read};
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use super::dsk::{DskImage, DskSector, DskTrack};
+
+/// The number of floppy disk drives that can be connected to the controller.
+pub const FDC_MAX_DRIVES: usize = 2;
+
+bitflags! {
+    /// The main status register flags.
+    #[derive(Default)]
+    pub struct FdcMainStatus: u8 {
+        /// Request for master: the data register is ready.
+        const RQM = 0b1000_0000;
+        /// Data input/output: set if the data should be read by the CPU.
+        const DIO = 0b0100_0000;
+        /// The execution phase of a non-DMA transfer.
+        const EXM = 0b0010_0000;
+        /// The controller is busy with a command.
+        const CB  = 0b0001_0000;
+        /// The drives in the seek mode.
+        const DB_MASK = 0b0000_1111;
+    }
+}
+
+const ST0_ABNORMAL: u8 = 0x40;
+const ST0_INVALID: u8  = 0x80;
+const ST0_SEEK_END: u8 = 0x20;
+const ST0_NOT_READY: u8 = 0x08;
+const ST1_END_OF_CYLINDER: u8 = 0x80;
+const ST1_DATA_ERROR: u8 = 0x20;
+const ST1_NO_DATA: u8 = 0x04;
+const ST1_NOT_WRITABLE: u8 = 0x02;
+const ST1_MISSING_AM: u8 = 0x01;
+const ST2_CONTROL_MARK: u8 = 0x40;
+const ST2_DATA_ERROR: u8 = 0x20;
+const ST2_MISSING_DAM: u8 = 0x01;
+const ST3_WRITE_PROTECT: u8 = 0x40;
+const ST3_READY: u8 = 0x20;
+const ST3_TRACK0: u8 = 0x10;
+const ST3_TWO_SIDE: u8 = 0x08;
+
+const CMD_READ_TRACK: u8 = 0x02;
+const CMD_SPECIFY: u8 = 0x03;
+const CMD_SENSE_DRIVE: u8 = 0x04;
+const CMD_WRITE_DATA: u8 = 0x05;
+const CMD_READ_DATA: u8 = 0x06;
+const CMD_RECALIBRATE: u8 = 0x07;
+const CMD_SENSE_INTERRUPT: u8 = 0x08;
+const CMD_WRITE_DELETED: u8 = 0x09;
+const CMD_READ_ID: u8 = 0x0A;
+const CMD_READ_DELETED: u8 = 0x0C;
+const CMD_FORMAT_TRACK: u8 = 0x0D;
+const CMD_SEEK: u8 = 0x0F;
+const CMD_SCAN_EQUAL: u8 = 0x11;
+const CMD_SCAN_LOW_OR_EQUAL: u8 = 0x19;
+const CMD_SCAN_HIGH_OR_EQUAL: u8 = 0x1D;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+enum FdcPhase {
+    Command,
+    ExecutionRead,
+    ExecutionWrite,
+    Result
+}
+
+/// A floppy disk drive connected to the [Upd765] controller.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
+pub struct FdcDrive {
+    /// The inserted disk, if any.
+    pub disk: Option<DskImage>,
+    /// Set to `true` to prevent writing to the inserted disk.
+    pub write_protect: bool,
+    cylinder: u8,
+    next_sector: usize
+}
+
+/// Emulates the **uPD765** floppy disk controller as found in the ZX Spectrum +3/+2A.
+///
+/// The following commands are implemented: `READ DATA`, `WRITE DATA`, `READ ID`, `FORMAT TRACK`,
+/// `SEEK`, `RECALIBRATE`, `SENSE INTERRUPT STATUS`, `SENSE DRIVE STATUS` and `SPECIFY`.
+/// Other valid commands terminate abnormally with the `No Data` flag.
+///
+/// The timing of the controller is simplified: seeks and transfers are completed instantly.
+/// The multi-track mode is not supported.
+///
+/// As the terminal count signal is not connected on the +3, the multi-sector transfers always end with
+/// the `End of Cylinder` flag after the last sector has been transferred.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
+pub struct Upd765 {
+    drives: [FdcDrive; FDC_MAX_DRIVES],
+    motor: bool,
+    phase: FdcPhase,
+    command: Vec<u8>,
+    buffer: Vec<u8>,
+    index: usize,
+    seek_int: u8,
+    st1: u8,
+    st2: u8
+}
+
+impl Default for Upd765 {
+    fn default() -> Self {
+        Upd765 {
+            drives: Default::default(),
+            motor: false,
+            phase: FdcPhase::Command,
+            command: Vec::with_capacity(9),
+            buffer: Vec::new(),
+            index: 0,
+            seek_int: 0,
+            st1: 0,
+            st2: 0
+        }
+    }
+}
+
+fn command_length(cmd: u8) -> Option<usize> {
+    Some(match cmd & 0x1F {
+        CMD_READ_TRACK|CMD_WRITE_DATA|CMD_READ_DATA|CMD_WRITE_DELETED|CMD_READ_DELETED|
+        CMD_SCAN_EQUAL|CMD_SCAN_LOW_OR_EQUAL|CMD_SCAN_HIGH_OR_EQUAL => 9,
+        CMD_SPECIFY|CMD_SEEK => 3,
+        CMD_SENSE_DRIVE|CMD_RECALIBRATE|CMD_READ_ID => 2,
+        CMD_SENSE_INTERRUPT => 1,
+        CMD_FORMAT_TRACK => 6,
+        _ => return None
+    })
+}
+
+impl Upd765 {
+    /// Reads a `.DSK` disk image and inserts it into the `drive`.
+    ///
+    /// Returns the previously inserted disk, if any.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [FDC_MAX_DRIVES].
+    pub fn attach_dsk<R: Read>(&mut self, drive: usize, rd: R) -> io::Result<Option<DskImage>> {
+        let disk = DskImage::read_dsk(rd)?;
+        Ok(self.insert_disk(drive, disk))
+    }
+    /// Inserts the `disk` into the `drive`, returning the previously inserted disk, if any.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [FDC_MAX_DRIVES].
+    pub fn insert_disk(&mut self, drive: usize, disk: DskImage) -> Option<DskImage> {
+        let drive = &mut self.drives[drive];
+        drive.next_sector = 0;
+        drive.disk.replace(disk)
+    }
+    /// Ejects and returns the disk from the `drive`, if any.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [FDC_MAX_DRIVES].
+    pub fn eject_disk(&mut self, drive: usize) -> Option<DskImage> {
+        self.drives[drive].disk.take()
+    }
+    /// Returns a reference to the `drive`.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [FDC_MAX_DRIVES].
+    pub fn drive(&self, drive: usize) -> &FdcDrive {
+        &self.drives[drive]
+    }
+    /// Returns a mutable reference to the `drive`.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [FDC_MAX_DRIVES].
+    pub fn drive_mut(&mut self, drive: usize) -> &mut FdcDrive {
+        &mut self.drives[drive]
+    }
+    /// Returns `true` if the drive motors are turned on.
+    pub fn is_motor_on(&self) -> bool {
+        self.motor
+    }
+    /// Turns the drive motors on or off.
+    pub fn set_motor(&mut self, motor: bool) {
+        self.motor = motor;
+    }
+    /// Resets the controller. Inserted disks are left intact.
+    pub fn reset(&mut self) {
+        let drives = core::mem::take(&mut self.drives);
+        *self = Upd765 { drives, ..Upd765::default() };
+    }
+    /// Returns the content of the main status register.
+    pub fn read_status(&self) -> u8 {
+        let seeking = FdcMainStatus::from_bits_truncate(self.seek_int) & FdcMainStatus::DB_MASK;
+        let status = match self.phase {
+            FdcPhase::Command if self.command.is_empty() => FdcMainStatus::RQM,
+            FdcPhase::Command => FdcMainStatus::RQM|FdcMainStatus::CB,
+            FdcPhase::ExecutionRead => FdcMainStatus::RQM|FdcMainStatus::DIO|FdcMainStatus::EXM|FdcMainStatus::CB,
+            FdcPhase::ExecutionWrite => FdcMainStatus::RQM|FdcMainStatus::EXM|FdcMainStatus::CB,
+            FdcPhase::Result => FdcMainStatus::RQM|FdcMainStatus::DIO|FdcMainStatus::CB
+        };
+        (status | seeking).bits()
+    }
+    /// Reads a byte from the data register.
+    pub fn read_data(&mut self) -> u8 {
+        match self.phase {
+            FdcPhase::ExecutionRead => {
+                let data = self.buffer[self.index];
+                self.index += 1;
+                if self.index == self.buffer.len() {
+                    self.next_sector();
+                }
+                data
+            }
+            FdcPhase::Result => {
+                let data = self.buffer[self.index];
+                self.index += 1;
+                if self.index == self.buffer.len() {
+                    self.phase = FdcPhase::Command;
+                    self.command.clear();
+                }
+                data
+            }
+            _ => !0
+        }
+    }
+    /// Writes a byte to the data register.
+    pub fn write_data(&mut self, data: u8) {
+        match self.phase {
+            FdcPhase::Command => {
+                self.command.push(data);
+                match command_length(self.command[0]) {
+                    Some(len) if len == self.command.len() => self.execute(),
+                    Some(..) => {}
+                    None => self.set_result(&[ST0_INVALID])
+                }
+            }
+            FdcPhase::ExecutionWrite => {
+                self.buffer[self.index] = data;
+                self.index += 1;
+                if self.index == self.buffer.len() {
+                    if self.command[0] & 0x1F == CMD_FORMAT_TRACK {
+                        self.format_track();
+                    }
+                    else {
+                        self.store_sector();
+                        self.next_sector();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[inline]
+    fn head_unit(&self) -> u8 {
+        self.command.get(1).copied().unwrap_or(0)
+    }
+
+    #[inline]
+    fn unit(&self) -> usize {
+        (self.head_unit() & 1) as usize
+    }
+
+    #[inline]
+    fn head(&self) -> u8 {
+        (self.head_unit() >> 2) & 1
+    }
+
+    #[inline]
+    fn st0(&self, flags: u8) -> u8 {
+        flags | self.head_unit() & 0b111
+    }
+
+    fn set_result(&mut self, result: &[u8]) {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(result);
+        self.index = 0;
+        self.phase = FdcPhase::Result;
+    }
+
+    fn end_command(&mut self) {
+        self.command.clear();
+        self.phase = FdcPhase::Command;
+    }
+
+    fn terminate(&mut self, st0: u8, st1: u8, st2: u8) {
+        let (c, h, r, n) = (self.command[2], self.command[3], self.command[4], self.command[5]);
+        let st0 = self.st0(st0);
+        self.set_result(&[st0, st1, st2, c, h, r, n]);
+    }
+
+    fn execute(&mut self) {
+        let unit = self.unit();
+        match self.command[0] & 0x1F {
+            CMD_SPECIFY => self.end_command(),
+            CMD_SENSE_DRIVE => {
+                let drive = &self.drives[unit];
+                let mut st3 = self.st0(0);
+                if let Some(disk) = drive.disk.as_ref() {
+                    st3 |= ST3_READY;
+                    if disk.sides > 1 {
+                        st3 |= ST3_TWO_SIDE;
+                    }
+                }
+                if drive.write_protect {
+                    st3 |= ST3_WRITE_PROTECT;
+                }
+                if drive.cylinder == 0 {
+                    st3 |= ST3_TRACK0;
+                }
+                self.set_result(&[st3]);
+            }
+            CMD_RECALIBRATE|CMD_SEEK => {
+                let cylinder = if self.command[0] & 0x1F == CMD_SEEK { self.command[2] } else { 0 };
+                self.drives[unit].cylinder = cylinder;
+                self.seek_int |= 1 << (self.command[1] & 3);
+                self.end_command();
+            }
+            CMD_SENSE_INTERRUPT => {
+                if self.seek_int == 0 {
+                    self.set_result(&[ST0_INVALID]);
+                }
+                else {
+                    let us = self.seek_int.trailing_zeros() as u8;
+                    self.seek_int &= !(1 << us);
+                    let cylinder = self.drives[(us & 1) as usize].cylinder;
+                    self.set_result(&[ST0_SEEK_END | us, cylinder]);
+                }
+            }
+            CMD_READ_ID => self.read_id(),
+            CMD_READ_DATA|CMD_WRITE_DATA => {
+                self.st1 = 0;
+                self.st2 = 0;
+                self.start_transfer();
+            }
+            CMD_FORMAT_TRACK => {
+                if let Err((st0, st1)) = self.writable_disk() {
+                    return self.terminate(st0, st1, 0)
+                }
+                let len = 4 * self.command[3] as usize;
+                if len == 0 {
+                    return self.terminate(0, 0, 0)
+                }
+                self.buffer.clear();
+                self.buffer.resize(len, 0);
+                self.index = 0;
+                self.phase = FdcPhase::ExecutionWrite;
+            }
+            _ => self.terminate(ST0_ABNORMAL, ST1_NO_DATA, 0)
+        }
+    }
+
+    fn writable_disk(&self) -> Result<(), (u8, u8)> {
+        let drive = &self.drives[self.unit()];
+        if drive.disk.is_none() {
+            Err((ST0_ABNORMAL|ST0_NOT_READY, 0))
+        }
+        else if drive.write_protect {
+            Err((ST0_ABNORMAL, ST1_NOT_WRITABLE))
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    fn current_track(&self) -> Result<&DskTrack, (u8, u8)> {
+        let drive = &self.drives[self.unit()];
+        let disk = drive.disk.as_ref().ok_or((ST0_ABNORMAL|ST0_NOT_READY, 0))?;
+        disk.track(drive.cylinder, self.head())
+            .filter(|track| !track.sectors.is_empty())
+            .ok_or((ST0_ABNORMAL, ST1_MISSING_AM))
+    }
+
+    fn current_sector_mut(&mut self) -> Result<&mut DskSector, (u8, u8)> {
+        let (c, h, r, n) = (self.command[2], self.command[3], self.command[4], self.command[5]);
+        let head = self.head();
+        let drive = &mut self.drives[self.unit()];
+        let cylinder = drive.cylinder;
+        let disk = drive.disk.as_mut().ok_or((ST0_ABNORMAL|ST0_NOT_READY, 0))?;
+        let track = disk.track_mut(cylinder, head)
+                        .filter(|track| !track.sectors.is_empty())
+                        .ok_or((ST0_ABNORMAL, ST1_MISSING_AM))?;
+        track.find_sector_mut(c, h, r, n).ok_or((ST0_ABNORMAL, ST1_NO_DATA))
+    }
+
+    fn read_id(&mut self) {
+        let unit = self.unit();
+        match self.current_track() {
+            Ok(track) => {
+                let drive = &self.drives[unit];
+                let sector = &track.sectors[drive.next_sector % track.sectors.len()];
+                let result = [self.st0(0), 0, 0, sector.cylinder, sector.head, sector.id, sector.size];
+                self.drives[unit].next_sector += 1;
+                self.set_result(&result);
+            }
+            Err((st0, st1)) => {
+                let st0 = self.st0(st0);
+                let cylinder = self.drives[unit].cylinder;
+                let head = self.head();
+                self.set_result(&[st0, st1, 0, cylinder, head, 0, 0]);
+            }
+        }
+    }
+
+    fn transfer_length(&self) -> usize {
+        match self.command[5] {
+            0 => self.command[8] as usize,
+            n => 128 << n.min(8)
+        }
+    }
+
+    fn start_transfer(&mut self) {
+        let writing = self.command[0] & 0x1F == CMD_WRITE_DATA;
+        if writing {
+            if let Err((st0, st1)) = self.writable_disk() {
+                return self.terminate(st0, st1, 0)
+            }
+        }
+        let len = self.transfer_length();
+        let data = match self.current_sector_mut() {
+            Ok(sector) => {
+                let st1 = sector.st1 & (ST1_DATA_ERROR|ST1_NO_DATA|ST1_MISSING_AM);
+                let st2 = sector.st2 & (ST2_CONTROL_MARK|ST2_DATA_ERROR|ST2_MISSING_DAM);
+                (st1, st2, if writing { None } else { Some(sector.data.clone()) })
+            }
+            Err((st0, st1)) => return self.terminate(st0, st1, 0)
+        };
+        let (st1, st2, data) = data;
+        self.st1 = st1;
+        self.st2 = st2;
+        self.buffer.clear();
+        match data {
+            Some(data) => {
+                self.buffer.extend(data.into_iter().chain(core::iter::repeat(0)).take(len));
+                self.phase = FdcPhase::ExecutionRead;
+            }
+            None => {
+                self.buffer.resize(len, 0);
+                self.phase = FdcPhase::ExecutionWrite;
+            }
+        }
+        self.index = 0;
+        if len == 0 {
+            self.next_sector();
+        }
+    }
+
+    fn store_sector(&mut self) {
+        let buffer = core::mem::take(&mut self.buffer);
+        if let Ok(sector) = self.current_sector_mut() {
+            let len = sector.data.len().min(buffer.len());
+            sector.data[..len].copy_from_slice(&buffer[..len]);
+        }
+        self.buffer = buffer;
+    }
+
+    fn next_sector(&mut self) {
+        if self.st1 != 0 || self.st2 != 0 {
+            return self.terminate(ST0_ABNORMAL, self.st1, self.st2)
+        }
+        if self.command[4] == self.command[6] {
+            self.command[2] = self.command[2].wrapping_add(1);
+            self.command[4] = 1;
+            return self.terminate(ST0_ABNORMAL, ST1_END_OF_CYLINDER, 0)
+        }
+        self.command[4] = self.command[4].wrapping_add(1);
+        self.start_transfer();
+    }
+
+    fn format_track(&mut self) {
+        let (size, gap, filler) = (self.command[2], self.command[4], self.command[5]);
+        let head = self.head();
+        let unit = self.unit();
+        let sides = match self.drives[unit].disk.as_ref() {
+            Some(disk) => disk.sides.max(1),
+            // the disk has been ejected while receiving the sector IDs
+            None => return self.terminate(ST0_ABNORMAL|ST0_NOT_READY, 0, 0)
+        };
+        if head >= sides {
+            return self.terminate(ST0_ABNORMAL, ST1_MISSING_AM, 0)
+        }
+        let drive = &mut self.drives[unit];
+        let cylinder = drive.cylinder;
+        let disk = drive.disk.as_mut().unwrap();
+        let index = cylinder as usize * sides as usize + head as usize;
+        while disk.tracks.len() <= index {
+            let track = (disk.tracks.len() / sides as usize) as u8;
+            let side = (disk.tracks.len() % sides as usize) as u8;
+            disk.tracks.push(DskTrack { track, side, ..DskTrack::default() });
+        }
+        let sectors = self.buffer.chunks_exact(4).map(|id| {
+            let mut sector = DskSector {
+                cylinder: id[0], head: id[1], id: id[2], size: id[3], ..DskSector::default()
+            };
+            sector.data = vec![filler; 128 << size.min(6)];
+            sector
+        }).collect();
+        disk.tracks[index] = DskTrack { track: cylinder, side: head, gap, filler, sectors };
+        drive.next_sector = 0;
+        let last = self.buffer.len() - 4;
+        let (c, h, r, n) = (self.buffer[last], self.buffer[last + 1], self.buffer[last + 2], self.buffer[last + 3]);
+        let st0 = self.st0(0);
+        self.set_result(&[st0, 0, 0, c, h, r, n]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(fdc: &mut Upd765, bytes: &[u8]) {
+        for &b in bytes {
+            assert_eq!(fdc.read_status() & 0xC0, 0x80);
+            fdc.write_data(b);
+        }
+    }
+
+    fn result(fdc: &mut Upd765) -> Vec<u8> {
+        let mut res = Vec::new();
+        while fdc.read_status() & 0xD0 == 0xD0 {
+            res.push(fdc.read_data());
+        }
+        assert_eq!(fdc.read_status(), 0x80);
+        res
+    }
+
+    #[test]
+    fn upd765_works() {
+        let mut fdc = Upd765::default();
+        assert_eq!(fdc.read_status(), 0x80);
+        // invalid command
+        command(&mut fdc, &[0x1F]);
+        assert_eq!(result(&mut fdc), [0x80]);
+        command(&mut fdc, &[0x03, 0xAF, 0x03]);
+        assert_eq!(fdc.read_status(), 0x80);
+        // no disk
+        command(&mut fdc, &[0x04, 0x00]);
+        assert_eq!(result(&mut fdc), [0x10]);
+        command(&mut fdc, &[0x4A, 0x00]);
+        assert_eq!(result(&mut fdc), [0x48, 0, 0, 0, 0, 0, 0]);
+        // format a disk
+        fdc.insert_disk(0, DskImage { sides: 1, tracks: Vec::new() });
+        command(&mut fdc, &[0x0F, 0x00, 2]);
+        assert_eq!(fdc.read_status(), 0x81);
+        command(&mut fdc, &[0x08]);
+        assert_eq!(result(&mut fdc), [0x20, 2]);
+        command(&mut fdc, &[0x08]);
+        assert_eq!(result(&mut fdc), [0x80]);
+        command(&mut fdc, &[0x4D, 0x00, 2, 2, 0x2A, 0xE5]);
+        assert_eq!(fdc.read_status(), 0xB0);
+        command(&mut fdc, &[2, 0, 1, 2, 2, 0, 2, 2]);
+        assert_eq!(result(&mut fdc), [0x00, 0, 0, 2, 0, 2, 2]);
+        assert_eq!(fdc.drive(0).disk.as_ref().unwrap().tracks.len(), 3);
+        // eject the disk during the format
+        command(&mut fdc, &[0x4D, 0x00, 2, 1, 0x2A, 0xE5]);
+        assert_eq!(fdc.read_status(), 0xB0);
+        let disk = fdc.eject_disk(0);
+        command(&mut fdc, &[2, 0, 1, 2]);
+        assert_eq!(result(&mut fdc), [0x48, 0, 0, 2, 1, 0x2A, 0xE5]);
+        fdc.insert_disk(0, disk.unwrap());
+        // write 2 sectors
+        command(&mut fdc, &[0x45, 0x00, 2, 0, 1, 2, 2, 0x2A, 0xFF]);
+        for i in 0..1024 {
+            assert_eq!(fdc.read_status(), 0xB0);
+            fdc.write_data(i as u8);
+        }
+        assert_eq!(result(&mut fdc), [0x40, 0x80, 0, 3, 0, 1, 2]);
+        // read the second sector
+        command(&mut fdc, &[0x46, 0x00, 2, 0, 2, 2, 2, 0x2A, 0xFF]);
+        for i in 0..512 {
+            assert_eq!(fdc.read_status(), 0xF0);
+            assert_eq!(fdc.read_data(), i as u8);
+        }
+        assert_eq!(result(&mut fdc), [0x40, 0x80, 0, 3, 0, 1, 2]);
+        // missing sector
+        command(&mut fdc, &[0x46, 0x00, 2, 0, 3, 2, 3, 0x2A, 0xFF]);
+        assert_eq!(result(&mut fdc), [0x40, 0x04, 0, 2, 0, 3, 2]);
+        // read id
+        command(&mut fdc, &[0x4A, 0x00]);
+        assert_eq!(result(&mut fdc), [0x00, 0, 0, 2, 0, 1, 2]);
+        command(&mut fdc, &[0x4A, 0x00]);
+        assert_eq!(result(&mut fdc), [0x00, 0, 0, 2, 0, 2, 2]);
+        // unformatted track
+        command(&mut fdc, &[0x07, 0x00]);
+        command(&mut fdc, &[0x08]);
+        assert_eq!(result(&mut fdc), [0x20, 0]);
+        command(&mut fdc, &[0x4A, 0x00]);
+        assert_eq!(result(&mut fdc), [0x40, 0x01, 0, 0, 0, 0, 0]);
+        // write protection
+        fdc.drive_mut(0).write_protect = true;
+        command(&mut fdc, &[0x04, 0x00]);
+        assert_eq!(result(&mut fdc), [0x70]);
+        command(&mut fdc, &[0x45, 0x00, 0, 0, 1, 2, 1, 0x2A, 0xFF]);
+        assert_eq!(result(&mut fdc), [0x40, 0x02, 0, 0, 0, 1, 2]);
+
+        let mut dsk = Vec::new();
+        fdc.eject_disk(0).unwrap().write_dsk(&mut dsk).unwrap();
+        assert!(fdc.attach_dsk(1, &dsk[..]).unwrap().is_none());
+        let disk = fdc.drive(1).disk.as_ref().unwrap();
+        assert_eq!(disk.track(2, 0).unwrap().sectors[1].data[511], 255);
+        fdc.reset();
+        assert!(fdc.drive(1).disk.is_some());
+    }
+}
@@ -0,0 +1,687 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! An emulator of the **WD1793** floppy disk controller.
+use std::io::{self, Read};
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use spectrusty_core::clock::FTs;
+
+use super::trd::{TrdDisk, TRD_SECTOR_SIZE, TRD_SECTORS_PER_TRACK};
+
+/// The number of floppy disk drives that can be connected to the controller.
+pub const WD_MAX_DRIVES: usize = 4;
+/// The number of T-states of a single disk revolution (300 RPM at 3.5 MHz).
+pub const WD_REVOLUTION_TS: FTs = 700_000;
+/// The number of T-states of the index pulse.
+pub const WD_INDEX_PULSE_TS: FTs = 14_000;
+/// The number of T-states of a single head step for each of the step rate settings.
+pub const WD_STEP_RATE_TS: [FTs;4] = [21_000, 42_000, 70_000, 105_000];
+
+const MAX_CYLINDER: u8 = 85;
+const TRACK_ID_SIZE_CODE: u8 = 1;
+
+bitflags! {
+    /// The status register flags.
+    #[derive(Default)]
+    #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+    pub struct WdStatus: u8 {
+        const NOT_READY     = 0b1000_0000;
+        const WRITE_PROTECT = 0b0100_0000;
+        /// Head loaded (type I) or record type/write fault (type II and III commands).
+        const HEAD_LOADED   = 0b0010_0000;
+        /// Seek error (type I) or record not found (type II and III commands).
+        const SEEK_ERROR    = 0b0001_0000;
+        const CRC_ERROR     = 0b0000_1000;
+        /// Track 00 (type I) or lost data (type II and III commands).
+        const TRACK00       = 0b0000_0100;
+        /// Index pulse (type I) or data request (type II and III commands).
+        const INDEX         = 0b0000_0010;
+        const BUSY          = 0b0000_0001;
+        const RECORD_NOT_FOUND = Self::SEEK_ERROR.bits();
+        const DATA_REQUEST = Self::INDEX.bits();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+enum WdPhase {
+    Idle,
+    Seeking(FTs),
+    ReadSector,
+    WriteSector,
+    ReadAddress,
+    WriteTrack
+}
+
+/// A floppy disk drive connected to the [Wd1793] controller.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
+pub struct WdDrive {
+    /// The inserted disk, if any.
+    pub disk: Option<TrdDisk>,
+    /// Set to `true` to prevent writing to the inserted disk.
+    pub write_protect: bool,
+    cylinder: u8,
+    next_sector: u8
+}
+
+/// Emulates the **WD1793** floppy disk controller operating on **TR-DOS** disk images.
+///
+/// All commands are implemented. The `WRITE TRACK` command only recognizes sector data fields
+/// while formatting and `READ TRACK` returns the sector data fields of the track.
+///
+/// The type I commands take time according to the number of steps and the step rate. The completion
+/// of those commands is advanced by [Wd1793::update_timestamp] and the status reads. The data transfers
+/// of the other commands are not throttled: the data is requested as soon as the previous byte has been
+/// transferred.
+///
+/// The time is given as the number of T-states relative to the beginning of the current frame.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
+pub struct Wd1793 {
+    drives: [WdDrive; WD_MAX_DRIVES],
+    drive: u8,
+    side: u8,
+    status: WdStatus,
+    command: u8,
+    track: u8,
+    sector: u8,
+    data: u8,
+    step_in: bool,
+    type1: bool,
+    intrq: bool,
+    phase: WdPhase,
+    buffer: Vec<u8>,
+    index: usize,
+    frame_offset: FTs
+}
+
+impl Default for Wd1793 {
+    fn default() -> Self {
+        Wd1793 {
+            drives: Default::default(),
+            drive: 0,
+            side: 0,
+            status: WdStatus::empty(),
+            command: 0,
+            track: 0,
+            sector: 1,
+            data: 0,
+            step_in: true,
+            type1: true,
+            intrq: false,
+            phase: WdPhase::Idle,
+            buffer: Vec::new(),
+            index: 0,
+            frame_offset: 0
+        }
+    }
+}
+
+impl Wd1793 {
+    /// Reads a `.TRD` disk image and inserts it into the `drive`.
+    ///
+    /// Returns the previously inserted disk, if any.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [WD_MAX_DRIVES].
+    pub fn attach_trd<R: Read>(&mut self, drive: usize, rd: R) -> io::Result<Option<TrdDisk>> {
+        let disk = TrdDisk::read_trd(rd)?;
+        Ok(self.insert_disk(drive, disk))
+    }
+    /// Reads an `.SCL` disk image and inserts it into the `drive`.
+    ///
+    /// Returns the previously inserted disk, if any.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [WD_MAX_DRIVES].
+    pub fn attach_scl<R: Read>(&mut self, drive: usize, rd: R) -> io::Result<Option<TrdDisk>> {
+        let disk = TrdDisk::read_scl(rd)?;
+        Ok(self.insert_disk(drive, disk))
+    }
+    /// Inserts the `disk` into the `drive`, returning the previously inserted disk, if any.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [WD_MAX_DRIVES].
+    pub fn insert_disk(&mut self, drive: usize, disk: TrdDisk) -> Option<TrdDisk> {
+        self.drives[drive].disk.replace(disk)
+    }
+    /// Ejects and returns the disk from the `drive`, if any.
+    ///
+    /// A pending command is aborted with the `NOT READY` status.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [WD_MAX_DRIVES].
+    pub fn eject_disk(&mut self, drive: usize) -> Option<TrdDisk> {
+        let disk = self.drives[drive].disk.take();
+        if self.phase != WdPhase::Idle {
+            self.not_ready();
+        }
+        disk
+    }
+    /// Returns a reference to the `drive`.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [WD_MAX_DRIVES].
+    pub fn drive(&self, drive: usize) -> &WdDrive {
+        &self.drives[drive]
+    }
+    /// Returns a mutable reference to the `drive`.
+    ///
+    /// # Panics
+    /// Panics if `drive` is not less than [WD_MAX_DRIVES].
+    pub fn drive_mut(&mut self, drive: usize) -> &mut WdDrive {
+        &mut self.drives[drive]
+    }
+    /// Selects the current `drive` (`0` - `3`) and the disk `side` (`0` - `1`).
+    pub fn select(&mut self, drive: u8, side: u8) {
+        self.drive = drive & 3;
+        self.side = side & 1;
+    }
+    /// Returns the index of the currently selected drive.
+    pub fn selected_drive(&self) -> u8 {
+        self.drive
+    }
+    /// Returns the currently selected disk side.
+    pub fn selected_side(&self) -> u8 {
+        self.side
+    }
+    /// Resets the controller. Inserted disks are left intact.
+    pub fn reset(&mut self) {
+        let drives = core::mem::take(&mut self.drives);
+        let frame_offset = self.frame_offset;
+        *self = Wd1793 { drives, frame_offset, ..Wd1793::default() };
+    }
+    /// Returns the state of the interrupt request signal at the given `timestamp`.
+    pub fn intrq(&mut self, timestamp: FTs) -> bool {
+        self.update_timestamp(timestamp);
+        self.intrq
+    }
+    /// Returns the state of the data request signal.
+    pub fn drq(&self) -> bool {
+        matches!(self.phase,
+            WdPhase::ReadSector|WdPhase::WriteSector|WdPhase::ReadAddress|WdPhase::WriteTrack)
+    }
+    /// Completes the pending type I command if its execution time has elapsed at the given `timestamp`.
+    pub fn update_timestamp(&mut self, timestamp: FTs) {
+        if let WdPhase::Seeking(end_ts) = self.phase {
+            if timestamp >= end_ts {
+                self.phase = WdPhase::Idle;
+                self.intrq = true;
+            }
+        }
+    }
+    /// Should be called at the end of each frame with the frame duration in T-states.
+    pub fn next_frame(&mut self, eof_timestamp: FTs) {
+        self.frame_offset = (self.frame_offset + eof_timestamp) % WD_REVOLUTION_TS;
+        if let WdPhase::Seeking(ref mut end_ts) = self.phase {
+            *end_ts -= eof_timestamp;
+        }
+    }
+    /// Reads the status register at the given `timestamp`. Clears the interrupt request.
+    pub fn read_status(&mut self, timestamp: FTs) -> u8 {
+        self.update_timestamp(timestamp);
+        self.intrq = false;
+        let drive = &self.drives[self.drive as usize];
+        let mut status = self.status;
+        if drive.disk.is_none() {
+            status.insert(WdStatus::NOT_READY);
+        }
+        if self.phase != WdPhase::Idle {
+            status.insert(WdStatus::BUSY);
+        }
+        if self.type1 {
+            status.set(WdStatus::WRITE_PROTECT, drive.write_protect);
+            status.set(WdStatus::TRACK00, drive.cylinder == 0);
+            let position = (self.frame_offset + timestamp).rem_euclid(WD_REVOLUTION_TS);
+            status.set(WdStatus::INDEX, drive.disk.is_some() && position < WD_INDEX_PULSE_TS);
+        }
+        else {
+            status.set(WdStatus::DATA_REQUEST, self.drq());
+        }
+        status.bits()
+    }
+    /// Returns the content of the track register.
+    pub fn read_track(&self) -> u8 {
+        self.track
+    }
+    /// Writes `data` to the track register.
+    pub fn write_track(&mut self, data: u8) {
+        if self.phase == WdPhase::Idle {
+            self.track = data;
+        }
+    }
+    /// Returns the content of the sector register.
+    pub fn read_sector(&self) -> u8 {
+        self.sector
+    }
+    /// Writes `data` to the sector register.
+    pub fn write_sector(&mut self, data: u8) {
+        if self.phase == WdPhase::Idle {
+            self.sector = data;
+        }
+    }
+    /// Reads the data register.
+    pub fn read_data(&mut self) -> u8 {
+        match self.phase {
+            WdPhase::ReadSector|WdPhase::ReadAddress => {
+                self.data = self.buffer[self.index];
+                self.index += 1;
+                if self.index == self.buffer.len() {
+                    if self.phase == WdPhase::ReadSector && self.command & 0x10 != 0 {
+                        self.sector = self.sector.wrapping_add(1);
+                        self.start_read_sector();
+                    }
+                    else {
+                        self.complete();
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.data
+    }
+    /// Writes `data` to the data register.
+    pub fn write_data(&mut self, data: u8) {
+        self.data = data;
+        match self.phase {
+            WdPhase::WriteSector|WdPhase::WriteTrack => {
+                self.buffer[self.index] = data;
+                self.index += 1;
+                if self.index == self.buffer.len() {
+                    if self.drives[self.drive as usize].disk.is_none() {
+                        // the drive has been deselected or the disk has been ejected
+                        self.not_ready();
+                    }
+                    else if self.phase == WdPhase::WriteTrack {
+                        self.format_track();
+                        self.complete();
+                    }
+                    else {
+                        self.store_sector();
+                        if self.command & 0x10 != 0 {
+                            self.sector = self.sector.wrapping_add(1);
+                            self.start_write_sector();
+                        }
+                        else {
+                            self.complete();
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    /// Writes the `command` to the command register at the given `timestamp`.
+    pub fn write_command(&mut self, command: u8, timestamp: FTs) {
+        self.update_timestamp(timestamp);
+        if command & 0xF0 == 0xD0 {
+            // FORCE INTERRUPT
+            if self.phase == WdPhase::Idle {
+                self.type1 = true;
+                self.status = WdStatus::empty();
+            }
+            self.phase = WdPhase::Idle;
+            self.intrq = command & 0x08 != 0;
+            return
+        }
+        if self.phase != WdPhase::Idle {
+            return
+        }
+        self.command = command;
+        self.intrq = false;
+        self.status = WdStatus::empty();
+        self.type1 = command & 0x80 == 0;
+        if self.type1 {
+            return self.execute_type1(command, timestamp)
+        }
+        if self.drives[self.drive as usize].disk.is_none() {
+            self.intrq = true;
+            return
+        }
+        match command & 0xF0 {
+            0x80|0x90 => self.start_read_sector(),
+            0xA0|0xB0 => self.start_write_sector(),
+            0xC0 => self.read_address(),
+            0xE0 => self.read_whole_track(),
+            _ => self.start_write_track()
+        }
+    }
+
+    fn execute_type1(&mut self, command: u8, timestamp: FTs) {
+        let drive = &mut self.drives[self.drive as usize];
+        let start_cylinder = drive.cylinder;
+        let update_track = command & 0x10 != 0;
+        match command & 0xE0 {
+            0x00 if command & 0x10 == 0 => { // RESTORE
+                self.track = 0;
+                drive.cylinder = 0;
+                self.step_in = false;
+            }
+            0x00 => { // SEEK
+                self.step_in = self.data > self.track;
+                let delta = self.data as i16 - self.track as i16;
+                drive.cylinder = (drive.cylinder as i16 + delta).clamp(0, MAX_CYLINDER as i16) as u8;
+                self.track = self.data;
+            }
+            cmd => {
+                match cmd {
+                    0x40 => self.step_in = true,
+                    0x60 => self.step_in = false,
+                    _ => {}
+                }
+                if self.step_in {
+                    drive.cylinder = drive.cylinder.saturating_add(1).min(MAX_CYLINDER);
+                    if update_track {
+                        self.track = self.track.wrapping_add(1);
+                    }
+                }
+                else {
+                    drive.cylinder = drive.cylinder.saturating_sub(1);
+                    if update_track {
+                        self.track = self.track.wrapping_sub(1);
+                    }
+                }
+            }
+        }
+        if command & 0x08 != 0 {
+            self.status.insert(WdStatus::HEAD_LOADED);
+        }
+        // verify
+        if command & 0x04 != 0 && (drive.disk.is_none() || self.track != drive.cylinder) {
+            self.status.insert(WdStatus::SEEK_ERROR);
+        }
+        let steps = (drive.cylinder as i16 - start_cylinder as i16).unsigned_abs().max(1) as FTs;
+        self.phase = WdPhase::Seeking(timestamp + steps * WD_STEP_RATE_TS[(command & 3) as usize]);
+    }
+
+    fn complete(&mut self) {
+        self.phase = WdPhase::Idle;
+        self.intrq = true;
+    }
+
+    fn not_ready(&mut self) {
+        self.status.insert(WdStatus::NOT_READY);
+        self.complete();
+    }
+
+    fn record_not_found(&mut self) {
+        self.status.insert(WdStatus::RECORD_NOT_FOUND);
+        self.complete();
+    }
+
+    fn sector_position(&self) -> Option<(u8, u8)> {
+        let drive = &self.drives[self.drive as usize];
+        let disk = drive.disk.as_ref()?;
+        if self.track != drive.cylinder || self.side >= disk.sides() || drive.cylinder >= disk.cylinders() {
+            return None
+        }
+        Some((drive.cylinder, self.side))
+    }
+
+    fn start_read_sector(&mut self) {
+        let (cylinder, side) = match self.sector_position() {
+            Some(pos) => pos,
+            None => return self.record_not_found()
+        };
+        let disk = self.drives[self.drive as usize].disk.as_ref().unwrap();
+        match disk.sector(cylinder, side, self.sector) {
+            Some(data) => {
+                self.buffer.clear();
+                self.buffer.extend_from_slice(data);
+                self.index = 0;
+                self.phase = WdPhase::ReadSector;
+            }
+            None => self.record_not_found()
+        }
+    }
+
+    fn start_write_sector(&mut self) {
+        if self.drives[self.drive as usize].write_protect {
+            self.status.insert(WdStatus::WRITE_PROTECT);
+            return self.complete()
+        }
+        match self.sector_position() {
+            Some(_) if (1..=TRD_SECTORS_PER_TRACK).contains(&(self.sector as usize)) => {
+                self.buffer.clear();
+                self.buffer.resize(TRD_SECTOR_SIZE, 0);
+                self.index = 0;
+                self.phase = WdPhase::WriteSector;
+            }
+            _ => self.record_not_found()
+        }
+    }
+
+    fn store_sector(&mut self) {
+        if let Some((cylinder, side)) = self.sector_position() {
+            let sector = self.sector;
+            let disk = self.drives[self.drive as usize].disk.as_mut().unwrap();
+            if let Some(data) = disk.sector_mut(cylinder, side, sector) {
+                data.copy_from_slice(&self.buffer);
+            }
+        }
+    }
+
+    fn read_address(&mut self) {
+        let drive = &mut self.drives[self.drive as usize];
+        let disk = match drive.disk.as_ref() {
+            Some(disk) => disk,
+            None => return self.not_ready()
+        };
+        if self.side >= disk.sides() || drive.cylinder >= disk.cylinders() {
+            return self.record_not_found()
+        }
+        let id = drive.next_sector % TRD_SECTORS_PER_TRACK as u8 + 1;
+        drive.next_sector = id;
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&[drive.cylinder, 0, id, TRACK_ID_SIZE_CODE, 0, 0]);
+        self.sector = drive.cylinder;
+        self.index = 0;
+        self.phase = WdPhase::ReadAddress;
+    }
+
+    fn read_whole_track(&mut self) {
+        let drive = &self.drives[self.drive as usize];
+        let disk = match drive.disk.as_ref() {
+            Some(disk) => disk,
+            None => return self.not_ready()
+        };
+        let cylinder = drive.cylinder;
+        self.buffer.clear();
+        for id in 1..=TRD_SECTORS_PER_TRACK as u8 {
+            match disk.sector(cylinder, self.side, id) {
+                Some(data) => self.buffer.extend_from_slice(data),
+                None => return self.record_not_found()
+            }
+        }
+        self.index = 0;
+        // the data is presented the same way as for the read sector command
+        self.phase = WdPhase::ReadSector;
+        self.command &= !0x10;
+    }
+
+    fn start_write_track(&mut self) {
+        if self.drives[self.drive as usize].write_protect {
+            self.status.insert(WdStatus::WRITE_PROTECT);
+            return self.complete()
+        }
+        self.buffer.clear();
+        self.buffer.resize(6250, 0x4E);
+        self.index = 0;
+        self.phase = WdPhase::WriteTrack;
+    }
+
+    fn format_track(&mut self) {
+        let side = self.side;
+        let drive = &mut self.drives[self.drive as usize];
+        let cylinder = drive.cylinder;
+        let disk = match drive.disk.as_mut() {
+            Some(disk) => disk,
+            None => return
+        };
+        let mut id = None;
+        let mut bytes = self.buffer.iter().copied().enumerate();
+        while let Some((index, byte)) = bytes.next() {
+            match byte {
+                // ID address mark: C, H, R, N
+                0xFE => {
+                    id = self.buffer.get(index + 3).copied();
+                    bytes.nth(3);
+                }
+                // data address mark
+                0xFB|0xF8 => {
+                    if let Some(data) = id.take().and_then(|id| disk.sector_mut(cylinder, side, id)) {
+                        if let Some(source) = self.buffer.get(index + 1..index + 1 + TRD_SECTOR_SIZE) {
+                            data.copy_from_slice(source);
+                            bytes.nth(TRD_SECTOR_SIZE - 1);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wd1793_works() {
+        let mut wd = Wd1793::default();
+        assert_eq!(wd.read_status(0) & 0x80, 0x80);
+        let mut disk = TrdDisk::new_formatted(80, 2);
+        disk.sector_mut(2, 1, 5).unwrap().iter_mut().for_each(|p| *p = 0xA5);
+        assert!(wd.insert_disk(0, disk).is_none());
+        // restore
+        wd.write_command(0x08, 0);
+        assert_eq!(wd.read_status(1), 0x27);
+        assert!(!wd.intrq(2));
+        wd.update_timestamp(21_000);
+        assert!(wd.intrq(21_000));
+        assert_eq!(wd.read_status(21_001) & 0x07, 0x04);
+        assert_eq!(wd.read_status(WD_REVOLUTION_TS + 10) & 0x07, 0x06);
+        // seek with verify
+        wd.write_data(2);
+        wd.write_command(0x1C, 30_000);
+        assert_eq!(wd.read_status(30_000 + 2 * 21_000 - 1) & 0x01, 0x01);
+        wd.next_frame(50_000);
+        assert_eq!(wd.read_status(30_000 + 2 * 21_000 - 50_000) & 0x15, 0x00);
+        assert_eq!(wd.read_track(), 2);
+        // read sector
+        wd.select(0, 1);
+        wd.write_sector(5);
+        wd.write_command(0x80, 0);
+        assert_eq!(wd.read_status(0), 0x03);
+        for _ in 0..TRD_SECTOR_SIZE {
+            assert!(wd.drq());
+            assert_eq!(wd.read_data(), 0xA5);
+        }
+        assert!(!wd.drq());
+        assert!(wd.intrq(0));
+        assert_eq!(wd.read_status(0), 0x00);
+        // write sector
+        wd.write_sector(6);
+        wd.write_command(0xA0, 0);
+        for i in 0..TRD_SECTOR_SIZE {
+            wd.write_data(i as u8);
+        }
+        assert_eq!(wd.read_status(0), 0x00);
+        let data = wd.drive(0).disk.as_ref().unwrap().sector(2, 1, 6).unwrap();
+        assert!(data.iter().enumerate().all(|(i, &b)| b == i as u8));
+        // record not found
+        wd.write_sector(17);
+        wd.write_command(0x80, 0);
+        assert_eq!(wd.read_status(0), 0x10);
+        // read address
+        wd.write_command(0xC0, 0);
+        let id: Vec<u8> = (0..6).map(|_| wd.read_data()).collect();
+        assert_eq!(id, [2, 0, 1, 1, 0, 0]);
+        assert_eq!(wd.read_sector(), 2);
+        // write protect
+        wd.drive_mut(0).write_protect = true;
+        wd.write_command(0xA0, 0);
+        assert_eq!(wd.read_status(0), 0x40);
+        wd.drive_mut(0).write_protect = false;
+        // format track
+        wd.write_command(0xF0, 0);
+        let mut track = Vec::new();
+        for id in 1..=16 {
+            track.extend_from_slice(&[0xF5, 0xF5, 0xF5, 0xFE, 2, 0, id, 1, 0xF7, 0x4E, 0xF5, 0xF5, 0xF5, 0xFB]);
+            track.extend_from_slice(&[id; TRD_SECTOR_SIZE]);
+            track.push(0xF7);
+        }
+        track.resize(6250, 0x4E);
+        for byte in track {
+            wd.write_data(byte);
+        }
+        assert!(wd.intrq(0));
+        let disk = wd.drive(0).disk.as_ref().unwrap();
+        assert!((1..=16).all(|id| disk.sector(2, 1, id).unwrap().iter().all(|&b| b == id)));
+        // force interrupt
+        wd.write_command(0x80, 0);
+        assert!(wd.drq());
+        wd.write_command(0xD0, 0);
+        assert!(!wd.drq());
+        assert!(!wd.intrq(0));
+        wd.write_command(0xD8, 0);
+        assert!(wd.intrq(0));
+        // no disk
+        wd.select(1, 0);
+        wd.write_command(0x80, 0);
+        assert_eq!(wd.read_status(0), 0x80);
+        wd.reset();
+        assert!(wd.drive(0).disk.is_some());
+    }
+
+    #[test]
+    fn wd1793_drive_lost_during_command() {
+        let mut wd = Wd1793::default();
+        wd.insert_disk(0, TrdDisk::new_formatted(80, 2));
+        // write track, then select an empty drive in the middle of the command
+        wd.write_command(0xF0, 0);
+        for _ in 0..100 {
+            wd.write_data(0x4E);
+        }
+        wd.select(1, 0);
+        for _ in 100..6250 {
+            wd.write_data(0x4E);
+        }
+        assert!(!wd.drq());
+        assert!(wd.intrq(0));
+        assert_eq!(wd.read_status(0), 0x80);
+        // write sector, the same
+        wd.select(0, 0);
+        wd.write_sector(1);
+        wd.write_command(0xA0, 0);
+        wd.select(2, 0);
+        for _ in 0..TRD_SECTOR_SIZE {
+            wd.write_data(0xAA);
+        }
+        assert!(wd.intrq(0));
+        assert_eq!(wd.read_status(0), 0x80);
+        wd.select(0, 0);
+        assert!(wd.drive(0).disk.as_ref().unwrap().sector(0, 0, 1).unwrap().iter().all(|&b| b != 0xAA));
+        // ejecting the disk aborts the command
+        wd.write_command(0xF0, 0);
+        assert!(wd.drq());
+        assert!(wd.eject_disk(0).is_some());
+        assert!(!wd.drq());
+        assert!(wd.intrq(0));
+        assert_eq!(wd.read_status(0) & 0x81, 0x80);
+        for _ in 0..6250 {
+            wd.write_data(0x4E);
+        }
+        assert!(!wd.intrq(0));
+    }
+}
@@ -23,4 +23,5 @@
 pub mod keyboard;
 pub mod io;
 pub mod printer;
+pub mod replay;
 pub mod tap;
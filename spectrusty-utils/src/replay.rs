@@ -0,0 +1,355 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+//! Utilities for recording and replaying user input.
+//!
+//! [InputRecorder] logs changes of the keyboard state, joystick states and `EAR IN` changes together
+//! with the frame counter and the video timestamp at which they were applied to the emulated chipset.
+//!
+//! [InputReplayer] applies recorded events to the emulated chipset exactly at the same frame and video
+//! timestamp during playback. Given the same initial state of the emulator, the replay is deterministic.
+//!
+//! With the `snapshot` feature enabled, both types can be serialized with [serde].
+use core::slice;
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use spectrusty::z80emu::{Cpu, CpuDebug};
+use spectrusty::clock::VideoTs;
+use spectrusty::chip::{ControlUnit, FrameState, EarIn};
+use spectrusty::video::Video;
+use spectrusty::peripherals::{
+    KeyboardInterface, ZXKeyboardMap,
+    joystick::{JoystickInterface, Directions}
+};
+
+/// The bit of the [InputEvent::Joystick] `buttons` mask indicating the primary fire button is pressed.
+pub const FIRE_BUTTON_MASK: u8 = 0b01;
+/// The bit of the [InputEvent::Joystick] `buttons` mask indicating the second fire button is pressed.
+pub const FIRE2_BUTTON_MASK: u8 = 0b10;
+
+/// A timestamp of an input event.
+///
+/// The order of timestamps is determined first by the `frame` and then by the `vts` value.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct InputTimestamp {
+    /// The value of the frame counter.
+    pub frame: u64,
+    /// The video timestamp within the frame.
+    pub vts: VideoTs
+}
+
+/// An enumeration of the recorded input events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
+pub enum InputEvent {
+    /// The new state of the keyboard.
+    KeyState(ZXKeyboardMap),
+    /// The new state of the joystick with the given `index`.
+    ///
+    /// `directions` contains bits of [Directions] and `buttons` contains the mask of the pressed
+    /// fire buttons: [FIRE_BUTTON_MASK] and [FIRE2_BUTTON_MASK].
+    Joystick { index: usize, directions: u8, buttons: u8 },
+    /// The `EAR IN` change as passed to [EarIn::set_ear_in].
+    EarIn { ear_in: bool, delta_fts: u32 }
+}
+
+/// A single recorded input event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct InputRecord {
+    pub timestamp: InputTimestamp,
+    pub event: InputEvent
+}
+
+/// Records input events applied to the emulated chipset.
+///
+/// Use the setter methods of the recorder instead of the chipset ones for the recorded input
+/// to be applied and timestamped at the same time.
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct InputRecorder {
+    records: Vec<InputRecord>
+}
+
+/// Replays input events recorded with [InputRecorder].
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct InputReplayer {
+    records: Vec<InputRecord>,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    position: usize
+}
+
+impl InputTimestamp {
+    /// Returns the current timestamp of the given chipset.
+    pub fn current<U: FrameState + Video>(ula: &U) -> Self {
+        InputTimestamp { frame: ula.current_frame(), vts: ula.current_video_ts() }
+    }
+}
+
+/// Sets the stick `directions` and the state of the fire buttons of the joystick device `joy`.
+///
+/// `buttons` is the mask of the pressed fire buttons: [FIRE_BUTTON_MASK] and [FIRE2_BUTTON_MASK].
+/// The second button is set with [JoystickInterface::set_fire2], so it is being ignored by devices
+/// having only one button.
+pub fn set_joystick_state<J>(joy: &mut J, directions: Directions, buttons: u8)
+    where J: JoystickInterface + ?Sized
+{
+    joy.set_directions(directions);
+    joy.fire(0, buttons & FIRE_BUTTON_MASK != 0);
+    joy.set_fire2(buttons & FIRE2_BUTTON_MASK != 0);
+}
+
+impl InputRecorder {
+    /// Creates a new recorder without any recorded events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Records the given `event` with the current timestamp of `ula`.
+    ///
+    /// The event is not applied to the chipset.
+    pub fn record<U: FrameState + Video>(&mut self, ula: &U, event: InputEvent) {
+        let timestamp = InputTimestamp::current(ula);
+        self.records.push(InputRecord { timestamp, event });
+    }
+    /// Sets the state of the keyboard via [KeyboardInterface::set_key_state] and records the change.
+    ///
+    /// Nothing is recorded if the state of the keyboard has not changed.
+    pub fn set_key_state<U>(&mut self, ula: &mut U, keymap: ZXKeyboardMap)
+        where U: KeyboardInterface + FrameState + Video
+    {
+        if ula.get_key_state() != keymap {
+            ula.set_key_state(keymap);
+            self.record(ula, InputEvent::KeyState(keymap));
+        }
+    }
+    /// Sets the state of the joystick device `joy` with [set_joystick_state] and records the change.
+    ///
+    /// `index` identifies the joystick when replaying events.
+    pub fn set_joystick<U, J>(
+            &mut self,
+            ula: &U,
+            index: usize,
+            joy: &mut J,
+            directions: Directions,
+            buttons: u8
+        )
+        where U: FrameState + Video,
+              J: JoystickInterface + ?Sized
+    {
+        set_joystick_state(joy, directions, buttons);
+        self.record(ula, InputEvent::Joystick { index, directions: directions.bits(), buttons });
+    }
+    /// Sets the `EAR IN` bit via [EarIn::set_ear_in] and records the change.
+    pub fn set_ear_in<U>(&mut self, ula: &mut U, ear_in: bool, delta_fts: u32)
+        where U: EarIn + FrameState + Video
+    {
+        ula.set_ear_in(ear_in, delta_fts);
+        self.record(ula, InputEvent::EarIn { ear_in, delta_fts });
+    }
+    /// Returns the recorded events.
+    pub fn records(&self) -> &[InputRecord] {
+        &self.records
+    }
+    /// Returns an iterator of the recorded events.
+    pub fn iter(&self) -> slice::Iter<'_, InputRecord> {
+        self.records.iter()
+    }
+    /// Returns the number of recorded events.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+    /// Returns `true` if there are no recorded events.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+    /// Removes all recorded events.
+    pub fn clear(&mut self) {
+        self.records.clear()
+    }
+    /// Converts the recorder into the [InputReplayer].
+    pub fn into_replayer(self) -> InputReplayer {
+        InputReplayer::new(self.records)
+    }
+}
+
+impl InputReplayer {
+    /// Creates a new replayer from the given records.
+    ///
+    /// The `records` should be ordered by their timestamps.
+    pub fn new(records: Vec<InputRecord>) -> Self {
+        InputReplayer { records, position: 0 }
+    }
+    /// Returns the timestamp of the next event to be replayed.
+    pub fn next_timestamp(&self) -> Option<InputTimestamp> {
+        self.records.get(self.position).map(|rec| rec.timestamp)
+    }
+    /// Returns `true` if all events have been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.records.len()
+    }
+    /// Rewinds the replayer to the first event.
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+    /// Returns the recorded events.
+    pub fn records(&self) -> &[InputRecord] {
+        &self.records
+    }
+    /// Applies all events with timestamps up to the current timestamp of `ula`.
+    ///
+    /// Joystick events are passed to the `joystick` function with the arguments:
+    /// `(ula, index, directions, buttons)`, e.g. to be applied with [set_joystick_state].
+    ///
+    /// Returns the number of applied events.
+    pub fn apply_due_events<U, J>(&mut self, ula: &mut U, mut joystick: J) -> usize
+        where U: KeyboardInterface + EarIn + FrameState + Video,
+              J: FnMut(&mut U, usize, Directions, u8)
+    {
+        let now = InputTimestamp::current(ula);
+        let mut count = 0;
+        while let Some(&InputRecord { timestamp, event }) = self.records.get(self.position) {
+            if timestamp > now {
+                break
+            }
+            match event {
+                InputEvent::KeyState(keymap) => ula.set_key_state(keymap),
+                InputEvent::Joystick { index, directions, buttons } => {
+                    joystick(ula, index, Directions::from_bits_truncate(directions), buttons)
+                }
+                InputEvent::EarIn { ear_in, delta_fts } => ula.set_ear_in(ear_in, delta_fts)
+            }
+            self.position += 1;
+            count += 1;
+        }
+        count
+    }
+    /// Executes the next frame on the `cpu` applying the recorded events at their exact timestamps.
+    ///
+    /// This method should be called in place of [ControlUnit::execute_next_frame] during playback.
+    /// When there are recorded events within the executed frame, instructions are being executed one
+    /// by one with [ControlUnit::execute_single_step] until the next event is due.
+    ///
+    /// See [InputReplayer::apply_due_events] for the description of the `joystick` argument.
+    pub fn execute_next_frame<U, C, J>(&mut self, ula: &mut U, cpu: &mut C, mut joystick: J)
+        where U: ControlUnit + KeyboardInterface + EarIn + FrameState + Video,
+              C: Cpu,
+              J: FnMut(&mut U, usize, Directions, u8)
+    {
+        const DEBUG: Option<fn(CpuDebug)> = None;
+        self.apply_due_events(ula, &mut joystick);
+        if ula.is_frame_over() {
+            ula.ensure_next_frame();
+            self.apply_due_events(ula, &mut joystick);
+        }
+        let frame = ula.current_frame();
+        while let Some(InputTimestamp { vts, .. }) = self.next_timestamp()
+                                                         .filter(|ts| ts.frame == frame) {
+            while !ula.is_frame_over() && ula.current_video_ts() < vts {
+                let _ = ula.execute_single_step(cpu, DEBUG);
+            }
+            if ula.is_frame_over() {
+                break
+            }
+            self.apply_due_events(ula, &mut joystick);
+        }
+        if !ula.is_frame_over() {
+            ula.execute_next_frame(cpu);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spectrusty::z80emu::{Z80NMOS, Cpu};
+    use spectrusty::chip::{MemoryAccess, ula::UlaPAL};
+    use spectrusty::memory::{Memory48k, ZxMemory};
+    use spectrusty::peripherals::joystick::kempston::TwoButtonJoystickDevice;
+    use super::*;
+
+    type TestUla = UlaPAL<Memory48k>;
+
+    // DI; LD HL,0xC000; LD BC,0x7FFE;
+    // loop: IN A,(C); LD (HL),A; INC HL; LD A,H; AND 0x3F; OR 0xC0; LD H,A; JR loop
+    const PROGRAM: &[u8] = &[0xF3, 0x21, 0x00, 0xC0, 0x01, 0xFE, 0x7F,
+                             0xED, 0x78, 0x77, 0x23, 0x7C, 0xE6, 0x3F, 0xF6, 0xC0, 0x67, 0x18, 0xF4];
+
+    fn init() -> (TestUla, Z80NMOS) {
+        let mut ula = TestUla::default();
+        let mut cpu = Z80NMOS::default();
+        for (addr, &byte) in (0x8000..).zip(PROGRAM) {
+            ula.memory_mut().write(addr, byte);
+        }
+        cpu.reset();
+        cpu.set_pc(0x8000);
+        (ula, cpu)
+    }
+
+    #[test]
+    fn replay_works() {
+        const FRAMES: u64 = 8;
+        let (mut ula, mut cpu) = init();
+        let (mut ula_replay, mut cpu_replay) = (ula.clone(), cpu.clone());
+        let mut joy = TwoButtonJoystickDevice::default();
+        let mut recorder = InputRecorder::new();
+        assert!(recorder.is_empty());
+        for frame in 0..FRAMES {
+            for _ in 0..(frame + 1) * 97 {
+                ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+            }
+            let keymap = ZXKeyboardMap::from_bits_truncate(1 << (frame * 5 + 3));
+            recorder.set_key_state(&mut ula, keymap);
+            recorder.set_key_state(&mut ula, keymap);
+            recorder.set_joystick(&ula, 1, &mut joy, Directions::from_bits_truncate(frame as u8),
+                                  (frame % 4) as u8);
+            recorder.set_ear_in(&mut ula, frame & 1 == 1, 1000);
+            for _ in 0..frame * 13 {
+                ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+            }
+            recorder.set_key_state(&mut ula, ZXKeyboardMap::empty());
+            ula.execute_next_frame(&mut cpu);
+        }
+        assert!(ula.is_frame_over());
+        assert_eq!(ula.current_frame(), FRAMES - 1);
+        assert_eq!(recorder.len(), FRAMES as usize * 4);
+        assert!(recorder.iter().zip(recorder.iter().skip(1)).all(|(a, b)| a.timestamp <= b.timestamp));
+        assert!(recorder.records().iter().any(|rec| {
+            rec.event == InputEvent::Joystick { index: 1, directions: 3, buttons: FIRE_BUTTON_MASK|FIRE2_BUTTON_MASK }
+        }));
+
+        let mut joy_replay = TwoButtonJoystickDevice::default();
+        let mut replayer = recorder.into_replayer();
+        assert_eq!(replayer.next_timestamp(), Some(replayer.records()[0].timestamp));
+        for _ in 0..FRAMES {
+            replayer.execute_next_frame(&mut ula_replay, &mut cpu_replay, |_, index, directions, buttons| {
+                assert_eq!(index, 1);
+                set_joystick_state(&mut joy_replay, directions, buttons);
+            });
+        }
+        assert!(replayer.is_finished());
+        assert_eq!(ula_replay.current_frame(), ula.current_frame());
+        assert_eq!(ula_replay.current_tstate(), ula.current_tstate());
+        assert_eq!(cpu_replay.get_pc(), cpu.get_pc());
+        assert_eq!(cpu_replay.get_reg16(spectrusty::z80emu::StkReg16::HL),
+                   cpu.get_reg16(spectrusty::z80emu::StkReg16::HL));
+        assert_eq!(ula_replay.get_key_state(), ula.get_key_state());
+        assert_eq!(joy_replay.get_directions(), joy.get_directions());
+        assert_eq!(joy_replay.get_fire(0), joy.get_fire(0));
+        assert_eq!(joy_replay.get_fire2(), joy.get_fire2());
+        assert!(joy.get_fire(0) && joy.get_fire2());
+        assert_eq!(ula_replay.memory_ref().mem_ref(), ula.memory_ref().mem_ref());
+        // the replayed keyboard reads are being stored in the memory
+        assert!(ula.memory_ref().mem_ref()[0xC000..].iter().any(|&b| b & 0x1F != 0x1F));
+
+        replayer.rewind();
+        assert!(!replayer.is_finished());
+    }
+}
@@ -11,6 +11,16 @@ use std::io::{self, Read};
 use spectrusty::z80emu::{Cpu, CpuFlags, Prefix, StkReg16, Reg8};
 use spectrusty::memory::ZxMemory;
 
+/// The address in the 48k ROM loading routine `LD-BYTES` at which [try_instant_rom_tape_load_or_verify]
+/// detects the loading or verifying request.
+///
+/// Pass it to [ControlUnit::execute_next_frame_with_trap] together with a function calling
+/// [try_instant_rom_tape_load_or_verify], to load the tape data instantly as soon as the routine is being
+/// called. The same address is used by the 128k, +2 and +2A/+3 ROMs when the 48k BASIC ROM is paged in.
+///
+/// [ControlUnit::execute_next_frame_with_trap]: spectrusty::chip::ControlUnit::execute_next_frame_with_trap
+pub const ROM_LOAD_TRAP_ADDRESS: u16 = 0x056B;
+
 /// Detects if a loading routine in Spectrum's ROM has been called and if so, attempts to
 /// instantly load (or verify) data directly into the emulator's memory, reading from the
 /// provided data source.
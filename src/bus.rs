@@ -20,3 +20,4 @@
 // pub use spectrusty_peripherals::bus::*;
 pub use spectrusty_core::bus::*;
 #[cfg(feature = "peripherals")] pub use crate::peripherals::bus::*;
+#[cfg(feature = "snapshot")] pub use spectrusty_core::dyn_device_registry;
@@ -13,38 +13,71 @@ pub mod scld;
 pub mod plus;
 #[cfg(feature = "peripherals")]
 pub mod ay_player;
-use crate::memory::{ZxMemory, PagedMemory8k};
-use crate::video::{VideoFrame, Video};
-use crate::clock::FTs;
-use crate::peripherals::KeyboardInterface;
-use ula::{Ula, UlaVideoFrame, UlaNTSC, UlaNTSCVidFrame};
+use core::ops::RangeInclusive;
+
+use crate::memory::{self, ZxMemory, PagedMemory8k, MemoryKind};
+use crate::video::{BorderColor, VideoFrame, Video, frame_cache::ATTRS_OFFSET};
+use crate::clock::{FTs, VideoTs, MemoryContention};
+use crate::peripherals::{KeyboardInterface, ZXKeyboardMap};
+use ula::{Ula, UlaVideoFrame, UlaVideoFrameLateTiming, UlaNTSCVidFrame, PentagonVidFrame};
 use ula128::{Ula128, Ula128VidFrame};
-use ula3::Ula3;
+use ula3::{Ula3, Ula3VidFrame};
 use scld::Scld;
 use plus::UlaPlus;
 pub use spectrusty_core::chip::*;
 
+/// The default duration of the frame interrupt request signal in T-states.
+pub const DEFAULT_INTERRUPT_LENGTH: u32 = UlaVideoFrame::INT_LENGTH_TS as u32;
+
 /// ZX Spectrum PAL configuration parameters.
 pub struct ZxSpectrumPALConfig;
 impl HostConfig for ZxSpectrumPALConfig {
-    const CPU_HZ: u32 = 3_500_000;
+    const CPU_HZ: u32 = UlaVideoFrame::CPU_HZ;
     const FRAME_TSTATES: FTs = UlaVideoFrame::FRAME_TSTATES_COUNT;
 }
 
 /// ZX Spectrum NTSC configuration parameters.
 pub struct ZxSpectrumNTSCConfig;
 impl HostConfig for ZxSpectrumNTSCConfig {
-    const CPU_HZ: u32 = 3_527_500;
+    const CPU_HZ: u32 = UlaNTSCVidFrame::CPU_HZ;
     const FRAME_TSTATES: FTs = UlaNTSCVidFrame::FRAME_TSTATES_COUNT;
 }
 
+/// Pentagon 128 configuration parameters.
+pub struct PentagonConfig;
+impl HostConfig for PentagonConfig {
+    const CPU_HZ: u32 = PentagonVidFrame::CPU_HZ;
+    const FRAME_TSTATES: FTs = PentagonVidFrame::FRAME_TSTATES_COUNT;
+}
+
 /// ZX Spectrum 128k/+2/+2A/+3 configuration parameters.
 pub struct ZxSpectrum128Config;
 impl HostConfig for ZxSpectrum128Config {
-    const CPU_HZ: u32 = 3_546_900;
+    const CPU_HZ: u32 = Ula128VidFrame::CPU_HZ;
     const FRAME_TSTATES: FTs = Ula128VidFrame::FRAME_TSTATES_COUNT;
 }
 
+/// A closure called by chipsets implementing [UlaControl::set_write_watch_hook] each time the CPU writes
+/// to a memory address within one of the ranges added with [UlaControl::add_write_watchpoint].
+///
+/// The arguments are the address of the memory write, the written value and the video timestamp of the write.
+///
+/// The closure is required to be `Send` and `Sync`, so the chipsets holding it remain such.
+pub type WriteWatchHook = Box<dyn FnMut(u16, u8, VideoTs) + Send + Sync>;
+
+/// A closure called by chipsets implementing [UlaControl::set_frame_end_hook] each time a new frame begins.
+///
+/// The arguments are the new value of the frame counter and the current video timestamp, already wrapped
+/// to the new frame.
+pub type FrameEndHook = Box<dyn FnMut(u64, VideoTs) + Send + Sync>;
+
+/// A closure called by chipsets implementing [UlaControl::set_page_swap_hook] each time a memory page
+/// is being mapped to a different memory bank by the guest software.
+///
+/// The arguments are the memory page index, the kind and the index of the newly mapped memory bank,
+/// and the video timestamp of the paging port write.
+pub type PageSwapHook = Box<dyn FnMut(u8, MemoryKind, usize, VideoTs) + Send + Sync>;
+
 /// A grouping trait of all common control traits for all emulated `Ula` chipsets except audio rendering.
 ///
 /// For audio rendering see [crate::audio::UlaAudioFrame].
@@ -55,7 +88,39 @@ pub trait UlaCommon: UlaControl
                    + Video
                    + KeyboardInterface
                    + EarIn
-                   + for<'a> MicOut<'a> {}
+                   + for<'a> MicOut<'a>
+{
+    /// Returns a view of the raw screen memory of the indicated `screen_bank` together with the current
+    /// border color and the flash state, e.g. for rendering the screen with an external renderer.
+    ///
+    /// Use [Video::visible_screen_bank] to select the screen bank currently being displayed.
+    ///
+    /// # Errors
+    /// Returns an error if the `screen_bank` doesn't exist.
+    fn screen_view(&self, screen_bank: usize) -> memory::Result<ScreenView<'_>> {
+        let screen = self.memory_ref().screen_ref(screen_bank)?;
+        let (pixels, attrs) = screen.split_at(ATTRS_OFFSET);
+        Ok(ScreenView {
+            pixels,
+            attrs,
+            border: self.border_color(),
+            flash: self.flash_state()
+        })
+    }
+}
+
+/// The raw screen memory of a single screen bank with the video state, as returned by [UlaCommon::screen_view].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenView<'a> {
+    /// The 6144 bytes of the screen bitmap, in the order of the screen memory.
+    pub pixels: &'a [u8],
+    /// The 768 bytes of the screen color attributes.
+    pub attrs: &'a [u8],
+    /// The current border color.
+    pub border: BorderColor,
+    /// `true` if the colors of the flashing cells are currently inverted.
+    pub flash: bool
+}
 
 /// Specialized ULA functionality access methods.
 pub trait UlaControl {
@@ -65,7 +130,86 @@ pub trait UlaControl {
     ///
     /// In this mode interrupts are being requested just one T-state earlier than normally.
     /// This results in all other timings being one T-state later.
+    ///
+    /// The mode has no effect on the chipsets with the video frame already requesting interrupts
+    /// earlier, e.g. [UlaPALLate][ula::UlaPALLate], as the two don't stack.
     fn set_late_timings(&mut self, late_timings: bool);
+    /// Returns the duration of the frame interrupt request signal in T-states.
+    fn interrupt_length(&self) -> u32 { DEFAULT_INTERRUPT_LENGTH }
+    /// Sets the duration of the frame interrupt request signal in T-states. Returns `true` if supported.
+    /// Otherwise, returns `false` and the setting is ignored.
+    ///
+    /// The CPU accepts the frame interrupt only if the interrupts are enabled before the signal ends.
+    /// The default is given by [VideoFrame::INT_LENGTH_TS] of the chipset's video frame, e.g. 36 T-states
+    /// for the 128k models and [DEFAULT_INTERRUPT_LENGTH] for the 16k/48k models.
+    ///
+    /// Implementations also return `false` and ignore the setting if `length` exceeds the maximum
+    /// value of [Ts][crate::clock::Ts], the horizontal T-state counter type.
+    fn set_interrupt_length(&mut self, _length: u32) -> bool { false }
+    /// Returns the configured number of audio samples per second or `0` if not configured.
+    fn audio_sample_rate(&self) -> u32 { 0 }
+    /// Configures the number of audio samples per second. Returns `true` if supported.
+    /// Otherwise, returns `false` and the setting is ignored.
+    ///
+    /// The configured rate is being used by [AudioFrame::ensure_audio_frame_time] when `0` is passed
+    /// as its `sample_rate` argument. Set to `0` to clear the configuration.
+    ///
+    /// [AudioFrame::ensure_audio_frame_time]: crate::audio::AudioFrame::ensure_audio_frame_time
+    fn set_audio_sample_rate(&mut self, _rate: u32) -> bool { false }
+    /// Returns the configured CPU clock rate in T-states per second or `0` if not configured.
+    ///
+    /// When not configured, the chipset runs at the rate given by [HostConfig::CPU_HZ].
+    fn cpu_clock_rate(&self) -> u32 { 0 }
+    /// Configures the CPU clock rate in T-states per second. Returns `true` if supported.
+    /// Otherwise, returns `false` and the setting is ignored.
+    ///
+    /// The configured rate is being used by [AudioFrame::ensure_audio_frame_time] when `0.0` is passed
+    /// as its `cpu_hz` argument, so the rendered EAR/MIC and AY audio follows the same clock.
+    /// The number of T-states per frame remains the same. Set to `0` to clear the configuration.
+    ///
+    /// [AudioFrame::ensure_audio_frame_time]: crate::audio::AudioFrame::ensure_audio_frame_time
+    fn set_cpu_clock_rate(&mut self, _rate: u32) -> bool { false }
+    /// Returns the configured CPU clock rate or [HostConfig::CPU_HZ] if not configured.
+    fn effective_cpu_clock_rate(&self) -> u32
+        where Self: HostConfig + Sized
+    {
+        match self.cpu_clock_rate() {
+            0 => Self::CPU_HZ,
+            rate => rate
+        }
+    }
+    /// Returns the duration of a single execution frame in nanoseconds, derived from
+    /// [UlaControl::effective_cpu_clock_rate] and [HostConfig::FRAME_TSTATES].
+    fn cpu_frame_duration_nanos(&self) -> u32
+        where Self: HostConfig + Sized
+    {
+        nanos_from_frame_tc_cpu_hz(Self::FRAME_TSTATES as u32, self.effective_cpu_clock_rate()) as u32
+    }
+    /// Returns the precise number of execution frames per second, derived from
+    /// [UlaControl::effective_cpu_clock_rate] and [HostConfig::FRAME_TSTATES].
+    ///
+    /// E.g. for ZX Spectrum 48k it's `3_500_000 / 69_888` ≈ `50.08` Hz, not `50` Hz.
+    fn exact_frame_rate(&self) -> f64
+        where Self: HostConfig + Sized
+    {
+        self.effective_cpu_clock_rate() as f64 / Self::FRAME_TSTATES as f64
+    }
+    /// Returns `true` if the "snow" interference effect is being emulated.
+    fn has_snow_interference(&self) -> bool { false }
+    /// Enables or disables the emulation of the "snow" interference effect, which distorts the displayed
+    /// screen when the `I` register points to the contended memory. Returns `true` if supported.
+    /// Otherwise, returns `false` and the setting is ignored.
+    ///
+    /// By default, the effect is enabled on chipsets that support it.
+    fn set_snow_interference(&mut self, _enabled: bool) -> bool { false }
+    /// Returns `true` if border color changes are being rendered with the precision of a single T-state.
+    fn has_precise_border(&self) -> bool { false }
+    /// Enables or disables rendering of border color changes with the precision of a single T-state (2 pixels).
+    /// Returns `true` if supported. Otherwise, returns `false` and the setting is ignored.
+    ///
+    /// By default, border color changes are latched every 4 T-states (8 pixels), as on the original ULA.
+    /// Enable it for emulating hardware that updates the border immediately.
+    fn set_precise_border(&mut self, _enabled: bool) -> bool { false }
     /// Returns the last value sent to the memory port `0x7FFD` if supported.
     fn ula128_mem_port_value(&self) -> Option<Ula128MemFlags> { None }
     /// Sets the current value of the memory port `0x7FFD`. Returns `true` if supported.
@@ -96,9 +240,70 @@ pub trait UlaControl {
     /// Sets the current value of the memory port `0xFF3B`. Returns `true` if supported.
     /// Otherwise, returns `false` and no writing is performed.
     fn set_ulaplus_data_port_value(&mut self, _value: u8) -> bool { false }
+    /// Adds a memory write watchpoint for the given `range` of addresses. Returns `true` if supported.
+    /// Otherwise, returns `false` and no watchpoint is added.
+    ///
+    /// Memory writes to the watched addresses are reported to the hook set with
+    /// [UlaControl::set_write_watch_hook] at the moment they happen.
+    fn add_write_watchpoint(&mut self, _range: RangeInclusive<u16>) -> bool { false }
+    /// Removes all memory write watchpoints.
+    fn clear_write_watchpoints(&mut self) {}
+    /// Sets or clears the function called on memory writes to the watched addresses. Returns `true`
+    /// if supported. Otherwise, returns `false` and the `hook` is ignored.
+    ///
+    /// The `hook` is being called by the CPU memory write handler before the value is written to the
+    /// memory. When no hook is set, watched memory writes are not being reported.
+    ///
+    /// The hook is not a part of the serialized chipset state and is not being cloned with the chipset.
+    fn set_write_watch_hook(&mut self, _hook: Option<WriteWatchHook>) -> bool { false }
+    /// Returns the accumulated difference between the number of executed T-states and the number of
+    /// T-states of the audio frames finalized with [AudioFrame::end_audio_frame].
+    ///
+    /// A positive value indicates that some frames were executed without their audio being rendered,
+    /// and a negative value that some audio frames were finalized more than once. Either case leads to
+    /// audible clicks. The value is updated when the next frame begins and stays unchanged as long as
+    /// the audio is rendered exactly once after each frame.
+    ///
+    /// Returns `0` if not supported.
+    ///
+    /// [AudioFrame::end_audio_frame]: crate::audio::AudioFrame::end_audio_frame
+    fn audio_debt_tstates(&self) -> i32 { 0 }
+    /// Presses the given `keys` now and releases them automatically after `frames` next frames begin.
+    /// Returns `true` if supported. Otherwise, returns `false` and the keyboard state is not changed.
+    ///
+    /// Pressing a key that is already waiting to be released extends its hold if the new number of
+    /// `frames` is larger. `frames` equal to `0` is treated as `1`. Combinations of keys, e.g. with
+    /// the `CAPS SHIFT` or `SYMBOL SHIFT`, are pressed and released together.
+    fn press_key_for_frames(&mut self, _keys: ZXKeyboardMap, _frames: u32) -> bool { false }
+    /// Sets or clears the function called at the frame boundary. Returns `true` if supported.
+    /// Otherwise, returns `false` and the `hook` is ignored.
+    ///
+    /// The `hook` is being called right after [BusDevice::next_frame] has been propagated to the
+    /// attached bus devices and the frame counter has been increased, both from the methods executing
+    /// frames and from [ControlUnit::ensure_next_frame]. When no hook is set, nothing is being called.
+    ///
+    /// The hook is not a part of the serialized chipset state and is not being cloned with the chipset.
+    ///
+    /// [BusDevice::next_frame]: crate::bus::BusDevice::next_frame
+    fn set_frame_end_hook(&mut self, _hook: Option<FrameEndHook>) -> bool { false }
+    /// Sets or clears the function called when the guest software changes the memory paging.
+    /// Returns `true` if supported. Otherwise, returns `false` and the `hook` is ignored.
+    ///
+    /// The `hook` is being called from the memory paging I/O port handlers, once for each memory page
+    /// mapped to a different memory bank. Changes made with the [UlaControl] methods, e.g.
+    /// [UlaControl::set_ula128_mem_port_value], are not being reported. When no hook is set, the paging
+    /// is not being tracked.
+    ///
+    /// The hook is not a part of the serialized chipset state and is not being cloned with the chipset.
+    fn set_page_swap_hook(&mut self, _hook: Option<PageSwapHook>) -> bool { false }
+}
+
+impl<M: ZxMemory, B, X, T: MemoryContention> HostConfig for Ula<M, B, X, UlaVideoFrame, T> {
+    const CPU_HZ: u32 = ZxSpectrumPALConfig::CPU_HZ;
+    const FRAME_TSTATES: FTs = <Self as Video>::VideoFrame::FRAME_TSTATES_COUNT;
 }
 
-impl<M: ZxMemory, B, X> HostConfig for Ula<M, B, X, UlaVideoFrame> {
+impl<M: ZxMemory, B, X, T: MemoryContention> HostConfig for Ula<M, B, X, UlaVideoFrameLateTiming, T> {
     const CPU_HZ: u32 = ZxSpectrumPALConfig::CPU_HZ;
     const FRAME_TSTATES: FTs = <Self as Video>::VideoFrame::FRAME_TSTATES_COUNT;
 }
@@ -108,11 +313,26 @@ impl<M: PagedMemory8k, B, X> HostConfig for Scld<M, B, X, UlaVideoFrame> {
     const FRAME_TSTATES: FTs = <Self as Video>::VideoFrame::FRAME_TSTATES_COUNT;
 }
 
-impl<M: ZxMemory, B, X> HostConfig for UlaNTSC<M, B, X> {
+impl<M: ZxMemory, B, X, T: MemoryContention> HostConfig for Ula<M, B, X, UlaNTSCVidFrame, T> {
     const CPU_HZ: u32 = ZxSpectrumNTSCConfig::CPU_HZ;
     const FRAME_TSTATES: FTs = <Self as Video>::VideoFrame::FRAME_TSTATES_COUNT;
 }
 
+impl<M: ZxMemory, B, X, T: MemoryContention> HostConfig for Ula<M, B, X, PentagonVidFrame, T> {
+    const CPU_HZ: u32 = PentagonConfig::CPU_HZ;
+    const FRAME_TSTATES: FTs = <Self as Video>::VideoFrame::FRAME_TSTATES_COUNT;
+}
+
+impl<M: ZxMemory, B, X, T: MemoryContention> HostConfig for Ula<M, B, X, Ula128VidFrame, T> {
+    const CPU_HZ: u32 = ZxSpectrum128Config::CPU_HZ;
+    const FRAME_TSTATES: FTs = <Self as Video>::VideoFrame::FRAME_TSTATES_COUNT;
+}
+
+impl<M: ZxMemory, B, X, T: MemoryContention> HostConfig for Ula<M, B, X, Ula3VidFrame, T> {
+    const CPU_HZ: u32 = ZxSpectrum128Config::CPU_HZ;
+    const FRAME_TSTATES: FTs = <Self as Video>::VideoFrame::FRAME_TSTATES_COUNT;
+}
+
 impl<B, X> HostConfig for Ula128<B, X> {
     const CPU_HZ: u32 = ZxSpectrum128Config::CPU_HZ;
     const FRAME_TSTATES: FTs = <Self as Video>::VideoFrame::FRAME_TSTATES_COUNT;
@@ -144,7 +364,10 @@ mod tests {
     use core::mem::size_of;
     use crate::memory::*;
     use crate::bus::VFNullDevice;
-    use crate::chip::{UlaVideoFrame, UlaNTSCVidFrame};
+    use crate::chip::{UlaVideoFrame, UlaNTSCVidFrame, PentagonVidFrame};
+    use crate::chip::ula::UlaVideoFrameLateTiming;
+    use crate::clock::Ts;
+    use crate::video::{BorderSize, VideoFrame};
     use super::ula::frame_cache::UlaFrameCache;
     use super::ula::{UlaPAL, UlaNTSC};
     use super::scld::Scld;
@@ -154,6 +377,45 @@ mod tests {
 
     type TC2048 = Scld::<Memory48kDock64kEx, VFNullDevice<UlaVideoFrame>, NoMemoryExtension, UlaVideoFrame>;
 
+    fn screen_size_matches_renderer<V: VideoFrame>() {
+        use BorderSize::*;
+        for &border_size in &[Overscan, Full, Large, Medium, Small, Tiny, Minimal, Nil] {
+            let (width, height) = V::screen_size_pixels(border_size);
+            let border_width = V::border_left_hts_iter(border_size).count() +
+                               V::border_right_hts_iter(border_size).count();
+            assert_eq!(width as usize, 256 + border_width * 8);
+            let lines = V::border_top_vsl_iter(border_size).len() +
+                        V::VSL_PIXELS.len() +
+                        V::border_bot_vsl_iter(border_size).len();
+            assert_eq!(height as usize, lines);
+            if border_size == BorderSize::Nil {
+                assert_eq!((width, height), (256, 192));
+            }
+            if border_size == BorderSize::Overscan {
+                assert_eq!(height as Ts, V::VSL_COUNT);
+                assert!(width as Ts >= 2 * V::HTS_COUNT);
+                assert_eq!(V::border_whole_line_hts_iter(border_size).count() * 8, width as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn test_screen_size_pixels() {
+        screen_size_matches_renderer::<UlaVideoFrame>();
+        screen_size_matches_renderer::<UlaVideoFrameLateTiming>();
+        screen_size_matches_renderer::<UlaNTSCVidFrame>();
+        screen_size_matches_renderer::<PentagonVidFrame>();
+        screen_size_matches_renderer::<Ula128VidFrame>();
+        screen_size_matches_renderer::<Ula3VidFrame>();
+        assert_eq!(UlaVideoFrame::screen_size_pixels(BorderSize::Full), (352, 288));
+        assert_eq!(UlaNTSCVidFrame::screen_size_pixels(BorderSize::Full), (352, 240));
+        assert_eq!(Ula128VidFrame::screen_size_pixels(BorderSize::Full), (352, 288));
+        assert_eq!(UlaVideoFrame::screen_size_pixels(BorderSize::Overscan), (448, 312));
+        assert_eq!(UlaNTSCVidFrame::screen_size_pixels(BorderSize::Overscan), (448, 264));
+        assert_eq!(Ula128VidFrame::screen_size_pixels(BorderSize::Overscan), (456, 311));
+        assert_eq!(PentagonVidFrame::screen_size_pixels(BorderSize::Overscan), (448, 320));
+    }
+
     #[test]
     fn test_chip_sizes() {
         println!("ULA     {:?}", size_of::<UlaPAL::<Memory48k>>());
@@ -190,4 +452,27 @@ mod tests {
             assert!(size_of::<UlaPlus<Ula3>>() > size_of::<UlaFrameCache<Ula3VidFrame>>() * 4);
         }
     }
+
+    #[test]
+    fn screen_view_works() {
+        use crate::chip::{FrameState, MemoryAccess, UlaCommon};
+        use crate::video::{BorderColor, Video};
+        let mut ula: Ula128 = Ula128::default();
+        ula.set_border_color(BorderColor::RED);
+        let screen = ula.memory_mut().screen_mut(1).unwrap();
+        screen[0] = 0xAA;
+        screen[0x1800] = 0x55;
+        assert_eq!(ula.visible_screen_bank(), 0);
+        let view = ula.screen_view(ula.visible_screen_bank()).unwrap();
+        assert_eq!((view.pixels.len(), view.attrs.len()), (6144, 768));
+        assert_eq!(view.pixels, &ula.memory_ref().screen_ref(0).unwrap()[..6144]);
+        assert_eq!(view.attrs, &ula.memory_ref().screen_ref(0).unwrap()[6144..]);
+        assert_eq!(view.border, BorderColor::RED);
+        assert!(!view.flash);
+        ula.set_frame_counter(16);
+        let view = ula.screen_view(1).unwrap();
+        assert_eq!((view.pixels[0], view.attrs[0]), (0xAA, 0x55));
+        assert!(view.flash);
+        assert!(ula.screen_view(4).is_err());
+    }
 }
@@ -65,6 +65,9 @@ In 48k mode, there are two available screen memory areas:
 [ZxMemory::screen_mut]: crate::memory::ZxMemory::screen_mut
 */
 use core::fmt;
+use core::iter::Copied;
+use core::ops::RangeInclusive;
+use core::slice;
 
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
@@ -83,9 +86,9 @@ use crate::clock::{
     VideoTsData2, VideoTsData6
 };
 use crate::chip::{
-    ControlUnit, MemoryAccess,
+    ControlUnit, FrameStats, MemoryAccess,
     UlaPortFlags, ScldCtrlFlags, UlaPlusRegFlags, ColorMode, Ula128MemFlags, Ula3CtrlFlags,
-    UlaControl,
+    UlaControl, WriteWatchHook, FrameEndHook, PageSwapHook,
     InnerAccess,
     scld::frame_cache::SourceMode,
     ula::{
@@ -98,6 +101,7 @@ use crate::video::{
     VideoFrame
 };
 use crate::memory::{ZxMemory, MemoryExtension};
+use crate::peripherals::ZXKeyboardMap;
 
 /*
   G G i i i h a s UlaPlusRegFlags
@@ -132,26 +136,26 @@ pub struct UlaPlus<U: Video> {
     cur_palette: UlaPlusPalette,
 
     #[cfg(feature = "boxed_frame_cache")]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     sec_frame_cache: Box<UlaFrameCache<U::VideoFrame>>,
 
     #[cfg(feature = "boxed_frame_cache")]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     shadow_sec_frame_cache: Box<UlaFrameCache<U::VideoFrame>>,
 
     #[cfg(not(feature = "boxed_frame_cache"))]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     sec_frame_cache: UlaFrameCache<U::VideoFrame>,
 
     #[cfg(not(feature = "boxed_frame_cache"))]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     shadow_sec_frame_cache: UlaFrameCache<U::VideoFrame>,
 
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     palette_changes: Vec<PaletteChange>,
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     mode_changes: Vec<VideoTsData6>,
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     source_changes: Vec<VideoTsData2>,
 }
 
@@ -178,6 +182,10 @@ pub trait UlaPlusInner<'a>: Video + MemoryAccess {
     fn ula_write_earmic(&mut self, flags: UlaPortFlags, ts: VideoTs);
     /// Records a shadow screen swap.
     fn push_screen_change(&mut self, ts: VideoTs);
+    /// Records a memory write if the `addr` is being watched.
+    fn watch_write(&mut self, addr: u16, value: u8, ts: VideoTs);
+    /// Writes a byte to the memory via the memory extension.
+    fn write_mem_ext(&mut self, addr: u16, value: u8);
     /// Updates the border color, returns `true` if the border color has changed.
     fn update_last_border_color(&mut self, border: BorderColor) -> bool;
     /// Returns `Some(is_shadow)` if a screen memory is accessible at page address: 0x4000-0x5FFF.
@@ -200,6 +208,10 @@ pub trait UlaPlusInner<'a>: Video + MemoryAccess {
     fn video_render_data_view(
         &'a mut self
     ) -> VideoRenderDataView<'a, Self::ScreenSwapIter, Self::Memory, Self::VideoFrame>;
+    /// Returns references to components necessary for video rendering without draining the recorded changes.
+    fn video_render_data_ref(
+        &'a self
+    ) -> VideoRenderDataView<'a, Copied<slice::Iter<'a, VideoTs>>, Self::Memory, Self::VideoFrame>;
 }
 
 impl<U> Default for UlaPlus<U>
@@ -282,6 +294,66 @@ impl<'a, U> UlaControl for UlaPlus<U>
         self.ula.set_late_timings(late_timings)
     }
 
+    fn interrupt_length(&self) -> u32 {
+        self.ula.interrupt_length()
+    }
+
+    fn set_interrupt_length(&mut self, length: u32) -> bool {
+        self.ula.set_interrupt_length(length)
+    }
+
+    fn audio_sample_rate(&self) -> u32 {
+        self.ula.audio_sample_rate()
+    }
+
+    fn set_audio_sample_rate(&mut self, rate: u32) -> bool {
+        self.ula.set_audio_sample_rate(rate)
+    }
+
+    fn cpu_clock_rate(&self) -> u32 {
+        self.ula.cpu_clock_rate()
+    }
+
+    fn set_cpu_clock_rate(&mut self, rate: u32) -> bool {
+        self.ula.set_cpu_clock_rate(rate)
+    }
+
+    fn has_snow_interference(&self) -> bool {
+        self.ula.has_snow_interference()
+    }
+
+    fn set_snow_interference(&mut self, enabled: bool) -> bool {
+        self.ula.set_snow_interference(enabled)
+    }
+
+    fn add_write_watchpoint(&mut self, range: RangeInclusive<u16>) -> bool {
+        self.ula.add_write_watchpoint(range)
+    }
+
+    fn clear_write_watchpoints(&mut self) {
+        self.ula.clear_write_watchpoints()
+    }
+
+    fn press_key_for_frames(&mut self, keys: ZXKeyboardMap, frames: u32) -> bool {
+        self.ula.press_key_for_frames(keys, frames)
+    }
+
+    fn set_frame_end_hook(&mut self, hook: Option<FrameEndHook>) -> bool {
+        self.ula.set_frame_end_hook(hook)
+    }
+
+    fn set_page_swap_hook(&mut self, hook: Option<PageSwapHook>) -> bool {
+        self.ula.set_page_swap_hook(hook)
+    }
+
+    fn set_write_watch_hook(&mut self, hook: Option<WriteWatchHook>) -> bool {
+        self.ula.set_write_watch_hook(hook)
+    }
+
+    fn audio_debt_tstates(&self) -> i32 {
+        self.ula.audio_debt_tstates()
+    }
+
     fn ula128_mem_port_value(&self) -> Option<Ula128MemFlags> {
         self.ula.ula128_mem_port_value()
     }
@@ -569,6 +641,10 @@ impl<U, B, X> ControlUnit for UlaPlus<U>
     {
         self.ula_execute_single_step(cpu, debug)
     }
+
+    fn run_frame_stats<C: Cpu>(&mut self, cpu: &mut C) -> FrameStats {
+        self.ula_run_frame_stats(cpu)
+    }
 }
 
 impl<U> UlaControlExt for UlaPlus<U>
@@ -591,3 +667,41 @@ impl<U> UlaControlExt for UlaPlus<U>
         self.ula.prepare_next_frame(vtsc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::chip::ula::UlaPAL;
+    use crate::memory::Memory48k;
+    use super::*;
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_ulaplus_snapshot_mid_frame() {
+        use crate::video::{BorderSize, pixel::{PixelBufA24, SpectrumPalRGB24}};
+        type TestUla = UlaPlus<UlaPAL<Memory48k>>;
+        let mut ula = TestUla::default();
+        ula.memory_mut().screen_mut(0).unwrap().iter_mut().for_each(|p| *p = 0x55);
+        // enable the palette mode and change the palette mid-frame
+        for (vc, port, data) in [(100, 0xBF3B, 0x40), (100, 0xFF3B, 0x01),
+                                 (150, 0xBF3B, 0x08), (150, 0xFF3B, 0xE3)] {
+            let ts = VideoTs::new(vc, 0);
+            ula.set_video_ts(ts);
+            ula.write_io(port, data, ts);
+        }
+        ula.set_video_ts(VideoTs::new(250, 0));
+
+        let sernap = serde_json::to_string(&ula).unwrap();
+        let mut ula_de: TestUla = serde_json::from_str(&sernap).unwrap();
+        assert_eq!(ula_de.palette_changes, ula.palette_changes);
+        assert_eq!(ula_de.mode_changes, ula.mode_changes);
+
+        let border_size = BorderSize::Full;
+        let (width, height) = TestUla::render_size_pixels(border_size);
+        let pitch = width as usize * 3;
+        let mut buffer = vec![0u8; pitch * height as usize];
+        let mut buffer_de = vec![0u8; pitch * height as usize];
+        ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer, pitch, border_size);
+        ula_de.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer_de, pitch, border_size);
+        assert!(buffer == buffer_de);
+    }
+}
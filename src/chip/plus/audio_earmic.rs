@@ -11,7 +11,7 @@ use crate::audio::*;
 use crate::peripherals::ay::audio::AyAudioFrame;
 use crate::video::Video;
 use crate::chip::{
-    EarIn, MicOut, ReadEarMode,
+    EarIn, MicOut, EarMicOut, ReadEarMode,
 };
 use super::UlaPlus;
 
@@ -39,6 +39,11 @@ impl<A, U> AudioFrame<A> for UlaPlus<U>
     fn get_audio_frame_end_time(&self) -> FTs {
         self.ula.get_audio_frame_end_time()
     }
+
+    #[inline]
+    fn end_audio_frame(&self, blep: &mut A) -> usize {
+        self.ula.end_audio_frame(blep)
+    }
 }
 
 impl<A, U> EarMicOutAudioFrame<A> for UlaPlus<U>
@@ -59,6 +64,18 @@ impl<A, U> EarInAudioFrame<A> for UlaPlus<U>
     fn render_ear_in_audio_frame<V: AmpLevels<A::SampleDelta>>(&self, blep: &mut A, channel: usize) {
         self.ula.render_ear_in_audio_frame::<V>(blep, channel)
     }
+
+    #[inline(always)]
+    fn render_ear_in_audio_frame_smooth<V: AmpLevels<A::SampleDelta>>(
+            &self,
+            blep: &mut A,
+            channel: usize,
+            transition_ts: u32
+        )
+        where A::SampleDelta: MulNorm + FromSample<f32>
+    {
+        self.ula.render_ear_in_audio_frame_smooth::<V>(blep, channel, transition_ts)
+    }
 }
 
 impl<U> EarIn for UlaPlus<U>
@@ -82,6 +99,10 @@ impl<U> EarIn for UlaPlus<U>
         self.ula.read_ear_in_count()
     }
 
+    fn frames_since_last_ear_read(&self) -> u32 {
+        self.ula.frames_since_last_ear_read()
+    }
+
     fn read_ear_mode(&self) -> ReadEarMode {
         self.ula.read_ear_mode()
     }
@@ -99,3 +120,12 @@ impl<'a, U: 'a> MicOut<'a> for UlaPlus<U>
         self.ula.mic_out_pulse_iter()
     }
 }
+
+impl<'a, U: 'a> EarMicOut<'a> for UlaPlus<U>
+    where U: Video + EarMicOut<'a>
+{
+    type EarMicIter = <U as EarMicOut<'a>>::EarMicIter;
+    fn earmic_out_iter(&'a self) -> Self::EarMicIter {
+        self.ula.earmic_out_iter()
+    }
+}
@@ -15,7 +15,6 @@ use crate::chip::{
     scld::io::ScldCtrlPortAddress
 };
 use crate::peripherals::{KeyboardInterface, ZXKeyboardMap};
-use crate::memory::ZxMemory;
 use crate::video::{Video, BorderColor};
 use super::{UlaPlus, UlaPlusInner};
 
@@ -110,7 +109,8 @@ impl<'a, U> Memory for UlaPlus<U>
     #[inline]
     fn write_mem(&mut self, addr: u16, val: u8, ts: VideoTs) {
         self.update_frame_cache(addr, ts);
-        self.ula.memory_mut().write(addr, val);
+        self.ula.watch_write(addr, val, ts);
+        self.ula.write_mem_ext(addr, val);
     }
 }
 
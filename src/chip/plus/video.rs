@@ -5,12 +5,15 @@
 
     For the full copyright notice, see the lib.rs file.
 */
+use core::iter::Copied;
+use core::ops::Range;
+use core::slice;
 use std::vec::Drain;
 
-use crate::memory::ZxMemory;
-use crate::clock::{VideoTs, VideoTsData2, VideoTsData6, VFrameTsCounter};
+use crate::memory::{ZxMemory, ScreenArray};
+use crate::clock::{VideoTs, Ts, VideoTsData2, VideoTsData6, VFrameTsCounter};
 use crate::video::{
-    RendererPlus, PaletteChange, BorderSize, BorderColor, PixelBuffer, Palette, Video,
+    RendererPlus, UlaPlusPalette, PaletteChange, BorderSize, BorderColor, PixelBuffer, Palette, Video,
     frame_cache::{
         pixel_address_coords, color_address_coords
     }
@@ -48,6 +51,19 @@ impl<U> Video for UlaPlus<U>
         renderer.render_pixels::<B, P, U::VideoFrame>(buffer, pitch);
     }
 
+    fn render_video_scanlines<'b, B: PixelBuffer<'b>, P: Palette<Pixel=B::Pixel>>(
+            &mut self,
+            buffer: &'b mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            vsl_range: Range<Ts>
+        )
+    {
+        let mut palette = self.beg_palette;
+        let renderer = self.create_scanlines_renderer(border_size, &mut palette);
+        renderer.render_pixels_range::<B, P, U::VideoFrame>(buffer, pitch, vsl_range);
+    }
+
     fn current_video_ts(&self) -> VideoTs {
         self.ula.current_video_ts()
     }
@@ -67,6 +83,10 @@ impl<U> Video for UlaPlus<U>
     fn flash_state(&self) -> bool {
         self.ula.flash_state()
     }
+
+    fn floating_bus_value(&self) -> u8 {
+        self.ula.floating_bus_value()
+    }
 }
 
 impl<'a, U> UlaPlus<U>
@@ -151,15 +171,7 @@ impl<'a, U> UlaPlus<U>
             frame_cache: frame_cache0,
             frame_cache_shadow: frame_cache_shadow0
         } = self.ula.video_render_data_view();
-        let (s0p0, s0p1, s1p0, s1p1) = match U::Memory::SCR_BANKS_MAX {
-            3 => (0, 1, 2, 3),
-            1 => (0, 0, 1, 1),
-            _ => panic!("unexpected number of screen banks")
-        };
-        let screen0        = memory.screen_ref(s0p0).unwrap();
-        let screen_shadow0 = memory.screen_ref(s0p1).unwrap();
-        let screen1        = memory.screen_ref(s1p0).unwrap();
-        let screen_shadow1 = memory.screen_ref(s1p1).unwrap();
+        let (screen0, screen_shadow0, screen1, screen_shadow1) = screens_ref(memory);
         let palette = &mut self.beg_palette;
         let frame_cache1 = &mut self.sec_frame_cache;
         let frame_cache_shadow1 = &mut self.shadow_sec_frame_cache;
@@ -183,4 +195,62 @@ impl<'a, U> UlaPlus<U>
             invert_flash
         }
     }
+
+    #[allow(clippy::type_complexity)]
+    fn create_scanlines_renderer<'r>(
+            &'a self,
+            border_size: BorderSize,
+            palette: &'r mut UlaPlusPalette
+        ) -> RendererPlus<'r,
+                PlusFrameProducer<'a,
+                    U::VideoFrame,
+                    Copied<slice::Iter<'a, VideoTsData2>>,
+                    Copied<slice::Iter<'a, VideoTs>>>,
+                Copied<slice::Iter<'a, VideoTsData6>>,
+                Copied<slice::Iter<'a, PaletteChange>>>
+    {
+        let invert_flash = self.ula.flash_state();
+        let render_mode = self.beg_render_mode;
+        let source_mode = self.beg_source_mode;
+        let swap_screens = source_mode.is_shadow_bank() ^ self.ula.beg_screen_shadow();
+        let VideoRenderDataView {
+            screen_changes,
+            memory,
+            frame_cache: frame_cache0,
+            frame_cache_shadow: frame_cache_shadow0
+        } = self.ula.video_render_data_ref();
+        let (screen0, screen_shadow0, screen1, screen_shadow1) = screens_ref(memory);
+        let frame_image_producer = PlusFrameProducer::new(
+            swap_screens,
+            source_mode,
+            screen0, frame_cache0,
+            screen1, &self.sec_frame_cache,
+            screen_shadow0, frame_cache_shadow0,
+            screen_shadow1, &self.shadow_sec_frame_cache,
+            screen_changes,
+            self.source_changes.iter().copied());
+
+        RendererPlus {
+            render_mode,
+            palette,
+            frame_image_producer,
+            mode_changes: self.mode_changes.iter().copied(),
+            palette_changes: self.palette_changes.iter().copied(),
+            border_size,
+            invert_flash
+        }
+    }
+}
+
+/// Returns references to the normal and shadow screens: (screen0, shadow0, screen1, shadow1).
+fn screens_ref<M: ZxMemory>(memory: &M) -> (&ScreenArray, &ScreenArray, &ScreenArray, &ScreenArray) {
+    let (s0p0, s0p1, s1p0, s1p1) = match M::SCR_BANKS_MAX {
+        3 => (0, 1, 2, 3),
+        1 => (0, 0, 1, 1),
+        _ => panic!("unexpected number of screen banks")
+    };
+    (memory.screen_ref(s0p0).unwrap(),
+     memory.screen_ref(s0p1).unwrap(),
+     memory.screen_ref(s1p0).unwrap(),
+     memory.screen_ref(s1p1).unwrap())
 }
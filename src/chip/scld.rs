@@ -28,6 +28,8 @@ In case of Tx2068 the 24k ROM should be loaded to ROM banks: `[ROM_BANKS_MAX - 1
 [UlaNTSCVidFrame]: crate::chip::ula::UlaNTSCVidFrame
 [ZxMemory::ROM_BANKS_MAX]: crate::memory::ZxMemory::ROM_BANKS_MAX
 */
+use core::ops::RangeInclusive;
+
 use crate::z80emu::{*, host::Result};
 use crate::clock::{
     VFrameTs, VideoTs,
@@ -36,8 +38,8 @@ use crate::clock::{
 };
 use crate::bus::{BusDevice};
 use crate::chip::{
-    ScldCtrlFlags, UlaControl,
-    InnerAccess, EarIn, ReadEarMode, ControlUnit, MemoryAccess,
+    ScldCtrlFlags, UlaControl, WriteWatchHook, FrameEndHook,
+    InnerAccess, EarIn, ReadEarMode, ControlUnit, FrameStats, MemoryAccess,
     ula::{
         Ula,
         UlaControlExt, UlaCpuExt,
@@ -48,6 +50,7 @@ use crate::memory::{
     PagedMemory8k, MemoryExtension
 };
 use crate::video::{Video, VideoFrame, BorderColor, RenderMode};
+use crate::peripherals::ZXKeyboardMap;
 
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
@@ -83,16 +86,16 @@ pub struct Scld<M: PagedMemory8k, B, X, V>
     mem_paged: u8,
 
     #[cfg(feature = "boxed_frame_cache")]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     sec_frame_cache: Box<UlaFrameCache<V>>,
 
     #[cfg(not(feature = "boxed_frame_cache"))]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     sec_frame_cache: UlaFrameCache<V>,
 
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     mode_changes: Vec<VideoTsData6>,
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     source_changes: Vec<VideoTsData2>,
 }
 
@@ -165,6 +168,54 @@ impl<M, B, X, V> UlaControl for Scld<M, B, X, V>
         self.ula.set_late_timings(late_timings)
     }
 
+    fn interrupt_length(&self) -> u32 {
+        self.ula.interrupt_length()
+    }
+
+    fn set_interrupt_length(&mut self, length: u32) -> bool {
+        self.ula.set_interrupt_length(length)
+    }
+
+    fn audio_sample_rate(&self) -> u32 {
+        self.ula.audio_sample_rate()
+    }
+
+    fn set_audio_sample_rate(&mut self, rate: u32) -> bool {
+        self.ula.set_audio_sample_rate(rate)
+    }
+
+    fn cpu_clock_rate(&self) -> u32 {
+        self.ula.cpu_clock_rate()
+    }
+
+    fn set_cpu_clock_rate(&mut self, rate: u32) -> bool {
+        self.ula.set_cpu_clock_rate(rate)
+    }
+
+    fn add_write_watchpoint(&mut self, range: RangeInclusive<u16>) -> bool {
+        self.ula.add_write_watchpoint(range)
+    }
+
+    fn clear_write_watchpoints(&mut self) {
+        self.ula.clear_write_watchpoints()
+    }
+
+    fn press_key_for_frames(&mut self, keys: ZXKeyboardMap, frames: u32) -> bool {
+        self.ula.press_key_for_frames(keys, frames)
+    }
+
+    fn set_frame_end_hook(&mut self, hook: Option<FrameEndHook>) -> bool {
+        self.ula.set_frame_end_hook(hook)
+    }
+
+    fn set_write_watch_hook(&mut self, hook: Option<WriteWatchHook>) -> bool {
+        self.ula.set_write_watch_hook(hook)
+    }
+
+    fn audio_debt_tstates(&self) -> i32 {
+        self.ula.audio_debt_tstates()
+    }
+
     fn scld_ctrl_port_value(&self) -> Option<ScldCtrlFlags> {
         Some(self.cur_ctrl_flags)
     }
@@ -361,6 +412,10 @@ impl<M, B, X, V> ControlUnit for Scld<M, B, X, V>
     {
         self.ula_execute_single_step(cpu, debug)
     }
+
+    fn run_frame_stats<C: Cpu>(&mut self, cpu: &mut C) -> FrameStats {
+        self.ula_run_frame_stats(cpu)
+    }
 }
 
 impl<M, B, X, V> UlaControlExt for Scld<M, B, X, V>
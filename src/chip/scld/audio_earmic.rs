@@ -15,7 +15,7 @@ use crate::bus::BusDevice;
 use crate::clock::VFrameTs;
 use crate::memory::PagedMemory8k;
 use crate::chip::{
-    EarIn, MicOut, ReadEarMode,
+    EarIn, MicOut, EarMicOut, ReadEarMode,
     ula::Ula
 };
 use crate::video::VideoFrame;
@@ -49,6 +49,11 @@ impl<A, M, B, X, V> AudioFrame<A> for Scld<M, B, X, V>
     fn get_audio_frame_end_time(&self) -> FTs {
         self.ula.get_audio_frame_end_time()
     }
+
+    #[inline]
+    fn end_audio_frame(&self, blep: &mut A) -> usize {
+        self.ula.end_audio_frame(blep)
+    }
 }
 
 impl<A, M, B, X, F> EarMicOutAudioFrame<A> for Scld<M, B, X, F>
@@ -71,6 +76,18 @@ impl<A, M, B, X, F> EarInAudioFrame<A> for Scld<M, B, X, F>
     fn render_ear_in_audio_frame<V: AmpLevels<A::SampleDelta>>(&self, blep: &mut A, channel: usize) {
         self.ula.render_ear_in_audio_frame::<V>(blep, channel)
     }
+
+    #[inline(always)]
+    fn render_ear_in_audio_frame_smooth<V: AmpLevels<A::SampleDelta>>(
+            &self,
+            blep: &mut A,
+            channel: usize,
+            transition_ts: u32
+        )
+        where A::SampleDelta: MulNorm + FromSample<f32>
+    {
+        self.ula.render_ear_in_audio_frame_smooth::<V>(blep, channel, transition_ts)
+    }
 }
 
 impl<M, B, X, V> EarIn for Scld<M, B, X, V>
@@ -95,6 +112,10 @@ impl<M, B, X, V> EarIn for Scld<M, B, X, V>
         self.ula.read_ear_in_count()
     }
 
+    fn frames_since_last_ear_read(&self) -> u32 {
+        self.ula.frames_since_last_ear_read()
+    }
+
     fn read_ear_mode(&self) -> ReadEarMode {
         self.ula.read_ear_mode()
     }
@@ -113,3 +134,13 @@ impl<'a, M: 'a, B: 'a, X: 'a, V: 'a> MicOut<'a> for Scld<M, B, X, V>
         self.ula.mic_out_pulse_iter()
     }
 }
+
+impl<'a, M: 'a, B: 'a, X: 'a, V: 'a> EarMicOut<'a> for Scld<M, B, X, V>
+    where M: PagedMemory8k,
+          V: VideoFrame
+{
+    type EarMicIter = <Ula<M, B, X, V> as EarMicOut<'a>>::EarMicIter;
+    fn earmic_out_iter(&'a self) -> Self::EarMicIter {
+        self.ula.earmic_out_iter()
+    }
+}
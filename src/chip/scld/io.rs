@@ -126,7 +126,8 @@ impl<M, B, X, V> Memory for Scld<M, B, X, V>
     #[inline]
     fn write_mem(&mut self, addr: u16, val: u8, ts: VideoTs) {
         self.update_frame_cache(addr, ts);
-        self.ula.memory.write(addr, val);
+        self.ula.watch_write(addr, val, ts);
+        self.ula.memext.write_mem(addr, val, &mut self.ula.memory);
     }
 }
 
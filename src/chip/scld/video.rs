@@ -5,11 +5,13 @@
 
     For the full copyright notice, see the lib.rs file.
 */
-use core::iter::{self, Empty};
+use core::iter::{self, Copied, Empty};
+use core::ops::Range;
+use core::slice;
 use std::vec::Drain;
 
 use crate::memory::PagedMemory8k;
-use crate::clock::{VideoTs, VideoTsData2, VideoTsData6, VFrameTsCounter};
+use crate::clock::{VideoTs, Ts, VideoTsData2, VideoTsData6, VFrameTsCounter};
 use crate::video::{
     RendererPlus, UlaPlusPalette, PaletteChange, BorderSize, BorderColor, PixelBuffer, Palette,
     VideoFrame, Video,
@@ -52,6 +54,19 @@ impl<M, D, X, V> Video for Scld<M, D, X, V>
             .render_pixels::<B, P, V>(buffer, pitch)
     }
 
+    fn render_video_scanlines<'a, B: PixelBuffer<'a>, P: Palette<Pixel=B::Pixel>>(
+            &mut self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            vsl_range: Range<Ts>
+        )
+    {
+        let mut palette = UlaPlusPalette::default();
+        self.create_scanlines_renderer(border_size, &mut palette)
+            .render_pixels_range::<B, P, V>(buffer, pitch, vsl_range)
+    }
+
     fn current_video_ts(&self) -> VideoTs {
         self.ula.current_video_ts()
     }
@@ -120,4 +135,34 @@ impl<M, D, X, V> Scld<M, D, X, V>
             invert_flash
         }
     }
+
+    #[allow(clippy::type_complexity)]
+    fn create_scanlines_renderer<'a, 'r>(
+            &'a self,
+            border_size: BorderSize,
+            palette: &'r mut UlaPlusPalette
+        ) -> RendererPlus<'r, ScldFrameProducer<'a, V, Copied<slice::Iter<'a, VideoTsData2>>>,
+                          Copied<slice::Iter<'a, VideoTsData6>>,
+                          Empty<PaletteChange>>
+    {
+        let render_mode = self.beg_render_mode();
+        let invert_flash = self.flash_state();
+        let screen0 = self.ula.memory.screen_ref(0).unwrap();
+        let screen1 = self.ula.memory.screen_ref(1).unwrap();
+        let frame_image_producer = ScldFrameProducer::new(
+            SourceMode::from_scld_flags(self.beg_ctrl_flags),
+            screen0, &self.ula.frame_cache,
+            screen1, &self.sec_frame_cache,
+            self.source_changes.iter().copied());
+
+        RendererPlus {
+            render_mode,
+            palette,
+            frame_image_producer,
+            mode_changes: self.mode_changes.iter().copied(),
+            palette_changes: iter::empty(),
+            border_size,
+            invert_flash
+        }
+    }
 }
@@ -14,13 +14,18 @@ mod earmic;
 pub mod frame_cache;
 mod io;
 mod video;
+mod video_late;
 mod video_ntsc;
+mod video_pentagon;
 mod plus;
 mod cpuext;
 #[cfg(feature = "formats")]
 mod screen;
 
+use core::convert::TryInto;
 use core::num::Wrapping;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::ops::RangeInclusive;
 
 #[allow(unused_imports)]
 use log::{error, warn, info, debug, trace};
@@ -31,25 +36,81 @@ use serde::{Serialize, Deserialize};
 
 use crate::bus::{BusDevice, VFNullDevice};
 use crate::chip::{
-    UlaControl, FrameState, ControlUnit, MemoryAccess, EarMic, ReadEarMode
+    UlaControl, FrameState, FrameStats, ControlUnit, MemoryAccess, EarMic, ReadEarMode, WriteWatchHook,
+    FrameEndHook, PageSwapHook
 };
 use crate::video::{BorderColor, VideoFrame};
-use crate::memory::{ZxMemory, MemoryExtension, NoMemoryExtension};
+use crate::memory::{ZxMemory, MemoryKind, MemoryExtension, NoMemoryExtension};
 use crate::peripherals::ZXKeyboardMap;
 use crate::clock::{
-    FTs, VFrameTs, VFrameTsCounter, MemoryContention,
+    FTs, Ts, VideoTs, VFrameTs, VFrameTsCounter, MemoryContention,
     VideoTsData1, VideoTsData2, VideoTsData3
 };
 use frame_cache::UlaFrameCache;
 
 pub use cpuext::*;
 pub use video::UlaVideoFrame;
+pub use video_late::UlaVideoFrameLateTiming;
 pub use video_ntsc::UlaNTSCVidFrame;
+pub use video_pentagon::PentagonVidFrame;
 
 /// NTSC 16k/48k ULA (Uncommitted Logic Array).
 pub type UlaNTSC<M, B=VFNullDevice<UlaNTSCVidFrame>, X=NoMemoryExtension> = Ula<M, B, X, UlaNTSCVidFrame>;
 /// PAL 16k/48k ULA (Uncommitted Logic Array).
 pub type UlaPAL<M, B=VFNullDevice<UlaVideoFrame>, X=NoMemoryExtension> = Ula<M, B, X, UlaVideoFrame>;
+/// PAL 16k/48k ULA (Uncommitted Logic Array) with the "late" interrupt timing.
+pub type UlaPALLate<M, B=VFNullDevice<UlaVideoFrameLateTiming>, X=NoMemoryExtension> = Ula<M, B, X, UlaVideoFrameLateTiming>;
+/// 16k/48k ULA with Pentagon 128 timings and without memory contention.
+pub type UlaPentagon<M, B=VFNullDevice<PentagonVidFrame>, X=NoMemoryExtension> = Ula<M, B, X, PentagonVidFrame>;
+
+// The number of audio frames ended during the current frame, counted atomically so Ula stays Sync.
+#[derive(Debug, Default)]
+pub(super) struct AudioFramesEnded(AtomicU32);
+
+impl Clone for AudioFramesEnded {
+    fn clone(&self) -> Self {
+        AudioFramesEnded(AtomicU32::new(self.get()))
+    }
+}
+
+impl AudioFramesEnded {
+    #[inline]
+    pub(super) fn get(&self) -> u32 {
+        self.0.load(Ordering::Relaxed)
+    }
+    /// Increments the counter and returns the previous value.
+    #[inline]
+    pub(super) fn increment(&self) -> u32 {
+        self.0.fetch_add(1, Ordering::Relaxed)
+    }
+    /// Resets the counter and returns the previous value.
+    #[inline]
+    pub(super) fn take(&mut self) -> u32 {
+        core::mem::take(self.0.get_mut())
+    }
+}
+
+// An optional hook closure, which is not cloned with the chipset.
+pub(super) struct UlaHook<F>(pub(super) Option<F>);
+
+impl<F> Default for UlaHook<F> {
+    fn default() -> Self {
+        UlaHook(None)
+    }
+}
+
+impl<F> Clone for UlaHook<F> {
+    fn clone(&self) -> Self {
+        UlaHook(None)
+    }
+}
+
+impl<F> UlaHook<F> {
+    #[inline]
+    pub(super) fn is_some(&self) -> bool {
+        self.0.is_some()
+    }
+}
 
 /// A struct implementing [MemoryContention] for addresses in the range: [0x4000, 0x7FFF] being contended.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
@@ -61,13 +122,17 @@ pub struct UlaMemoryContention;
 /// * `B` - [BusDevice]
 /// * `X` - [MemoryExtension]
 /// * `V` - [VideoFrame]
+/// * `T` - [MemoryContention], [UlaMemoryContention] by default. Use [NoContention] to run the chipset
+///   without memory contention, e.g. for benchmarking or accuracy testing.
+///
+/// [NoContention]: crate::clock::NoContention
 ///
 /// The type used for [`<B as BusDevice>::Timestamp`][BusDevice::Timestamp] should at least
 /// satisfy the condition: `From<VFrameTs<V>>`.
 #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
 #[derive(Clone)]
-pub struct Ula<M, B, X, V> {
+pub struct Ula<M, B, X, V, T=UlaMemoryContention> {
     pub(super) frames: Wrapping<u64>, // frame counter
     #[cfg_attr(feature = "snapshot", serde(bound = "V: VideoFrame"))]
     pub(super) tsc: VFrameTs<V>, // current T-state timestamp
@@ -75,37 +140,69 @@ pub struct Ula<M, B, X, V> {
     pub(super) bus: B,
     // #[cfg_attr(feature = "snapshot", serde(default))]
     pub(super) memext: X,
-    // keyboard
     #[cfg_attr(feature = "snapshot", serde(skip))]
+    pub(super) contention: T,
+    // keyboard
+    #[cfg_attr(feature = "snapshot", serde(default))]
     keyboard: ZXKeyboardMap,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    key_releases: Vec<(ZXKeyboardMap, u32)>, // pressed keys with the number of frames until released
     read_ear_mode: ReadEarMode,
     late_timings: bool,
+    #[cfg_attr(feature = "snapshot", serde(default = "interrupt_length_default::<V>"))]
+    interrupt_length: Ts,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    audio_sample_rate: u32,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    pub(super) cpu_clock_rate: u32,
+    #[cfg_attr(feature = "snapshot", serde(default = "snow_interference_default"))]
+    pub(super) snow_interference: bool,
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    pub(super) precise_border: bool,
     // video related
     #[cfg(feature = "boxed_frame_cache")]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     pub(super) frame_cache: Box<UlaFrameCache<V>>,
 
     #[cfg(not(feature = "boxed_frame_cache"))]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     pub(super) frame_cache: UlaFrameCache<V>,
 
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     border_out_changes: Vec<VideoTsData3>, // frame timestamp with packed border on 3 bits
     pub(super) border: BorderColor, // video frame start border color
     pub(super) last_border: BorderColor, // last recorded change
     // EAR, MIC
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     ear_in_changes: Vec<VideoTsData1>,  // frame timestamp with packed earin on 1 bit
     prev_ear_in: bool, // EAR IN state before first change in ear_in_changes
     ear_in_last_index: usize, // index into ear_in_changes of the last probed EAR IN
     read_ear_in_count: Wrapping<u32>, // the number of EAR IN probes during the last frame
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    pub(super) last_ear_read: Option<(u64, VideoTs)>, // the frame counter and the timestamp of the last EAR IN probe
+    #[cfg_attr(feature = "snapshot", serde(default))]
     earmic_out_changes: Vec<VideoTsData2>, // frame timestamp with packed earmic on 2 bits
     prev_earmic_ts: FTs, // previously recorded change timestamp
     prev_earmic_data: EarMic, // previous frame last recorded data
     last_earmic_data: EarMic, // last recorded data
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    audio_frames_ended: AudioFramesEnded, // the number of audio frames finalized during the current frame
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    audio_debt: i32, // the accumulated difference between the executed and rendered audio T-states
+    // debugging
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    write_watchpoints: Vec<RangeInclusive<u16>>,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    write_watch_hook: UlaHook<WriteWatchHook>,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    frame_end_hook: UlaHook<FrameEndHook>,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    pub(super) page_swap_hook: UlaHook<PageSwapHook>,
 }
 
+/// Memory banks mapped to memory pages, as returned by [Ula::paged_banks_if_hooked].
+pub(super) type PagedBanks = [Option<(MemoryKind, usize)>; 8];
+
 impl MemoryContention for UlaMemoryContention {
     #[inline(always)]
     fn is_contended_address(self, address: u16) -> bool {
@@ -113,7 +210,7 @@ impl MemoryContention for UlaMemoryContention {
     }
 }
 
-impl<M, B, X, V: VideoFrame> FrameState for Ula<M, B, X, V> {
+impl<M, B, X, V: VideoFrame, T> FrameState for Ula<M, B, X, V, T> {
     fn current_frame(&self) -> u64 {
         self.frames.0
     }
@@ -141,7 +238,7 @@ impl<M, B, X, V: VideoFrame> FrameState for Ula<M, B, X, V> {
     }
 }
 
-impl<M, B, X, V> UlaControl for Ula<M, B, X, V> {
+impl<M, B, X, V, T> UlaControl for Ula<M, B, X, V, T> {
     fn has_late_timings(&self) -> bool {
         self.late_timings
     }
@@ -149,12 +246,145 @@ impl<M, B, X, V> UlaControl for Ula<M, B, X, V> {
     fn set_late_timings(&mut self, late_timings: bool) {
         self.late_timings = late_timings;
     }
+
+    fn interrupt_length(&self) -> u32 {
+        self.interrupt_length as u32
+    }
+
+    fn set_interrupt_length(&mut self, length: u32) -> bool {
+        match length.try_into() {
+            Ok(length) => {
+                self.interrupt_length = length;
+                true
+            }
+            Err(_) => false
+        }
+    }
+
+    fn audio_sample_rate(&self) -> u32 {
+        self.audio_sample_rate
+    }
+
+    fn set_audio_sample_rate(&mut self, rate: u32) -> bool {
+        self.audio_sample_rate = rate;
+        true
+    }
+
+    fn cpu_clock_rate(&self) -> u32 {
+        self.cpu_clock_rate
+    }
+
+    fn set_cpu_clock_rate(&mut self, rate: u32) -> bool {
+        self.cpu_clock_rate = rate;
+        true
+    }
+
+    fn has_snow_interference(&self) -> bool {
+        self.snow_interference
+    }
+
+    fn set_snow_interference(&mut self, enabled: bool) -> bool {
+        self.snow_interference = enabled;
+        true
+    }
+
+    fn has_precise_border(&self) -> bool {
+        self.precise_border
+    }
+
+    fn set_precise_border(&mut self, enabled: bool) -> bool {
+        self.precise_border = enabled;
+        true
+    }
+
+    fn add_write_watchpoint(&mut self, range: RangeInclusive<u16>) -> bool {
+        self.write_watchpoints.push(range);
+        true
+    }
+
+    fn clear_write_watchpoints(&mut self) {
+        self.write_watchpoints.clear();
+    }
+
+    fn press_key_for_frames(&mut self, keys: ZXKeyboardMap, frames: u32) -> bool {
+        self.press_keys_with_release(keys, frames.max(1));
+        true
+    }
+
+    fn set_frame_end_hook(&mut self, hook: Option<FrameEndHook>) -> bool {
+        self.frame_end_hook = UlaHook(hook);
+        true
+    }
+
+    fn set_write_watch_hook(&mut self, hook: Option<WriteWatchHook>) -> bool {
+        self.write_watch_hook = UlaHook(hook);
+        true
+    }
+
+    fn audio_debt_tstates(&self) -> i32 {
+        self.audio_debt
+    }
+}
+
+impl<M, B, X, V, T> Ula<M, B, X, V, T> {
+    /// Calls the write watch hook if the `addr` is being watched.
+    #[inline(always)]
+    pub(crate) fn watch_write(&mut self, addr: u16, value: u8, ts: VideoTs) {
+        if let Some(hook) = self.write_watch_hook.0.as_mut() {
+            if self.write_watchpoints.iter().any(|range| range.contains(&addr)) {
+                hook(addr, value, ts);
+            }
+        }
+    }
+}
+
+impl<M: ZxMemory, B, X, V, T> Ula<M, B, X, V, T> {
+    /// Returns memory banks currently mapped to memory pages, but only if the page swap hook is set.
+    #[inline(always)]
+    pub(super) fn paged_banks_if_hooked(&self) -> Option<PagedBanks> {
+        self.page_swap_hook.is_some().then(|| {
+            let mut banks = PagedBanks::default();
+            for (page, bank) in banks.iter_mut().enumerate() {
+                *bank = self.memory.page_bank(page as u8).ok();
+            }
+            banks
+        })
+    }
+    /// Calls the page swap hook for each memory page mapped to a different bank than in `prev_banks`.
+    #[inline(always)]
+    pub(super) fn report_page_swaps(&mut self, prev_banks: Option<PagedBanks>, ts: VideoTs) {
+        if let Some(prev_banks) = prev_banks {
+            let banks = match self.paged_banks_if_hooked() {
+                Some(banks) => banks,
+                None => return
+            };
+            let hook = self.page_swap_hook.0.as_mut().unwrap();
+            for (page, (prev, cur)) in prev_banks.iter().zip(banks.iter()).enumerate() {
+                match *cur {
+                    Some((kind, bank)) if prev != cur => hook(page as u8, kind, bank, ts),
+                    _ => {}
+                }
+            }
+        }
+    }
 }
 
-impl<M, B, X, V> Default for Ula<M, B, X, V>
+#[cfg(feature = "snapshot")]
+fn snow_interference_default() -> bool {
+    true
+}
+
+#[cfg(feature = "snapshot")]
+fn interrupt_length_default<V: VideoFrame>() -> Ts {
+    V::INT_LENGTH_TS
+}
+
+impl<M, B, X, V, T> Default for Ula<M, B, X, V, T>
 where M: Default,
       B: Default,
-      X: Default
+      X: Default,
+      V: VideoFrame,
+      T: Default
 {
     fn default() -> Self {
         Ula {
@@ -163,10 +393,17 @@ where M: Default,
             memory: M::default(),
             bus: B::default(),
             memext: X::default(),
+            contention: T::default(),
             // keyboard
             keyboard: ZXKeyboardMap::empty(),
+            key_releases: Vec::new(),
             read_ear_mode: ReadEarMode::Issue3,
             late_timings: false,
+            interrupt_length: V::INT_LENGTH_TS,
+            audio_sample_rate: 0,
+            cpu_clock_rate: 0,
+            snow_interference: true,
+            precise_border: false,
             // video related
             frame_cache: Default::default(),
             border_out_changes: Vec::new(),
@@ -177,15 +414,56 @@ where M: Default,
             prev_ear_in: false,
             ear_in_last_index: 0,
             read_ear_in_count: Wrapping(0),
+            last_ear_read: None,
             earmic_out_changes: Vec::new(),
             prev_earmic_ts: FTs::min_value(),
             prev_earmic_data: EarMic::empty(),
             last_earmic_data: EarMic::empty(),
+            audio_frames_ended: AudioFramesEnded::default(),
+            audio_debt: 0,
+            write_watchpoints: Vec::new(),
+            write_watch_hook: UlaHook::default(),
+            frame_end_hook: UlaHook::default(),
+            page_swap_hook: UlaHook::default(),
+        }
+    }
+}
+
+impl<M, B, X, V: VideoFrame, T> Ula<M, B, X, V, T> {
+    /// The worst-case number of border or EAR/MIC output changes in a single frame.
+    ///
+    /// The shortest instruction able to change the output, `OUT (n),A`, takes 11 T-states.
+    pub const FRAME_OUT_CHANGES_MAX: usize = (V::FRAME_TSTATES_COUNT / 11) as usize;
+
+    /// Creates a new instance with the buffers of recorded changes pre-allocated to contain at least
+    /// the given number of `border` out, `earmic` out and `ear_in` changes without reallocation.
+    ///
+    /// Use [Ula::FRAME_OUT_CHANGES_MAX] for the worst-case number of output changes per frame.
+    pub fn with_capacities(border: usize, earmic: usize, ear_in: usize) -> Self
+        where M: Default,
+              B: Default,
+              X: Default,
+              T: Default
+    {
+        let mut ula = Self::default();
+        ula.reserve_change_buffers(border, earmic, ear_in);
+        ula
+    }
+    /// Reserves the capacity of the buffers of recorded changes for at least the given number of `border` out,
+    /// `earmic` out and `ear_in` changes to be recorded without reallocation.
+    ///
+    /// The buffers are being cleared between frames without releasing the allocated memory.
+    pub fn reserve_change_buffers(&mut self, border: usize, earmic: usize, ear_in: usize) {
+        fn reserve<T>(buf: &mut Vec<T>, capacity: usize) {
+            buf.reserve(capacity.saturating_sub(buf.len()));
         }
+        reserve(&mut self.border_out_changes, border);
+        reserve(&mut self.earmic_out_changes, earmic);
+        reserve(&mut self.ear_in_changes, ear_in);
     }
 }
 
-impl<M, B, X, V> fmt::Debug for Ula<M, B, X, V>
+impl<M, B, X, V, T> fmt::Debug for Ula<M, B, X, V, T>
     where M: ZxMemory,
           B: BusDevice,
           X: MemoryExtension,
@@ -199,8 +477,12 @@ impl<M, B, X, V> fmt::Debug for Ula<M, B, X, V>
             .field("bus", &self.bus)
             .field("memext", &self.memext)
             .field("keyboard", &self.keyboard)
+            .field("key_releases", &self.key_releases)
             .field("read_ear_mode", &self.read_ear_mode)
             .field("late_timings", &self.late_timings)
+            .field("interrupt_length", &self.interrupt_length)
+            .field("cpu_clock_rate", &self.cpu_clock_rate)
+            .field("precise_border", &self.precise_border)
             .field("frame_cache", &self.frame_cache)
             .field("border_out_changes", &self.border_out_changes.len())
             .field("border", &self.border)
@@ -208,14 +490,21 @@ impl<M, B, X, V> fmt::Debug for Ula<M, B, X, V>
             .field("prev_ear_in", &self.prev_ear_in)
             .field("ear_in_changes", &self.ear_in_changes.len())
             .field("read_ear_in_count", &self.read_ear_in_count.0)
+            .field("last_ear_read", &self.last_ear_read)
             .field("earmic_out_changes", &self.earmic_out_changes.len())
             .field("prev_earmic_data", &self.prev_earmic_data)
             .field("last_earmic_data", &self.last_earmic_data)
+            .field("audio_frames_ended", &self.audio_frames_ended.get())
+            .field("audio_debt", &self.audio_debt)
+            .field("write_watchpoints", &self.write_watchpoints)
+            .field("write_watch_hook", &self.write_watch_hook.is_some())
+            .field("frame_end_hook", &self.frame_end_hook.is_some())
+            .field("page_swap_hook", &self.page_swap_hook.is_some())
             .finish()
     }
 }
 
-impl<M, B, X, V> MemoryAccess for Ula<M, B, X, V>
+impl<M, B, X, V, T> MemoryAccess for Ula<M, B, X, V, T>
     where M: ZxMemory, X: MemoryExtension
 {
     type Memory = M;
@@ -243,12 +532,13 @@ impl<M, B, X, V> MemoryAccess for Ula<M, B, X, V>
     }
 }
 
-impl<M, B, X, V> ControlUnit for Ula<M, B, X, V>
+impl<M, B, X, V, T> ControlUnit for Ula<M, B, X, V, T>
     where M: ZxMemory,
           B: BusDevice,
           B::Timestamp: From<VFrameTs<V>>,
           X: MemoryExtension,
-          V: VideoFrame
+          V: VideoFrame,
+          T: MemoryContention
 {
     type BusDevice = B;
 
@@ -272,7 +562,7 @@ impl<M, B, X, V> ControlUnit for Ula<M, B, X, V>
         }
         else {
             const DEBUG: Option<CpuDebugFn> = None;
-            let mut vtsc = VFrameTsCounter::from_vframe_ts(VFrameTs::<V>::default(), UlaMemoryContention);
+            let mut vtsc = VFrameTsCounter::from_vframe_ts(VFrameTs::<V>::default(), self.contention);
             let _ = cpu.execute_instruction(self, &mut vtsc, DEBUG, opconsts::RST_00H_OPCODE);
         }
     }
@@ -297,13 +587,18 @@ impl<M, B, X, V> ControlUnit for Ula<M, B, X, V>
     {
         self.ula_execute_single_step(cpu, debug)
     }
+
+    fn run_frame_stats<C: Cpu>(&mut self, cpu: &mut C) -> FrameStats {
+        self.ula_run_frame_stats(cpu)
+    }
 }
 
-impl<M, B, X, V> UlaControlExt for Ula<M, B, X, V>
+impl<M, B, X, V, T> UlaControlExt for Ula<M, B, X, V, T>
     where M: ZxMemory,
           B: BusDevice,
           B::Timestamp: From<VFrameTs<V>>,
-          V: VideoFrame
+          V: VideoFrame,
+          T: MemoryContention
 {
     fn prepare_next_frame<C: MemoryContention>(
             &mut self,
@@ -312,10 +607,15 @@ impl<M, B, X, V> UlaControlExt for Ula<M, B, X, V>
     {
         self.bus.next_frame(VFrameTs::<V>::EOF.into());
         self.frames += Wrapping(1);
+        self.release_due_keys();
         self.cleanup_video_frame_data();
         self.cleanup_earmic_frame_data();
+        self.settle_audio_debt();
         vtsc.wrap_frame();
         self.tsc = vtsc.into();
+        if let Some(hook) = self.frame_end_hook.0.as_mut() {
+            hook(self.frames.0, self.tsc.into());
+        }
         vtsc
     }
 }
@@ -323,6 +623,7 @@ impl<M, B, X, V> UlaControlExt for Ula<M, B, X, V>
 #[cfg(test)]
 mod tests {
     use crate::memory::Memory64k;
+    use crate::chip::EarIn;
     use crate::video::Video;
     use super::*;
     type TestUla = UlaPAL::<Memory64k>;
@@ -339,4 +640,411 @@ mod tests {
             assert_eq!(clock.is_contended_address(addr), false);
         }
     }
+
+    #[test]
+    fn test_ula_auto_traits() {
+        fn is_send_sync<T: Send + Sync>() {}
+        is_send_sync::<TestUla>();
+    }
+
+    #[test]
+    fn test_ula_with_capacities() {
+        assert_eq!(TestUla::FRAME_OUT_CHANGES_MAX, 6353);
+        let mut ula = TestUla::with_capacities(TestUla::FRAME_OUT_CHANGES_MAX, 100, 10);
+        assert!(ula.border_out_changes.capacity() >= TestUla::FRAME_OUT_CHANGES_MAX);
+        assert!(ula.earmic_out_changes.capacity() >= 100);
+        assert!(ula.ear_in_changes.capacity() >= 10);
+        ula.ear_in_changes.push(VideoTsData1::from((VideoTs::new(0, 0), 1)));
+        ula.reserve_change_buffers(0, 0, 1000);
+        assert!(ula.ear_in_changes.capacity() >= 1000);
+    }
+
+    #[test]
+    fn test_ula_frame_stats() {
+        for &(addr, contended) in &[(0x8000u16, false), (0x6000, true)] {
+            let mut ula = TestUla::default();
+            let mut cpu = Z80NMOS::default();
+            // NOP; NOP; JR -4
+            ula.memory.load_into_mem(addr..addr + 4, &[0x00, 0x00, 0x18, 0xFC][..]).unwrap();
+            cpu.set_pc(addr);
+            let mut ula_ref = ula.clone();
+            let mut cpu_ref = cpu.clone();
+            let stats = ula.run_frame_stats(&mut cpu);
+            ula_ref.execute_next_frame(&mut cpu_ref);
+            assert!(ula.is_frame_over());
+            assert_eq!(stats.tstates as FTs, ula_ref.current_tstate());
+            assert_eq!(ula.current_tstate(), ula_ref.current_tstate());
+            assert_eq!(cpu, cpu_ref);
+            assert!(stats.instructions > 0);
+            assert_eq!(stats.contention_tstates > 0, contended);
+            // the uncontended loop takes 20 T-states per 3 instructions
+            if !contended {
+                assert_eq!(stats.tstates as u64 / 20, stats.instructions / 3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ula_snow_interference() {
+        use crate::video::{BorderSize, pixel::{PixelBufA24, SpectrumPalRGB24}};
+        let mut ula = TestUla::default();
+        assert!(ula.has_snow_interference());
+        assert!(ula.set_snow_interference(false));
+        assert!(!ula.has_snow_interference());
+        assert!(!ula.clone().has_snow_interference());
+        assert!(ula.set_snow_interference(true));
+        assert!(ula.has_snow_interference());
+        // execute JR $ with the I register pointing to the contended memory
+        let run_frame = |snow_interference: bool| -> TestUla {
+            let mut ula = TestUla::default();
+            assert!(ula.set_snow_interference(snow_interference));
+            for (i, byte) in ula.memory.screen_mut(0).unwrap().iter_mut().enumerate() {
+                *byte = (i ^ (i >> 8)) as u8;
+            }
+            ula.memory.load_into_mem(0x8000..0x8002, &[0x18, 0xFE][..]).unwrap();
+            let mut cpu = Z80NMOS::default();
+            cpu.reset();
+            cpu.set_pc(0x8000);
+            cpu.set_i(0x40);
+            ula.execute_next_frame(&mut cpu);
+            ula
+        };
+        let mut ula = run_frame(false);
+        let mut ula_snow = run_frame(true);
+        assert!(ula.frame_cache.frame_pixels.iter().all(|&(mask, _)| mask == 0));
+        assert!(ula.frame_cache.frame_colors.iter().all(|&(mask, _)| mask == 0));
+        assert!(ula_snow.frame_cache.frame_pixels.iter().any(|&(mask, _)| mask != 0));
+        assert!(ula_snow.frame_cache.frame_colors.iter().any(|&(mask, _)| mask != 0));
+
+        let border_size = BorderSize::Full;
+        let (width, height) = TestUla::render_size_pixels(border_size);
+        let pitch = width as usize * 3;
+        let mut buffer = vec![0u8; pitch * height as usize];
+        let mut buffer_snow = vec![0u8; pitch * height as usize];
+        ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer, pitch, border_size);
+        ula_snow.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer_snow, pitch, border_size);
+        assert!(buffer != buffer_snow);
+    }
+
+    #[test]
+    fn test_ula_earmic_out_iter() {
+        use core::num::NonZeroU32;
+        use crate::chip::{MicOut, EarMicOut};
+        let mut ula = TestUla::default();
+        for (vc, earmic) in [(1, EarMic::MIC), (2, EarMic::EARMIC), (3, EarMic::EAR),
+                             (4, EarMic::EAR), (6, EarMic::empty())] {
+            ula.earmic_out_changes.push((VideoTs::new(vc, 0), earmic.bits()).into());
+        }
+        let pulse = |n| NonZeroU32::new(n).unwrap();
+        let mic: Vec<_> = ula.mic_out_pulse_iter().collect();
+        assert_eq!(mic, [pulse(u32::MAX), pulse(448)]);
+        let earmic: Vec<_> = ula.earmic_out_iter().collect();
+        assert_eq!(earmic, [(pulse(u32::MAX), EarMic::MIC),
+                            (pulse(224), EarMic::EARMIC),
+                            (pulse(224), EarMic::EAR),
+                            (pulse(672), EarMic::empty())]);
+    }
+
+    #[test]
+    fn test_ula_interrupt_length() {
+        let mut ula = TestUla::default();
+        assert_eq!(ula.interrupt_length(), 32);
+        let irq = |ula: &mut TestUla, hc| -> Vec<bool> {
+            [-1, 0, hc - 1, hc].iter().map(|&hc| ula.is_irq(VideoTs::new(0, hc))).collect()
+        };
+        assert_eq!(irq(&mut ula, 32), [false, true, true, false]);
+        assert!(!ula.is_irq(VideoTs::new(1, 0)));
+        assert!(ula.set_interrupt_length(36));
+        assert_eq!(ula.interrupt_length(), 36);
+        assert_eq!(irq(&mut ula, 36), [false, true, true, false]);
+        ula.set_late_timings(true);
+        assert_eq!(irq(&mut ula, 35), [true, true, true, false]);
+        assert_eq!(ula.clone().interrupt_length(), 36);
+        assert!(ula.set_interrupt_length(0));
+        assert_eq!(irq(&mut ula, 1), [false, false, false, false]);
+        assert!(ula.set_interrupt_length(Ts::MAX as u32));
+        assert_eq!(ula.interrupt_length(), Ts::MAX as u32);
+        assert!(!ula.set_interrupt_length(Ts::MAX as u32 + 1));
+        assert!(!ula.set_interrupt_length(u32::MAX));
+        assert_eq!(ula.interrupt_length(), Ts::MAX as u32);
+    }
+
+    #[test]
+    fn test_ula_int_offset_ts() {
+        assert_eq!(UlaVideoFrameLateTiming::INT_OFFSET_TS, -1);
+        let hcs = [-2, -1, 0, 30, 31, 32];
+        let mut ula = UlaPALLate::<Memory64k>::default();
+        let irq = |ula: &mut UlaPALLate<Memory64k>| -> Vec<bool> {
+            hcs.iter().map(|&hc| ula.is_irq(VideoTs::new(0, hc))).collect()
+        };
+        // the late timing frame matches the "late timings" mode of the default frame
+        let mut ula_pal = TestUla::default();
+        ula_pal.set_late_timings(true);
+        let irq_pal: Vec<bool> = hcs.iter().map(|&hc| ula_pal.is_irq(VideoTs::new(0, hc))).collect();
+        assert_eq!(irq(&mut ula), [false, true, true, true, false, false]);
+        assert_eq!(irq(&mut ula), irq_pal);
+        // the "late timings" mode doesn't stack with the late timing frame
+        ula.set_late_timings(true);
+        assert_eq!(irq(&mut ula), irq_pal);
+        assert!(ula.set_interrupt_length(2));
+        assert_eq!(irq(&mut ula), [false, true, true, false, false, false]);
+    }
+
+    #[test]
+    fn test_ula_keyboard_multiple_rows() {
+        use crate::peripherals::KeyboardInterface;
+        let mut ula = TestUla::default();
+        ula.set_key_state(ZXKeyboardMap::Q|ZXKeyboardMap::W|ZXKeyboardMap::S|ZXKeyboardMap::BR);
+        let keys = |ula: &mut TestUla, port| ula.read_io(port, VideoTs::default()).0 & 0x1f;
+        assert_eq!(keys(&mut ula, 0xfbfe), 0b11100);
+        assert_eq!(keys(&mut ula, 0xfdfe), 0b11101);
+        assert_eq!(keys(&mut ula, 0x7ffe), 0b11110);
+        assert_eq!(keys(&mut ula, 0xf7fe), 0b11111);
+        // rows selected at once are combined
+        assert_eq!(keys(&mut ula, 0xf9fe), 0b11100);
+        assert_eq!(keys(&mut ula, 0x79fe), 0b11100);
+        assert_eq!(keys(&mut ula, 0x7dfe), 0b11100);
+        assert_eq!(keys(&mut ula, 0x00fe), 0b11100);
+        assert_eq!(keys(&mut ula, 0xfffe), 0b11111);
+        for port in (0..=0xff).map(|line| line << 8 | 0xfe) {
+            assert_eq!(keys(&mut ula, port), ula.get_key_state().read_keyboard((port >> 8) as u8) & 0x1f);
+        }
+    }
+
+    #[test]
+    fn test_ula_press_key_for_frames() {
+        use crate::peripherals::KeyboardInterface;
+        let mut ula = TestUla::default();
+        let mut cpu = Z80NMOS::default();
+        assert!(ula.press_key_for_frames(ZXKeyboardMap::CS|ZXKeyboardMap::N0, 2));
+        assert!(ula.press_key_for_frames(ZXKeyboardMap::A, 0));
+        assert_eq!(ula.get_key_state(), ZXKeyboardMap::CS|ZXKeyboardMap::N0|ZXKeyboardMap::A);
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.get_key_state(), ZXKeyboardMap::CS|ZXKeyboardMap::N0|ZXKeyboardMap::A);
+        // overlapping presses extend the hold
+        assert!(ula.press_key_for_frames(ZXKeyboardMap::N0, 3));
+        assert!(ula.press_key_for_frames(ZXKeyboardMap::CS, 1));
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.get_key_state(), ZXKeyboardMap::CS|ZXKeyboardMap::N0);
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.get_key_state(), ZXKeyboardMap::N0);
+        ula.set_key_state(ula.get_key_state()|ZXKeyboardMap::Q);
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.get_key_state(), ZXKeyboardMap::Q);
+        assert!(ula.key_releases.is_empty());
+    }
+
+    #[test]
+    fn test_ula_frame_end_hook() {
+        use std::sync::{Arc, Mutex};
+        let frames: Arc<Mutex<Vec<u64>>> = Arc::default();
+        let hook_frames = Arc::clone(&frames);
+        let hook = move |frame, ts: VideoTs| {
+            assert!(ts.vc < UlaVideoFrame::VSL_COUNT);
+            hook_frames.lock().unwrap().push(frame);
+        };
+        let mut ula = TestUla::default();
+        let mut cpu = Z80NMOS::default();
+        ula.execute_next_frame(&mut cpu);
+        assert!(frames.lock().unwrap().is_empty());
+        assert!(ula.set_frame_end_hook(Some(Box::new(hook))));
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(*frames.lock().unwrap(), [1]);
+        assert_eq!(ula.current_frame(), 1);
+        ula.ensure_next_frame();
+        assert_eq!(*frames.lock().unwrap(), [1, 2]);
+        assert!(ula.set_frame_end_hook(None));
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(*frames.lock().unwrap(), [1, 2]);
+        assert_eq!(ula.current_frame(), 2);
+    }
+
+    #[test]
+    fn test_ula_frames_since_last_ear_read() {
+        let mut ula = TestUla::default();
+        let mut cpu = Z80NMOS::default();
+        assert_eq!(ula.frames_since_last_ear_read(), u32::MAX);
+        // IN A,(0xFE); JR -4
+        ula.memory.load_into_mem(0x8000..0x8004, &[0xDB, 0xFE, 0x18, 0xFC][..]).unwrap();
+        cpu.set_pc(0x8000);
+        ula.execute_next_frame(&mut cpu);
+        assert!(ula.read_ear_in_count() > 0);
+        assert_eq!(ula.frames_since_last_ear_read(), 0);
+        // JR -2
+        ula.memory.load_into_mem(0x8000..0x8002, &[0x18, 0xFE][..]).unwrap();
+        cpu.set_pc(0x8000);
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.read_ear_in_count(), 0);
+        assert_eq!(ula.frames_since_last_ear_read(), 1);
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.frames_since_last_ear_read(), 2);
+    }
+
+    #[test]
+    fn test_ula_run_frames() {
+        let mut ula = TestUla::default();
+        let mut cpu = Z80NMOS::default();
+        ula.run_frames(&mut cpu, 0);
+        assert_eq!(ula.current_frame(), 0);
+        ula.run_frames(&mut cpu, 3);
+        assert_eq!(ula.current_frame(), 2);
+        assert!(ula.is_frame_over());
+        // LD HL,0x9000; INC (HL); HALT
+        ula.memory.load_into_mem(0x8000..0x8005, &[0x21, 0x00, 0x90, 0x34, 0x76][..]).unwrap();
+        cpu.set_pc(0x8000);
+        assert_eq!(ula.run_until(&mut cpu, 0, |_| true), None);
+        assert_eq!(ula.current_frame(), 2);
+        assert_eq!(ula.run_until(&mut cpu, 10, |ula| ula.memory.read(0x9000) != 0), Some(1));
+        assert_eq!(ula.current_frame(), 3);
+        assert_eq!(ula.run_until(&mut cpu, 5, |ula| ula.memory.read(0x9000) > 10), None);
+        assert_eq!(ula.current_frame(), 8);
+    }
+
+    #[test]
+    fn test_ula_breakpoints() {
+        let mut ula = TestUla::default();
+        let mut cpu = Z80NMOS::default();
+        // NOP; NOP; JR -4
+        ula.memory.load_into_mem(0x8000..0x8004, &[0x00, 0x00, 0x18, 0xFC][..]).unwrap();
+        cpu.set_pc(0x8000);
+        assert!(!ula.execute_next_frame_with_breakpoints(&mut cpu, &[0x8001]));
+        assert_eq!(cpu.get_pc(), 0x8001);
+        assert_eq!(ula.current_tstate(), 4);
+        assert!(!ula.execute_next_frame_with_breakpoints(&mut cpu, &[0x8001]));
+        assert_eq!(cpu.get_pc(), 0x8001);
+        assert_eq!(ula.current_tstate(), 4 + 4 + 12 + 4);
+        assert!(ula.execute_next_frame_with_breakpoints(&mut cpu, &[0x9000]));
+        assert!(ula.is_frame_over());
+        assert_eq!(ula.current_frame(), 0);
+        assert!(!ula.execute_next_frame_with_breakpoints(&mut cpu, &[0x8000, 0x8002]));
+        assert_eq!(ula.current_frame(), 1);
+        assert!(ula.execute_next_frame_with_breakpoints(&mut cpu, &[]));
+        assert!(ula.is_frame_over());
+    }
+
+    #[test]
+    fn test_ula_trap() {
+        let mut ula = TestUla::default();
+        let mut cpu = Z80NMOS::default();
+        // 0x8000: CALL 0x9000; JR -5
+        ula.memory.load_into_mem(0x8000..0x8005, &[0xCD, 0x00, 0x90, 0x18, 0xFB][..]).unwrap();
+        // 0x9000: HALT
+        ula.memory.load_into_mem(0x9000..0x9001, &[0x76][..]).unwrap();
+        cpu.set_pc(0x8000);
+        cpu.set_sp(0xC000);
+        let count = ula.execute_next_frame_with_trap(&mut cpu, 0x9000, |ula, cpu| {
+            // emulate RET with a side effect
+            let n = ula.memory.read(0xA000);
+            ula.memory.write(0xA000, n.wrapping_add(1));
+            let sp = cpu.get_sp();
+            cpu.set_pc(ula.memory.read16(sp));
+            cpu.set_sp(sp.wrapping_add(2));
+        });
+        assert!(ula.is_frame_over());
+        assert!(!cpu.is_halt());
+        assert!(count > 1);
+        assert_eq!(ula.memory.read(0xA000), count as u8);
+        assert_eq!(ula.execute_next_frame_with_trap(&mut cpu, 0x9001, |_, _| unreachable!()), 0);
+        assert!(cpu.is_halt());
+        assert_eq!(ula.current_frame(), 1);
+    }
+
+    #[test]
+    fn test_ula_no_contention() {
+        type TestUlaNoContention = Ula<Memory64k, VFNullDevice<UlaVideoFrame>, NoMemoryExtension,
+                                       UlaVideoFrame, crate::clock::NoContention>;
+        fn run<U: ControlUnit + MemoryAccess>(ula: &mut U) -> u16 {
+            let mut cpu = Z80NMOS::default();
+            // DI; LD HL,0; loop: INC HL; JR loop
+            ula.memory_mut().load_into_mem(0x6000..0x6007, &[0xF3, 0x21, 0x00, 0x00, 0x23, 0x18, 0xFD][..]).unwrap();
+            cpu.set_pc(0x6000);
+            ula.execute_next_frame(&mut cpu);
+            cpu.get_reg16(StkReg16::HL)
+        }
+        let mut ula = TestUla::default();
+        let mut ula_nc = TestUlaNoContention::default();
+        assert_eq!(<TestUlaNoContention as crate::chip::HostConfig>::CPU_HZ, <TestUla as crate::chip::HostConfig>::CPU_HZ);
+        let count = run(&mut ula);
+        let count_nc = run(&mut ula_nc);
+        assert!(count < count_nc);
+        assert_eq!(ula.current_frame(), ula_nc.current_frame());
+    }
+
+    #[test]
+    fn test_ula_write_watchpoints() {
+        use std::sync::{Arc, Mutex};
+        let log: Arc<Mutex<Vec<(u16, u8, VideoTs)>>> = Arc::default();
+        let hook_log = Arc::clone(&log);
+        let hook = move |addr, value, ts| hook_log.lock().unwrap().push((addr, value, ts));
+        let hits = || log.lock().unwrap().split_off(0);
+        let mut ula = TestUla::default();
+        let mut cpu = Z80NMOS::default();
+        // LD A, 0x42; LD (0x8010), A; LD (0x9000), A
+        ula.memory.load_into_mem(0x8000..0x8008, &[0x3E, 0x42, 0x32, 0x10, 0x80, 0x32, 0x00, 0x90][..]).unwrap();
+        cpu.set_pc(0x8000);
+        assert!(ula.add_write_watchpoint(0x8010..=0x801F));
+        assert!(ula.add_write_watchpoint(0x9000..=0x9000));
+        ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+        ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+        assert!(hits().is_empty());
+        assert!(ula.set_write_watch_hook(Some(Box::new(hook))));
+        cpu.set_pc(0x8000);
+        ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+        assert!(hits().is_empty());
+        ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+        assert_eq!(hits(), [(0x8010, 0x42, VideoTs::new(0, 40))]);
+        ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+        assert_eq!(hits(), [(0x9000, 0x42, VideoTs::new(0, 53))]);
+        ula.clear_write_watchpoints();
+        cpu.set_pc(0x8000);
+        for _ in 0..3 {
+            ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+        }
+        assert!(hits().is_empty());
+        assert!(ula.add_write_watchpoint(0x8000..=0xFFFF));
+        assert!(ula.set_write_watch_hook(None));
+        cpu.set_pc(0x8000);
+        for _ in 0..3 {
+            ula.execute_single_step(&mut cpu, None::<fn(CpuDebug)>).unwrap();
+        }
+        assert!(hits().is_empty());
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_ula_snapshot() {
+        use crate::clock::VideoTs;
+        use crate::chip::EarIn;
+        use crate::peripherals::KeyboardInterface;
+        use crate::video::{BorderSize, pixel::{PixelBufA24, SpectrumPalRGB24}};
+        let mut ula = TestUla::default();
+        ula.set_key_state(ZXKeyboardMap::A|ZXKeyboardMap::EN);
+        ula.memory.fill_mem(0x4000..0x5B00, || 0x55).unwrap();
+        for (vc, color) in [(20, BorderColor::RED), (120, BorderColor::CYAN)] {
+            ula.set_video_ts(VideoTs::new(vc, 10));
+            ula.set_border_color(color);
+        }
+        let ts = VideoTs::new(150, 0);
+        ula.set_video_ts(ts);
+        ula.update_frame_cache(0x4000, ts);
+        ula.memory.write(0x4000, 0xAA);
+        ula.set_ear_in(true, 200);
+
+        let sernap = serde_json::to_string(&ula).unwrap();
+        let mut ula_de: TestUla = serde_json::from_str(&sernap).unwrap();
+        assert_eq!(ula_de.get_key_state(), ZXKeyboardMap::A|ZXKeyboardMap::EN);
+        assert_eq!(ula_de.current_video_ts(), ts);
+        assert_eq!(ula_de.border_out_changes, ula.border_out_changes);
+        assert_eq!(ula_de.ear_in_changes, ula.ear_in_changes);
+        assert_eq!(ula_de.earmic_out_changes, ula.earmic_out_changes);
+
+        let border_size = BorderSize::Full;
+        let (width, height) = TestUla::render_size_pixels(border_size);
+        let pitch = width as usize * 3;
+        let mut buffer = vec![0u8; pitch * height as usize];
+        let mut buffer_de = vec![0u8; pitch * height as usize];
+        ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer, pitch, border_size);
+        ula_de.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer_de, pitch, border_size);
+        assert!(buffer == buffer_de);
+    }
 }
@@ -17,7 +17,7 @@ use crate::video::VideoFrame;
 use super::Ula;
 
 #[cfg(feature = "peripherals")]
-impl<B, M, D, X, V> AyAudioFrame<B> for Ula<M, D, X, V>
+impl<B, M, D, X, V, T> AyAudioFrame<B> for Ula<M, D, X, V, T>
     where B: Blep,
           D: AyAudioBusDevice + BusDevice,
           D::Timestamp: From<VFrameTs<V>>,
@@ -29,13 +29,35 @@ impl<B, M, D, X, V> AyAudioFrame<B> for Ula<M, D, X, V>
     }
 }
 
-impl<A, M, B, X, V> AudioFrame<A> for Ula<M, B, X, V>
+impl<A, M, B, X, V, T> AudioFrame<A> for Ula<M, B, X, V, T>
     where A: Blep,
           V: VideoFrame
 {
+    /// If `sample_rate` is `0`, the rate configured with
+    /// [UlaControl::set_audio_sample_rate][crate::chip::UlaControl::set_audio_sample_rate] is used instead.
+    /// If neither is set, the [Blep] is left uninitialized.
+    ///
+    /// Likewise, if `cpu_hz` is `0.0`, the rate configured with
+    /// [UlaControl::set_cpu_clock_rate][crate::chip::UlaControl::set_cpu_clock_rate] is used instead.
+    /// If not configured, the rate given by [VideoFrame::CPU_HZ] is used.
     #[inline]
     fn ensure_audio_frame_time(&self, blep: &mut A, sample_rate: u32, cpu_hz: f64) {
-        blep.ensure_frame_time(sample_rate, cpu_hz, V::FRAME_TSTATES_COUNT, MARGIN_TSTATES)
+        let sample_rate = match sample_rate {
+            0 => self.audio_sample_rate,
+            rate => rate
+        };
+        let cpu_hz = if cpu_hz == 0.0 {
+            match self.cpu_clock_rate {
+                0 => V::CPU_HZ,
+                rate => rate
+            }.into()
+        }
+        else {
+            cpu_hz
+        };
+        if sample_rate != 0 {
+            blep.ensure_frame_time(sample_rate, cpu_hz, V::FRAME_TSTATES_COUNT, MARGIN_TSTATES)
+        }
     }
 
     #[inline]
@@ -44,9 +66,28 @@ impl<A, M, B, X, V> AudioFrame<A> for Ula<M, B, X, V>
         assert!(vts.is_eof(), "Ula::get_audio_frame_end_time: frame execution didn't finish yet");
         vts.into_tstates()
     }
+
+    /// # Panics
+    /// In debug builds, panics if the audio frame has already been ended since the current frame
+    /// execution has finished.
+    #[inline]
+    fn end_audio_frame(&self, blep: &mut A) -> usize {
+        let ended = self.audio_frames_ended.increment();
+        debug_assert_eq!(ended, 0, "Ula::end_audio_frame: audio frame already ended");
+        blep.end_frame(AudioFrame::<A>::get_audio_frame_end_time(self))
+    }
+}
+
+impl<M, B, X, V: VideoFrame, T> Ula<M, B, X, V, T> {
+    /// Adds the T-states of the ending frame to the audio debt less the T-states of the ended audio frames.
+    pub(super) fn settle_audio_debt(&mut self) {
+        let ended = self.audio_frames_ended.take() as i32;
+        self.audio_debt = self.audio_debt
+                          .saturating_add(V::FRAME_TSTATES_COUNT.saturating_mul(1 - ended));
+    }
 }
 
-impl<A, M, B, X, V> EarMicOutAudioFrame<A> for Ula<M, B, X, V>
+impl<A, M, B, X, V, T> EarMicOutAudioFrame<A> for Ula<M, B, X, V, T>
     where A: Blep,
           V: VideoFrame
 {
@@ -60,7 +101,7 @@ impl<A, M, B, X, V> EarMicOutAudioFrame<A> for Ula<M, B, X, V>
     }
 }
 
-impl<A, M, B, X, V> EarInAudioFrame<A> for Ula<M, B, X, V>
+impl<A, M, B, X, V, T> EarInAudioFrame<A> for Ula<M, B, X, V, T>
     where A: Blep,
           V: VideoFrame
 {
@@ -72,4 +113,169 @@ impl<A, M, B, X, V> EarInAudioFrame<A> for Ula<M, B, X, V>
                                         &self.ear_in_changes,
                                         blep, channel)
     }
+
+    #[inline]
+    fn render_ear_in_audio_frame_smooth<L: AmpLevels<A::SampleDelta>>(
+            &self,
+            blep: &mut A,
+            channel: usize,
+            transition_ts: u32
+        )
+        where A::SampleDelta: MulNorm + FromSample<f32>
+    {
+        render_smooth_audio_frame_vts::<V,L,A::SampleDelta,A,_>(
+                                        self.prev_ear_in.into(),
+                                        Some(self.tsc),
+                                        &self.ear_in_changes,
+                                        blep, channel, transition_ts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip::{ControlUnit, EarIn, HostConfig, UlaControl};
+    use crate::memory::Memory64k;
+    use crate::z80emu::Z80NMOS;
+    use super::super::UlaPAL;
+    use super::*;
+
+    #[derive(Default)]
+    struct TestBlep(Vec<(FTs, f32)>, u32, f64);
+
+    impl Blep for TestBlep {
+        type SampleDelta = f32;
+        fn ensure_frame_time(&mut self, sample_rate: u32, ts_rate: f64, _frame_ts: FTs, _margin_ts: FTs) {
+            self.1 = sample_rate;
+            self.2 = ts_rate;
+        }
+        fn channels(&self) -> usize { 1 }
+        fn end_frame(&mut self, _timestamp: FTs) -> usize { 0 }
+        fn add_step(&mut self, channel: usize, timestamp: FTs, delta: f32) {
+            assert_eq!(channel, 0);
+            self.0.push((timestamp, delta));
+        }
+    }
+
+    struct EarInAmps;
+    impl AmpLevels<f32> for EarInAmps {
+        fn amp_level(level: u32) -> f32 {
+            level as f32
+        }
+    }
+
+    #[test]
+    fn test_ula_audio_sample_rate() {
+        let mut ula = UlaPAL::<Memory64k>::default();
+        let mut blep = TestBlep::default();
+        assert_eq!(ula.audio_sample_rate(), 0);
+        ula.ensure_audio_frame_time(&mut blep, 0, 3_500_000.0);
+        assert_eq!(blep.1, 0);
+        ula.ensure_audio_frame_time(&mut blep, 22050, 3_500_000.0);
+        assert_eq!(blep.1, 22050);
+        assert!(ula.set_audio_sample_rate(44100));
+        assert_eq!(ula.audio_sample_rate(), 44100);
+        ula.ensure_audio_frame_time(&mut blep, 0, 3_500_000.0);
+        assert_eq!(blep.1, 44100);
+        ula.ensure_audio_frame_time(&mut blep, 48000, 3_500_000.0);
+        assert_eq!(blep.1, 48000);
+    }
+
+    #[test]
+    fn test_ula_cpu_clock_rate() {
+        let mut ula = UlaPAL::<Memory64k>::default();
+        let mut blep = TestBlep::default();
+        assert_eq!(ula.cpu_clock_rate(), 0);
+        assert_eq!(ula.effective_cpu_clock_rate(), 3_500_000);
+        assert_eq!(ula.cpu_frame_duration_nanos(), UlaPAL::<Memory64k>::frame_duration_nanos());
+        ula.ensure_audio_frame_time(&mut blep, 44100, 0.0);
+        assert_eq!((blep.1, blep.2), (44100, 3_500_000.0));
+        assert!(ula.set_cpu_clock_rate(7_000_000));
+        assert_eq!(ula.cpu_clock_rate(), 7_000_000);
+        assert_eq!(ula.effective_cpu_clock_rate(), 7_000_000);
+        assert_eq!(ula.cpu_frame_duration_nanos(), 9_984_000);
+        ula.ensure_audio_frame_time(&mut blep, 44100, 0.0);
+        assert_eq!((blep.1, blep.2), (44100, 7_000_000.0));
+        ula.ensure_audio_frame_time(&mut blep, 44100, 3_546_900.0);
+        assert_eq!((blep.1, blep.2), (44100, 3_546_900.0));
+        assert!(ula.set_cpu_clock_rate(0));
+        assert_eq!(ula.effective_cpu_clock_rate(), 3_500_000);
+        let ula128: crate::chip::ula128::Ula128 = Default::default();
+        ula128.ensure_audio_frame_time(&mut blep, 48000, 0.0);
+        assert_eq!((blep.1, blep.2), (48000, 3_546_900.0));
+        let ula_ntsc = super::super::UlaNTSC::<Memory64k>::default();
+        ula_ntsc.ensure_audio_frame_time(&mut blep, 48000, 0.0);
+        assert_eq!((blep.1, blep.2), (48000, 3_527_500.0));
+    }
+
+    #[test]
+    fn test_ula_exact_frame_rate() {
+        let mut ula = UlaPAL::<Memory64k>::default();
+        let rate = ula.exact_frame_rate();
+        assert_eq!(rate, 3_500_000.0 / 69_888.0);
+        assert!((rate - 50.08).abs() < 0.001);
+        let nanos = UlaPAL::<Memory64k>::frame_duration_nanos() as f64;
+        assert!((1e9 / rate - nanos).abs() < 1.0);
+        assert!(ula.set_cpu_clock_rate(7_000_000));
+        assert_eq!(ula.exact_frame_rate(), 2.0 * rate);
+    }
+
+    #[test]
+    fn test_ula_audio_debt_tstates() {
+        let mut ula = UlaPAL::<Memory64k>::default();
+        let mut cpu = Z80NMOS::default();
+        let mut blep = TestBlep::default();
+        for _ in 0..3 {
+            ula.execute_next_frame(&mut cpu);
+            ula.end_audio_frame(&mut blep);
+        }
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.audio_debt_tstates(), 0);
+        // audio render skipped
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.audio_debt_tstates(), 69888);
+        ula.end_audio_frame(&mut blep);
+        ula.execute_next_frame(&mut cpu);
+        ula.end_audio_frame(&mut blep);
+        ula.execute_next_frame(&mut cpu);
+        assert_eq!(ula.audio_debt_tstates(), 69888);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "audio frame already ended")]
+    fn test_ula_end_audio_frame_twice() {
+        let mut ula = UlaPAL::<Memory64k>::default();
+        let mut cpu = Z80NMOS::default();
+        let mut blep = TestBlep::default();
+        ula.execute_next_frame(&mut cpu);
+        ula.end_audio_frame(&mut blep);
+        ula.end_audio_frame(&mut blep);
+    }
+
+    #[test]
+    fn test_ula_render_ear_in_smooth() {
+        let mut ula = UlaPAL::<Memory64k>::default();
+        let mut cpu = Z80NMOS::default();
+        ula.set_ear_in(true, 1000);
+        ula.set_ear_in(false, 2000);
+        ula.set_ear_in(true, 50);
+        ula.execute_next_frame(&mut cpu);
+        let end_ts = ula.tsc.into_tstates();
+        ula.set_ear_in(false, (end_ts - 3050 - 10) as u32);
+        let mut blep = TestBlep::default();
+        ula.render_ear_in_audio_frame::<EarInAmps>(&mut blep, 0);
+        assert_eq!(blep.0, [(1000, 1.0), (3000, -1.0), (3050, 1.0), (end_ts - 10, -1.0)]);
+        let mut blep_smooth = TestBlep::default();
+        ula.render_ear_in_audio_frame_smooth::<EarInAmps>(&mut blep_smooth, 0, 0);
+        assert_eq!(blep.0, blep_smooth.0);
+        let mut blep_smooth = TestBlep::default();
+        ula.render_ear_in_audio_frame_smooth::<EarInAmps>(&mut blep_smooth, 0, 80);
+        let steps = &blep_smooth.0;
+        assert_eq!(&steps[0..8], (0..8).map(|i| (1000 + i * 10, 0.125)).collect::<Vec<_>>().as_slice());
+        // cut short by the next change
+        assert_eq!(&steps[8..13], (0..5).map(|i| (3000 + i * 10, -0.125)).collect::<Vec<_>>().as_slice());
+        assert_eq!(&steps[13..21], (0..8).map(|i| (3050 + i * 10, 0.078125)).collect::<Vec<_>>().as_slice());
+        // completed before the end of the frame
+        assert_eq!(&steps[21..], [(end_ts - 10, -0.125), (end_ts - 1, -0.875)]);
+    }
 }
@@ -5,17 +5,19 @@
 
     For the full copyright notice, see the lib.rs file.
 */
+use core::num::{NonZeroU8, NonZeroU16};
 #[allow(unused_imports)]
 use log::{error, warn, info, debug, trace};
 
 use crate::z80emu::{
-    Cpu, CpuDebug, CpuDebugFn, Memory, Io, BreakCause,
+    Cpu, CpuDebug, CpuDebugFn, Clock, Memory, Io, BreakCause,
     host::{
-        cycles::M1_CYCLE_TS, Result
+        cycles::{M1_CYCLE_TS, MEMRW_CYCLE_TS, IO_CYCLE_TS, IRQ_ACK_CYCLE_TS},
+        Result
     }
 };
 use crate::bus::BusDevice;
-use crate::chip::{MemoryAccess, ControlUnit};
+use crate::chip::{MemoryAccess, ControlUnit, FrameStats};
 use crate::clock::{
     HALT_VC_THRESHOLD,
     VideoTs, VFrameTs, Ts, VFrameTsCounter, MemoryContention
@@ -55,6 +57,7 @@ pub(crate) trait UlaCpuExt: UlaControlExt {
             cpu: &mut C,
             code: u8
         ) -> Result<(), ()>;
+    fn ula_run_frame_stats<C: Cpu>(&mut self, cpu: &mut C) -> FrameStats;
 
     #[inline]
     fn ula_check_halt<C: Cpu>(mut vts: VideoTs, cpu: &mut C) -> VideoTs {
@@ -93,6 +96,7 @@ impl<U, B, X> UlaCpuExt for U
     {
         let mut vtsc = self.ensure_next_frame_vtsc();
         while !vtsc.is_eof() {
+            // the interrupt request, shifted by VideoFrame::INT_OFFSET_TS, is confined to the first line
             let vc_limit = if vtsc.vc < 1 { 1 }
             else {
                 Self::VideoFrame::VSL_COUNT
@@ -155,6 +159,80 @@ impl<U, B, X> UlaCpuExt for U
         self.bus_device_mut().update_timestamp(vtsc.vts.into());
         res
     }
+
+    fn ula_run_frame_stats<C: Cpu>(&mut self, cpu: &mut C) -> FrameStats {
+        const DEBUG: Option<CpuDebugFn> = None;
+        let vtsc = self.ensure_next_frame_vtsc();
+        let start = vtsc.vts;
+        let mut clock = ContentionCountingClock { vtsc, contention_ts: 0 };
+        let mut instructions = 0;
+        while !clock.vtsc.is_eof() {
+            let _ = cpu.execute_next(self, &mut clock, DEBUG);
+            **clock.vtsc = Self::ula_check_halt(clock.vtsc.into(), cpu);
+            self.set_video_ts(clock.vtsc.into());
+            instructions += 1;
+        }
+        let ContentionCountingClock { vtsc, contention_ts } = clock;
+        self.bus_device_mut().update_timestamp(vtsc.vts.into());
+        let tstates = (vtsc.vts.into_tstates() - start.into_tstates()) as u32;
+        FrameStats { tstates, instructions, contention_tstates: contention_ts }
+    }
+}
+
+/// Wraps the video T-state counter, counting T-states added due to contention.
+struct ContentionCountingClock<V, T> {
+    vtsc: VFrameTsCounter<V, T>,
+    contention_ts: u32
+}
+
+impl<V: VideoFrame, T: MemoryContention> ContentionCountingClock<V, T> {
+    #[inline(always)]
+    fn count<F: FnOnce(&mut VFrameTsCounter<V, T>) -> R, R>(&mut self, nominal_ts: u32, f: F) -> R {
+        let VideoTs { vc, hc } = **self.vtsc;
+        let res = f(&mut self.vtsc);
+        let delta = i32::from(self.vtsc.vc - vc) * V::HTS_COUNT as i32 + i32::from(self.vtsc.hc - hc);
+        self.contention_ts += (delta as u32).saturating_sub(nominal_ts);
+        res
+    }
+}
+
+impl<V: VideoFrame, T: MemoryContention> Clock for ContentionCountingClock<V, T> {
+    type Limit = Ts;
+    type Timestamp = VideoTs;
+
+    #[inline(always)]
+    fn is_past_limit(&self, limit: Self::Limit) -> bool {
+        self.vtsc.is_past_limit(limit)
+    }
+
+    fn add_irq(&mut self, pc: u16) -> Self::Timestamp {
+        self.count(IRQ_ACK_CYCLE_TS.into(), |vtsc| vtsc.add_irq(pc))
+    }
+
+    fn add_no_mreq(&mut self, address: u16, add_ts: NonZeroU8) {
+        self.count(add_ts.get().into(), |vtsc| vtsc.add_no_mreq(address, add_ts))
+    }
+
+    fn add_m1(&mut self, address: u16) -> Self::Timestamp {
+        self.count(M1_CYCLE_TS.into(), |vtsc| vtsc.add_m1(address))
+    }
+
+    fn add_mreq(&mut self, address: u16) -> Self::Timestamp {
+        self.count(MEMRW_CYCLE_TS.into(), |vtsc| vtsc.add_mreq(address))
+    }
+
+    fn add_io(&mut self, port: u16) -> Self::Timestamp {
+        self.count(IO_CYCLE_TS.into(), |vtsc| vtsc.add_io(port))
+    }
+
+    fn add_wait_states(&mut self, bus: u16, wait_states: NonZeroU16) {
+        self.vtsc.add_wait_states(bus, wait_states)
+    }
+
+    #[inline(always)]
+    fn as_timestamp(&self) -> Self::Timestamp {
+        self.vtsc.as_timestamp()
+    }
 }
 
 /// Emulates the CPU's halted state at the given video timestamp.
@@ -7,7 +7,7 @@
 */
 use core::num::Wrapping;
 
-use crate::chip::{EarIn, MicOut, EarMic, ReadEarMode};
+use crate::chip::{EarIn, MicOut, EarMicOut, EarMic, ReadEarMode};
 use crate::clock::FTs;
 use crate::video::VideoFrame;
 use super::Ula;
@@ -18,7 +18,7 @@ use core::num::NonZeroU32;
 
 use crate::clock::{Ts, VFrameTs, VideoTs, VideoTsData1, VideoTsData2};
 
-impl<M, B, X, V> EarIn for Ula<M, B, X, V>
+impl<M, B, X, V, T> EarIn for Ula<M, B, X, V, T>
     where V: VideoFrame
 {
     fn set_ear_in(&mut self, ear_in: bool, delta_fts: u32) {
@@ -73,6 +73,13 @@ impl<M, B, X, V> EarIn for Ula<M, B, X, V>
         self.read_ear_in_count.0
     }
 
+    fn frames_since_last_ear_read(&self) -> u32 {
+        match self.last_ear_read {
+            Some((frame, _)) => self.frames.0.wrapping_sub(frame).try_into().unwrap_or(u32::MAX),
+            None => u32::MAX
+        }
+    }
+
     fn read_ear_mode(&self) -> ReadEarMode {
         self.read_ear_mode
     }
@@ -82,7 +89,7 @@ impl<M, B, X, V> EarIn for Ula<M, B, X, V>
     }
 }
 
-impl<'a, M: 'a, B: 'a, X: 'a, F: 'a> MicOut<'a> for Ula<M, B, X, F>
+impl<'a, M: 'a, B: 'a, X: 'a, F: 'a, T> MicOut<'a> for Ula<M, B, X, F, T>
     where F: VideoFrame
 {
     type PulseIter = MicPulseIter<core::slice::Iter<'a, VideoTsData2>, F>;
@@ -94,6 +101,18 @@ impl<'a, M: 'a, B: 'a, X: 'a, F: 'a> MicOut<'a> for Ula<M, B, X, F>
     }
 }
 
+impl<'a, M: 'a, B: 'a, X: 'a, F: 'a, T> EarMicOut<'a> for Ula<M, B, X, F, T>
+    where F: VideoFrame
+{
+    type EarMicIter = EarMicPulseIter<core::slice::Iter<'a, VideoTsData2>, F>;
+    fn earmic_out_iter(&'a self) -> Self::EarMicIter {
+        EarMicPulseIter(MicPulseIter::new(
+                self.prev_earmic_ts,
+                self.prev_earmic_data,
+                self.earmic_out_changes.iter()))
+    }
+}
+
 /// `MIC out` pulse iterator.
 pub struct MicPulseIter<I, V>
 {
@@ -111,18 +130,12 @@ impl<'a, I, V> MicPulseIter<I, V>
         let last_data = last_data.bits();
         MicPulseIter { last_pulse_ts, last_data, iter, _video: PhantomData }
     }
-}
 
-impl<'a, I, V> Iterator for MicPulseIter<I, V>
-    where I: Iterator<Item=&'a VideoTsData2>,
-          V: VideoFrame
-{
-    type Item = NonZeroU32;
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_change(&mut self, mask: EarMic) -> Option<NonZeroU32> {
         loop {
             if let Some(&vtsd) = self.iter.next() {
                 let (vts, data):(VFrameTs<V>, u8) = vtsd.into();
-                if !(EarMic::from_bits_truncate(self.last_data ^ data) & EarMic::MIC).is_empty() {
+                if !(EarMic::from_bits_truncate(self.last_data ^ data) & mask).is_empty() {
                     let ts = vts.into_tstates();
                     let maybe_delta = ts.checked_sub(self.last_pulse_ts);
 
@@ -151,7 +164,34 @@ impl<'a, I, V> Iterator for MicPulseIter<I, V>
     }
 }
 
-impl<M, B, X, V> Ula<M, B, X, V>
+impl<'a, I, V> Iterator for MicPulseIter<I, V>
+    where I: Iterator<Item=&'a VideoTsData2>,
+          V: VideoFrame
+{
+    type Item = NonZeroU32;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_change(EarMic::MIC)
+    }
+}
+
+/// `EAR` and `MIC out` level change iterator.
+///
+/// Yields the intervals in T-states between changes of any of the two lines together with their new state.
+pub struct EarMicPulseIter<I, V>(MicPulseIter<I, V>);
+
+impl<'a, I, V> Iterator for EarMicPulseIter<I, V>
+    where I: Iterator<Item=&'a VideoTsData2>,
+          V: VideoFrame
+{
+    type Item = (NonZeroU32, EarMic);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_change(EarMic::EARMIC).map(|pulse|
+            (pulse, EarMic::from_bits_truncate(self.0.last_data))
+        )
+    }
+}
+
+impl<M, B, X, V, T> Ula<M, B, X, V, T>
     where V: VideoFrame
 {
     pub(super) fn cleanup_earmic_frame_data(&mut self) {
@@ -7,7 +7,13 @@
 */
 use core::fmt;
 use core::marker::PhantomData;
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
 use crate::clock::{Ts, VideoTs};
+#[cfg(feature = "snapshot")]
+use crate::memory::arrays;
 use crate::memory::{ZxMemory, ScreenArray};
 use crate::video::{
     pixel_line_offset, color_line_offset,
@@ -31,14 +37,35 @@ const COL_ATTR_HTS: &[Ts;COLUMNS] = &[2, 4, 10, 12, 18, 20, 26, 28, 34, 36, 42,
 ///
 /// When a screen is being drawn the data stored in cache will override any value currently residing in
 /// video memory.
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "snapshot", serde(rename_all = "camelCase"))]
 #[derive(Clone)]
 pub struct UlaFrameCache<V> {
+    #[cfg_attr(feature = "snapshot", serde(with = "arrays"))]
     pub frame_pixels: [(u32, [u8;COLUMNS]);PIXEL_LINES],      // read precedence pixels < memory
+    #[cfg_attr(feature = "snapshot", serde(with = "arrays"))]
     pub frame_colors: [(u32, [u8;COLUMNS]);PIXEL_LINES],
+    #[cfg_attr(feature = "snapshot", serde(with = "arrays"))]
     pub frame_colors_coarse: [(u32, [u8;COLUMNS]);ATTR_ROWS], // read precedence colors < colors_coarse < memory
+    #[cfg(feature = "frame_cache_stats")]
+    #[cfg_attr(feature = "snapshot", serde(skip))]
+    stats: FrameCacheStats,
+    #[cfg_attr(feature = "snapshot", serde(skip))]
     _video_frame: PhantomData<V>
 }
 
+/// Statistics of the [UlaFrameCache] updates in the current frame.
+///
+/// Available with the `"frame_cache_stats"` feature.
+#[cfg(feature = "frame_cache_stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FrameCacheStats {
+    /// The number of screen cells cached due to writes to the video memory.
+    pub cells_cached: u32,
+    /// The number of writes to the video memory that hit already cached cells.
+    pub cached_hits: u32
+}
+
 /// A reference to screen data with a relevant frame cache.
 pub struct UlaFrameRef<'a, V> {
     pub screen: &'a ScreenArray,
@@ -174,6 +201,8 @@ impl<V> Default for UlaFrameCache<V> {
             frame_pixels: [(0, [0;COLUMNS]);PIXEL_LINES],
             frame_colors: [(0, [0;COLUMNS]);PIXEL_LINES],
             frame_colors_coarse: [(0, [0;COLUMNS]);ATTR_ROWS],
+            #[cfg(feature = "frame_cache_stats")]
+            stats: FrameCacheStats::default(),
             _video_frame: PhantomData
         }
     }
@@ -193,6 +222,27 @@ impl<V> fmt::Debug for UlaFrameCache<V> {
 }
 
 impl<V> UlaFrameCache<V> {
+    /// Returns the statistics of the cache updates since the last call to [UlaFrameCache::clear].
+    #[cfg(feature = "frame_cache_stats")]
+    pub fn cache_stats(&self) -> FrameCacheStats {
+        self.stats
+    }
+
+    #[cfg(feature = "frame_cache_stats")]
+    #[inline(always)]
+    fn count_cached(&mut self, cells: u32) {
+        if cells == 0 {
+            self.stats.cached_hits += 1;
+        }
+        else {
+            self.stats.cells_cached += cells;
+        }
+    }
+
+    #[cfg(not(feature = "frame_cache_stats"))]
+    #[inline(always)]
+    fn count_cached(&mut self, _cells: u32) {}
+
     pub fn clear(&mut self) {
         for p in self.frame_pixels.iter_mut() {
             p.0 = 0;
@@ -203,6 +253,10 @@ impl<V> UlaFrameCache<V> {
         for p in self.frame_colors_coarse.iter_mut() {
             p.0 = 0;
         }
+        #[cfg(feature = "frame_cache_stats")]
+        {
+            self.stats = FrameCacheStats::default();
+        }
     }
 }
 
@@ -225,10 +279,12 @@ impl<V: VideoFrame> UlaFrameCache<V> {
         if y < vy || y == vy && ts.hc > COL_INK_HTS[column] {
             let (mask, pixels) = &mut self.frame_pixels[row as usize];
             let mbit = 1 << column;
-            if *mask & mbit == 0 {
+            let cached = *mask & mbit == 0;
+            if cached {
                 pixels[column] = memory.read(addr);
                 *mask |= mbit;
             }
+            self.count_cached(cached.into());
         }
     }
     /// Compares the given attribute cell coordinates with the video timestamp and depending
@@ -253,10 +309,12 @@ impl<V: VideoFrame> UlaFrameCache<V> {
                 ts.hc > COL_ATTR_HTS[column] {
             let (mask, colors) = &mut self.frame_colors_coarse[row as usize];
             let mbit = 1 << column;
-            if *mask & mbit == 0 {
+            let cached = *mask & mbit == 0;
+            if cached {
                 *mask |= mbit;
                 colors[column] = memory.read(addr);
             }
+            self.count_cached(cached.into());
         }
         else if coarse_y == coarse_vy {
             let line_top = (coarse_vy << 3) as usize;
@@ -268,13 +326,16 @@ impl<V: VideoFrame> UlaFrameCache<V> {
             if line_top < line_bot {
                 let memval = memory.read(addr);
                 let mbit = 1 << column;
+                let mut cells = 0;
                 for (mask, colors) in self.frame_colors[line_top..line_bot].iter_mut().rev() {
                     if *mask & mbit != 0 {
                         break;
                     }
                     *mask |= mbit;
                     colors[column] = memval;
+                    cells += 1;
                 }
+                self.count_cached(cells);
             }
         }
     }
@@ -9,14 +9,14 @@ use core::num::NonZeroU16;
 
 use crate::z80emu::{Io, Memory};
 use crate::bus::BusDevice;
-use crate::clock::{Ts, VideoTs, VFrameTs};
+use crate::clock::{VideoTs, VFrameTs, MemoryContention};
 use crate::chip::{EarMic, UlaPortFlags};
 use crate::peripherals::{KeyboardInterface, ZXKeyboardMap};
 use crate::memory::{ZxMemory, MemoryExtension};
 use crate::video::{BorderColor, VideoFrame};
 use super::Ula;
 
-impl<M, B, X, V> Io for Ula<M, B, X, V>
+impl<M, B, X, V, T> Io for Ula<M, B, X, V, T>
     where M: ZxMemory,
           B: BusDevice,
           B::Timestamp: From<VFrameTs<V>>,
@@ -28,7 +28,10 @@ impl<M, B, X, V> Io for Ula<M, B, X, V>
 
     #[inline(always)]
     fn is_irq(&mut self, VideoTs{ vc, hc }: VideoTs) -> bool {
-        vc == 0 && (hc + Ts::from(self.late_timings)) & !31 == 0
+        // the "late timings" mode doesn't stack with the frame's own negative interrupt offset
+        let offset = if self.late_timings { V::INT_OFFSET_TS.min(-1) } else { V::INT_OFFSET_TS };
+        let hc = hc - offset;
+        vc == 0 && (0..self.interrupt_length).contains(&hc)
     }
 
     fn read_io(&mut self, port: u16, ts: VideoTs) -> (u8, Option<NonZeroU16>) {
@@ -53,10 +56,11 @@ impl<M, B, X, V> Io for Ula<M, B, X, V>
     }
 }
 
-impl<M, B, X, V> Memory for Ula<M, B, X, V>
+impl<M, B, X, V, T> Memory for Ula<M, B, X, V, T>
     where M: ZxMemory,
           X: MemoryExtension,
-          V: VideoFrame
+          V: VideoFrame,
+          T: MemoryContention
 {
     type Timestamp = VideoTs;
 
@@ -84,11 +88,12 @@ impl<M, B, X, V> Memory for Ula<M, B, X, V>
     #[inline(always)]
     fn write_mem(&mut self, addr: u16, val: u8, ts: VideoTs) {
         self.update_frame_cache(addr, ts);
-        self.memory.write(addr, val);
+        self.watch_write(addr, val, ts);
+        self.memext.write_mem(addr, val, &mut self.memory);
     }
 }
 
-impl<M, B, X, V> KeyboardInterface for Ula<M, B, X, V> {
+impl<M, B, X, V, T> KeyboardInterface for Ula<M, B, X, V, T> {
     fn get_key_state(&self) -> ZXKeyboardMap {
         self.keyboard
     }
@@ -97,7 +102,36 @@ impl<M, B, X, V> KeyboardInterface for Ula<M, B, X, V> {
     }
 }
 
-impl<M, B, X, V> Ula<M, B, X, V>
+impl<M, B, X, V, T> Ula<M, B, X, V, T> {
+    pub(super) fn press_keys_with_release(&mut self, keys: ZXKeyboardMap, frames: u32) {
+        self.keyboard |= keys;
+        let mut bits = keys.bits();
+        while bits != 0 {
+            let key = ZXKeyboardMap::from_bits_truncate(bits & bits.wrapping_neg());
+            bits &= bits - 1;
+            match self.key_releases.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, left)) => *left = frames.max(*left),
+                None => self.key_releases.push((key, frames))
+            }
+        }
+    }
+
+    pub(super) fn release_due_keys(&mut self) {
+        if self.key_releases.is_empty() {
+            return
+        }
+        let keyboard = &mut self.keyboard;
+        for (key, left) in self.key_releases.iter_mut() {
+            *left -= 1;
+            if *left == 0 {
+                keyboard.remove(*key);
+            }
+        }
+        self.key_releases.retain(|&(_, left)| left != 0);
+    }
+}
+
+impl<M, B, X, V, T> Ula<M, B, X, V, T>
     where V: VideoFrame
 {
     #[inline(always)]
@@ -111,6 +145,7 @@ impl<M, B, X, V> Ula<M, B, X, V>
 
     #[inline(always)]
     pub(crate) fn ula_io_data(&mut self, port: u16, ts: VideoTs) -> u8 {
+        self.last_ear_read = Some((self.frames.0, ts));
         self.keyboard.read_keyboard((port >> 8) as u8) &
                 (u8::from(self.read_ear_in(ts)) << 6 | 0b1011_1111)
     }
@@ -134,7 +169,7 @@ impl<M, B, X, V> Ula<M, B, X, V>
     }
 
     #[inline]
-    fn floating_bus(&self, ts: VideoTs) -> u8
+    pub(super) fn floating_bus(&self, ts: VideoTs) -> u8
         where M: ZxMemory
     {
         if let Some(addr) = V::floating_bus_screen_address(ts) {
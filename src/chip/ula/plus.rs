@@ -5,18 +5,20 @@
 
     For the full copyright notice, see the lib.rs file.
 */
-use core::iter::Empty;
+use core::iter::{Copied, Empty};
+use core::slice;
 
-use crate::clock::VideoTs;
+use crate::clock::{VideoTs, MemoryContention};
 use crate::chip::{UlaPortFlags, ula::frame_cache::UlaFrameCache};
 use crate::memory::{ZxMemory, MemoryExtension};
 use crate::video::{BorderColor, VideoFrame};
 use super::{Ula, super::plus::{UlaPlusInner, VideoRenderDataView}};
 
-impl<'a, M, B, X, V> UlaPlusInner<'a> for Ula<M, B, X, V>
+impl<'a, M, B, X, V, T> UlaPlusInner<'a> for Ula<M, B, X, V, T>
     where M: ZxMemory,
           X: MemoryExtension,
-          V: VideoFrame
+          V: VideoFrame,
+          T: MemoryContention
 {
     type ScreenSwapIter = Empty<VideoTs>;
 
@@ -30,6 +32,16 @@ impl<'a, M, B, X, V> UlaPlusInner<'a> for Ula<M, B, X, V>
 
     fn push_screen_change(&mut self, _ts: VideoTs) {}
 
+    #[inline(always)]
+    fn watch_write(&mut self, addr: u16, value: u8, ts: VideoTs) {
+        Ula::watch_write(self, addr, value, ts)
+    }
+
+    #[inline(always)]
+    fn write_mem_ext(&mut self, addr: u16, value: u8) {
+        self.memext.write_mem(addr, value, &mut self.memory)
+    }
+
     fn update_last_border_color(&mut self, border: BorderColor) -> bool {
         if self.last_border != border {
             self.last_border = border;
@@ -81,4 +93,16 @@ impl<'a, M, B, X, V> UlaPlusInner<'a> for Ula<M, B, X, V>
             frame_cache_shadow: &self.frame_cache
         }
     }
+
+    fn video_render_data_ref(
+        &self
+    ) -> VideoRenderDataView<'_, Copied<slice::Iter<'_, VideoTs>>, Self::Memory, Self::VideoFrame>
+    {
+        VideoRenderDataView {
+            screen_changes: [].iter().copied(),
+            memory: &self.memory,
+            frame_cache: &self.frame_cache,
+            frame_cache_shadow: &self.frame_cache
+        }
+    }
 }
@@ -10,7 +10,7 @@ use crate::memory::{MemoryExtension, ZxMemory};
 use crate::formats::scr::*;
 use super::Ula;
 
-impl<M, B, X, V> ScreenDataProvider for Ula<M, B, X, V>
+impl<M, B, X, V, T> ScreenDataProvider for Ula<M, B, X, V, T>
     where M: ZxMemory,
           X: MemoryExtension
 {
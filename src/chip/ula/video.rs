@@ -5,8 +5,9 @@
 
     For the full copyright notice, see the lib.rs file.
 */
-use core::iter::StepBy;
+use core::iter::{Copied, StepBy};
 use core::ops::Range;
+use core::slice;
 
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
@@ -20,10 +21,12 @@ use crate::video::{
         pixel_address_coords, color_address_coords
     }
 };
-use super::{Ula, UlaMemoryContention};
+use super::Ula;
 use super::frame_cache::{
     UlaFrameCache, UlaFrameProducer
 };
+#[cfg(feature = "frame_cache_stats")]
+use super::frame_cache::FrameCacheStats;
 
 /// Implements [VideoFrame] for PAL ULA.
 #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
@@ -45,16 +48,25 @@ impl VideoFrame for UlaVideoFrame {
     type BorderHtsIter = StepBy<Range<Ts>>;
 
     fn border_whole_line_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (-68..156).step_by(4)
+        }
         let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
         (-20+invborder..156-invborder).step_by(4)
     }
 
     fn border_left_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (-68..4).step_by(4)
+        }
         let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
         (-20+invborder..4).step_by(4)
     }
 
     fn border_right_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (132..156).step_by(4)
+        }
         let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
         (132..156-invborder).step_by(4)
     }
@@ -99,9 +111,9 @@ impl VideoFrame for UlaVideoFrame {
     }
 }
 
-impl<M: ZxMemory, D, X, V: VideoFrame> Video for Ula<M, D, X, V> {
+impl<M: ZxMemory, D, X, V: VideoFrame, T: MemoryContention> Video for Ula<M, D, X, V, T> {
     type VideoFrame = V;
-    type Contention = UlaMemoryContention;
+    type Contention = T;
 
     #[inline]
     fn border_color(&self) -> BorderColor {
@@ -125,14 +137,40 @@ impl<M: ZxMemory, D, X, V: VideoFrame> Video for Ula<M, D, X, V> {
         self.create_renderer(border_size).render_pixels::<B, P, V>(buffer, pitch)
     }
 
+    fn render_video_frame_with_line_hook<'a, B, P, F>(
+            &mut self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            line_hook: F
+        )
+        where B: PixelBuffer<'a>,
+              P: Palette<Pixel=B::Pixel>,
+              F: FnMut(usize, VideoTs)
+    {
+        self.create_renderer(border_size)
+            .render_pixels_with_line_hook::<B, P, V, F>(buffer, pitch, line_hook)
+    }
+
+    fn render_video_scanlines<'a, B: PixelBuffer<'a>, P: Palette<Pixel=B::Pixel>>(
+            &mut self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            vsl_range: Range<Ts>
+        )
+    {
+        self.create_scanlines_renderer(border_size).render_pixels_range::<B, P, V>(buffer, pitch, vsl_range)
+    }
+
     #[inline]
     fn current_video_ts(&self) -> VideoTs {
         self.tsc.into()
     }
 
     #[inline]
-    fn current_video_clock(&self) -> VFrameTsCounter<V, UlaMemoryContention> {
-        VFrameTsCounter::from_video_ts(self.tsc.ts, UlaMemoryContention)
+    fn current_video_clock(&self) -> VFrameTsCounter<V, T> {
+        VFrameTsCounter::from_video_ts(self.tsc.ts, self.contention)
     }
 
     fn set_video_ts(&mut self, vts: VideoTs) {
@@ -142,9 +180,13 @@ impl<M: ZxMemory, D, X, V: VideoFrame> Video for Ula<M, D, X, V> {
     fn flash_state(&self) -> bool {
         self.frames.0 & 16 != 0
     }
+
+    fn floating_bus_value(&self) -> u8 {
+        self.floating_bus(self.tsc.ts)
+    }
 }
 
-impl<M: ZxMemory, B, X, V: VideoFrame> Ula<M, B, X, V> {
+impl<M: ZxMemory, B, X, V: VideoFrame, T: MemoryContention> Ula<M, B, X, V, T> {
     #[inline(always)]
     pub(super) fn update_frame_cache(&mut self, addr: u16, ts: VideoTs) {
         match addr {
@@ -162,7 +204,7 @@ impl<M: ZxMemory, B, X, V: VideoFrame> Ula<M, B, X, V> {
 
     #[inline(always)]
     pub(super) fn update_snow_interference(&mut self, ts: VideoTs, ir: u16) {
-        if UlaMemoryContention.is_contended_address(ir) {
+        if self.snow_interference && self.contention.is_contended_address(ir) {
             if let Some(coords) = V::snow_interference_coords(ts) {
                 let screen = self.memory.screen_ref(0).unwrap();
                 self.frame_cache.apply_snow_interference(screen, coords, ir as u8)
@@ -171,7 +213,27 @@ impl<M: ZxMemory, B, X, V: VideoFrame> Ula<M, B, X, V> {
     }
 }
 
-impl<M: ZxMemory, B, X, V> Ula<M, B, X, V> {
+impl<M: ZxMemory, B, X, V, T> Ula<M, B, X, V, T> {
+    /// Returns the statistics of the frame cache updates in the current frame.
+    ///
+    /// The statistics are reset at the beginning of each frame.
+    #[cfg(feature = "frame_cache_stats")]
+    pub fn cache_stats(&self) -> FrameCacheStats {
+        self.frame_cache.cache_stats()
+    }
+
+    /// Returns an iterator of the border color changes recorded in the current frame, with their video
+    /// timestamps, without consuming them.
+    ///
+    /// The changes are available after the frame has been executed until the video frame is rendered
+    /// with [Video::render_video_frame] or the next frame begins.
+    pub fn border_changes(&self) -> impl Iterator<Item=(VideoTs, BorderColor)> + '_ {
+        self.border_out_changes.iter().map(|&vtsd| {
+            let (vts, color) = vtsd.into();
+            (vts, BorderColor::from_bits_truncate(color))
+        })
+    }
+
     pub(super) fn cleanup_video_frame_data(&mut self) {
         self.border = self.last_border;
         self.border_out_changes.clear();
@@ -182,11 +244,16 @@ impl<M: ZxMemory, B, X, V> Ula<M, B, X, V> {
         (&mut self.border_out_changes, &self.memory, &self.frame_cache)
     }
 
+    pub(crate) fn video_render_data_ref(&self) -> (BorderColor, &[VideoTsData3], &M, &UlaFrameCache<V>) {
+        (self.border, &self.border_out_changes, &self.memory, &self.frame_cache)
+    }
+
     fn create_renderer(
             &mut self,
             border_size: BorderSize
         ) -> Renderer<UlaFrameProducer<'_, V>, std::vec::Drain<'_, VideoTsData3>>
-        where V: VideoFrame
+        where V: VideoFrame,
+              T: MemoryContention
     {
         let border = self.border;
         let invert_flash = self.flash_state();
@@ -197,7 +264,28 @@ impl<M: ZxMemory, B, X, V> Ula<M, B, X, V> {
             border,
             border_size,
             border_changes: self.border_out_changes.drain(..),
-            invert_flash
+            invert_flash,
+            precise_border: self.precise_border
+        }
+    }
+
+    fn create_scanlines_renderer(
+            &self,
+            border_size: BorderSize
+        ) -> Renderer<UlaFrameProducer<'_, V>, Copied<slice::Iter<'_, VideoTsData3>>>
+        where V: VideoFrame,
+              T: MemoryContention
+    {
+        let (border, border_changes, memory, frame_cache) = self.video_render_data_ref();
+        let invert_flash = self.flash_state();
+        let screen = memory.screen_ref(0).unwrap();
+        Renderer {
+            frame_image_producer: UlaFrameProducer::new(screen, frame_cache),
+            border,
+            border_size,
+            border_changes: border_changes.iter().copied(),
+            invert_flash,
+            precise_border: self.precise_border
         }
     }
 }
@@ -206,6 +294,7 @@ impl<M: ZxMemory, B, X, V> Ula<M, B, X, V> {
 mod tests {
     use crate::clock::{TimestampOps, VFrameTs};
     use super::*;
+    use super::super::UlaMemoryContention;
     type TestVideoFrame = UlaVideoFrame;
     type TestVFTs = VFrameTs<TestVideoFrame>;
 
@@ -236,6 +325,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_io_contention() {
+        use crate::z80emu::Clock;
+        use crate::clock::VFrameTsCounter;
+        // the I/O contention patterns of 48k ULA, starting at the first contended T-state
+        let start = 14335;
+        for &(port, data_ts, end_ts) in &[
+                (0x00FF, start + 1,     start + 4),   // N:4
+                (0x00FE, start + 1,     start + 9),   // N:1 C:3
+                (0x40FE, start + 6 + 1, start + 10),  // C:1 C:3
+                (0x40FF, start + 6 + 1, start + 16)]  // C:1 C:1 C:1 C:1
+        {
+            let mut vtsc = VFrameTsCounter::<TestVideoFrame, _>::from_tstates(start, UlaMemoryContention);
+            let vts = vtsc.add_io(port);
+            assert_eq!(TestVFTs::from(vts).into_tstates(), data_ts, "0x{:04x}", port);
+            assert_eq!(VFrameTs::from(vtsc).into_tstates(), end_ts, "0x{:04x}", port);
+        }
+        // no contention outside of the contended area
+        for &port in &[0x00FF, 0x00FE, 0x40FE, 0x40FF] {
+            let mut vtsc = VFrameTsCounter::<TestVideoFrame, _>::from_tstates(start - 8, UlaMemoryContention);
+            vtsc.add_io(port);
+            assert_eq!(VFrameTs::from(vtsc).into_tstates(), start - 4, "0x{:04x}", port);
+        }
+    }
+
     #[test]
     fn test_video_frame_vts_utils() {
         assert_eq!(TestVFTs::EOF, TestVFTs::from_tstates(TestVideoFrame::FRAME_TSTATES_COUNT));
@@ -305,4 +419,274 @@ mod tests {
             assert_eq!(vts1.saturating_add(vts0), addvts);
         }
     }
+
+    #[test]
+    fn test_video_ts_tstates_utils() {
+        let vts = VideoTs::new(1, 150);
+        assert_eq!(vts.to_tstates::<TestVideoFrame>(), 224 + 150);
+        assert_eq!(vts.add_tstates::<TestVideoFrame>(10), VideoTs::new(2, -64));
+        assert_eq!(vts.add_tstates::<TestVideoFrame>(-224 - 150), VideoTs::new(0, 0));
+        assert_eq!(VideoTs::new(2, -64).diff::<TestVideoFrame>(vts), 10);
+        assert_eq!(vts.diff::<TestVideoFrame>(VideoTs::new(2, -64)), -10);
+        assert!(!vts.is_eof::<TestVideoFrame>());
+        let eof = VideoTs::new(0, 0).add_tstates::<TestVideoFrame>(TestVideoFrame::FRAME_TSTATES_COUNT);
+        assert!(eof.is_eof::<TestVideoFrame>());
+        assert_eq!(eof, TestVFTs::EOF.into());
+    }
+
+    #[test]
+    fn test_floating_bus_value() {
+        use crate::chip::ula::UlaPAL;
+        use crate::memory::Memory48k;
+        let mut ula = UlaPAL::<Memory48k>::default();
+        for (addr, byte) in ula.memory.screen_mut(0).unwrap().iter_mut().enumerate() {
+            *byte = (addr ^ (addr >> 8)) as u8;
+        }
+        let top = TestVideoFrame::VSL_PIXELS.start;
+        let bot = TestVideoFrame::VSL_PIXELS.end;
+        for ((vc, hc), value) in [((  0, 0), 0xFF),
+                                  ((top, 0), 0x00),
+                                  ((top, 1), 0x18),
+                                  ((top, 2), 0x01),
+                                  ((top, 3), 0x19),
+                                  ((top, 4), 0xFF),
+                                  ((top, 8), 0x02),
+                                  ((bot, 0), 0xFF)] {
+            ula.set_video_ts(VideoTs::new(vc, hc));
+            assert_eq!(ula.floating_bus_value(), value);
+        }
+    }
+
+    #[test]
+    fn test_contended_access_tstates() {
+        use crate::chip::ula::UlaPAL;
+        use crate::memory::Memory48k;
+        let ula = UlaPAL::<Memory48k>::default();
+        let top = TestVideoFrame::VSL_PIXELS.start;
+        let bot = TestVideoFrame::VSL_PIXELS.end;
+        for (addr, (vc, hc), delay) in [(0x4000, (top, -2), 0),
+                                        (0x4000, (top, -1), 6),
+                                        (0x4000, (top,  0), 5),
+                                        (0x4000, (top,  4), 1),
+                                        (0x7FFF, (top,  5), 0),
+                                        (0x7FFF, (top,  7), 6),
+                                        (0x7FFF, (top, 124), 1),
+                                        (0x7FFF, (top, 125), 0),
+                                        (0x8000, (top,  0), 0),
+                                        (0x3FFF, (top,  0), 0),
+                                        (0x4000, (bot - 1, 0), 5),
+                                        (0x4000, (bot, 0), 0),
+                                        (0x4000, (top - 1, 0), 0),
+                                        (0x4000, (top - 1, TestVideoFrame::HTS_RANGE.end + 68), 6)] {
+            assert_eq!(ula.contended_access_tstates(addr, VideoTs::new(vc, hc)), delay);
+        }
+    }
+
+    #[cfg(feature = "frame_cache_stats")]
+    #[test]
+    fn test_frame_cache_stats() {
+        use crate::chip::ula::UlaPAL;
+        use crate::memory::Memory48k;
+        let mut ula = UlaPAL::<Memory48k>::default();
+        assert_eq!(ula.cache_stats(), FrameCacheStats::default());
+        let ts = VideoTs::new(TestVideoFrame::VSL_PIXELS.end, 0);
+        // a write to a cell not yet drawn is not cached
+        ula.update_frame_cache(0x4000, VideoTs::new(0, 0));
+        for addr in [0x4000, 0x4001, 0x4000, 0x5800, 0x5800] {
+            ula.update_frame_cache(addr, ts);
+        }
+        assert_eq!(ula.cache_stats(), FrameCacheStats { cells_cached: 3, cached_hits: 2 });
+        // a partial attribute cell caches the lines already drawn
+        let ts = VideoTs::new(TestVideoFrame::VSL_PIXELS.start + 8 + 3, 0);
+        ula.update_frame_cache(0x5820, ts);
+        ula.update_frame_cache(0x5820, ts);
+        assert_eq!(ula.cache_stats(), FrameCacheStats { cells_cached: 6, cached_hits: 3 });
+        ula.cleanup_video_frame_data();
+        assert_eq!(ula.cache_stats(), FrameCacheStats::default());
+    }
+
+    #[test]
+    fn test_render_video_frame_overscan() {
+        use crate::chip::ula::UlaPAL;
+        use crate::memory::Memory48k;
+        use crate::video::pixel::{PixelBufA24, SpectrumPalRGB24};
+        let mut ula = UlaPAL::<Memory48k>::default();
+        for (vc, color) in [(2, BorderColor::RED), (310, BorderColor::BLUE)] {
+            ula.set_video_ts(VideoTs::new(vc, TestVideoFrame::HTS_RANGE.start));
+            ula.set_border_color(color);
+        }
+        let border_size = BorderSize::Overscan;
+        let (width, height) = UlaPAL::<Memory48k>::render_size_pixels(border_size);
+        assert_eq!((width, height), (448, 312));
+        let pitch = width as usize * 3;
+        let mut buffer = vec![0x55u8; pitch * height as usize];
+        ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer, pitch, border_size);
+        let lines: Vec<&[u8]> = buffer.chunks(pitch).collect();
+        let white = SpectrumPalRGB24::get_pixel(BorderColor::WHITE.into());
+        let red = SpectrumPalRGB24::get_pixel(BorderColor::RED.into());
+        let blue = SpectrumPalRGB24::get_pixel(BorderColor::BLUE.into());
+        assert!(lines[0..2].iter().all(|line| line.chunks(3).all(|p| p == white)));
+        for line in lines[2..TestVideoFrame::VSL_PIXELS.start as usize].iter()
+                    .chain(&lines[TestVideoFrame::VSL_PIXELS.end as usize..310]) {
+            assert!(line.chunks(3).all(|p| p == red));
+        }
+        for line in &lines[310..] {
+            assert!(line.chunks(3).all(|p| p == blue));
+        }
+    }
+
+    #[test]
+    fn test_border_changes() {
+        use crate::chip::ula::UlaPAL;
+        use crate::memory::Memory48k;
+        use crate::video::pixel::{PixelBufA24, SpectrumPalRGB24};
+        let mut ula = UlaPAL::<Memory48k>::default();
+        assert_eq!(ula.border_changes().next(), None);
+        let changes = [(VideoTs::new(10, 20), BorderColor::RED),
+                       (VideoTs::new(100, -8), BorderColor::BLUE),
+                       (VideoTs::new(200, 132), BorderColor::WHITE)];
+        for &(vts, color) in changes.iter() {
+            ula.set_video_ts(vts);
+            ula.set_border_color(color);
+        }
+        // the same color is not recorded again
+        ula.set_border_color(BorderColor::WHITE);
+        assert_eq!(ula.border_changes().collect::<Vec<_>>(), changes);
+        assert_eq!(ula.border_changes().collect::<Vec<_>>(), changes);
+        let border_size = BorderSize::Full;
+        let (width, height) = UlaPAL::<Memory48k>::render_size_pixels(border_size);
+        let pitch = width as usize * 3;
+        let mut buffer = vec![0u8; pitch * height as usize];
+        ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer, pitch, border_size);
+        assert_eq!(ula.border_changes().next(), None);
+    }
+
+    #[test]
+    fn test_render_precise_border() {
+        use crate::chip::{UlaControl, ula::UlaPAL};
+        use crate::memory::Memory48k;
+        use crate::video::pixel::{PixelBufA24, SpectrumPalRGB24};
+        let mut ula = UlaPAL::<Memory48k>::default();
+        assert!(!ula.has_precise_border());
+        let border_size = BorderSize::Overscan;
+        let (width, height) = UlaPAL::<Memory48k>::render_size_pixels(border_size);
+        let pitch = width as usize * 3;
+        let white = SpectrumPalRGB24::get_pixel(BorderColor::WHITE.into());
+        let red = SpectrumPalRGB24::get_pixel(BorderColor::RED.into());
+        let blue = SpectrumPalRGB24::get_pixel(BorderColor::BLUE.into());
+        let green = SpectrumPalRGB24::get_pixel(BorderColor::GREEN.into());
+        for &(precise, expected) in &[
+                (false, [(0, white), (88, red), (168, blue), (176, green)]),
+                (true,  [(0, white), (82, red), (166, blue), (170, green)])] {
+            assert!(ula.set_precise_border(precise));
+            assert_eq!(ula.has_precise_border(), precise);
+            ula.set_border_color(BorderColor::WHITE);
+            // the overscan line begins at hc: -68, 2 pixels per T-state
+            for &(hc, color) in &[(-27, BorderColor::RED), (15, BorderColor::BLUE), (17, BorderColor::GREEN)] {
+                ula.set_video_ts(VideoTs::new(2, hc));
+                ula.set_border_color(color);
+            }
+            let mut buffer = vec![0u8; pitch * height as usize];
+            ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer, pitch, border_size);
+            let line: Vec<[u8;3]> = buffer[2 * pitch..3 * pitch].chunks(3)
+                                    .map(|p| [p[0], p[1], p[2]]).collect();
+            let mut ends = expected.iter().skip(1).map(|&(x, _)| x).chain(Some(width as usize));
+            for &(start, color) in expected.iter() {
+                let end = ends.next().unwrap();
+                assert!(line[start..end].iter().all(|&p| p == color), "{} {}..{}", precise, start, end);
+            }
+            ula.set_video_ts(VideoTs::new(0, 0));
+        }
+    }
+
+    #[test]
+    fn test_render_video_frame_with_line_hook() {
+        use crate::chip::ula::UlaPAL;
+        use crate::memory::Memory48k;
+        use crate::video::pixel::{PixelBufA24, SpectrumPalRGB24};
+        let mut ula = UlaPAL::<Memory48k>::default();
+        for (addr, byte) in ula.memory.screen_mut(0).unwrap().iter_mut().enumerate() {
+            *byte = addr as u8;
+        }
+        ula.set_video_ts(VideoTs::new(100, 20));
+        ula.set_border_color(BorderColor::RED);
+        for &border_size in &[BorderSize::Full, BorderSize::Nil, BorderSize::Overscan] {
+            let (width, height) = UlaPAL::<Memory48k>::render_size_pixels(border_size);
+            let pitch = width as usize * 3;
+            let mut expected = vec![0u8; pitch * height as usize];
+            ula.clone().render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut expected, pitch, border_size);
+            let mut buffer = vec![0u8; pitch * height as usize];
+            let mut lines = Vec::new();
+            ula.clone().render_video_frame_with_line_hook::<PixelBufA24, SpectrumPalRGB24, _>(
+                &mut buffer, pitch, border_size, |line, vts| lines.push((line, vts)));
+            assert!(buffer == expected);
+            assert_eq!(lines.len(), height as usize);
+            let vsl_start = UlaVideoFrame::border_top_vsl_iter(border_size).start;
+            for (n, &(line, vts)) in lines.iter().enumerate() {
+                assert_eq!(line, n);
+                assert_eq!(vts, VideoTs::new(vsl_start + n as Ts, UlaVideoFrame::HTS_RANGE.start));
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_border_only() {
+        use crate::chip::ula::UlaPAL;
+        use crate::memory::Memory48k;
+        use crate::video::pixel::{PixelBufA24, SpectrumPalRGB24};
+        let mut ula = UlaPAL::<Memory48k>::default();
+        // red PAPER with no INK pixels
+        let screen = ula.memory.screen_mut(0).unwrap();
+        screen[..0x1800].fill(0);
+        screen[0x1800..].fill(0b00_010_000);
+        // red border from the beginning of the frame
+        ula.border = BorderColor::RED;
+        ula.last_border = BorderColor::RED;
+        for &border_size in &[BorderSize::Full, BorderSize::Nil, BorderSize::Overscan] {
+            let (width, height) = UlaPAL::<Memory48k>::render_size_pixels(border_size);
+            let pitch = width as usize * 3 + 6;
+            let mut expected = vec![0u8; pitch * height as usize + 9];
+            ula.clone().render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut expected, pitch, border_size);
+            let mut buffer = vec![0u8; pitch * height as usize + 9];
+            UlaPAL::<Memory48k>::render_border_only::<PixelBufA24, SpectrumPalRGB24>(
+                &mut buffer, pitch, border_size, BorderColor::RED);
+            assert!(buffer == expected);
+            // the padding and the space below the frame stay untouched
+            assert!(buffer[pitch - 6..pitch].iter().all(|&b| b == 0));
+            assert!(buffer[pitch * height as usize..].iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    fn test_render_video_scanlines() {
+        use crate::chip::ula::UlaPAL;
+        use crate::memory::Memory48k;
+        use crate::video::pixel::{PixelBufA24, SpectrumPalRGB24};
+        let mut ula = UlaPAL::<Memory48k>::default();
+        for (addr, byte) in ula.memory.screen_mut(0).unwrap().iter_mut().enumerate() {
+            *byte = addr as u8;
+        }
+        for (vc, color) in [(10, BorderColor::RED), (100, BorderColor::BLUE), (200, BorderColor::WHITE)] {
+            ula.set_video_ts(VideoTs::new(vc, 20));
+            ula.set_border_color(color);
+        }
+        let border_size = BorderSize::Full;
+        let (width, height) = UlaPAL::<Memory48k>::render_size_pixels(border_size);
+        let pitch = width as usize * 3;
+        let border_top = TestVideoFrame::border_top_vsl_iter(border_size).start;
+        let buffers: Vec<Vec<u8>> = [border_top, 100, 150, 250].iter().map(|&split| {
+            let mut buffer = vec![0u8; pitch * height as usize];
+            ula.render_video_scanlines::<PixelBufA24, SpectrumPalRGB24>(
+                &mut buffer, pitch, border_size, Ts::MIN..split);
+            assert!(buffer[(split - border_top) as usize * pitch..].iter().all(|&p| p == 0));
+            ula.render_video_scanlines::<PixelBufA24, SpectrumPalRGB24>(
+                &mut buffer, pitch, border_size, split..Ts::MAX);
+            buffer
+        }).collect();
+        let mut expected = vec![0u8; pitch * height as usize];
+        ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut expected, pitch, border_size);
+        for buffer in buffers {
+            assert!(buffer == expected);
+        }
+    }
 }
@@ -0,0 +1,72 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+use core::iter::StepBy;
+use core::ops::Range;
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use crate::clock::{VideoTs, Ts};
+use crate::video::{BorderSize, VideoFrame, CellCoords};
+use super::UlaVideoFrame;
+
+/// Implements [VideoFrame] for PAL ULA with the "late" interrupt timing.
+///
+/// The video frame is the same as [UlaVideoFrame], except that the frame interrupt is being requested
+/// 1 T-state earlier, so all other timings are 1 T-state later in relation to the interrupt, as observed
+/// on some of the 48k ULA revisions. This matches the "late timings" mode of [UlaControl::set_late_timings],
+/// which has no additional effect with this video frame.
+///
+/// [UlaControl::set_late_timings]: crate::chip::UlaControl::set_late_timings
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct UlaVideoFrameLateTiming;
+
+impl VideoFrame for UlaVideoFrameLateTiming {
+    /// A range of horizontal T-states, 0 should be when the frame starts.
+    const HTS_RANGE: Range<Ts> = UlaVideoFrame::HTS_RANGE;
+    /// The first video scan line index of the top border.
+    const VSL_BORDER_TOP: Ts = UlaVideoFrame::VSL_BORDER_TOP;
+    /// A range of video scan line indexes for the pixel area.
+    const VSL_PIXELS: Range<Ts> = UlaVideoFrame::VSL_PIXELS;
+    /// The last video scan line index of the bottom border.
+    const VSL_BORDER_BOT: Ts = UlaVideoFrame::VSL_BORDER_BOT;
+    /// A total number of video scan lines.
+    const VSL_COUNT: Ts = UlaVideoFrame::VSL_COUNT;
+    /// The frame interrupt is being requested 1 T-state earlier than with [UlaVideoFrame].
+    const INT_OFFSET_TS: Ts = -1;
+
+    type BorderHtsIter = StepBy<Range<Ts>>;
+
+    fn border_whole_line_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        UlaVideoFrame::border_whole_line_hts_iter(border_size)
+    }
+
+    fn border_left_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        UlaVideoFrame::border_left_hts_iter(border_size)
+    }
+
+    fn border_right_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        UlaVideoFrame::border_right_hts_iter(border_size)
+    }
+
+    #[inline]
+    fn contention(hc: Ts) -> Ts {
+        UlaVideoFrame::contention(hc)
+    }
+
+    #[inline(always)]
+    fn floating_bus_offset(hc: Ts) -> Option<u16> {
+        UlaVideoFrame::floating_bus_offset(hc)
+    }
+
+    #[inline(always)]
+    fn snow_interference_coords(vts: VideoTs) -> Option<CellCoords> {
+        UlaVideoFrame::snow_interference_coords(vts)
+    }
+}
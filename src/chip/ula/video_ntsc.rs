@@ -31,6 +31,8 @@ impl VideoFrame for UlaNTSCVidFrame {
     const VSL_BORDER_BOT: Ts = 256;
     /// A total number of video scan lines.
     const VSL_COUNT: Ts = 264;
+    /// The CPU clock rate of the NTSC models.
+    const CPU_HZ: u32 = 3_527_500;
 
     type BorderHtsIter = StepBy<Range<Ts>>;
 
@@ -0,0 +1,122 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+use core::iter::StepBy;
+use core::ops::Range;
+
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
+use crate::clock::{VideoTs, Ts};
+use crate::video::{BorderSize, VideoFrame, CellCoords, MAX_BORDER_SIZE};
+
+/// Implements [VideoFrame] for Pentagon 128 timings.
+///
+/// Pentagon has no memory or I/O contention, no floating bus and no snow interference.
+/// The frame consists of 320 lines, 224 T-states each.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+pub struct PentagonVidFrame;
+
+impl VideoFrame for PentagonVidFrame {
+    /// A range of horizontal T-states, 0 should be when the frame starts.
+    const HTS_RANGE: Range<Ts> = -68..156;
+    /// The first video scan line index of the top border.
+    const VSL_BORDER_TOP: Ts = 16;
+    /// A range of video scan line indexes for the pixel area.
+    const VSL_PIXELS: Range<Ts> = 80..272;
+    /// The last video scan line index of the bottom border.
+    const VSL_BORDER_BOT: Ts = 320;
+    /// A total number of video scan lines.
+    const VSL_COUNT: Ts = 320;
+
+    type BorderHtsIter = StepBy<Range<Ts>>;
+
+    fn border_whole_line_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (-68..156).step_by(4)
+        }
+        let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
+        (-24+invborder..152-invborder).step_by(4)
+    }
+
+    fn border_left_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (-68..0).step_by(4)
+        }
+        let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
+        (-24+invborder..0).step_by(4)
+    }
+
+    fn border_right_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (128..156).step_by(4)
+        }
+        let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
+        (128..152-invborder).step_by(4)
+    }
+
+    #[inline(always)]
+    fn contention(hc: Ts) -> Ts {
+        hc
+    }
+
+    #[inline(always)]
+    fn floating_bus_offset(_hc: Ts) -> Option<u16> {
+        None
+    }
+
+    #[inline(always)]
+    fn snow_interference_coords(_vts: VideoTs) -> Option<CellCoords> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::VFrameTs;
+    use super::*;
+    type TestVideoFrame = PentagonVidFrame;
+    type TestVFTs = VFrameTs<TestVideoFrame>;
+
+    #[test]
+    fn test_no_contention() {
+        for hc in TestVideoFrame::HTS_RANGE {
+            assert_eq!(TestVideoFrame::contention(hc), hc);
+            assert_eq!(TestVideoFrame::floating_bus_offset(hc), None);
+            for vc in 0..TestVideoFrame::VSL_COUNT {
+                assert_eq!(TestVideoFrame::snow_interference_coords(VideoTs::new(vc, hc)), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_video_frame_vts_utils() {
+        assert_eq!(TestVideoFrame::FRAME_TSTATES_COUNT, 71680);
+        assert_eq!(TestVFTs::EOF, TestVFTs::from_tstates(TestVideoFrame::FRAME_TSTATES_COUNT));
+        let items = [((  0, -68),   -68, ( 0, 71612), false, true , (  0, -68)),
+                     ((  0,   0),     0, ( 1,     0), false, true , (  0,   0)),
+                     ((  0,  -1),    -1, ( 0, 71679), false, true , (  0,  -1)),
+                     (( -1,   0),  -224, ( 0, 71456), false, true , ( -1,   0)),
+                     ((  1,   0),   224, ( 1,   224), false, true , (  1,   0)),
+                     ((320,  -1), 71679, ( 1, 71679), true , true , (320,  -1)),
+                     ((320,   0), 71680, ( 2,     0), true , true , (320,   0)),
+                     ((  0, 224),   224, ( 1,   224), false, false, (  1,   0)),
+                     ((640,-223),143137, ( 2, 71457), true,  false, (639,   1))];
+        for ((vc, hc), fts, (nfr, nfts), eof, is_norm, (nvc, nhc)) in items.iter().copied() {
+            let vts = TestVFTs::new(vc, hc);
+            let nvts = TestVFTs::new(nvc, nhc);
+            assert_eq!(TestVideoFrame::vc_hc_to_tstates(vc, hc), fts);
+            assert_eq!(vts.into_tstates(), fts);
+            assert_eq!(TestVFTs::from_tstates(fts), nvts);
+            assert_eq!(vts.into_frame_tstates(1), (nfr, nfts));
+            assert_eq!(vts.is_eof(), eof);
+            assert_eq!(vts.is_normalized(), is_norm);
+            assert_eq!(vts.normalized(), nvts);
+        }
+    }
+}
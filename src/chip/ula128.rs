@@ -17,6 +17,7 @@ mod screen;
 
 use core::fmt;
 use core::convert::TryFrom;
+use core::ops::RangeInclusive;
 
 use crate::z80emu::{*, host::Result};
 #[cfg(feature = "snapshot")]
@@ -25,7 +26,8 @@ use serde::{Serialize, Deserialize};
 use crate::bus::{BusDevice, VFNullDevice};
 use crate::clock::{VFrameTs, VideoTs, VFrameTsCounter, MemoryContention};
 use crate::chip::{
-    InnerAccess, ControlUnit, MemoryAccess, Ula128MemFlags, UlaControl,
+    InnerAccess, ControlUnit, FrameStats, MemoryAccess, Ula128MemFlags, UlaControl, WriteWatchHook, FrameEndHook,
+    PageSwapHook,
     ula::{
         Ula, UlaControlExt, UlaCpuExt,
         frame_cache::UlaFrameCache
@@ -33,6 +35,7 @@ use crate::chip::{
 };
 use crate::memory::{Memory128k, ZxMemory, MemoryExtension, NoMemoryExtension, MemoryKind};
 use crate::video::Video;
+use crate::peripherals::ZXKeyboardMap;
 pub use video::Ula128VidFrame;
 
 
@@ -66,6 +69,19 @@ pub enum MemPage8 {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TryFromU8MemPage8Error(pub u8);
 
+/// The current memory paging configuration of [Ula128], as returned by [Ula128::paging_state].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ula128Paging {
+    /// The RAM bank paged in at `0xC000`.
+    pub ram_bank: MemPage8,
+    /// The ROM bank paged in at `0x0000`.
+    pub rom_bank: usize,
+    /// `true` if the shadow screen (in the RAM bank 7) is being displayed.
+    pub shadow_screen: bool,
+    /// `true` if the memory paging has been locked until the next hard reset.
+    pub locked: bool
+}
+
 /// 128k ULA (Uncommitted Logic Array).
 ///
 /// See [Ula] for description of generic parameters.
@@ -80,14 +96,14 @@ pub struct Ula128<B=VFNullDevice<Ula128VidFrame>, X=NoMemoryExtension> {
     mem_locked: bool,
 
     #[cfg(feature = "boxed_frame_cache")]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     shadow_frame_cache: Box<UlaFrameCache<Ula128VidFrame>>,
 
     #[cfg(not(feature = "boxed_frame_cache"))]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     shadow_frame_cache: UlaFrameCache<Ula128VidFrame>,
 
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     screen_changes: Vec<VideoTs>,
 }
 
@@ -202,6 +218,75 @@ impl<B, X> UlaControl for Ula128<B, X> {
         self.ula.set_late_timings(late_timings)
     }
 
+    fn interrupt_length(&self) -> u32 {
+        self.ula.interrupt_length()
+    }
+
+    fn set_interrupt_length(&mut self, length: u32) -> bool {
+        self.ula.set_interrupt_length(length)
+    }
+
+    fn audio_sample_rate(&self) -> u32 {
+        self.ula.audio_sample_rate()
+    }
+
+    fn set_audio_sample_rate(&mut self, rate: u32) -> bool {
+        self.ula.set_audio_sample_rate(rate)
+    }
+
+    fn cpu_clock_rate(&self) -> u32 {
+        self.ula.cpu_clock_rate()
+    }
+
+    fn set_cpu_clock_rate(&mut self, rate: u32) -> bool {
+        self.ula.set_cpu_clock_rate(rate)
+    }
+
+    fn has_precise_border(&self) -> bool {
+        self.ula.has_precise_border()
+    }
+
+    fn set_precise_border(&mut self, enabled: bool) -> bool {
+        self.ula.set_precise_border(enabled)
+    }
+
+    fn has_snow_interference(&self) -> bool {
+        self.ula.has_snow_interference()
+    }
+
+    fn set_snow_interference(&mut self, enabled: bool) -> bool {
+        self.ula.set_snow_interference(enabled)
+    }
+
+    fn add_write_watchpoint(&mut self, range: RangeInclusive<u16>) -> bool {
+        self.ula.add_write_watchpoint(range)
+    }
+
+    fn clear_write_watchpoints(&mut self) {
+        self.ula.clear_write_watchpoints()
+    }
+
+    fn press_key_for_frames(&mut self, keys: ZXKeyboardMap, frames: u32) -> bool {
+        self.ula.press_key_for_frames(keys, frames)
+    }
+
+    fn set_frame_end_hook(&mut self, hook: Option<FrameEndHook>) -> bool {
+        self.ula.set_frame_end_hook(hook)
+    }
+
+    fn set_page_swap_hook(&mut self, hook: Option<PageSwapHook>) -> bool {
+        self.ula.page_swap_hook.0 = hook;
+        true
+    }
+
+    fn set_write_watch_hook(&mut self, hook: Option<WriteWatchHook>) -> bool {
+        self.ula.set_write_watch_hook(hook)
+    }
+
+    fn audio_debt_tstates(&self) -> i32 {
+        self.ula.audio_debt_tstates()
+    }
+
     fn ula128_mem_port_value(&self) -> Option<Ula128MemFlags> {
         let mut flags = Ula128MemFlags::empty()
                         .with_last_ram_page_bank(self.mem_page3_bank.into());
@@ -226,6 +311,31 @@ impl<B, X> UlaControl for Ula128<B, X> {
 }
 
 impl<B, X> Ula128<B, X> {
+    /// Returns the target audio channels for the `[A, B, C]` channels of the built-in AY-3-8912 sound
+    /// chip, matching the wiring of the original machine (ZX Spectrum 128k/+2).
+    ///
+    /// The original machines mix all three AY channels into a single mono output, so the returned
+    /// channels are all `0`. Pass the result to `AyAudioBusDevice::render_ay_audio` to render the AY
+    /// audio on a single channel. Stereo add-ons and clones wiring the AY in the "ABC" or "ACB" stereo
+    /// configurations need a different channel map.
+    pub fn ay_stereo_channels(&self) -> [usize; 3] {
+        [0, 0, 0]
+    }
+
+    /// Returns the current memory paging configuration.
+    pub fn paging_state(&self) -> Ula128Paging {
+        let rom_bank = match self.ula.memory.page_bank(0) {
+            Ok((MemoryKind::Rom, rom_bank)) => rom_bank,
+            _ => 0
+        };
+        Ula128Paging {
+            ram_bank: self.mem_page3_bank,
+            rom_bank,
+            shadow_screen: self.cur_screen_shadow,
+            locked: self.mem_locked
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn memory_contention(&self) -> Ula128MemContention {
         if self.mem_page3_bank as u8 & 1 == 1 { // banks: 1, 3, 5 and 7 are contended
@@ -350,6 +460,10 @@ impl<B, X> ControlUnit for Ula128<B, X>
     {
         self.ula_execute_single_step(cpu, debug)
     }
+
+    fn run_frame_stats<C: Cpu>(&mut self, cpu: &mut C) -> FrameStats {
+        self.ula_run_frame_stats(cpu)
+    }
 }
 
 impl<B, X> UlaControlExt for Ula128<B, X>
@@ -378,6 +492,7 @@ mod tests {
     fn test_ula128() {
         assert_eq!(<Ula128 as Video>::VideoFrame::FRAME_TSTATES_COUNT, 70908);
         let mut ula: Ula128 = Default::default();
+        assert_eq!(ula.ay_stereo_channels(), [0, 0, 0]);
         for bank in 0..8 {
             let flags = Ula128MemFlags::with_last_ram_page_bank(Ula128MemFlags::empty(), bank);
             ula.set_ula128_mem_port_value(flags);
@@ -393,4 +508,123 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_ula128_interrupt_length() {
+        let mut ula: Ula128 = Default::default();
+        assert_eq!(ula.interrupt_length(), 36);
+        assert!(ula.is_irq(VideoTs::new(0, 35)));
+        assert!(!ula.is_irq(VideoTs::new(0, 36)));
+        assert!(ula.set_interrupt_length(32));
+        assert!(!ula.is_irq(VideoTs::new(0, 32)));
+        let ula3: crate::chip::ula3::Ula3 = Default::default();
+        assert_eq!(ula3.interrupt_length(), crate::chip::DEFAULT_INTERRUPT_LENGTH);
+    }
+
+    #[test]
+    fn test_ula128_paging_state() {
+        let mut ula: Ula128 = Default::default();
+        assert_eq!(ula.paging_state(), Ula128Paging {
+            ram_bank: MemPage8::Bank0, rom_bank: 0, shadow_screen: false, locked: false
+        });
+        let flags = Ula128MemFlags::empty().with_last_ram_page_bank(7)
+                    | Ula128MemFlags::ROM_BANK | Ula128MemFlags::SCREEN_BANK | Ula128MemFlags::LOCK_MMU;
+        ula.set_ula128_mem_port_value(flags);
+        assert_eq!(ula.paging_state(), Ula128Paging {
+            ram_bank: MemPage8::Bank7, rom_bank: 1, shadow_screen: true, locked: true
+        });
+        ula.reset(&mut Z80NMOS::default(), true);
+        assert_eq!(ula.paging_state().ram_bank, MemPage8::Bank0);
+        assert!(!ula.paging_state().locked);
+    }
+
+    #[test]
+    fn test_ula128_page_swap_hook() {
+        use std::sync::{Arc, Mutex};
+        use crate::z80emu::Io;
+        use crate::memory::MemoryKind;
+        type Swaps = Vec<(u8, MemoryKind, usize, VideoTs)>;
+        let swaps: Arc<Mutex<Swaps>> = Arc::default();
+        let hook_swaps = Arc::clone(&swaps);
+        let hook = move |page, kind, bank, ts| hook_swaps.lock().unwrap().push((page, kind, bank, ts));
+        let mut ula: Ula128 = Default::default();
+        let ts = VideoTs::new(10, 20);
+        ula.write_io(0x7ffd, 1, ts);
+        assert!(swaps.lock().unwrap().is_empty());
+        assert!(ula.set_page_swap_hook(Some(Box::new(hook))));
+        ula.write_io(0x7ffd, 0x13, ts);
+        assert_eq!(*swaps.lock().unwrap(), [(0, MemoryKind::Rom, 1, ts), (3, MemoryKind::Ram, 3, ts)]);
+        swaps.lock().unwrap().clear();
+        // the screen bank change alone doesn't swap pages
+        ula.write_io(0x7ffd, 0x1b, ts);
+        assert!(swaps.lock().unwrap().is_empty());
+        ula.set_ula128_mem_port_value(Ula128MemFlags::empty());
+        assert!(swaps.lock().unwrap().is_empty());
+        assert!(ula.set_page_swap_hook(None));
+        ula.write_io(0x7ffd, 0x17, ts);
+        assert!(swaps.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ula128_floating_bus_value() {
+        let mut ula: Ula128 = Default::default();
+        ula.ula.memory.screen_mut(0).unwrap().iter_mut().for_each(|p| *p = 0x11);
+        ula.ula.memory.screen_mut(1).unwrap().iter_mut().for_each(|p| *p = 0x22);
+        ula.set_video_ts(VideoTs::new(0, 0));
+        assert_eq!(ula.floating_bus_value(), 0xFF);
+        ula.set_video_ts(VideoTs::new(Ula128VidFrame::VSL_PIXELS.start, -2));
+        assert_eq!(ula.floating_bus_value(), 0x11);
+        ula.set_ula128_mem_port_value(Ula128MemFlags::SCREEN_BANK);
+        assert_eq!(ula.floating_bus_value(), 0x22);
+        ula.set_video_ts(VideoTs::new(Ula128VidFrame::VSL_PIXELS.start, 2));
+        assert_eq!(ula.floating_bus_value(), 0xFF);
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn test_ula128_snapshot_mid_frame() {
+        use crate::z80emu::Io;
+        use crate::video::{BorderSize, pixel::{PixelBufA24, SpectrumPalRGB24}};
+        let mut ula: Ula128 = Default::default();
+        ula.ula.memory.screen_mut(0).unwrap().iter_mut().for_each(|p| *p = 0x55);
+        ula.ula.memory.screen_mut(1).unwrap().iter_mut().for_each(|p| *p = 0x0F);
+        // swap the screen mid-frame
+        let ts = VideoTs::new(150, 0);
+        ula.set_video_ts(ts);
+        ula.write_io(0x7ffd, 0x08, ts);
+        ula.set_video_ts(VideoTs::new(250, 0));
+
+        let sernap = serde_json::to_string(&ula).unwrap();
+        let mut ula_de: Ula128 = serde_json::from_str(&sernap).unwrap();
+        assert_eq!(ula_de.screen_changes, ula.screen_changes);
+
+        let border_size = BorderSize::Full;
+        let (width, height) = <Ula128>::render_size_pixels(border_size);
+        let pitch = width as usize * 3;
+        let mut buffer = vec![0u8; pitch * height as usize];
+        let mut buffer_de = vec![0u8; pitch * height as usize];
+        ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer, pitch, border_size);
+        ula_de.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer_de, pitch, border_size);
+        assert!(buffer == buffer_de);
+    }
+
+    #[test]
+    fn test_ula128_video_clock() {
+        let mut ula: Ula128 = Default::default();
+        let frame_ts = <Ula128 as Video>::VideoFrame::FRAME_TSTATES_COUNT;
+        ula.set_video_ts(VideoTs::new(100, 20));
+        let clock = ula.current_video_clock();
+        let vfts = VFrameTs::from(clock);
+        assert_eq!(VideoTs::from(vfts), ula.current_video_ts());
+        let ts = vfts.into_tstates();
+        let delta: crate::clock::FTs = 1000;
+        assert_eq!((vfts + delta).into_tstates(), ts + delta);
+        let next = vfts + frame_ts;
+        assert!(next.is_eof());
+        assert_eq!(next.into_tstates(), ts + frame_ts);
+        assert_eq!(next.saturating_sub_frame().into_tstates(), ts);
+        assert!(!clock.is_contended_address(0xC000));
+        ula.set_ula128_mem_port_value(Ula128MemFlags::with_last_ram_page_bank(Ula128MemFlags::empty(), 1));
+        assert!(ula.current_video_clock().is_contended_address(0xC000));
+    }
 }
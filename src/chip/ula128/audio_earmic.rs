@@ -14,7 +14,7 @@ use crate::peripherals::ay::audio::AyAudioFrame;
 use crate::peripherals::bus::ay::AyAudioBusDevice;
 use crate::clock::VFrameTs;
 use crate::bus::BusDevice;
-use crate::chip::{EarIn, MicOut, ReadEarMode};
+use crate::chip::{EarIn, MicOut, EarMicOut, ReadEarMode};
 use super::{Ula128, InnerUla, Ula128VidFrame};
 
 #[cfg(feature = "peripherals")]
@@ -42,6 +42,11 @@ impl<B, D, X> AudioFrame<B> for Ula128<D, X>
     fn get_audio_frame_end_time(&self) -> FTs {
         self.ula.get_audio_frame_end_time()
     }
+
+    #[inline]
+    fn end_audio_frame(&self, blep: &mut B) -> usize {
+        self.ula.end_audio_frame(blep)
+    }
 }
 
 impl<B, D, X> EarMicOutAudioFrame<B> for Ula128<D, X>
@@ -60,6 +65,18 @@ impl<B, D, X> EarInAudioFrame<B> for Ula128<D, X>
     fn render_ear_in_audio_frame<L: AmpLevels<B::SampleDelta>>(&self, blep: &mut B, channel: usize) {
         self.ula.render_ear_in_audio_frame::<L>(blep, channel)
     }
+
+    #[inline(always)]
+    fn render_ear_in_audio_frame_smooth<L: AmpLevels<B::SampleDelta>>(
+            &self,
+            blep: &mut B,
+            channel: usize,
+            transition_ts: u32
+        )
+        where B::SampleDelta: MulNorm + FromSample<f32>
+    {
+        self.ula.render_ear_in_audio_frame_smooth::<L>(blep, channel, transition_ts)
+    }
 }
 
 impl<D, X> EarIn for Ula128<D, X> {
@@ -81,6 +98,10 @@ impl<D, X> EarIn for Ula128<D, X> {
         self.ula.read_ear_in_count()
     }
 
+    fn frames_since_last_ear_read(&self) -> u32 {
+        self.ula.frames_since_last_ear_read()
+    }
+
     fn read_ear_mode(&self) -> ReadEarMode {
         self.ula.read_ear_mode()
     }
@@ -96,3 +117,10 @@ impl<'a, D: 'a, X: 'a> MicOut<'a> for Ula128<D, X> {
         self.ula.mic_out_pulse_iter()
     }
 }
+
+impl<'a, D: 'a, X: 'a> EarMicOut<'a> for Ula128<D, X> {
+    type EarMicIter = <InnerUla<D, X> as EarMicOut<'a>>::EarMicIter;
+    fn earmic_out_iter(&'a self) -> Self::EarMicIter {
+        self.ula.earmic_out_iter()
+    }
+}
@@ -53,8 +53,11 @@ impl<B, X> Io for Ula128<B, X>
 
     fn write_io(&mut self, port: u16, data: u8, ts: VideoTs) -> (Option<()>, Option<NonZeroU16>) {
         if Ula128MemPortAddress::match_port(port) {
+            let prev_banks = self.ula.paged_banks_if_hooked();
             // (self.write_mem_port(data, ts).then_some(()), None) // after stabilizing # 64260
-            if self.write_mem_port(data, ts) {
+            let contention_changed = self.write_mem_port(data, ts);
+            self.ula.report_page_swaps(prev_banks, ts);
+            if contention_changed {
                 return (Some(()), None)
             }
             (None, None)
@@ -94,7 +97,8 @@ impl<B, X> Memory for Ula128<B, X>
     #[inline]
     fn write_mem(&mut self, addr: u16, val: u8, ts: VideoTs) {
         self.update_frame_cache(addr, ts);
-        self.ula.memory.write(addr, val);
+        self.ula.watch_write(addr, val, ts);
+        self.ula.memext.write_mem(addr, val, &mut self.ula.memory);
     }
 }
 
@@ -120,7 +124,7 @@ impl<B, X> Ula128<B, X> {
     }
 
     #[inline]
-    fn floating_bus(&self, ts: VideoTs) -> u8 {
+    pub(super) fn floating_bus(&self, ts: VideoTs) -> u8 {
         if let Some(addr) = Ula128VidFrame::floating_bus_screen_address(ts) {
             self.ula.memory.read_screen(self.cur_screen_shadow.into(), addr)
         }
@@ -5,8 +5,9 @@
 
     For the full copyright notice, see the lib.rs file.
 */
-use core::iter::StepBy;
+use core::iter::{Copied, StepBy};
 use core::ops::Range;
+use core::slice;
 
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
@@ -43,20 +44,33 @@ impl VideoFrame for Ula128VidFrame {
     const VSL_BORDER_BOT: Ts = 303;
     /// A total number of video scan lines.
     const VSL_COUNT: Ts = 311;
+    /// The CPU clock rate of the 128k models.
+    const CPU_HZ: u32 = 3_546_900;
+    /// The 128k ULA asserts the frame interrupt signal for 36 T-states.
+    const INT_LENGTH_TS: Ts = 36;
 
     type BorderHtsIter = StepBy<Range<Ts>>;
 
     fn border_whole_line_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (-70..158).step_by(4)
+        }
         let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
         (-22+invborder..154-invborder).step_by(4)
     }
 
     fn border_left_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (-70..2).step_by(4)
+        }
         let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
         (-22+invborder..2).step_by(4)
     }
 
     fn border_right_hts_iter(border_size: BorderSize) -> Self::BorderHtsIter {
+        if border_size == BorderSize::Overscan {
+            return (130..158).step_by(4)
+        }
         let invborder = ((MAX_BORDER_SIZE - Self::border_size_pixels(border_size))/2) as Ts;
         (130..154-invborder).step_by(4)
     }
@@ -126,6 +140,41 @@ impl<D, X> Video for Ula128<D, X> {
         .render_pixels::<B, P, Self::VideoFrame>(buffer, pitch)
     }
 
+    fn render_video_frame_with_line_hook<'a, B, P, F>(
+            &mut self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            line_hook: F
+        )
+        where B: PixelBuffer<'a>,
+              P: Palette<Pixel=B::Pixel>,
+              F: FnMut(usize, VideoTs)
+    {
+        create_ula128_renderer(border_size,
+                               &mut self.ula,
+                               self.beg_screen_shadow,
+                               &self.shadow_frame_cache,
+                               &mut self.screen_changes)
+        .render_pixels_with_line_hook::<B, P, Self::VideoFrame, F>(buffer, pitch, line_hook)
+    }
+
+    fn render_video_scanlines<'a, B: PixelBuffer<'a>, P: Palette<Pixel=B::Pixel>>(
+            &mut self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            vsl_range: Range<Ts>
+        )
+    {
+        create_ula128_scanlines_renderer(border_size,
+                                         &self.ula,
+                                         self.beg_screen_shadow,
+                                         &self.shadow_frame_cache,
+                                         &self.screen_changes)
+        .render_pixels_range::<B, P, Self::VideoFrame>(buffer, pitch, vsl_range)
+    }
+
     fn visible_screen_bank(&self) -> usize {
         self.cur_screen_shadow.into()
     }
@@ -146,6 +195,10 @@ impl<D, X> Video for Ula128<D, X> {
     fn flash_state(&self) -> bool {
         self.ula.flash_state()
     }
+
+    fn floating_bus_value(&self) -> u8 {
+        self.floating_bus(self.ula.current_video_ts())
+    }
 }
 
 impl<B, X> Ula128<B, X> {
@@ -172,7 +225,7 @@ impl<B, X> Ula128<B, X> {
 
     #[inline(always)]
     pub(super) fn update_snow_interference(&mut self, ts: VideoTs, ir: u16) {
-        if self.memory_contention().is_contended_address(ir) {
+        if self.ula.snow_interference && self.memory_contention().is_contended_address(ir) {
             if let Some(coords) = Ula128VidFrame::snow_interference_coords(ts) {
                 let (screen, frame_cache) = if self.cur_screen_shadow {
                     (self.ula.memory.screen_ref(1).unwrap(), &mut self.shadow_frame_cache)
@@ -200,6 +253,7 @@ pub(crate) fn create_ula128_renderer<'a, V, M, B, X>(
     let swap_screens = beg_screen_shadow;
     let border = ula.border_color();
     let invert_flash = ula.flash_state();
+    let precise_border = ula.precise_border;
     let (border_changes, memory, frame_cache0) = ula.video_render_data_view();
     let frame_cache1 = shadow_frame_cache;
     let screen0 = memory.screen_ref(0).unwrap();
@@ -218,7 +272,40 @@ pub(crate) fn create_ula128_renderer<'a, V, M, B, X>(
         border,
         border_size,
         border_changes: border_changes.drain(..),
-        invert_flash
+        invert_flash,
+        precise_border
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn create_ula128_scanlines_renderer<'a, V, M, B, X>(
+            border_size: BorderSize,
+            ula: &'a Ula<M, B, X, V>,
+            beg_screen_shadow: bool,
+            shadow_frame_cache: &'a UlaFrameCache<V>,
+            screen_changes: &'a [VideoTs]
+        ) -> Renderer<Ula128FrameProducer<'a, V, Copied<slice::Iter<'a, VideoTs>>>, Copied<slice::Iter<'a, VideoTsData3>>>
+    where V: VideoFrame,
+          M: ZxMemory,
+          Ula<M, B, X, V>: Video
+{
+    let invert_flash = ula.flash_state();
+    let (border, border_changes, memory, frame_cache0) = ula.video_render_data_ref();
+    let frame_image_producer = Ula128FrameProducer::new(
+        beg_screen_shadow,
+        memory.screen_ref(0).unwrap(),
+        memory.screen_ref(1).unwrap(),
+        frame_cache0,
+        shadow_frame_cache,
+        screen_changes.iter().copied()
+    );
+    Renderer {
+        frame_image_producer,
+        border,
+        border_size,
+        border_changes: border_changes.iter().copied(),
+        invert_flash,
+        precise_border: ula.precise_border
     }
 }
 
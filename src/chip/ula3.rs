@@ -16,6 +16,7 @@ mod screen;
 
 use core::convert::TryFrom;
 use core::fmt;
+use core::ops::RangeInclusive;
 
 #[cfg(feature = "snapshot")]
 use serde::{Serialize, Deserialize};
@@ -24,8 +25,9 @@ use crate::z80emu::{*, host::Result};
 use crate::bus::{BusDevice, VFNullDevice};
 use crate::clock::{VFrameTs, VideoTs, VFrameTsCounter, MemoryContention};
 use crate::chip::{
-    Ula128MemFlags, Ula3CtrlFlags, Ula3Paging, UlaControl,
-    InnerAccess, EarIn, ReadEarMode, ControlUnit, MemoryAccess,
+    Ula128MemFlags, Ula3CtrlFlags, Ula3Paging, UlaControl, WriteWatchHook, FrameEndHook,
+    PageSwapHook,
+    InnerAccess, EarIn, ReadEarMode, ControlUnit, FrameStats, MemoryAccess,
     ula::{
         Ula, UlaControlExt, UlaCpuExt,
         frame_cache::UlaFrameCache
@@ -34,6 +36,7 @@ use crate::chip::{
 };
 use crate::memory::{ZxMemory, Memory128kPlus, MemoryExtension, NoMemoryExtension};
 use crate::video::Video;
+use crate::peripherals::ZXKeyboardMap;
 pub use video::Ula3VidFrame;
 
 /// A struct implementing [MemoryContention] for any 8kb memory page being contended.
@@ -80,14 +83,14 @@ pub struct Ula3<B=VFNullDevice<Ula3VidFrame>, X=NoMemoryExtension> {
     mem_locked: bool,
 
     #[cfg(feature = "boxed_frame_cache")]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     shadow_frame_cache: Box<UlaFrameCache<Ula3VidFrame>>,
 
     #[cfg(not(feature = "boxed_frame_cache"))]
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     shadow_frame_cache: UlaFrameCache<Ula3VidFrame>,
 
-    #[cfg_attr(feature = "snapshot", serde(skip))]
+    #[cfg_attr(feature = "snapshot", serde(default))]
     screen_changes: Vec<VideoTs>
 }
 
@@ -192,6 +195,67 @@ impl<B, X> UlaControl for Ula3<B, X> {
         self.ula.set_late_timings(late_timings)
     }
 
+    fn interrupt_length(&self) -> u32 {
+        self.ula.interrupt_length()
+    }
+
+    fn set_interrupt_length(&mut self, length: u32) -> bool {
+        self.ula.set_interrupt_length(length)
+    }
+
+    fn audio_sample_rate(&self) -> u32 {
+        self.ula.audio_sample_rate()
+    }
+
+    fn set_audio_sample_rate(&mut self, rate: u32) -> bool {
+        self.ula.set_audio_sample_rate(rate)
+    }
+
+    fn cpu_clock_rate(&self) -> u32 {
+        self.ula.cpu_clock_rate()
+    }
+
+    fn set_cpu_clock_rate(&mut self, rate: u32) -> bool {
+        self.ula.set_cpu_clock_rate(rate)
+    }
+
+    fn has_precise_border(&self) -> bool {
+        self.ula.has_precise_border()
+    }
+
+    fn set_precise_border(&mut self, enabled: bool) -> bool {
+        self.ula.set_precise_border(enabled)
+    }
+
+    fn add_write_watchpoint(&mut self, range: RangeInclusive<u16>) -> bool {
+        self.ula.add_write_watchpoint(range)
+    }
+
+    fn clear_write_watchpoints(&mut self) {
+        self.ula.clear_write_watchpoints()
+    }
+
+    fn press_key_for_frames(&mut self, keys: ZXKeyboardMap, frames: u32) -> bool {
+        self.ula.press_key_for_frames(keys, frames)
+    }
+
+    fn set_frame_end_hook(&mut self, hook: Option<FrameEndHook>) -> bool {
+        self.ula.set_frame_end_hook(hook)
+    }
+
+    fn set_page_swap_hook(&mut self, hook: Option<PageSwapHook>) -> bool {
+        self.ula.page_swap_hook.0 = hook;
+        true
+    }
+
+    fn set_write_watch_hook(&mut self, hook: Option<WriteWatchHook>) -> bool {
+        self.ula.set_write_watch_hook(hook)
+    }
+
+    fn audio_debt_tstates(&self) -> i32 {
+        self.ula.audio_debt_tstates()
+    }
+
     fn ula128_mem_port_value(&self) -> Option<Ula128MemFlags> {
         let mut flags = Ula128MemFlags::empty()
                         .with_last_ram_page_bank(self.mem_page3_bank.into());
@@ -231,6 +295,17 @@ impl<B, X> UlaControl for Ula3<B, X> {
 }
 
 impl<B, X> Ula3<B, X> {
+    /// Returns the target audio channels for the `[A, B, C]` channels of the built-in AY-3-8912 sound
+    /// chip, matching the wiring of the original machine (ZX Spectrum +2A/+3).
+    ///
+    /// The original machines mix all three AY channels into a single mono output, so the returned
+    /// channels are all `0`. Pass the result to `AyAudioBusDevice::render_ay_audio` to render the AY
+    /// audio on a single channel. Stereo add-ons and clones wiring the AY in the "ABC" or "ACB" stereo
+    /// configurations need a different channel map.
+    pub fn ay_stereo_channels(&self) -> [usize; 3] {
+        [0, 0, 0]
+    }
+
     #[inline(always)]
     pub(super) fn memory_contention(&self) -> Ula3MemContention {
         if let Some(paging) = self.mem_special_paging {
@@ -432,6 +507,10 @@ impl<B, X> ControlUnit for Ula3<B, X>
     {
         self.ula_execute_single_step(cpu, debug)
     }
+
+    fn run_frame_stats<C: Cpu>(&mut self, cpu: &mut C) -> FrameStats {
+        self.ula_run_frame_stats(cpu)
+    }
 }
 
 impl<B, X> UlaControlExt for Ula3<B, X>
@@ -460,6 +539,7 @@ mod tests {
     fn test_ula3() {
         assert_eq!(<Ula3 as Video>::VideoFrame::FRAME_TSTATES_COUNT, 70908);
         let mut ula: Ula3 = Default::default();
+        assert_eq!(ula.ay_stereo_channels(), [0, 0, 0]);
         for bank in 0..8 {
             let flags = Ula128MemFlags::with_last_ram_page_bank(Ula128MemFlags::empty(), bank);
             ula.set_ula128_mem_port_value(flags);
@@ -499,4 +579,40 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_ula3_paging_ports() {
+        use crate::z80emu::Io;
+        use crate::memory::MemoryKind;
+        let mut ula: Ula3 = Default::default();
+        let ts = VideoTs::default();
+        let pages = |ula: &Ula3| -> Vec<(MemoryKind, usize)> {
+            (0..4).map(|page| ula.ula.memory.page_bank(page).unwrap()).collect()
+        };
+        let rom = |bank| (MemoryKind::Rom, bank);
+        let ram = |bank| (MemoryKind::Ram, bank);
+        assert_eq!(pages(&ula), [rom(0), ram(5), ram(2), ram(0)]);
+        // 4 ROMs selected with bit 4 of 0x7FFD and bit 2 of 0x1FFD
+        for (rom_hi, rom_lo, bank) in [(0, 0, 0), (0, 0x10, 1), (4, 0, 2), (4, 0x10, 3)] {
+            ula.write_io(0x1ffd, rom_hi, ts);
+            ula.write_io(0x7ffd, rom_lo | 6, ts);
+            assert_eq!(pages(&ula), [rom(bank), ram(5), ram(2), ram(6)]);
+        }
+        // special all-RAM configurations
+        for (data, banks) in [(1, [0, 1, 2, 3]), (3, [4, 5, 6, 7]), (5, [4, 5, 6, 3]), (7, [4, 7, 6, 3])] {
+            ula.write_io(0x1ffd, data, ts);
+            assert_eq!(pages(&ula), banks.iter().map(|&b| ram(b)).collect::<Vec<_>>());
+            // 0x7FFD doesn't change the paging in the special mode
+            ula.write_io(0x7ffd, 1, ts);
+            assert_eq!(pages(&ula), banks.iter().map(|&b| ram(b)).collect::<Vec<_>>());
+        }
+        // back to the normal paging, the ROM and the top RAM bank selected while in the special mode
+        ula.write_io(0x1ffd, 4, ts);
+        assert_eq!(pages(&ula), [rom(2), ram(5), ram(2), ram(1)]);
+        // the lock bit disables both paging ports
+        ula.write_io(0x7ffd, 0x20 | 3, ts);
+        ula.write_io(0x7ffd, 0, ts);
+        ula.write_io(0x1ffd, 3, ts);
+        assert_eq!(pages(&ula), [rom(2), ram(5), ram(2), ram(3)]);
+    }
 }
@@ -71,8 +71,11 @@ impl<B, X> Io for Ula3<B, X>
     fn write_io(&mut self, port: u16, data: u8, ts: VideoTs) -> (Option<()>, Option<NonZeroU16>) {
         if Ula3Mem1PortAddress::match_port(port) {
             if !self.mem_locked {
+                let prev_banks = self.ula.paged_banks_if_hooked();
                 let flags = Ula128MemFlags::from_bits_truncate(data);
-                if self.set_mem1_port_value(flags, ts) {
+                let contention_changed = self.set_mem1_port_value(flags, ts);
+                self.ula.report_page_swaps(prev_banks, ts);
+                if contention_changed {
                     return (Some(()), None)
                 }
             }
@@ -81,10 +84,12 @@ impl<B, X> Io for Ula3<B, X>
         else {
             let (mut res, ws) = self.ula.write_io(port, data, ts);
             if Ula3Mem2PortAddress::match_port(port) && !self.mem_locked {
+                let prev_banks = self.ula.paged_banks_if_hooked();
                 let flags = Ula3CtrlFlags::from_bits_truncate(data);
                 if self.set_mem2_port_value(flags) {
                     res = Some(());
                 }
+                self.ula.report_page_swaps(prev_banks, ts);
             }
             (res, ws)
         }
@@ -121,7 +126,8 @@ impl<B, X> Memory for Ula3<B, X>
     #[inline]
     fn write_mem(&mut self, addr: u16, val: u8, ts: VideoTs) {
         self.update_frame_cache(addr, ts);
-        self.ula.memory.write(addr, val);
+        self.ula.watch_write(addr, val, ts);
+        self.ula.memext.write_mem(addr, val, &mut self.ula.memory);
     }
 }
 
@@ -5,6 +5,8 @@
 
     For the full copyright notice, see the lib.rs file.
 */
+use core::iter::Copied;
+use core::slice;
 use std::vec::Drain;
 
 use crate::clock::VideoTs;
@@ -30,6 +32,16 @@ impl<'a, B, X> UlaPlusInner<'a> for Ula3<B, X>
         self.screen_changes.push(ts);
     }
 
+    #[inline(always)]
+    fn watch_write(&mut self, addr: u16, value: u8, ts: VideoTs) {
+        self.ula.watch_write(addr, value, ts)
+    }
+
+    #[inline(always)]
+    fn write_mem_ext(&mut self, addr: u16, value: u8) {
+        self.ula.memext.write_mem(addr, value, &mut self.ula.memory)
+    }
+
     fn update_last_border_color(&mut self, border: BorderColor) -> bool {
         self.ula.update_last_border_color(border)
     }
@@ -77,4 +89,16 @@ impl<'a, B, X> UlaPlusInner<'a> for Ula3<B, X>
             frame_cache_shadow: &self.shadow_frame_cache
         }
     }
+
+    fn video_render_data_ref(
+        &self
+    ) -> VideoRenderDataView<'_, Copied<slice::Iter<'_, VideoTs>>, Self::Memory, Self::VideoFrame>
+    {
+        VideoRenderDataView {
+            screen_changes: self.screen_changes.iter().copied(),
+            memory: &self.ula.memory,
+            frame_cache: &self.ula.frame_cache,
+            frame_cache_shadow: &self.shadow_frame_cache
+        }
+    }
 }
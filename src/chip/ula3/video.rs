@@ -13,7 +13,10 @@ use serde::{Serialize, Deserialize};
 
 use crate::clock::{VideoTs, Ts, VFrameTsCounter};
 use crate::chip::{
-    ula128::{Ula128VidFrame, video::create_ula128_renderer}
+    ula128::{
+        Ula128VidFrame,
+        video::{create_ula128_renderer, create_ula128_scanlines_renderer}
+    }
 };
 use crate::video::{
     BorderSize, BorderColor, PixelBuffer, Palette,
@@ -38,6 +41,8 @@ impl VideoFrame for Ula3VidFrame {
     const VSL_BORDER_BOT: Ts = Ula128VidFrame::VSL_BORDER_BOT;
     /// A total number of video scan lines.
     const VSL_COUNT: Ts = Ula128VidFrame::VSL_COUNT;
+    /// The CPU clock rate of the 128k models.
+    const CPU_HZ: u32 = Ula128VidFrame::CPU_HZ;
 
     type BorderHtsIter = StepBy<Range<Ts>>;
 
@@ -99,6 +104,41 @@ impl<D, X> Video for Ula3<D, X> {
         .render_pixels::<B, P, Self::VideoFrame>(buffer, pitch)
     }
 
+    fn render_video_frame_with_line_hook<'a, B, P, F>(
+            &mut self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            line_hook: F
+        )
+        where B: PixelBuffer<'a>,
+              P: Palette<Pixel=B::Pixel>,
+              F: FnMut(usize, VideoTs)
+    {
+        create_ula128_renderer(border_size,
+                               &mut self.ula,
+                               self.beg_screen_shadow,
+                               &self.shadow_frame_cache,
+                               &mut self.screen_changes)
+        .render_pixels_with_line_hook::<B, P, Self::VideoFrame, F>(buffer, pitch, line_hook)
+    }
+
+    fn render_video_scanlines<'a, B: PixelBuffer<'a>, P: Palette<Pixel=B::Pixel>>(
+            &mut self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            border_size: BorderSize,
+            vsl_range: Range<Ts>
+        )
+    {
+        create_ula128_scanlines_renderer(border_size,
+                                         &self.ula,
+                                         self.beg_screen_shadow,
+                                         &self.shadow_frame_cache,
+                                         &self.screen_changes)
+        .render_pixels_range::<B, P, Self::VideoFrame>(buffer, pitch, vsl_range)
+    }
+
     fn visible_screen_bank(&self) -> usize {
         self.cur_screen_shadow.into()
     }
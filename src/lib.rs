@@ -57,8 +57,11 @@ Additional features:
 * `"compression"` - enables gzip compression/decompression of memory chunks stored in snapshots.
 * `"boxed_frame_cache"` - chipset implementations will have significantly reduced struct sizes
   by boxing their internal [UlaFrameCache] instances at the cost of a minimal performance penalty.
+* `"frame_cache_stats"` - enables counting of the screen cells cached by [UlaFrameCache] in each frame,
+  for profiling purposes.
 * `"sdl2"` - enables audio implementation for [SDL2] hardware abstraction layer.
 * `"cpal"` - enables audio implementation for [cpal] native audio library.
+* `"png"` - enables [video::save_screenshot] for encoding rendered video frames as PNG images.
 
 The default features are:
 
@@ -82,6 +85,7 @@ Responsible for code execution, keyboard input, video, and accessing peripheral
 | [KeyboardInterface][peripherals::KeyboardInterface] | Keyboard input control |
 | [EarIn][chip::EarIn]               | EAR line input access |
 | [MicOut][chip::MicOut]             | MIC line output access |
+| [EarMicOut][chip::EarMicOut]       | EAR and MIC lines output access |
 | [UlaControl][chip::UlaControl]     | Accessors for specialized ULA functionality |
 
 Audio output:
@@ -1184,4 +1184,35 @@ mod tests {
         test_page(&mem3, 2, b"RAM2");
         test_page(&mem3, 3, b"RAM0");
     }
+
+    #[test]
+    fn memory_load_rom_works() {
+        let mut mem = Memory128k::default();
+        let rom0 = [b'R',b'O',b'M',b'0'].repeat(0x1000);
+        let rom1 = [b'R',b'O',b'M',b'1'].repeat(0x1000);
+        mem.load_rom_bytes(1, &rom0).unwrap();
+        test_rom_bank(&mem, 0, &[!0;4]);
+        test_rom_bank(&mem, 1, b"ROM0");
+        mem.load_rom(0, [&rom1[..], &rom0[..]].concat().as_slice()).unwrap();
+        test_rom_bank(&mem, 0, b"ROM1");
+        test_rom_bank(&mem, 1, b"ROM0");
+        for &(bank, size, expected) in &[(0, 0x3fff, 0x4000), (0, 0x4001, 0x8000), (0, 0x8001, 0x8000),
+                                         (1, 0x8000, 0x4000), (0, 0, 0x4000)] {
+            match mem.load_rom(bank, &[0u8; 0x8001][..size]) {
+                Err(ZxMemoryError::InvalidRomSize { expected: e, found }) => {
+                    assert_eq!(e, expected);
+                    assert_eq!(found, size);
+                }
+                res => panic!("unexpected result: {:?}", res)
+            }
+        }
+        assert!(matches!(mem.load_rom_bytes(2, &rom0), Err(ZxMemoryError::InvalidBankIndex)));
+        test_rom_bank(&mem, 0, b"ROM1");
+        test_rom_bank(&mem, 1, b"ROM0");
+        let mut mem = Memory128kPlus::default();
+        mem.load_rom_bytes(2, &[&rom0[..], &rom1[..]].concat()).unwrap();
+        assert_eq!(mem.rom_bank_ref(1).unwrap(), &[!0u8; 0x4000][..]);
+        assert_eq!(mem.rom_bank_ref(2).unwrap(), &rom0[..]);
+        assert_eq!(mem.rom_bank_ref(3).unwrap(), &rom1[..]);
+    }
 }
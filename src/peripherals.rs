@@ -12,6 +12,9 @@
 //! Please see also [this](http://rk.nvg.ntnu.no/sinclair/computers/zxspectrum/spec48versions.htm#issue1)
 use bitflags::bitflags;
 
+#[cfg(feature = "snapshot")]
+use serde::{Serialize, Deserialize};
+
 #[cfg(feature = "peripherals")]
 pub use spectrusty_peripherals::*;
 
@@ -19,6 +22,8 @@ bitflags! {
     /// Every key's state is encoded as a single bit on this 40-bit flag type.
     /// * Bit = 1 a key is being pressed.
     /// * Bit = 0 a key is not being pressed.
+    #[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
+    #[cfg_attr(feature = "snapshot", serde(from = "u64", into = "u64"))]
     #[derive(Default)]
     pub struct ZXKeyboardMap: u64 {
         const V  = 0x00_0000_0001;
@@ -64,6 +69,18 @@ bitflags! {
     }
 }
 
+impl From<u64> for ZXKeyboardMap {
+    fn from(keymap: u64) -> Self {
+        ZXKeyboardMap::from_bits_truncate(keymap)
+    }
+}
+
+impl From<ZXKeyboardMap> for u64 {
+    fn from(keymap: ZXKeyboardMap) -> Self {
+        keymap.bits()
+    }
+}
+
 /// An interface for providing changes of a **ZX Spectrum** keyboard state to one of the `ULA` chipset emulators.
 ///
 /// This trait is implemented by [ControlUnit][spectrusty_core::chip::ControlUnit] implementations which provide
@@ -96,6 +113,10 @@ impl ZXKeyboardMap {
     /// 0xfd [G], [F], [D], [S],  [A]   0xbf [H], [J], [K],  [L], [EN]
     /// 0xfe [V], [C], [X], [Z], [CS]   0x7f [B], [N], [M], [SS], [BR]
     /// ```
+    ///
+    /// `line` is the high byte of the I/O port address. When more than one bit of `line` is reset, all
+    /// the indicated half-rows are scanned at once and the result has a key bit reset if that key is
+    /// pressed in any of the selected half-rows, as if the results of reading each half-row were ANDed.
     pub fn read_keyboard(self, line: u8) -> u8 {
         let mask = !line;
         let mut res: u8 = !0;
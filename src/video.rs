@@ -9,6 +9,12 @@
 pub mod frame_cache;
 mod render_pixels;
 mod render_pixels_plus;
+mod scanline_filter;
+#[cfg(feature = "png")]
+mod screenshot;
 pub use spectrusty_core::video::*;
 pub use render_pixels::Renderer;
 pub use render_pixels_plus::*;
+pub use scanline_filter::*;
+#[cfg(feature = "png")]
+pub use screenshot::*;
@@ -6,6 +6,7 @@
     For the full copyright notice, see the lib.rs file.
 */
 use core::marker::PhantomData;
+use core::ops::Range;
 use std::iter::Peekable;
 use crate::clock::{VideoTs, Ts, VideoTsData3};
 use crate::video::{
@@ -30,7 +31,12 @@ pub struct Renderer<VD, BI> {
     /// Determines the size of the rendered screen.
     pub border_size: BorderSize,
     /// Flash state.
-    pub invert_flash: bool
+    pub invert_flash: bool,
+    /// Determines if border color changes are rendered with the precision of a single T-state (2 pixels).
+    ///
+    /// Otherwise, border color changes are being latched at the beginning of each 8 pixel border group,
+    /// every 4 T-states.
+    pub precise_border: bool
 }
 
 struct Worker<'a, VD,
@@ -44,6 +50,7 @@ struct Worker<'a, VD,
     border_changes: Peekable<BI>,
     border_size: BorderSize,
     invert_flash: bool,
+    precise_border: bool,
     _palette: PhantomData<P>,
     _vframe: PhantomData<V>,
 }
@@ -58,13 +65,34 @@ impl<VD, BI> Renderer<VD, BI>
             buffer: &'a mut [u8],
             pitch: usize
         )
+    {
+        self.render_pixels_with_line_hook::<B, P, V, _>(buffer, pitch, |_, _| ())
+    }
+
+    /// Renders pixels the same way as [Renderer::render_pixels], calling `line_hook` after each line
+    /// has been rendered.
+    ///
+    /// The `line_hook` receives the index of the rendered line in the `buffer` and the video timestamp
+    /// at the beginning of the corresponding video scan line.
+    #[inline(never)]
+    pub fn render_pixels_with_line_hook<'a, B, P, V, F>(
+            self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            mut line_hook: F
+        )
+        where B: PixelBuffer<'a>,
+              P: Palette<Pixel=B::Pixel>,
+              V: VideoFrame,
+              F: FnMut(usize, VideoTs)
     {
         let Renderer {
             border,
             frame_image_producer,
             border_changes,
             border_size,
-            invert_flash
+            invert_flash,
+            precise_border
         } = self;
 
         let border_pixel = P::get_pixel(border.into());
@@ -72,29 +100,89 @@ impl<VD, BI> Renderer<VD, BI>
         let border_top = V::border_top_vsl_iter(border_size);
         let border_bot = V::border_bot_vsl_iter(border_size);
         let mut line_chunks_vc = buffer.chunks_mut(pitch)
-                                       .zip(border_top.start..border_bot.end);
+                                       .zip(border_top.start..border_bot.end)
+                                       .enumerate();
         let mut worker: Worker<VD, BI, B, P, V> = Worker {
             border_pixel,
             frame_image_producer,
             border_changes,
             border_size,
             invert_flash,
+            precise_border,
             _palette: PhantomData,
             _vframe: PhantomData,
         };
 
         // render top border
-        for (rgb_line, vc) in line_chunks_vc.by_ref().take(border_top.len()) {
+        for (line, (rgb_line, vc)) in line_chunks_vc.by_ref().take(border_top.len()) {
             worker.render_border_line(rgb_line, vc);
+            line_hook(line, VideoTs::new(vc, V::HTS_RANGE.start));
         }
         // render ink/paper area with left and right border
-        for (rgb_line, vc) in line_chunks_vc.by_ref().take(PIXEL_LINES) {
+        for (line, (rgb_line, vc)) in line_chunks_vc.by_ref().take(PIXEL_LINES) {
             worker.render_ink_paper_line(rgb_line, vc);
             worker.frame_image_producer.next_line();
+            line_hook(line, VideoTs::new(vc, V::HTS_RANGE.start));
         }
         // render bottom border
-        for (rgb_line, vc) in line_chunks_vc {
+        for (line, (rgb_line, vc)) in line_chunks_vc {
             worker.render_border_line(rgb_line, vc);
+            line_hook(line, VideoTs::new(vc, V::HTS_RANGE.start));
+        }
+    }
+
+    /// Renders only the video scan lines with indexes within the given `vsl_range`.
+    ///
+    /// The `buffer` should have the layout of the whole rendered frame, as for [Renderer::render_pixels].
+    /// Lines outside of the `vsl_range` are left untouched, border changes and frame data preceding
+    /// the range are skipped.
+    #[inline(never)]
+    pub fn render_pixels_range<'a, B: PixelBuffer<'a>, P: Palette<Pixel=B::Pixel>, V: VideoFrame>(
+            self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            vsl_range: Range<Ts>
+        )
+    {
+        let Renderer {
+            border,
+            frame_image_producer,
+            border_changes,
+            border_size,
+            invert_flash,
+            precise_border
+        } = self;
+
+        let border_pixel = P::get_pixel(border.into());
+        let border_changes = border_changes.peekable();
+        let border_top = V::border_top_vsl_iter(border_size);
+        let border_bot = V::border_bot_vsl_iter(border_size);
+        let vsl_end = vsl_range.end.min(border_bot.end);
+        let mut worker: Worker<VD, BI, B, P, V> = Worker {
+            border_pixel,
+            frame_image_producer,
+            border_changes,
+            border_size,
+            invert_flash,
+            precise_border,
+            _palette: PhantomData,
+            _vframe: PhantomData,
+        };
+
+        for (rgb_line, vc) in buffer.chunks_mut(pitch).zip(border_top.start..vsl_end) {
+            let is_pixel_line = V::VSL_PIXELS.contains(&vc);
+            if vc < vsl_range.start {
+                worker.consume_border_changes(VideoTs::new(vc, V::HTS_RANGE.start));
+            }
+            else if is_pixel_line {
+                worker.render_ink_paper_line(rgb_line, vc);
+            }
+            else {
+                worker.render_border_line(rgb_line, vc);
+            }
+            if is_pixel_line {
+                worker.frame_image_producer.next_line();
+            }
         }
     }
 }
@@ -123,17 +211,45 @@ impl<'a, VD, BI, B, P, V> Worker<'a, VD, BI, B, P, V>
     }
 
     #[inline(always)]
-    fn render_border_pixels(&mut self, line_buffer: &mut B, ts: VideoTs) {
-            while let Some(tsc) = self.border_changes.peek().map(|&t| VideoTs::from(t)) {
-                if tsc < ts {
-                    let border = self.border_changes.next().unwrap().into_data();
-                    self.border_pixel = P::get_pixel(border);
-                }
-                else {
-                    break;
-                }
+    fn consume_border_changes(&mut self, ts: VideoTs) {
+        while let Some(tsc) = self.border_changes.peek().map(|&t| VideoTs::from(t)) {
+            if tsc < ts {
+                let border = self.border_changes.next().unwrap().into_data();
+                self.border_pixel = P::get_pixel(border);
+            }
+            else {
+                break;
             }
+        }
+    }
+
+    #[inline(always)]
+    fn render_border_pixels(&mut self, line_buffer: &mut B, ts: VideoTs) {
+        self.consume_border_changes(ts);
+        if self.precise_border {
+            self.render_precise_border_pixels(line_buffer, ts);
+        }
+        else {
             line_buffer.put_pixels(self.border_pixel, 8);
+        }
+    }
+
+    #[inline(never)]
+    fn render_precise_border_pixels(&mut self, line_buffer: &mut B, ts: VideoTs) {
+        let ts_end = VideoTs::new(ts.vc, ts.hc + 4);
+        let mut offset = 0;
+        while let Some(tsc) = self.border_changes.peek().map(|&t| VideoTs::from(t)) {
+            if tsc >= ts_end {
+                break;
+            }
+            // each T-state renders 2 pixels
+            let change_offset = 2 * (tsc.hc - ts.hc) as usize;
+            line_buffer.put_pixels(self.border_pixel, change_offset - offset);
+            offset = change_offset;
+            let border = self.border_changes.next().unwrap().into_data();
+            self.border_pixel = P::get_pixel(border);
+        }
+        line_buffer.put_pixels(self.border_pixel, 8 - offset);
     }
 
     #[inline(never)]
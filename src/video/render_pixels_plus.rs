@@ -9,7 +9,7 @@
 use core::convert::TryFrom;
 use core::fmt;
 use core::marker::PhantomData;
-use core::ops::{Deref, DerefMut};
+use core::ops::{Deref, DerefMut, Range};
 use std::iter::Peekable;
 
 #[cfg(feature = "snapshot")]
@@ -78,6 +78,7 @@ bitflags! {
 pub struct TryFromU8RenderModeError(pub u8);
 
 /// The type used to record changes of ULAplus palette entries.
+#[cfg_attr(feature = "snapshot", derive(Serialize, Deserialize))]
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PaletteChange {
     vts: VideoTs,
@@ -330,6 +331,71 @@ impl<'r, VD, MI, PI> RendererPlus<'r, VD, MI, PI>
             worker.render_border_line(rgb_line, vc);
         }
     }
+
+    /// Renders only the video scan lines with indexes within the given `vsl_range`.
+    ///
+    /// The `buffer` should have the layout of the whole rendered frame, as for [RendererPlus::render_pixels].
+    /// Lines outside of the `vsl_range` are left untouched, mode and palette changes preceding
+    /// the range are applied without rendering.
+    #[inline(never)]
+    pub fn render_pixels_range<'a, B: PixelBuffer<'a>, P: Palette<Pixel=B::Pixel>, V: VideoFrame>(
+            self,
+            buffer: &'a mut [u8],
+            pitch: usize,
+            vsl_range: Range<Ts>
+        )
+    {
+        let RendererPlus {
+            mut frame_image_producer,
+            render_mode,
+            palette,
+            mode_changes,
+            palette_changes,
+            border_size,
+            invert_flash
+        } = self;
+
+        let border_pixel = get_border_pixel::<P>(render_mode, palette);
+        let hi_res_pixel = get_hi_res_ink_pixel::<P>(render_mode, palette);
+        let mode_changes = mode_changes.peekable();
+        let palette_changes = palette_changes.peekable();
+        let border_top = V::border_top_vsl_iter(border_size);
+        let border_bot = V::border_bot_vsl_iter(border_size);
+        let vsl_end = vsl_range.end.min(border_bot.end);
+
+        let mut worker: Worker<MI, PI, B, P, V> = Worker {
+            border_pixel,
+            hi_res_pixel,
+            render_mode,
+            palette,
+            mode_changes,
+            palette_changes,
+            border_size,
+            invert_flash,
+            _palette: PhantomData,
+            _vframe: PhantomData
+        };
+
+        for (rgb_line, vc) in buffer.chunks_mut(pitch).zip(border_top.start..vsl_end) {
+            let is_pixel_line = V::VSL_PIXELS.contains(&vc);
+            if vc < vsl_range.start {
+                let ts = VideoTs::new(vc, V::HTS_RANGE.start);
+                worker.consume_mode_changes(ts);
+                if worker.render_mode.is_palette() {
+                    worker.consume_palette_changes(ts);
+                }
+            }
+            else if is_pixel_line {
+                worker.render_ink_paper_line(rgb_line, &mut frame_image_producer, vc);
+            }
+            else {
+                worker.render_border_line(rgb_line, vc);
+            }
+            if is_pixel_line {
+                frame_image_producer.next_line();
+            }
+        }
+    }
 }
 
 impl<'r, 'a, MI, PI, B, P, V> Worker<'r, 'a, MI, PI, B, P, V>
@@ -0,0 +1,183 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+/// A CRT-like post-processing filter for pixel buffers rendered with [Video::render_video_frame].
+///
+/// The filter darkens every other row of pixels, optionally doubling the vertical resolution.
+///
+/// * `N` is the number of bytes per pixel: `3` e.g. for [PixelBufA24] or `4` e.g. for [PixelBufA32].
+/// * `ALPHA` is the index of the alpha channel byte of each pixel in the memory order, which is left
+///   unmodified. An index not less than `N` means there is no alpha channel.
+///
+/// Use the type alias matching the palette of the rendered pixels, e.g. [ScanlineFilterRGBA32] for
+/// [SpectrumPalRGBA32] or [ScanlineFilterR8G8B8A8] for [SpectrumPalR8G8B8A8]. The packed pixel aliases
+/// take the byte order of the target into account.
+///
+/// [Video::render_video_frame]: crate::video::Video::render_video_frame
+/// [PixelBufA24]: crate::video::pixel::PixelBufA24
+/// [PixelBufA32]: crate::video::pixel::PixelBufA32
+/// [SpectrumPalRGBA32]: crate::video::pixel::SpectrumPalRGBA32
+/// [SpectrumPalR8G8B8A8]: crate::video::pixel::SpectrumPalR8G8B8A8
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ScanlineFilter<const N: usize, const ALPHA: usize = 3> {
+    /// The intensity of the darkened rows: 0 - black, 255 - unmodified.
+    pub level: u8
+}
+
+/// A [ScanlineFilter] for 3 bytes per pixel buffers.
+pub type ScanlineFilter24 = ScanlineFilter<3>;
+/// A [ScanlineFilter] for 4 bytes per pixel buffers with the alpha channel in the last byte.
+pub type ScanlineFilter32 = ScanlineFilter<4>;
+/// A [ScanlineFilter] for `[r, g, b, a]` pixels, e.g. of [SpectrumPalRGBA32][crate::video::pixel::SpectrumPalRGBA32].
+pub type ScanlineFilterRGBA32 = ScanlineFilter<4, 3>;
+/// A [ScanlineFilter] for `[a, r, g, b]` pixels, e.g. of [SpectrumPalARGB32][crate::video::pixel::SpectrumPalARGB32].
+pub type ScanlineFilterARGB32 = ScanlineFilter<4, 0>;
+/// A [ScanlineFilter] for `0xAARRGGBB` packed pixels in the native byte order,
+/// e.g. of [SpectrumPalA8R8G8B8][crate::video::pixel::SpectrumPalA8R8G8B8].
+pub type ScanlineFilterA8R8G8B8 = ScanlineFilter<4, { packed_alpha_index(24) }>;
+/// A [ScanlineFilter] for `0xRRGGBBAA` packed pixels in the native byte order,
+/// e.g. of [SpectrumPalR8G8B8A8][crate::video::pixel::SpectrumPalR8G8B8A8].
+pub type ScanlineFilterR8G8B8A8 = ScanlineFilter<4, { packed_alpha_index(0) }>;
+
+// Returns the memory index of the byte of a packed u32 pixel at the given bit `shift`.
+const fn packed_alpha_index(shift: u32) -> usize {
+    let index = (shift / 8) as usize;
+    if cfg!(target_endian = "little") { index } else { 3 - index }
+}
+
+impl<const N: usize, const ALPHA: usize> Default for ScanlineFilter<N, ALPHA> {
+    fn default() -> Self {
+        ScanlineFilter { level: 160 }
+    }
+}
+
+impl<const N: usize, const ALPHA: usize> ScanlineFilter<N, ALPHA> {
+    /// Creates a new filter with the given intensity `level` of the darkened rows.
+    pub const fn new(level: u8) -> Self {
+        ScanlineFilter { level }
+    }
+    /// Darkens every odd row of pixels in the `buffer` in place.
+    ///
+    /// * `pitch` is the number of bytes in a single row of pixel data, including padding between lines.
+    /// * `width` and `height` are the dimensions of the filtered area in pixels.
+    ///
+    /// Only the first `width` pixels of each row are modified, so the filter can be applied to
+    /// a sub-region of a larger buffer.
+    ///
+    /// # Panics
+    /// Panics if `pitch` is smaller than `width * N`.
+    pub fn apply(&self, buffer: &mut [u8], pitch: usize, width: usize, height: usize) {
+        let line_len = width * N;
+        assert!(pitch >= line_len, "pitch is too small");
+        for line in buffer.chunks_mut(pitch).take(height).skip(1).step_by(2) {
+            self.darken_line(&mut line[..line_len]);
+        }
+    }
+    /// Writes rows of pixels from the `source` buffer to the `target` buffer, doubling the vertical
+    /// resolution. Every source row is copied twice, the second copy being darkened.
+    ///
+    /// * `source_pitch` and `target_pitch` are the number of bytes in a single row of pixel data
+    ///   of the relevant buffer, including padding between lines.
+    /// * `width` and `height` are the dimensions of the source area in pixels.
+    ///
+    /// The `target` buffer should be large enough to hold `2 * height` rows.
+    ///
+    /// # Panics
+    /// Panics if any of the pitch values is smaller than `width * N`.
+    pub fn apply_doubled(
+            &self,
+            source: &[u8],
+            source_pitch: usize,
+            target: &mut [u8],
+            target_pitch: usize,
+            width: usize,
+            height: usize
+        )
+    {
+        let line_len = width * N;
+        assert!(source_pitch >= line_len && target_pitch >= line_len, "pitch is too small");
+        let mut target_lines = target.chunks_mut(target_pitch);
+        for line in source.chunks(source_pitch).take(height) {
+            let line = &line[..line_len];
+            match target_lines.next() {
+                Some(target) => target[..line_len].copy_from_slice(line),
+                None => break
+            }
+            match target_lines.next() {
+                Some(target) => {
+                    let target = &mut target[..line_len];
+                    target.copy_from_slice(line);
+                    self.darken_line(target);
+                }
+                None => break
+            }
+        }
+    }
+
+    #[inline]
+    fn darken_line(&self, line: &mut [u8]) {
+        let level = self.level as u16 + 1;
+        for pixel in line.chunks_exact_mut(N) {
+            for (index, channel) in pixel.iter_mut().enumerate() {
+                if index != ALPHA {
+                    *channel = ((*channel as u16 * level) >> 8) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::TryInto;
+    use super::*;
+
+    #[test]
+    fn scanline_filter_works() {
+        let filter = ScanlineFilter24::new(127);
+        let mut buffer = vec![200u8; 4 * 7];
+        filter.apply(&mut buffer, 7, 2, 4);
+        for (y, line) in buffer.chunks(7).enumerate() {
+            let expected = if y & 1 == 1 { 100 } else { 200 };
+            assert!(line[..6].iter().all(|&c| c == expected));
+            assert_eq!(line[6], 200);
+        }
+
+        let filter = ScanlineFilter32::new(255);
+        let mut buffer = vec![200u8; 2 * 8];
+        filter.apply(&mut buffer, 8, 2, 2);
+        assert!(buffer.iter().all(|&c| c == 200));
+
+        let filter = ScanlineFilter32::new(0);
+        let source: Vec<u8> = (1..=16).collect();
+        let mut target = vec![0xAAu8; 4 * 9];
+        filter.apply_doubled(&source, 8, &mut target, 9, 2, 2);
+        assert_eq!(&target[0..9],  &[1, 2, 3, 4, 5, 6, 7, 8, 0xAA]);
+        assert_eq!(&target[9..18], &[0, 0, 0, 4, 0, 0, 0, 8, 0xAA]);
+        assert_eq!(&target[18..27], &[9, 10, 11, 12, 13, 14, 15, 16, 0xAA]);
+        assert_eq!(&target[27..36], &[0, 0, 0, 12, 0, 0, 0, 16, 0xAA]);
+    }
+
+    #[test]
+    fn scanline_filter_keeps_alpha() {
+        let mut buffer = [200, 200, 200, 0x80].repeat(4);
+        ScanlineFilterRGBA32::new(127).apply(&mut buffer, 8, 2, 2);
+        assert_eq!(&buffer[8..], &[100, 100, 100, 0x80, 100, 100, 100, 0x80]);
+
+        let mut buffer = [0x80, 200, 200, 200].repeat(4);
+        ScanlineFilterARGB32::new(127).apply(&mut buffer, 8, 2, 2);
+        assert_eq!(&buffer[8..], &[0x80, 100, 100, 100, 0x80, 100, 100, 100]);
+
+        let mut buffer = 0x80C8C8C8u32.to_ne_bytes().repeat(2);
+        ScanlineFilterA8R8G8B8::new(127).apply(&mut buffer, 4, 1, 2);
+        assert_eq!(u32::from_ne_bytes(buffer[4..].try_into().unwrap()), 0x80646464);
+
+        let mut buffer = 0xC8C8C880u32.to_ne_bytes().repeat(2);
+        ScanlineFilterR8G8B8A8::new(127).apply(&mut buffer, 4, 1, 2);
+        assert_eq!(u32::from_ne_bytes(buffer[4..].try_into().unwrap()), 0x64646480);
+    }
+}
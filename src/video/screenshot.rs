@@ -0,0 +1,116 @@
+/*
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This file is part of SPECTRUSTY, a Rust library for building emulators.
+
+    For the full copyright notice, see the lib.rs file.
+*/
+use std::io::{self, Write};
+
+use crate::video::{BorderSize, Video};
+
+/// The pixel format of a buffer rendered with [Video::render_video_frame] to be saved with [save_screenshot].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScreenshotFormat {
+    /// 3 bytes per pixel: red, green, blue, e.g. rendered with [SpectrumPalRGB24].
+    ///
+    /// [SpectrumPalRGB24]: crate::video::pixel::SpectrumPalRGB24
+    RGB24,
+    /// 4 bytes per pixel: red, green, blue, alpha, e.g. rendered with [SpectrumPalRGBA32].
+    ///
+    /// [SpectrumPalRGBA32]: crate::video::pixel::SpectrumPalRGBA32
+    RGBA32
+}
+
+impl ScreenshotFormat {
+    /// Returns the number of bytes of a single pixel.
+    pub fn pixel_stride(self) -> usize {
+        match self {
+            ScreenshotFormat::RGB24  => 3,
+            ScreenshotFormat::RGBA32 => 4,
+        }
+    }
+}
+
+/// Encodes a video frame rendered by the chipset `U` into a PNG image and writes it to `wr`.
+///
+/// * `buffer` should contain pixels rendered with [Video::render_video_frame].
+/// * `pitch` is the number of bytes in a single row of pixel data, including padding between lines.
+/// * `border_size` must be the same as the one used for rendering.
+/// * `format` determines the layout of pixels in the `buffer`.
+///
+/// The dimensions of the image are determined by [Video::render_size_pixels].
+///
+/// Returns an error with [io::ErrorKind::InvalidInput] if the `buffer` is too small for the image
+/// dimensions or the `pitch` is smaller than the width of a single row of pixels.
+pub fn save_screenshot<U: Video, W: Write>(
+        wr: W,
+        buffer: &[u8],
+        pitch: usize,
+        border_size: BorderSize,
+        format: ScreenshotFormat
+    ) -> io::Result<()>
+{
+    let (width, height) = U::render_size_pixels(border_size);
+    let line_len = width as usize * format.pixel_stride();
+    if pitch < line_len || height != 0 && buffer.len() < pitch * (height as usize - 1) + line_len {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "the buffer is too small for the screenshot dimensions"))
+    }
+    let mut encoder = png::Encoder::new(wr, width, height);
+    encoder.set_color(match format {
+        ScreenshotFormat::RGB24  => png::ColorType::RGB,
+        ScreenshotFormat::RGBA32 => png::ColorType::RGBA,
+    });
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(png_to_io_error)?;
+    if pitch == line_len {
+        writer.write_image_data(&buffer[..line_len * height as usize])
+    }
+    else {
+        let data: Vec<u8> = buffer.chunks(pitch)
+                                  .take(height as usize)
+                                  .flat_map(|line| &line[..line_len])
+                                  .copied()
+                                  .collect();
+        writer.write_image_data(&data)
+    }.map_err(png_to_io_error)
+}
+
+fn png_to_io_error(err: png::EncodingError) -> io::Error {
+    match err {
+        png::EncodingError::IoError(err) => err,
+        err => io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip::ula::UlaPAL;
+    use crate::memory::Memory48k;
+    use crate::video::pixel::{PixelBufA24, SpectrumPalRGB24};
+    use super::*;
+
+    #[test]
+    fn save_screenshot_works() {
+        type TestUla = UlaPAL<Memory48k>;
+        let mut ula = TestUla::default();
+        let border_size = BorderSize::Full;
+        let (width, height) = TestUla::render_size_pixels(border_size);
+        let pitch = width as usize * 3 + 6;
+        let mut buffer = vec![0u8; pitch * height as usize];
+        ula.render_video_frame::<PixelBufA24, SpectrumPalRGB24>(&mut buffer, pitch, border_size);
+        let mut png_data = Vec::new();
+        save_screenshot::<TestUla, _>(&mut png_data, &buffer, pitch, border_size, ScreenshotFormat::RGB24)
+            .unwrap();
+        assert_eq!(&png_data[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png_data[16..20], &width.to_be_bytes());
+        assert_eq!(&png_data[20..24], &height.to_be_bytes());
+        let err = save_screenshot::<TestUla, _>(io::sink(), &buffer[..pitch], pitch, border_size,
+                                                ScreenshotFormat::RGB24).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = save_screenshot::<TestUla, _>(io::sink(), &buffer, pitch, border_size,
+                                                ScreenshotFormat::RGBA32).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,129 @@
+/*
+    test_divide: tests for the SPECTRUSTY library.
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+    Author contact information: see Cargo.toml file, section [package.authors].
+*/
+//! Tests the **DivIDE** memory paging controlled by the control port and the automatic paging.
+#![cfg(feature = "peripherals")]
+use std::io::Cursor;
+
+use spectrusty::z80emu::*;
+use spectrusty::bus::{NullDevice, divide::*};
+use spectrusty::memory::{Memory48kEx, ZxMemory};
+use spectrusty::peripherals::memory::{DIVIDE_EPROM_SIZE, DIVIDE_RAM_BANK_SIZE};
+use spectrusty::chip::{*, ula::*};
+use spectrusty::clock::*;
+
+type DivIdeUla = UlaPAL<Memory48kEx,
+                        DivIdeBusDevice<Cursor<Vec<u8>>, NullDevice<VideoTs>>,
+                        DivIdeMemExt>;
+
+const RESULTS: u16 = 0x9000;
+
+fn out_control(code: &mut Vec<u8>, control: u8) {
+    code.extend_from_slice(&[
+        0x3E, control, // LD   A, control
+        0xD3, 0xE3     // OUT  (0xE3), A
+    ]);
+}
+
+fn store_byte(code: &mut Vec<u8>, addr: u16, value: u8) {
+    let [lo, hi] = addr.to_le_bytes();
+    code.extend_from_slice(&[
+        0x3E, value,   // LD   A, value
+        0x32, lo, hi   // LD   (addr), A
+    ]);
+}
+
+fn copy_byte(code: &mut Vec<u8>, src: u16, dst: u16) {
+    let [slo, shi] = src.to_le_bytes();
+    let [dlo, dhi] = dst.to_le_bytes();
+    code.extend_from_slice(&[
+        0x3A, slo, shi, // LD   A, (src)
+        0x32, dlo, dhi  // LD   (dst), A
+    ]);
+}
+
+/// The automatically paged routine: stores `marker` at `addr` and returns via `0x1FF8`.
+fn automap_routine(mem: &mut [u8], addr: u16, marker: u8) {
+    let [lo, hi] = addr.to_le_bytes();
+    mem[0x0009..0x0011].copy_from_slice(&[
+        0x3E, marker,     // LD   A, marker
+        0x32, lo, hi,     // LD   (addr), A
+        0xC3, 0xF8, 0x1F  // JP   0x1FF8
+    ]);
+    mem[0x1FF8] = 0xC9;   // RET
+}
+
+#[test]
+fn test_divide_paging() {
+    let mut eprom = vec![0xA5u8; DIVIDE_EPROM_SIZE];
+    automap_routine(&mut eprom, RESULTS + 5, 0xAA);
+    let mut memext = DivIdeMemExt::default();
+    memext.load_eprom(&eprom[..]).unwrap();
+    // the routine executed with MAPRAM set, from RAM bank 3
+    automap_routine(&mut memext.ram_mut()[3*DIVIDE_RAM_BANK_SIZE..], RESULTS + 6, 0xBB);
+    let mut ula = DivIdeUla::default();
+    ula.bus_device_mut().link_memory_extension(&memext);
+    *ula.memory_ext_mut() = memext;
+
+    let mut code = Vec::new();
+    // page in with CONMEM and write to RAM banks 1 and 2
+    out_control(&mut code, DIVIDE_CONMEM|1);
+    store_byte(&mut code, 0x2000, 0x11);
+    out_control(&mut code, DIVIDE_CONMEM|2);
+    store_byte(&mut code, 0x2000, 0x22);
+    // EPROM is write-protected
+    store_byte(&mut code, 0x0000, 0x33);
+    out_control(&mut code, DIVIDE_CONMEM|1);
+    copy_byte(&mut code, 0x2000, RESULTS);
+    out_control(&mut code, DIVIDE_CONMEM|2);
+    copy_byte(&mut code, 0x2000, RESULTS + 1);
+    copy_byte(&mut code, 0x0000, RESULTS + 2);
+    // page out
+    out_control(&mut code, 0);
+    copy_byte(&mut code, 0x2000, RESULTS + 3);
+    // automatic paging with EPROM
+    code.push(0xCF);       // RST  8
+    copy_byte(&mut code, 0x0009, RESULTS + 4);
+    // automatic paging with RAM bank 3 in place of EPROM, MAPRAM can't be reset
+    out_control(&mut code, DIVIDE_MAPRAM|1);
+    code.push(0xCF);       // RST  8
+    out_control(&mut code, 3);
+    code.push(0x76);       // HALT
+    // NOPs in ROM
+    ula.memory_mut().rom_mut().fill(0);
+    ula.memory_mut().load_into_mem(0x8000..0x8000 + code.len() as u16, &code[..]).unwrap();
+    let mut cpu = Z80NMOS::default();
+    cpu.reset();
+    cpu.set_pc(0x8000);
+    ula.execute_next_frame(&mut cpu);
+    assert!(cpu.is_halt());
+    let mut res = [0u8;7];
+    for (n, data) in res.iter_mut().enumerate() {
+        *data = ula.memory_ref().read(RESULTS + n as u16);
+    }
+    assert_eq!(res, [0x11, 0x22, 0xA5, 0x00, 0x00, 0xAA, 0xBB]);
+    assert_eq!(ula.bus_device_ref().control(), DIVIDE_MAPRAM|3);
+    let memext = ula.memory_ext_ref();
+    assert!(!memext.is_automap());
+    assert!(!memext.is_mapped_exrom(ula.memory_ref()));
+    assert_eq!(memext.control(), DIVIDE_MAPRAM|3);
+    assert_eq!(memext.ram_ref()[DIVIDE_RAM_BANK_SIZE], 0x11);
+    assert_eq!(memext.ram_ref()[2*DIVIDE_RAM_BANK_SIZE], 0x22);
+    assert_eq!(memext.eprom_ref()[0], 0xA5);
+}
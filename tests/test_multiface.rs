@@ -0,0 +1,74 @@
+/*
+    test_multiface: tests for the SPECTRUSTY library.
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+    Author contact information: see Cargo.toml file, section [package.authors].
+*/
+//! Tests the **Multiface** memory paging and its writable RAM.
+#![cfg(feature = "peripherals")]
+use spectrusty::z80emu::*;
+use spectrusty::bus::{NullDevice, multiface::*};
+use spectrusty::memory::{Memory48kEx, ZxMemory};
+use spectrusty::peripherals::memory::{MultifaceMemExt, MULTIFACE_ROM_SIZE};
+use spectrusty::chip::{*, ula::*};
+use spectrusty::clock::*;
+
+type MultifaceUla = UlaPAL<Memory48kEx,
+                           MultifaceOne<NullDevice<VideoTs>>,
+                           MultifaceMemExt>;
+
+const RESULTS: u16 = 0x9000;
+
+#[test]
+fn test_multiface_ram() {
+    let mut rom = vec![0u8; MULTIFACE_ROM_SIZE];
+    rom[0x0066..0x0074].copy_from_slice(&[
+        0x3E, 0x5A,       // LD   A, 0x5A
+        0x32, 0x00, 0x20, // LD   (0x2000), A
+        0x3A, 0x00, 0x20, // LD   A, (0x2000)
+        0x32, 0x00, 0x90, // LD   (RESULTS), A
+        0xC3, 0x01, 0x80  // JP   0x8001
+    ]);
+    let mut memext = MultifaceMemExt::default();
+    memext.load_rom(&rom[..]).unwrap();
+    let mut ula = MultifaceUla::default();
+    ula.bus_device_mut().link_memory_extension(&memext);
+    *ula.memory_ext_mut() = memext;
+
+    let code = [
+        0x00,             // NOP
+        0xDB, 0x1F,       // IN   A, (0x1F) page out
+        0x00,             // NOP
+        0x3A, 0x00, 0x20, // LD   A, (0x2000)
+        0x32, 0x01, 0x90, // LD   (RESULTS+1), A
+        0x76              // HALT
+    ];
+    ula.memory_mut().rom_mut().fill(0);
+    ula.memory_mut().load_into_mem(0x8000..0x8000 + code.len() as u16, &code[..]).unwrap();
+    let mut cpu = Z80NMOS::default();
+    cpu.reset();
+    cpu.set_pc(0x8000);
+    ula.bus_device_mut().press_button();
+    assert!(ula.nmi(&mut cpu));
+    ula.execute_next_frame(&mut cpu);
+    assert!(cpu.is_halt());
+    assert_eq!(ula.memory_ref().read(RESULTS), 0x5A);
+    assert_eq!(ula.memory_ref().read(RESULTS + 1), 0x00);
+    assert!(!ula.bus_device_ref().is_paged_in());
+    let memext = ula.memory_ext_ref();
+    assert!(!memext.is_mapped_exrom(ula.memory_ref()));
+    assert_eq!(memext.exrom()[0x2000], 0x5A);
+}
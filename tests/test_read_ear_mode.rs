@@ -0,0 +1,91 @@
+/*
+    test_read_ear_mode: tests for the SPECTRUSTY library.
+    Copyright (C) 2020-2022  Rafal Michalski
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+    Author contact information: see Cargo.toml file, section [package.authors].
+*/
+//! Tests the `EAR IN` bit read from the port `0xFE` when there is no `EAR IN` feed, depending on
+//! the selected [ReadEarMode].
+//!
+//! The 48k ROM keyboard routines don't care about bit 6, but some tape loaders and games do, e.g.
+//! those written on an Issue 2 machine that rely on bit 6 following the `MIC` output. Running them
+//! with [ReadEarMode::Issue3] may break the keyboard reading.
+use spectrusty::z80emu::*;
+use spectrusty::memory::{Memory64k, ZxMemory};
+use spectrusty::chip::{*, ula::*, ula128::*};
+use spectrusty::clock::*;
+
+/// The values written to the port `0xFE`: (`MIC`, `EAR`) output bits.
+const PORT_OUT: [u8;4] = [0b0000_0000, 0b0000_1000, 0b0001_0000, 0b0001_1000];
+const RESULTS: u16 = 0x9000;
+
+/// Executes `LD A,out; OUT (0xFE),A; IN A,(0xFE); LD (RESULTS+n),A` for each of `PORT_OUT` values
+/// and returns the `EAR IN` bit read after each `OUT`.
+fn read_ear_in_after_out<U>(mode: ReadEarMode) -> [bool;4]
+    where U: UlaCommon + Default +
+             Memory<Timestamp=VideoTs> +
+             Io<Timestamp=VideoTs>
+{
+    let mut ula = U::default();
+    ula.set_read_ear_mode(mode);
+    assert_eq!(ula.read_ear_mode(), mode);
+    let mut code = Vec::new();
+    for (n, &out) in PORT_OUT.iter().enumerate() {
+        let [lo, hi] = (RESULTS + n as u16).to_le_bytes();
+        code.extend_from_slice(&[
+            0x3E, out,     // LD   A, out
+            0xD3, 0xFE,    // OUT  (0xFE), A
+            0xDB, 0xFE,    // IN   A, (0xFE)
+            0x32, lo, hi   // LD   (RESULTS+n), A
+        ]);
+    }
+    code.push(0x76);       // HALT
+    ula.memory_mut().load_into_mem(0x8000..0x8000 + code.len() as u16, &code[..]).unwrap();
+    let mut cpu = Z80NMOS::default();
+    cpu.reset();
+    cpu.set_pc(0x8000);
+    ula.execute_next_frame(&mut cpu);
+    assert!(cpu.is_halt());
+    assert_eq!(ula.read_ear_in_count(), PORT_OUT.len() as u32);
+    let mut res = [false;4];
+    for (n, bit) in res.iter_mut().enumerate() {
+        let data = ula.memory_ref().read(RESULTS + n as u16);
+        // no keys are pressed
+        assert_eq!(data | 0b0100_0000, 0xFF);
+        *bit = data & 0b0100_0000 != 0;
+    }
+    res
+}
+
+fn read_ear_mode_works<U>()
+    where U: UlaCommon + Default +
+             Memory<Timestamp=VideoTs> +
+             Io<Timestamp=VideoTs>
+{
+    // Issue 3: bit 6 follows the EAR output only
+    assert_eq!(read_ear_in_after_out::<U>(ReadEarMode::Issue3), [false, false, true, true]);
+    // Issue 2: bit 6 is set when either the EAR or the MIC output is set
+    assert_eq!(read_ear_in_after_out::<U>(ReadEarMode::Issue2), [false, true, true, true]);
+    assert_eq!(read_ear_in_after_out::<U>(ReadEarMode::Clear), [false;4]);
+    assert_eq!(read_ear_in_after_out::<U>(ReadEarMode::Set), [true;4]);
+}
+
+#[test]
+fn test_read_ear_mode() {
+    read_ear_mode_works::<UlaPAL<Memory64k>>();
+    read_ear_mode_works::<UlaNTSC<Memory64k>>();
+    read_ear_mode_works::<Ula128>();
+}